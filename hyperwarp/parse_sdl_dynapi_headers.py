@@ -1,11 +1,27 @@
-# dirty script to generate the list of SDL2 functions to hook
-PREFIX = "pub const DYNAPI_FUNCS: [&str; {}] = [\n"
+# dirty script to generate the list of SDL functions to hook
+# usage: python parse_sdl_dynapi_headers.py [2|3], defaults to 2
+import sys
+
+version = sys.argv[1] if len(sys.argv) > 1 else "2"
+if version == "2":
+    header = "sdl2_dynapi.h"
+    output = "src/utils/sdl2_dynapi.rs"
+    const_name = "DYNAPI_FUNCS"
+elif version == "3":
+    header = "sdl3_dynapi.h"
+    output = "src/utils/sdl3_dynapi.rs"
+    const_name = "SDL3_DYNAPI_FUNCS"
+else:
+    print("unknown SDL version", version)
+    sys.exit(1)
+
+PREFIX = "pub const " + const_name + ": [&str; {}] = [\n"
 FORMAT = "  \"{}\",\n"
 SUFFIX = "\n];"
 
 seen = set()
 funcs = []
-for line in open("sdl2_dynapi.h"):
+for line in open(header):
     line = line.strip()
     if not line.startswith("SDL_DYNAPI_PROC(") or not line.endswith(")"):
         continue
@@ -15,12 +31,11 @@ for line in open("sdl2_dynapi.h"):
     seen.add(func)
     funcs.append(func)
 
-# should say 601
+# should say 601 for old SDL2 headers
 print("Found", len(funcs), "functions")
 # write to file
-with open("src/utils/sdl2_dynapi.rs", "w") as f:
+with open(output, "w") as f:
     f.write(PREFIX.format(len(funcs)))
     for func in funcs:
         f.write(FORMAT.format(func))
     f.write(SUFFIX)
-    
@@ -2,4 +2,6 @@ pub mod gl;
 pub mod gl_safe;
 pub mod sdl2;
 pub mod sdl2_safe;
+pub mod sdl3;
+pub mod sdl3_safe;
 pub mod xlib;
\ No newline at end of file
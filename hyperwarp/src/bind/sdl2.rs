@@ -4,9 +4,9 @@
 // https://github.com/libsdl-org/SDL/blob/e264bb517827a2c9cf16570fd89385c0f1f7f344/src/video/SDL_video.c#L2623
 
 use sdl2_sys_lite::bindings::{SDL_Event, SDL_Joystick, SDL_JoystickID, SDL_JoystickType, SDL_VirtualJoystickDesc};
-use stellar_shared::vendor::sdl_bindings::{SDL_KeyCode, SDL_Scancode};
+use stellar_shared::vendor::sdl_bindings::{SDL_KeyCode, SDL_RendererInfo, SDL_Scancode, SDL_Surface};
 
-use crate::utils::manual_types::sdl2::SDL_Window;
+use crate::utils::manual_types::sdl2::{SDL12_SysWMinfo, SDL_SysWMinfo_X11, SDL_Window};
 
 // *const libc::c_char is a String
 
@@ -26,6 +26,12 @@ lazy_static::lazy_static! {
         std::mem::transmute(ptr)
     };
 
+    pub static ref SDL_GetWindowFromID: unsafe extern "C" fn(id: libc::c_uint) -> *const SDL_Window = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_GetWindowFromID_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
     pub static ref SDL_GetError: unsafe extern "C" fn() -> *const libc::c_char = unsafe {
         let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_GetError_hw_direct\0".as_ptr() as _);
         assert!(!ptr.is_null());
@@ -128,4 +134,59 @@ lazy_static::lazy_static! {
         assert!(!ptr.is_null());
         std::mem::transmute(ptr)
     };
-}
\ No newline at end of file
+
+    // used to read window icons, converting first means we don't need to care about the app's surface format
+    pub static ref SDL_ConvertSurfaceFormat: unsafe extern "C" fn(src: *mut SDL_Surface, pixel_format: u32, flags: u32) -> *mut SDL_Surface = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_ConvertSurfaceFormat_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_FreeSurface: unsafe extern "C" fn(surface: *mut SDL_Surface) = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_FreeSurface_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // renderer capture for non gl backends
+    pub static ref SDL_GetRendererInfo: unsafe extern "C" fn(renderer: *mut libc::c_void, info: *mut SDL_RendererInfo) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_GetRendererInfo_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GetRendererOutputSize: unsafe extern "C" fn(renderer: *mut libc::c_void, w: *mut libc::c_int, h: *mut libc::c_int) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_GetRendererOutputSize_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // for finding the X window underneath an SDL window
+    pub static ref SDL_GetWindowWMInfo: unsafe extern "C" fn(window: *const SDL_Window, info: *mut SDL_SysWMinfo_X11) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_GetWindowWMInfo_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // SDL 1.2 only, None when the app uses a newer SDL
+    pub static ref SDL12_GetWMInfo: Option<unsafe extern "C" fn(info: *mut SDL12_SysWMinfo) -> libc::c_int> = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_GetWMInfo_hw_direct\0".as_ptr() as _);
+        if ptr.is_null() {
+            None
+        } else {
+            Some(std::mem::transmute(ptr))
+        }
+    };
+
+    pub static ref SDL_RenderGetWindow: unsafe extern "C" fn(renderer: *mut libc::c_void) -> *mut SDL_Window = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_RenderGetWindow_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_RenderReadPixels: unsafe extern "C" fn(renderer: *mut libc::c_void, rect: *const libc::c_void, format: u32, pixels: *mut libc::c_void, pitch: libc::c_int) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"SDL_RenderReadPixels_hw_direct\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+}
@@ -1,9 +1,9 @@
 use std::ffi::CStr;
 
 use sdl2_sys_lite::bindings::{SDL_Event, SDL_JoystickID};
-use stellar_shared::vendor::sdl_bindings::{SDL_KeyCode, SDL_Scancode};
+use stellar_shared::vendor::sdl_bindings::{SDL_KeyCode, SDL_RendererInfo, SDL_Scancode, SDL_Surface};
 
-use crate::utils::manual_types::sdl2::SDL_Window;
+use crate::{constants::sdl2::{SDL_PIXELFORMAT_ABGR8888, SDL_SYSWM_X11, SDL12_SYSWM_X11}, utils::manual_types::sdl2::{SDL12_SysWMinfo, SDL_SysWMinfo_X11, SDL_Window, SDL_version}};
 
 use super::sdl2::SDL_GetError;
 
@@ -23,6 +23,12 @@ pub fn SDL_GetWindowID_safe(window: *const SDL_Window) -> u32 {
     }
 }
 
+pub fn SDL_GetWindowFromID_safe(id: u32) -> *const SDL_Window {
+    unsafe {
+        super::sdl2::SDL_GetWindowFromID(id)
+    }
+}
+
 // https://github.com/Rust-SDL2/rust-sdl2/blob/dba66e80b14e16de309df49df0c20fdaf35b8c67/src/sdl2/sdl.rs#L378
 pub fn SDL_GetError_safe() -> String {
     unsafe {
@@ -59,4 +65,90 @@ pub fn find_device_index_by_instance_id(inst: SDL_JoystickID) -> Option<i32> {
         }
     }
     None
-}
\ No newline at end of file
+}
+
+// copies a surface out as tightly packed rgba8, returns (width, height, pixels)
+pub fn SDL_Surface_to_rgba_safe(surface: *mut SDL_Surface) -> Option<(u32, u32, Vec<u8>)> {
+    if surface.is_null() {
+        return None;
+    }
+    unsafe {
+        let converted = super::sdl2::SDL_ConvertSurfaceFormat(surface, SDL_PIXELFORMAT_ABGR8888, 0);
+        if converted.is_null() {
+            return None;
+        }
+        let converted_ref = &*converted;
+        let width = converted_ref.w.max(0) as usize;
+        let height = converted_ref.h.max(0) as usize;
+        let pitch = converted_ref.pitch.max(0) as usize;
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        if !converted_ref.pixels.is_null() && pitch >= width * 4 {
+            for row in 0..height {
+                let row_ptr = (converted_ref.pixels as *const u8).add(row * pitch);
+                rgba.extend_from_slice(std::slice::from_raw_parts(row_ptr, width * 4));
+            }
+        }
+        super::sdl2::SDL_FreeSurface(converted);
+        if rgba.len() != width * height * 4 || rgba.is_empty() {
+            return None;
+        }
+        Some((width as u32, height as u32, rgba))
+    }
+}
+
+pub fn SDL_GetRendererName_safe(renderer: *mut libc::c_void) -> Option<String> {
+    let mut info: SDL_RendererInfo = unsafe { std::mem::zeroed() };
+    let result = unsafe { super::sdl2::SDL_GetRendererInfo(renderer, &mut info) };
+    if result != 0 || info.name.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(info.name) }.to_string_lossy().into_owned())
+}
+
+// size in pixels, can differ from the window size with high dpi
+pub fn SDL_GetRendererOutputSize_safe(renderer: *mut libc::c_void) -> Option<(i32, i32)> {
+    let mut w: i32 = 0;
+    let mut h: i32 = 0;
+    let result = unsafe { super::sdl2::SDL_GetRendererOutputSize(renderer, &mut w, &mut h) };
+    if result != 0 {
+        None
+    } else {
+        Some((w, h))
+    }
+}
+
+// the X window SDL created for this window, None on wayland or any other video driver
+pub fn SDL_GetWindowX11Window_safe(window: *const SDL_Window) -> Option<usize> {
+    if window.is_null() {
+        return None;
+    }
+    let mut info: SDL_SysWMinfo_X11 = unsafe { std::mem::zeroed() };
+    // the oldest version that has x11 info, newer SDLs refuse versions above their own
+    info.version = SDL_version { major: 2, minor: 0, patch: 0 };
+    let ok = unsafe { super::sdl2::SDL_GetWindowWMInfo(window, &mut info) };
+    if ok == 0 || info.subsystem != SDL_SYSWM_X11 || info.window == 0 {
+        return None;
+    }
+    Some(info.window as usize)
+}
+
+// SDL 1.2 has a single window, this is its X window
+pub fn SDL12_GetX11Window_safe() -> Option<usize> {
+    let get_wm_info = (*super::sdl2::SDL12_GetWMInfo)?;
+    let mut info: SDL12_SysWMinfo = unsafe { std::mem::zeroed() };
+    info.version = SDL_version { major: 1, minor: 2, patch: 0 };
+    let ok = unsafe { get_wm_info(&mut info) };
+    if ok != 1 || info.subsystem != SDL12_SYSWM_X11 || info.window == 0 {
+        return None;
+    }
+    Some(info.window as usize)
+}
+
+pub fn SDL_RenderGetWindow_safe(renderer: *mut libc::c_void) -> *mut SDL_Window {
+    unsafe { super::sdl2::SDL_RenderGetWindow(renderer) }
+}
+
+// reads the whole render target, pixels must hold pitch * height bytes
+pub fn SDL_RenderReadPixels_safe(renderer: *mut libc::c_void, format: u32, pixels: &mut [u8], pitch: i32) -> i32 {
+    unsafe { super::sdl2::SDL_RenderReadPixels(renderer, std::ptr::null(), format, pixels.as_mut_ptr() as *mut libc::c_void, pitch) }
+}
@@ -0,0 +1,204 @@
+// SDL3 versions of the functions we call ourselves, same _hw_direct trick as the SDL2 binds
+// most names collide with SDL2 so these are only safe to touch once SDL3 has been detected
+
+use std::ffi::CString;
+
+use crate::constants::sdl3::{SDL3_LIBRARY_NAME, SDL3_Event, SDL3_Joystick, SDL3_JoystickID, SDL3_Renderer, SDL3_Surface, SDL3_VirtualJoystickDesc, SDL3_Window, SDL3_WindowID};
+
+// sdl2-compat exports its SDL2 versions under the same names, so there we go to libSDL3 itself
+unsafe fn sdl3_symbol(hw_direct_name: &[u8]) -> *mut libc::c_void {
+    if crate::hooks::sdl3::is_sdl2_compat() {
+        let handle = libc::dlopen(SDL3_LIBRARY_NAME.as_ptr() as _, libc::RTLD_LAZY | libc::RTLD_NOLOAD);
+        if !handle.is_null() {
+            let name = CString::new(&hw_direct_name[..hw_direct_name.len() - b"_hw_direct\0".len()]).unwrap();
+            let ptr = crate::hooks::dlsym::odlsym(handle, name.as_ptr());
+            libc::dlclose(handle);
+            return ptr;
+        }
+    }
+    libc::dlsym(libc::RTLD_NEXT, hw_direct_name.as_ptr() as _)
+}
+
+lazy_static::lazy_static! {
+    pub static ref SDL_CreateWindow: unsafe extern "C" fn(title: *const libc::c_char, w: libc::c_int, h: libc::c_int, flags: u64) -> *mut SDL3_Window = unsafe {
+        let ptr = sdl3_symbol(b"SDL_CreateWindow_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_DestroyWindow: unsafe extern "C" fn(window: *mut SDL3_Window) = unsafe {
+        let ptr = sdl3_symbol(b"SDL_DestroyWindow_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_SetWindowTitle: unsafe extern "C" fn(window: *mut SDL3_Window, title: *const libc::c_char) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_SetWindowTitle_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_SetWindowIcon: unsafe extern "C" fn(window: *mut SDL3_Window, icon: *mut SDL3_Surface) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_SetWindowIcon_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_ShowWindow: unsafe extern "C" fn(window: *mut SDL3_Window) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_ShowWindow_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_HideWindow: unsafe extern "C" fn(window: *mut SDL3_Window) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_HideWindow_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_SetWindowFullscreen: unsafe extern "C" fn(window: *mut SDL3_Window, fullscreen: bool) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_SetWindowFullscreen_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GetWindowID: unsafe extern "C" fn(window: *mut SDL3_Window) -> SDL3_WindowID = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetWindowID_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GetWindowFromID: unsafe extern "C" fn(id: SDL3_WindowID) -> *mut SDL3_Window = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetWindowFromID_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // size in screen coordinates, only used to scale our pixel coordinates for events
+    pub static ref SDL_GetWindowSize: unsafe extern "C" fn(window: *mut SDL3_Window, w: *mut libc::c_int, h: *mut libc::c_int) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetWindowSize_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // what actually ends up in the backbuffer with a pixel density above 1
+    pub static ref SDL_GetWindowSizeInPixels: unsafe extern "C" fn(window: *mut SDL3_Window, w: *mut libc::c_int, h: *mut libc::c_int) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetWindowSizeInPixels_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GL_SwapWindow: unsafe extern "C" fn(window: *mut SDL3_Window) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GL_SwapWindow_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GetError: unsafe extern "C" fn() -> *const libc::c_char = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetError_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // events
+    pub static ref SDL_PollEvent: unsafe extern "C" fn(event: *mut SDL3_Event) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_PollEvent_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_PushEvent: unsafe extern "C" fn(event: *mut SDL3_Event) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_PushEvent_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // milliseconds, 64 bit now
+    pub static ref SDL_GetTicks: unsafe extern "C" fn() -> u64 = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetTicks_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GetTicksNS: unsafe extern "C" fn() -> u64 = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetTicksNS_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // renderer
+    pub static ref SDL_RenderPresent: unsafe extern "C" fn(renderer: *mut SDL3_Renderer) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_RenderPresent_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GetRenderWindow: unsafe extern "C" fn(renderer: *mut SDL3_Renderer) -> *mut SDL3_Window = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetRenderWindow_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_GetRendererName: unsafe extern "C" fn(renderer: *mut SDL3_Renderer) -> *const libc::c_char = unsafe {
+        let ptr = sdl3_symbol(b"SDL_GetRendererName_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // hands back a new surface instead of filling a buffer
+    pub static ref SDL_RenderReadPixels: unsafe extern "C" fn(renderer: *mut SDL3_Renderer, rect: *const libc::c_void) -> *mut SDL3_Surface = unsafe {
+        let ptr = sdl3_symbol(b"SDL_RenderReadPixels_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // surfaces
+    pub static ref SDL_ConvertSurface: unsafe extern "C" fn(surface: *mut SDL3_Surface, format: u32) -> *mut SDL3_Surface = unsafe {
+        let ptr = sdl3_symbol(b"SDL_ConvertSurface_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_DestroySurface: unsafe extern "C" fn(surface: *mut SDL3_Surface) = unsafe {
+        let ptr = sdl3_symbol(b"SDL_DestroySurface_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // joystick
+    pub static ref SDL_AttachVirtualJoystick: unsafe extern "C" fn(desc: *const SDL3_VirtualJoystickDesc) -> SDL3_JoystickID = unsafe {
+        let ptr = sdl3_symbol(b"SDL_AttachVirtualJoystick_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_DetachVirtualJoystick: unsafe extern "C" fn(instance_id: SDL3_JoystickID) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_DetachVirtualJoystick_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_OpenJoystick: unsafe extern "C" fn(instance_id: SDL3_JoystickID) -> *mut SDL3_Joystick = unsafe {
+        let ptr = sdl3_symbol(b"SDL_OpenJoystick_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_CloseJoystick: unsafe extern "C" fn(joystick: *mut SDL3_Joystick) = unsafe {
+        let ptr = sdl3_symbol(b"SDL_CloseJoystick_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_SetJoystickVirtualAxis: unsafe extern "C" fn(joystick: *mut SDL3_Joystick, axis: libc::c_int, value: i16) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_SetJoystickVirtualAxis_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref SDL_SetJoystickVirtualButton: unsafe extern "C" fn(joystick: *mut SDL3_Joystick, button: libc::c_int, down: bool) -> bool = unsafe {
+        let ptr = sdl3_symbol(b"SDL_SetJoystickVirtualButton_hw_direct\0");
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+}
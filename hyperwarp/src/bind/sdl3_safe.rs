@@ -0,0 +1,150 @@
+use std::ffi::{CStr, CString};
+
+use crate::constants::{sdl2::SDL_PIXELFORMAT_ABGR8888, sdl3::{SDL3_Event, SDL3_Joystick, SDL3_JoystickID, SDL3_Renderer, SDL3_Surface, SDL3_VirtualJoystickDesc, SDL3_Window, SDL3_WindowID, SDL3_JOYSTICK_TYPE_GAMEPAD}};
+
+// SDL3 functions report success with a bool instead of 0
+
+pub fn SDL_GetWindowID_safe(window: *mut SDL3_Window) -> SDL3_WindowID {
+    unsafe { super::sdl3::SDL_GetWindowID(window) }
+}
+
+pub fn SDL_GetWindowFromID_safe(id: SDL3_WindowID) -> *mut SDL3_Window {
+    unsafe { super::sdl3::SDL_GetWindowFromID(id) }
+}
+
+pub fn SDL_GetWindowSize_safe(window: *mut SDL3_Window) -> Option<(i32, i32)> {
+    let mut w: i32 = 0;
+    let mut h: i32 = 0;
+    if unsafe { super::sdl3::SDL_GetWindowSize(window, &mut w, &mut h) } {
+        Some((w, h))
+    } else {
+        None
+    }
+}
+
+pub fn SDL_GetWindowSizeInPixels_safe(window: *mut SDL3_Window) -> Option<(i32, i32)> {
+    let mut w: i32 = 0;
+    let mut h: i32 = 0;
+    if unsafe { super::sdl3::SDL_GetWindowSizeInPixels(window, &mut w, &mut h) } {
+        Some((w, h))
+    } else {
+        None
+    }
+}
+
+pub fn SDL_GetError_safe() -> String {
+    unsafe {
+        let err_ptr = super::sdl3::SDL_GetError();
+        if err_ptr.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(err_ptr).to_string_lossy().into_owned()
+    }
+}
+
+pub fn SDL_PushEvent_safe(event: &mut SDL3_Event) -> bool {
+    unsafe { super::sdl3::SDL_PushEvent(event) }
+}
+
+pub fn SDL_GetTicks_safe() -> u64 {
+    unsafe { super::sdl3::SDL_GetTicks() }
+}
+
+pub fn SDL_GetTicksNS_safe() -> u64 {
+    unsafe { super::sdl3::SDL_GetTicksNS() }
+}
+
+pub fn SDL_GetRenderWindow_safe(renderer: *mut SDL3_Renderer) -> *mut SDL3_Window {
+    unsafe { super::sdl3::SDL_GetRenderWindow(renderer) }
+}
+
+pub fn SDL_GetRendererName_safe(renderer: *mut SDL3_Renderer) -> Option<String> {
+    let name = unsafe { super::sdl3::SDL_GetRendererName(renderer) };
+    if name.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned())
+}
+
+// copies a surface out tightly packed in the given format, returns (width, height, pixels)
+pub fn SDL_Surface_to_packed_safe(surface: *mut SDL3_Surface, format: u32) -> Option<(u32, u32, Vec<u8>)> {
+    if surface.is_null() {
+        return None;
+    }
+    unsafe {
+        let converted = super::sdl3::SDL_ConvertSurface(surface, format);
+        if converted.is_null() {
+            return None;
+        }
+        let converted_ref = &*converted;
+        let width = converted_ref.w.max(0) as usize;
+        let height = converted_ref.h.max(0) as usize;
+        let pitch = converted_ref.pitch.max(0) as usize;
+        let mut packed = Vec::with_capacity(width * height * 4);
+        if !converted_ref.pixels.is_null() && pitch >= width * 4 {
+            for row in 0..height {
+                let row_ptr = (converted_ref.pixels as *const u8).add(row * pitch);
+                packed.extend_from_slice(std::slice::from_raw_parts(row_ptr, width * 4));
+            }
+        }
+        super::sdl3::SDL_DestroySurface(converted);
+        if packed.len() != width * height * 4 || packed.is_empty() {
+            return None;
+        }
+        Some((width as u32, height as u32, packed))
+    }
+}
+
+pub fn SDL_Surface_to_rgba_safe(surface: *mut SDL3_Surface) -> Option<(u32, u32, Vec<u8>)> {
+    SDL_Surface_to_packed_safe(surface, SDL_PIXELFORMAT_ABGR8888)
+}
+
+// reads the whole viewport, the pixel format enum is the same as SDL2's
+pub fn SDL_RenderReadPixels_safe(renderer: *mut SDL3_Renderer, format: u32) -> Option<(u32, u32, Vec<u8>)> {
+    unsafe {
+        let surface = super::sdl3::SDL_RenderReadPixels(renderer, std::ptr::null());
+        if surface.is_null() {
+            return None;
+        }
+        let result = SDL_Surface_to_packed_safe(surface, format);
+        super::sdl3::SDL_DestroySurface(surface);
+        result
+    }
+}
+
+// returns the instance id, SDL copies the name
+pub fn SDL_AttachVirtualGamepad_safe(name: &str, vendor_id: u16, product_id: u16, naxes: u16, nbuttons: u16, nhats: u16) -> Option<SDL3_JoystickID> {
+    let name_cstring = CString::new(name).ok()?;
+    let mut desc: SDL3_VirtualJoystickDesc = unsafe { std::mem::zeroed() };
+    desc.version = std::mem::size_of::<SDL3_VirtualJoystickDesc>() as u32;
+    desc.type_ = SDL3_JOYSTICK_TYPE_GAMEPAD;
+    desc.vendor_id = vendor_id;
+    desc.product_id = product_id;
+    desc.naxes = naxes;
+    desc.nbuttons = nbuttons;
+    desc.nhats = nhats;
+    desc.name = name_cstring.as_ptr();
+    match unsafe { super::sdl3::SDL_AttachVirtualJoystick(&desc) } {
+        0 => None,
+        instance_id => Some(instance_id),
+    }
+}
+
+pub fn SDL_OpenJoystick_safe(instance_id: SDL3_JoystickID) -> *mut SDL3_Joystick {
+    unsafe { super::sdl3::SDL_OpenJoystick(instance_id) }
+}
+
+pub fn SDL_CloseVirtualJoystick_safe(joystick: *mut SDL3_Joystick, instance_id: SDL3_JoystickID) -> bool {
+    unsafe {
+        super::sdl3::SDL_CloseJoystick(joystick);
+        super::sdl3::SDL_DetachVirtualJoystick(instance_id)
+    }
+}
+
+pub fn SDL_SetJoystickVirtualAxis_safe(joystick: *mut SDL3_Joystick, axis: i32, value: i16) -> bool {
+    unsafe { super::sdl3::SDL_SetJoystickVirtualAxis(joystick, axis, value) }
+}
+
+pub fn SDL_SetJoystickVirtualButton_safe(joystick: *mut SDL3_Joystick, button: i32, down: bool) -> bool {
+    unsafe { super::sdl3::SDL_SetJoystickVirtualButton(joystick, button, down) }
+}
@@ -1,5 +1,7 @@
 use gl::types::*;
 
+use crate::constants::xlib::{XErrorHandler, XImage, XShmSegmentInfo};
+
 /*extern "C" {
     pub fn glReadPixels(x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, type_: GLenum, pixels: *mut u8);
 }*/
@@ -24,4 +26,114 @@ lazy_static::lazy_static! {
         assert!(!ptr.is_null());
         std::mem::transmute(ptr)
     };
-}
\ No newline at end of file
+
+    // these go through our own display connection in host::xlib_capture, never the app's
+
+    pub static ref XOpenDisplay: unsafe extern "C" fn(name: *const libc::c_char) -> *mut libc::c_void = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XOpenDisplay\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref XCloseDisplay: unsafe extern "C" fn(display: *mut libc::c_void) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XCloseDisplay\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref XDefaultScreen: unsafe extern "C" fn(display: *mut libc::c_void) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XDefaultScreen\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref XDefaultVisual: unsafe extern "C" fn(display: *mut libc::c_void, screen: libc::c_int) -> *mut libc::c_void = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XDefaultVisual\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref XDefaultDepth: unsafe extern "C" fn(display: *mut libc::c_void, screen: libc::c_int) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XDefaultDepth\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref XGetImage: unsafe extern "C" fn(
+        display: *mut libc::c_void,
+        drawable: libc::c_ulong,
+        x: libc::c_int,
+        y: libc::c_int,
+        width: libc::c_uint,
+        height: libc::c_uint,
+        plane_mask: libc::c_ulong,
+        format: libc::c_int,
+    ) -> *mut XImage = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XGetImage\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    pub static ref XSync: unsafe extern "C" fn(display: *mut libc::c_void, discard: libc::c_int) -> libc::c_int = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XSync\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // process wide, xlib_capture only swaps it in around its own requests
+    pub static ref XSetErrorHandler: unsafe extern "C" fn(handler: XErrorHandler) -> XErrorHandler = unsafe {
+        let ptr = libc::dlsym(libc::RTLD_NEXT, b"XSetErrorHandler\0".as_ptr() as _);
+        assert!(!ptr.is_null());
+        std::mem::transmute(ptr)
+    };
+
+    // MIT-SHM lives in libXext which the app might not have loaded, so these are optional
+    pub static ref XShmQueryExtension: Option<unsafe extern "C" fn(display: *mut libc::c_void) -> libc::c_int> = unsafe {
+        std::mem::transmute(xext_symbol(b"XShmQueryExtension\0"))
+    };
+
+    pub static ref XShmCreateImage: Option<unsafe extern "C" fn(
+        display: *mut libc::c_void,
+        visual: *mut libc::c_void,
+        depth: libc::c_uint,
+        format: libc::c_int,
+        data: *mut libc::c_char,
+        shminfo: *mut XShmSegmentInfo,
+        width: libc::c_uint,
+        height: libc::c_uint,
+    ) -> *mut XImage> = unsafe {
+        std::mem::transmute(xext_symbol(b"XShmCreateImage\0"))
+    };
+
+    pub static ref XShmAttach: Option<unsafe extern "C" fn(display: *mut libc::c_void, shminfo: *mut XShmSegmentInfo) -> libc::c_int> = unsafe {
+        std::mem::transmute(xext_symbol(b"XShmAttach\0"))
+    };
+
+    pub static ref XShmDetach: Option<unsafe extern "C" fn(display: *mut libc::c_void, shminfo: *mut XShmSegmentInfo) -> libc::c_int> = unsafe {
+        std::mem::transmute(xext_symbol(b"XShmDetach\0"))
+    };
+
+    pub static ref XShmGetImage: Option<unsafe extern "C" fn(
+        display: *mut libc::c_void,
+        drawable: libc::c_ulong,
+        image: *mut XImage,
+        x: libc::c_int,
+        y: libc::c_int,
+        plane_mask: libc::c_ulong,
+    ) -> libc::c_int> = unsafe {
+        std::mem::transmute(xext_symbol(b"XShmGetImage\0"))
+    };
+}
+
+// null if libXext can't be found at all, Option<fn> has the same layout as a nullable pointer
+unsafe fn xext_symbol(name: &[u8]) -> *mut libc::c_void {
+    let ptr = libc::dlsym(libc::RTLD_NEXT, name.as_ptr() as _);
+    if !ptr.is_null() {
+        return ptr;
+    }
+    let handle = libc::dlopen(b"libXext.so.6\0".as_ptr() as _, libc::RTLD_NOW | libc::RTLD_GLOBAL);
+    if handle.is_null() {
+        return std::ptr::null_mut();
+    }
+    libc::dlsym(handle, name.as_ptr() as _)
+}
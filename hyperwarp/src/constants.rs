@@ -1,5 +1,9 @@
 pub mod sdl2;
 pub mod xlib;
+pub mod vulkan;
+pub mod egl;
+pub mod glfw;
+pub mod sdl3;
 
 pub const LIBRARY_NAME: &str = "Hyperwarp";
 pub const GAMEPAD_NAME: &str = "Hyperwarp Virtual Gamepad";
@@ -26,10 +30,3 @@ pub fn is_SDL(lib: Library) -> bool {
         _ => false,
     }
 }
-
-// try to avoid doing stuff here
-pub const BLACKLISTED_PROCESS_NAMES: &[&str] = &[
-    "env",
-    "exec",
-    "valgrind"
-];
\ No newline at end of file
@@ -0,0 +1,13 @@
+// from egl.h and eglext.h
+
+pub const EGL_FALSE: u32 = 0;
+pub const EGL_TRUE: u32 = 1;
+
+pub const EGL_HEIGHT: i32 = 0x3056;
+pub const EGL_WIDTH: i32 = 0x3057;
+
+pub const EGL_PLATFORM_GBM_KHR: u32 = 0x31D7;
+pub const EGL_PLATFORM_X11_KHR: u32 = 0x31D5;
+pub const EGL_PLATFORM_WAYLAND_KHR: u32 = 0x31D8;
+pub const EGL_PLATFORM_XCB_EXT: u32 = 0x31DC;
+pub const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
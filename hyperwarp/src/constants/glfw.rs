@@ -0,0 +1,38 @@
+// values from glfw3.h, only what the hooks in hooks::glfw need
+
+pub const GLFW_RELEASE: i32 = 0;
+pub const GLFW_PRESS: i32 = 1;
+pub const GLFW_REPEAT: i32 = 2;
+
+pub const GLFW_TRUE: i32 = 1;
+pub const GLFW_FALSE: i32 = 0;
+
+pub const GLFW_KEY_UNKNOWN: i32 = -1;
+pub const GLFW_KEY_LAST: i32 = 348;
+pub const GLFW_MOUSE_BUTTON_LAST: i32 = 7;
+
+pub const GLFW_MOD_SHIFT: i32 = 0x0001;
+pub const GLFW_MOD_CONTROL: i32 = 0x0002;
+pub const GLFW_MOD_ALT: i32 = 0x0004;
+pub const GLFW_MOD_SUPER: i32 = 0x0008;
+
+pub const GLFW_JOYSTICK_LAST: i32 = 15;
+pub const GLFW_GAMEPAD_BUTTON_COUNT: usize = 15;
+pub const GLFW_GAMEPAD_AXIS_COUNT: usize = 6;
+
+// opaque, we only ever pass these around
+pub enum GLFWwindow {}
+pub enum GLFWmonitor {}
+
+pub type GLFWkeyfun = Option<unsafe extern "C" fn(window: *mut GLFWwindow, key: i32, scancode: i32, action: i32, mods: i32)>;
+pub type GLFWcharfun = Option<unsafe extern "C" fn(window: *mut GLFWwindow, codepoint: u32)>;
+pub type GLFWcursorposfun = Option<unsafe extern "C" fn(window: *mut GLFWwindow, x: f64, y: f64)>;
+pub type GLFWmousebuttonfun = Option<unsafe extern "C" fn(window: *mut GLFWwindow, button: i32, action: i32, mods: i32)>;
+pub type GLFWscrollfun = Option<unsafe extern "C" fn(window: *mut GLFWwindow, x: f64, y: f64)>;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GLFWgamepadstate {
+    pub buttons: [u8; GLFW_GAMEPAD_BUTTON_COUNT],
+    pub axes: [f32; GLFW_GAMEPAD_AXIS_COUNT],
+}
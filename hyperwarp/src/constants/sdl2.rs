@@ -8,6 +8,9 @@ use crate::utils::manual_types::libc::ENUM_TYPE;
 pub const SDL_TRUE: ENUM_TYPE = 1;
 pub const SDL_FALSE: ENUM_TYPE = 0;
 
+// SDL_DYNAPI_VERSION in SDL2's SDL_dynapi.c, bumped only when the table layout breaks
+pub const SDL2_DYNAPI_VERSION: u32 = 1;
+
 // portable key mods, from SDL
 // https://wiki.libsdl.org/SDL2/SDL_Keymod
 
@@ -34,4 +37,18 @@ pub const KMOD_RESERVED: u32 = KMOD_SCROLL; /* "This is for source-level compati
 // the mouse id for touch isn't picked up by bindgen https://github.com/Rust-SDL2/rust-sdl2/blob/dba66e80b14e16de309df49df0c20fdaf35b8c67/src/sdl2/event.rs#L2357
 
 pub const SDL_TOUCH_MOUSEID: u32 = u32::MAX - 1;
-pub const SDL_OUR_FAKE_MOUSEID: u32 = u32::MAX - 2;
\ No newline at end of file
+pub const SDL_OUR_FAKE_MOUSEID: u32 = u32::MAX - 2;
+// https://wiki.libsdl.org/SDL2/SDL_WindowFlags
+pub const SDL_WINDOW_FULLSCREEN: u32 = 0x00000001;
+pub const SDL_WINDOW_HIDDEN: u32 = 0x00000008;
+pub const SDL_WINDOW_FULLSCREEN_DESKTOP: u32 = SDL_WINDOW_FULLSCREEN | 0x00001000;
+
+// SDL_SYSWM_TYPE, SDL 1.2 only ever had x11 so it's the first entry there
+pub const SDL_SYSWM_X11: i32 = 2;
+pub const SDL12_SYSWM_X11: i32 = 0;
+
+// SDL_PIXELFORMAT_RGBA32 on little endian, bytes are r g b a in memory
+pub const SDL_PIXELFORMAT_ABGR8888: u32 = 376840196;
+pub const SDL_PIXELFORMAT_XBGR8888: u32 = 374740996;
+pub const SDL_PIXELFORMAT_ARGB8888: u32 = 372645892;
+pub const SDL_PIXELFORMAT_XRGB8888: u32 = 370546692;
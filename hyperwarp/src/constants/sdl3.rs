@@ -0,0 +1,193 @@
+use libc::{c_char, c_int, c_void};
+
+// SDL3 reworked most of the structs we poke at, these are written from the SDL 3.2 headers
+// https://github.com/libsdl-org/SDL/blob/main/docs/README-migration.md
+
+// SDL_DYNAPI_VERSION in SDL3's SDL_dynapi.c, see SDL2_DYNAPI_VERSION
+pub const SDL3_DYNAPI_VERSION: u32 = 2;
+
+pub const SDL3_LIBRARY_NAME: &[u8] = b"libSDL3.so.0\0";
+pub const SDL2_LIBRARY_NAME: &[u8] = b"libSDL2-2.0.so.0\0";
+// sdl2-compat versions itself 2.x.50 and up
+pub const SDL2_COMPAT_MIN_PATCH: u8 = 50;
+
+// https://wiki.libsdl.org/SDL3/SDL_WindowFlags, a Uint64 now
+pub const SDL3_WINDOW_FULLSCREEN: u64 = 0x0000000000000001;
+pub const SDL3_WINDOW_HIDDEN: u64 = 0x0000000000000008;
+
+// https://wiki.libsdl.org/SDL3/SDL_EventType
+pub const SDL3_EVENT_WINDOW_SHOWN: u32 = 0x202;
+pub const SDL3_EVENT_WINDOW_HIDDEN: u32 = 0x203;
+pub const SDL3_EVENT_WINDOW_PIXEL_SIZE_CHANGED: u32 = 0x207;
+pub const SDL3_EVENT_WINDOW_MINIMIZED: u32 = 0x209;
+pub const SDL3_EVENT_WINDOW_FOCUS_GAINED: u32 = 0x20E;
+pub const SDL3_EVENT_WINDOW_FOCUS_LOST: u32 = 0x20F;
+pub const SDL3_EVENT_KEY_DOWN: u32 = 0x300;
+pub const SDL3_EVENT_KEY_UP: u32 = 0x301;
+pub const SDL3_EVENT_MOUSE_MOTION: u32 = 0x400;
+pub const SDL3_EVENT_MOUSE_BUTTON_DOWN: u32 = 0x401;
+pub const SDL3_EVENT_MOUSE_BUTTON_UP: u32 = 0x402;
+pub const SDL3_EVENT_MOUSE_WHEEL: u32 = 0x403;
+
+pub const SDL3_BUTTON_LEFT: u8 = 1;
+pub const SDL3_BUTTON_MIDDLE: u8 = 2;
+pub const SDL3_BUTTON_RIGHT: u8 = 3;
+pub const SDL3_BUTTON_X1: u8 = 4;
+pub const SDL3_BUTTON_X2: u8 = 5;
+
+pub const SDL3_MOUSEWHEEL_NORMAL: u32 = 0;
+
+pub const SDL3_JOYSTICK_TYPE_GAMEPAD: u16 = 1;
+
+pub type SDL3_WindowID = u32;
+pub type SDL3_JoystickID = u32;
+
+pub enum SDL3_Window {}
+pub enum SDL3_Renderer {}
+pub enum SDL3_Joystick {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SDL3_CommonEvent {
+    pub type_: u32,
+    pub reserved: u32,
+    // nanoseconds, SDL_PushEvent fills it in when it's 0
+    pub timestamp: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SDL3_WindowEvent {
+    pub type_: u32,
+    pub reserved: u32,
+    pub timestamp: u64,
+    pub windowID: SDL3_WindowID,
+    pub data1: i32,
+    pub data2: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SDL3_KeyboardEvent {
+    pub type_: u32,
+    pub reserved: u32,
+    pub timestamp: u64,
+    pub windowID: SDL3_WindowID,
+    pub which: u32,
+    pub scancode: u32,
+    pub key: u32,
+    pub mod_: u16,
+    pub raw: u16,
+    pub down: bool,
+    pub repeat: bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SDL3_MouseMotionEvent {
+    pub type_: u32,
+    pub reserved: u32,
+    pub timestamp: u64,
+    pub windowID: SDL3_WindowID,
+    pub which: u32,
+    pub state: u32,
+    pub x: f32,
+    pub y: f32,
+    pub xrel: f32,
+    pub yrel: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SDL3_MouseButtonEvent {
+    pub type_: u32,
+    pub reserved: u32,
+    pub timestamp: u64,
+    pub windowID: SDL3_WindowID,
+    pub which: u32,
+    pub button: u8,
+    pub down: bool,
+    pub clicks: u8,
+    pub padding: u8,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SDL3_MouseWheelEvent {
+    pub type_: u32,
+    pub reserved: u32,
+    pub timestamp: u64,
+    pub windowID: SDL3_WindowID,
+    pub which: u32,
+    pub x: f32,
+    pub y: f32,
+    pub direction: u32,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+}
+
+// SDL_Event is padded out to 128 bytes
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union SDL3_Event {
+    pub type_: u32,
+    pub common: SDL3_CommonEvent,
+    pub window: SDL3_WindowEvent,
+    pub key: SDL3_KeyboardEvent,
+    pub motion: SDL3_MouseMotionEvent,
+    pub button: SDL3_MouseButtonEvent,
+    pub wheel: SDL3_MouseWheelEvent,
+    pub padding: [u8; 128],
+}
+
+impl SDL3_Event {
+    pub fn zeroed() -> Self {
+        SDL3_Event { padding: [0; 128] }
+    }
+}
+
+// the public part of SDL_Surface, flags and format are enums
+#[repr(C)]
+pub struct SDL3_Surface {
+    pub flags: u32,
+    pub format: u32,
+    pub w: c_int,
+    pub h: c_int,
+    pub pitch: c_int,
+    pub pixels: *mut c_void,
+    pub refcount: c_int,
+    pub reserved: *mut c_void,
+}
+
+// version has to be set to the size of the struct (SDL_INIT_INTERFACE)
+#[repr(C)]
+pub struct SDL3_VirtualJoystickDesc {
+    pub version: u32,
+    pub type_: u16,
+    pub padding: u16,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub naxes: u16,
+    pub nbuttons: u16,
+    pub nballs: u16,
+    pub nhats: u16,
+    pub ntouchpads: u16,
+    pub nsensors: u16,
+    pub padding2: [u16; 2],
+    pub button_mask: u32,
+    pub axis_mask: u32,
+    pub name: *const c_char,
+    pub touchpads: *const c_void,
+    pub sensors: *const c_void,
+    pub userdata: *mut c_void,
+    pub Update: Option<unsafe extern "C" fn(userdata: *mut c_void)>,
+    pub SetPlayerIndex: Option<unsafe extern "C" fn(userdata: *mut c_void, player_index: c_int)>,
+    pub Rumble: Option<unsafe extern "C" fn(userdata: *mut c_void, low_frequency_rumble: u16, high_frequency_rumble: u16) -> bool>,
+    pub RumbleTriggers: Option<unsafe extern "C" fn(userdata: *mut c_void, left_rumble: u16, right_rumble: u16) -> bool>,
+    pub SetLED: Option<unsafe extern "C" fn(userdata: *mut c_void, red: u8, green: u8, blue: u8) -> bool>,
+    pub SendEffect: Option<unsafe extern "C" fn(userdata: *mut c_void, data: *const c_void, size: c_int) -> bool>,
+    pub SetSensorsEnabled: Option<unsafe extern "C" fn(userdata: *mut c_void, enabled: bool) -> bool>,
+    pub Cleanup: Option<unsafe extern "C" fn(userdata: *mut c_void)>,
+}
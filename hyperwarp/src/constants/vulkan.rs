@@ -0,0 +1,364 @@
+// just enough of vulkan_core.h and vk_layer.h for the capture layer in hooks::vulkan
+// dispatchable handles are pointers, non dispatchable ones are u64 everywhere we care about
+
+use libc::{c_char, c_void};
+
+pub type VkResult = i32;
+pub type VkDispatchable = *mut c_void;
+pub type VkHandle = u64;
+
+pub type PFN_vkVoidFunction = Option<unsafe extern "system" fn()>;
+pub type PFN_vkGetInstanceProcAddr = unsafe extern "system" fn(instance: VkDispatchable, name: *const c_char) -> PFN_vkVoidFunction;
+pub type PFN_vkGetDeviceProcAddr = unsafe extern "system" fn(device: VkDispatchable, name: *const c_char) -> PFN_vkVoidFunction;
+pub type PFN_vkSetDeviceLoaderData = unsafe extern "system" fn(device: VkDispatchable, object: *mut c_void) -> VkResult;
+
+pub const VK_SUCCESS: VkResult = 0;
+pub const VK_NOT_READY: VkResult = 1;
+pub const VK_ERROR_INITIALIZATION_FAILED: VkResult = -3;
+
+pub const VK_STRUCTURE_TYPE_SUBMIT_INFO: i32 = 4;
+pub const VK_STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO: i32 = 5;
+pub const VK_STRUCTURE_TYPE_FENCE_CREATE_INFO: i32 = 8;
+pub const VK_STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO: i32 = 9;
+pub const VK_STRUCTURE_TYPE_BUFFER_CREATE_INFO: i32 = 12;
+pub const VK_STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO: i32 = 39;
+pub const VK_STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO: i32 = 40;
+pub const VK_STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO: i32 = 42;
+pub const VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER: i32 = 45;
+pub const VK_STRUCTURE_TYPE_MEMORY_BARRIER: i32 = 46;
+pub const VK_STRUCTURE_TYPE_LOADER_INSTANCE_CREATE_INFO: i32 = 47;
+pub const VK_STRUCTURE_TYPE_LOADER_DEVICE_CREATE_INFO: i32 = 48;
+
+// VkLayerFunction
+pub const VK_LAYER_LINK_INFO: i32 = 0;
+pub const VK_LOADER_DATA_CALLBACK: i32 = 1;
+// VkNegotiateLayerStructType
+pub const LAYER_NEGOTIATE_INTERFACE_STRUCT: i32 = 1;
+
+pub const VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL: i32 = 6;
+pub const VK_IMAGE_LAYOUT_PRESENT_SRC_KHR: i32 = 1000001002;
+
+pub const VK_ACCESS_TRANSFER_READ_BIT: u32 = 0x00000800;
+pub const VK_ACCESS_TRANSFER_WRITE_BIT: u32 = 0x00001000;
+pub const VK_ACCESS_HOST_READ_BIT: u32 = 0x00002000;
+pub const VK_ACCESS_MEMORY_READ_BIT: u32 = 0x00008000;
+
+pub const VK_PIPELINE_STAGE_TRANSFER_BIT: u32 = 0x00001000;
+pub const VK_PIPELINE_STAGE_HOST_BIT: u32 = 0x00004000;
+pub const VK_PIPELINE_STAGE_ALL_COMMANDS_BIT: u32 = 0x00010000;
+
+pub const VK_QUEUE_GRAPHICS_BIT: u32 = 0x00000001;
+pub const VK_QUEUE_COMPUTE_BIT: u32 = 0x00000002;
+pub const VK_QUEUE_TRANSFER_BIT: u32 = 0x00000004;
+
+pub const VK_IMAGE_USAGE_TRANSFER_SRC_BIT: u32 = 0x00000001;
+pub const VK_BUFFER_USAGE_TRANSFER_DST_BIT: u32 = 0x00000002;
+pub const VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT: u32 = 0x00000002;
+pub const VK_MEMORY_PROPERTY_HOST_COHERENT_BIT: u32 = 0x00000004;
+pub const VK_IMAGE_ASPECT_COLOR_BIT: u32 = 0x00000001;
+pub const VK_COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER_BIT: u32 = 0x00000002;
+pub const VK_COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT_BIT: u32 = 0x00000001;
+pub const VK_COMMAND_BUFFER_LEVEL_PRIMARY: i32 = 0;
+pub const VK_SHARING_MODE_EXCLUSIVE: i32 = 0;
+pub const VK_QUEUE_FAMILY_IGNORED: u32 = !0;
+
+pub const VK_FORMAT_R8G8B8A8_UNORM: i32 = 37;
+pub const VK_FORMAT_R8G8B8A8_SRGB: i32 = 43;
+pub const VK_FORMAT_B8G8R8A8_UNORM: i32 = 44;
+pub const VK_FORMAT_B8G8R8A8_SRGB: i32 = 50;
+
+#[repr(C)]
+pub struct VkBaseInStructure {
+    pub s_type: i32,
+    pub p_next: *const VkBaseInStructure,
+}
+
+#[repr(C)]
+pub struct VkLayerInstanceLink {
+    pub p_next: *mut VkLayerInstanceLink,
+    pub pfn_next_get_instance_proc_addr: PFN_vkGetInstanceProcAddr,
+    pub pfn_next_get_physical_device_proc_addr: *mut c_void,
+}
+
+// u is a union in the header, we only ever read the link pointer or the loader data callback out of it
+#[repr(C)]
+pub struct VkLayerInstanceCreateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub function: i32,
+    pub u: *mut c_void,
+}
+
+#[repr(C)]
+pub struct VkLayerDeviceLink {
+    pub p_next: *mut VkLayerDeviceLink,
+    pub pfn_next_get_instance_proc_addr: PFN_vkGetInstanceProcAddr,
+    pub pfn_next_get_device_proc_addr: PFN_vkGetDeviceProcAddr,
+}
+
+#[repr(C)]
+pub struct VkLayerDeviceCreateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub function: i32,
+    pub u: *mut c_void,
+}
+
+#[repr(C)]
+pub struct VkNegotiateLayerInterface {
+    pub s_type: i32,
+    pub p_next: *mut c_void,
+    pub loader_layer_interface_version: u32,
+    pub pfn_get_instance_proc_addr: Option<PFN_vkGetInstanceProcAddr>,
+    pub pfn_get_device_proc_addr: Option<PFN_vkGetDeviceProcAddr>,
+    pub pfn_get_physical_device_proc_addr: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkExtent2D {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkExtent3D {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkOffset3D {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkSwapchainCreateInfoKHR {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub surface: VkHandle,
+    pub min_image_count: u32,
+    pub image_format: i32,
+    pub image_color_space: i32,
+    pub image_extent: VkExtent2D,
+    pub image_array_layers: u32,
+    pub image_usage: u32,
+    pub image_sharing_mode: i32,
+    pub queue_family_index_count: u32,
+    pub p_queue_family_indices: *const u32,
+    pub pre_transform: u32,
+    pub composite_alpha: u32,
+    pub present_mode: i32,
+    pub clipped: u32,
+    pub old_swapchain: VkHandle,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkPresentInfoKHR {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub wait_semaphore_count: u32,
+    pub p_wait_semaphores: *const VkHandle,
+    pub swapchain_count: u32,
+    pub p_swapchains: *const VkHandle,
+    pub p_image_indices: *const u32,
+    pub p_results: *mut VkResult,
+}
+
+#[repr(C)]
+pub struct VkDeviceQueueInfo2 {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub queue_family_index: u32,
+    pub queue_index: u32,
+}
+
+#[repr(C)]
+pub struct VkCommandPoolCreateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub queue_family_index: u32,
+}
+
+#[repr(C)]
+pub struct VkCommandBufferAllocateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub command_pool: VkHandle,
+    pub level: i32,
+    pub command_buffer_count: u32,
+}
+
+#[repr(C)]
+pub struct VkCommandBufferBeginInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub p_inheritance_info: *const c_void,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkImageSubresourceRange {
+    pub aspect_mask: u32,
+    pub base_mip_level: u32,
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkImageSubresourceLayers {
+    pub aspect_mask: u32,
+    pub mip_level: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+}
+
+#[repr(C)]
+pub struct VkMemoryBarrier {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub src_access_mask: u32,
+    pub dst_access_mask: u32,
+}
+
+#[repr(C)]
+pub struct VkImageMemoryBarrier {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub src_access_mask: u32,
+    pub dst_access_mask: u32,
+    pub old_layout: i32,
+    pub new_layout: i32,
+    pub src_queue_family_index: u32,
+    pub dst_queue_family_index: u32,
+    pub image: VkHandle,
+    pub subresource_range: VkImageSubresourceRange,
+}
+
+#[repr(C)]
+pub struct VkBufferImageCopy {
+    pub buffer_offset: u64,
+    pub buffer_row_length: u32,
+    pub buffer_image_height: u32,
+    pub image_subresource: VkImageSubresourceLayers,
+    pub image_offset: VkOffset3D,
+    pub image_extent: VkExtent3D,
+}
+
+#[repr(C)]
+pub struct VkSubmitInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub wait_semaphore_count: u32,
+    pub p_wait_semaphores: *const VkHandle,
+    pub p_wait_dst_stage_mask: *const u32,
+    pub command_buffer_count: u32,
+    pub p_command_buffers: *const VkDispatchable,
+    pub signal_semaphore_count: u32,
+    pub p_signal_semaphores: *const VkHandle,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkQueueFamilyProperties {
+    pub queue_flags: u32,
+    pub queue_count: u32,
+    pub timestamp_valid_bits: u32,
+    pub min_image_transfer_granularity: VkExtent3D,
+}
+
+#[repr(C)]
+pub struct VkXlibSurfaceCreateInfoKHR {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub dpy: *mut c_void,
+    pub window: libc::c_ulong,
+}
+
+#[repr(C)]
+pub struct VkXcbSurfaceCreateInfoKHR {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub connection: *mut c_void,
+    pub window: u32,
+}
+
+#[repr(C)]
+pub struct VkSemaphoreCreateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+}
+
+#[repr(C)]
+pub struct VkFenceCreateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+}
+
+#[repr(C)]
+pub struct VkBufferCreateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub flags: u32,
+    pub size: u64,
+    pub usage: u32,
+    pub sharing_mode: i32,
+    pub queue_family_index_count: u32,
+    pub p_queue_family_indices: *const u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VkMemoryRequirements {
+    pub size: u64,
+    pub alignment: u64,
+    pub memory_type_bits: u32,
+}
+
+#[repr(C)]
+pub struct VkMemoryAllocateInfo {
+    pub s_type: i32,
+    pub p_next: *const c_void,
+    pub allocation_size: u64,
+    pub memory_type_index: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkMemoryType {
+    pub property_flags: u32,
+    pub heap_index: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkMemoryHeap {
+    pub size: u64,
+    pub flags: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VkPhysicalDeviceMemoryProperties {
+    pub memory_type_count: u32,
+    pub memory_types: [VkMemoryType; 32],
+    pub memory_heap_count: u32,
+    pub memory_heaps: [VkMemoryHeap; 16],
+}
@@ -11,4 +11,71 @@ pub struct XWindowChanges {
     pub border_width: libc::c_int,
     pub sibling: Window, // TODO: this is antoher struct
     pub stack_mode: libc::c_ulong,
-}
\ No newline at end of file
+}
+pub const Z_PIXMAP: libc::c_int = 2;
+pub const ALL_PLANES: libc::c_ulong = !0;
+
+// the funcs struct at the end of XImage, only destroy_image is used
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct XImageFuncs {
+    pub create_image: *mut libc::c_void,
+    pub destroy_image: Option<unsafe extern "C" fn(image: *mut XImage) -> libc::c_int>,
+    pub get_pixel: *mut libc::c_void,
+    pub put_pixel: *mut libc::c_void,
+    pub sub_image: *mut libc::c_void,
+    pub add_pixel: *mut libc::c_void,
+}
+
+// https://tronche.com/gui/x/xlib/graphics/images.html
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct XImage {
+    pub width: libc::c_int,
+    pub height: libc::c_int,
+    pub xoffset: libc::c_int,
+    pub format: libc::c_int,
+    pub data: *mut libc::c_char,
+    pub byte_order: libc::c_int,
+    pub bitmap_unit: libc::c_int,
+    pub bitmap_bit_order: libc::c_int,
+    pub bitmap_pad: libc::c_int,
+    pub depth: libc::c_int,
+    pub bytes_per_line: libc::c_int,
+    pub bits_per_pixel: libc::c_int,
+    pub red_mask: libc::c_ulong,
+    pub green_mask: libc::c_ulong,
+    pub blue_mask: libc::c_ulong,
+    pub obdata: *mut libc::c_char,
+    pub f: XImageFuncs,
+}
+
+// https://tronche.com/gui/x/xlib/event-handling/protocol-errors/default-handlers.html
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct XErrorEvent {
+    pub type_: libc::c_int,
+    pub display: *mut libc::c_void,
+    pub resourceid: libc::c_ulong,
+    pub serial: libc::c_ulong,
+    pub error_code: libc::c_uchar,
+    pub request_code: libc::c_uchar,
+    pub minor_code: libc::c_uchar,
+}
+
+pub type XErrorHandler = Option<unsafe extern "C" fn(display: *mut libc::c_void, event: *mut XErrorEvent) -> libc::c_int>;
+
+// error codes that mean the window is gone or changed under us rather than anything being wrong with shm
+pub const BAD_WINDOW: libc::c_uchar = 3;
+pub const BAD_MATCH: libc::c_uchar = 8;
+pub const BAD_DRAWABLE: libc::c_uchar = 9;
+
+// from X11/extensions/XShm.h
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct XShmSegmentInfo {
+    pub shmseg: libc::c_ulong,
+    pub shmid: libc::c_int,
+    pub shmaddr: *mut libc::c_char,
+    pub read_only: libc::c_int,
+}
@@ -1,6 +1,12 @@
 pub mod glfw;
 pub mod glx;
 pub mod sdl2;
+pub mod sdl3;
 pub mod xlib;
 pub mod egl;
-pub mod dlsym;
\ No newline at end of file
+pub mod vulkan;
+pub mod dlopen;
+pub mod exec;
+pub mod dlsym;
+pub mod registry;
+pub mod telemetry;
\ No newline at end of file
@@ -0,0 +1,275 @@
+// dlopen and dlclose, so libraries loaded late (or by a launcher) still get detected and their symbols cached
+// after each one the link map is diffed with dl_iterate_phdr, which also sees the dependencies a dlopen pulled in
+// the real dlopen runs from inside us, so a bare name is searched with our RUNPATH instead of the caller's
+// LD_LIBRARY_PATH and absolute paths behave the same, which covers the launchers we've seen
+
+use std::{ffi::{CStr, CString}, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Mutex}};
+
+use lazy_static::lazy_static;
+use libc::{c_char, c_int, c_void};
+
+use crate::{constants::Library, host::{feature_flags::FeatureFlags, hosting::HOST}, utils::{pointer::Pointer, sdl2_dynapi::DYNAPI_FUNCS}};
+
+use super::{dlsym::{init_if_needed, odlsym, DLSYM_CACHE}, registry::{self, HOOKS}};
+use stellar_shared::log_debug;
+
+pub struct KnownLibrary {
+    // matched against the start of the file name
+    pub soname: &'static str,
+    // hooks registered for this library get their real pointers cached from the new handle
+    pub library: Option<Library>,
+    // cached on top of the hooks, the binds can't reach a privately loaded library through RTLD_NEXT
+    pub extra_symbols: &'static [&'static str],
+    pub detect: fn(&FeatureFlags),
+}
+
+// the sdl and glfw flags also switch on input injection, so they only go on when their hooks are allowed to run
+fn detect_sdl2(features: &FeatureFlags) {
+    if HOST.config.enable_sdl2 {
+        features.enable_sdl2();
+    }
+}
+
+fn detect_sdl3(features: &FeatureFlags) {
+    if HOST.config.enable_sdl3 {
+        features.enable_sdl3();
+    }
+}
+
+fn detect_glfw(features: &FeatureFlags) {
+    if HOST.config.enable_glfw {
+        features.enable_glfw();
+    }
+}
+
+pub static KNOWN_LIBRARIES: [KnownLibrary; 9] = [
+    KnownLibrary { soname: "libSDL2-2.0.so", library: Some(Library::SDL2), extra_symbols: &DYNAPI_FUNCS, detect: detect_sdl2 },
+    KnownLibrary { soname: "libSDL3.so", library: Some(Library::SDL3), extra_symbols: &[], detect: detect_sdl3 },
+    KnownLibrary { soname: "libglfw.so", library: Some(Library::GLFW), extra_symbols: &[], detect: detect_glfw },
+    KnownLibrary { soname: "libX11.so", library: Some(Library::Xlib), extra_symbols: &[], detect: FeatureFlags::enable_x11 },
+    KnownLibrary { soname: "libGLX.so", library: Some(Library::GLX), extra_symbols: &[], detect: FeatureFlags::enable_glx },
+    KnownLibrary { soname: "libGL.so", library: Some(Library::GLX), extra_symbols: &[], detect: FeatureFlags::enable_gl },
+    KnownLibrary { soname: "libOpenGL.so", library: None, extra_symbols: &[], detect: FeatureFlags::enable_gl },
+    KnownLibrary { soname: "libEGL.so", library: Some(Library::EGL), extra_symbols: &["eglQuerySurface"], detect: FeatureFlags::enable_egl },
+    KnownLibrary { soname: "libvulkan.so", library: None, extra_symbols: &[], detect: FeatureFlags::enable_vulkan },
+];
+
+pub fn find_known_library(path: &str) -> Option<&'static KnownLibrary> {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    KNOWN_LIBRARIES.iter().find(|known| file_name.starts_with(known.soname))
+}
+
+pub struct LoadedLibrary {
+    pub path: String,
+    // already in the link map when we first looked
+    pub startup: bool,
+    pub known: Option<&'static KnownLibrary>,
+    detected: bool,
+    // what we put in the dlsym cache, dropped again when the library goes away
+    cached: Vec<(String, Pointer)>,
+}
+
+lazy_static! {
+    static ref LOADED_LIBRARIES: Mutex<Vec<LoadedLibrary>> = Mutex::new(Vec::new());
+    // held for a whole poll so two threads don't both add the same library
+    static ref POLL_LOCK: Mutex<()> = Mutex::new(());
+}
+
+// libraries can load while HOST is still being created, feature detection waits for premain
+static HOST_READY: AtomicBool = AtomicBool::new(false);
+static POLLED: AtomicBool = AtomicBool::new(false);
+static LAST_ADDS: AtomicU64 = AtomicU64::new(0);
+static LAST_SUBS: AtomicU64 = AtomicU64::new(0);
+
+unsafe fn cache_library_symbols(handle: *mut c_void, known: &KnownLibrary) -> Vec<(String, Pointer)> {
+    let hooked = known.library.iter().flat_map(|library| {
+        HOOKS.entries().iter().filter(move |entry| entry.library == *library).map(|entry| entry.symbol)
+    });
+    let mut cached = Vec::new();
+    let mut cache = DLSYM_CACHE.lock().unwrap();
+    for symbol in hooked.chain(known.extra_symbols.iter().copied()) {
+        if cache.contains_key(symbol) {
+            continue;
+        }
+        let symbol_cstring = CString::new(symbol).unwrap();
+        let symbol_pointer = odlsym(handle, symbol_cstring.as_ptr());
+        if !symbol_pointer.is_null() {
+            cache.insert(symbol.to_string(), Pointer(symbol_pointer));
+            cached.push((symbol.to_string(), Pointer(symbol_pointer)));
+        }
+    }
+    cached
+}
+
+// the caller's dlopen already succeeded, NOLOAD with the full path from the link map only borrows a reference
+// straight to the real functions, going through our exports would poll again from inside this poll
+unsafe fn cache_loaded_library(path: &str, known: &KnownLibrary) -> Vec<(String, Pointer)> {
+    let path_cstring = match CString::new(path) {
+        Ok(path_cstring) => path_cstring,
+        Err(_) => return Vec::new(),
+    };
+    let handle = crate::hw_real!(dlopen: unsafe extern "C" fn(*const c_char, c_int) -> *mut c_void)(path_cstring.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD);
+    if handle.is_null() {
+        // unloaded again between the poll and now
+        return Vec::new();
+    }
+    init_if_needed();
+    let cached = cache_library_symbols(handle, known);
+    crate::hw_real!(dlclose: unsafe extern "C" fn(*mut c_void) -> c_int)(handle);
+    cached
+}
+
+fn on_library_opened(path: String, startup: bool) {
+    let known = find_known_library(&path);
+    let cached = match known {
+        Some(known) => unsafe { cache_loaded_library(&path, known) },
+        None => Vec::new(),
+    };
+    log_debug!(Hooks, "libraries: loaded {} (known: {}, cached {} symbols)", path, known.is_some(), cached.len());
+    LOADED_LIBRARIES.lock().unwrap().push(LoadedLibrary {
+        path,
+        startup,
+        known,
+        detected: false,
+        cached,
+    });
+}
+
+fn on_library_closed(library: LoadedLibrary) {
+    log_debug!(Hooks, "libraries: unloaded {}", library.path);
+    let mut cache = DLSYM_CACHE.lock().unwrap();
+    for (symbol, pointer) in library.cached {
+        // only if nothing replaced it since
+        if cache.get(&symbol).is_some_and(|current| current.0 == pointer.0) {
+            cache.remove(&symbol);
+        }
+    }
+}
+
+// flips feature flags for every known library that hasn't been counted yet
+fn apply_detection() {
+    if !HOST_READY.load(Ordering::Relaxed) {
+        return;
+    }
+    let detections: Vec<fn(&FeatureFlags)> = {
+        let mut loaded = LOADED_LIBRARIES.lock().unwrap();
+        loaded.iter_mut()
+            .filter(|library| !library.detected)
+            .filter_map(|library| {
+                library.detected = true;
+                library.known.map(|known| known.detect)
+            })
+            .collect()
+    };
+    for detect in detections {
+        detect(&HOST.features);
+    }
+}
+
+struct LinkMapScan {
+    changed: bool,
+    paths: Vec<String>,
+}
+
+unsafe extern "C" fn scan_link_map(info: *mut libc::dl_phdr_info, size: libc::size_t, data: *mut c_void) -> c_int {
+    let scan = &mut *(data as *mut LinkMapScan);
+    // the counters are the same for every entry, so the first one says whether anything changed
+    if scan.paths.is_empty() && !scan.changed {
+        let has_counters = size >= std::mem::size_of::<libc::dl_phdr_info>();
+        let adds = if has_counters { (*info).dlpi_adds } else { 0 };
+        let subs = if has_counters { (*info).dlpi_subs } else { 0 };
+        let unchanged = has_counters
+            && POLLED.load(Ordering::Relaxed)
+            && LAST_ADDS.load(Ordering::Relaxed) == adds
+            && LAST_SUBS.load(Ordering::Relaxed) == subs;
+        if unchanged {
+            return 1;
+        }
+        LAST_ADDS.store(adds, Ordering::Relaxed);
+        LAST_SUBS.store(subs, Ordering::Relaxed);
+        scan.changed = true;
+    }
+    let name = (*info).dlpi_name;
+    if !name.is_null() && *name != 0 {
+        scan.paths.push(CStr::from_ptr(name).to_string_lossy().into_owned());
+    }
+    0
+}
+
+// cheap when nothing was loaded or unloaded since the last call
+// only the dlopen and dlclose hooks and premain call this, so by the time dlopen returns the library is recorded
+pub fn poll_libraries() {
+    let _poll = POLL_LOCK.lock().unwrap();
+    let mut scan = LinkMapScan { changed: false, paths: Vec::new() };
+    unsafe {
+        libc::dl_iterate_phdr(Some(scan_link_map), &mut scan as *mut LinkMapScan as *mut c_void);
+    }
+    if !scan.changed {
+        return;
+    }
+    let startup = !POLLED.swap(true, Ordering::Relaxed);
+
+    let (closed, opened) = {
+        let mut loaded = LOADED_LIBRARIES.lock().unwrap();
+        let mut closed = Vec::new();
+        let mut index = 0;
+        while index < loaded.len() {
+            if scan.paths.contains(&loaded[index].path) {
+                index += 1;
+            } else {
+                closed.push(loaded.remove(index));
+            }
+        }
+        let opened: Vec<String> = scan.paths.into_iter()
+            .filter(|path| !loaded.iter().any(|library| &library.path == path))
+            .collect();
+        (closed, opened)
+    };
+    for library in closed {
+        on_library_closed(library);
+    }
+    // outside the list lock, dlopen with NOLOAD takes the loader lock
+    for path in opened {
+        on_library_opened(path, startup);
+    }
+    apply_detection();
+}
+
+crate::hw_hooks! {
+    library: Library::CNative, gate: registry::always;
+
+    // no track_hook in these, they run from other libraries' constructors before HOST exists
+    unsafe fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void => dlopen_first {
+        let handle = real()(filename, flags);
+        if !handle.is_null() {
+            poll_libraries();
+        }
+        handle
+    }
+
+    unsafe fn dlclose(handle: *mut c_void) -> c_int => dlclose_first {
+        let result = real()(handle);
+        if result == 0 {
+            poll_libraries();
+        }
+        result
+    }
+}
+
+// called from premain, counts whatever was linked in or loaded before HOST existed
+pub fn on_host_ready() {
+    HOST_READY.store(true, Ordering::Relaxed);
+    poll_libraries();
+    apply_detection();
+}
+
+pub fn create_debug_output() -> String {
+    poll_libraries();
+    let loaded = LOADED_LIBRARIES.lock().unwrap();
+    let mut output = format!("Loaded Libraries ({}):\n", loaded.len());
+    for library in loaded.iter() {
+        let source = if library.startup { "startup" } else { "loaded later" };
+        output += &format!("  {} ({}, known as {:?}, {} cached symbols)\n", library.path, source, library.known.map(|known| known.soname), library.cached.len());
+    }
+    output
+}
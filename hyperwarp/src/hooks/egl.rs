@@ -1,29 +1,283 @@
-use libc::{c_char, c_void};
+use std::{collections::HashMap, ffi::CStr, sync::Mutex};
 
-use crate::host::hosting::HOST;
+use lazy_static::lazy_static;
+use libc::{c_char, c_ulong, c_void};
+use stellar_protocol::protocol::GraphicsAPI;
+
+use crate::{
+    constants::{
+        egl::{EGL_HEIGHT, EGL_PLATFORM_X11_KHR, EGL_PLATFORM_XCB_EXT, EGL_TRUE, EGL_WIDTH},
+        Library,
+    },
+    host::{hosting::HOST, window::Window},
+    utils::{config::Config, pointer::Pointer},
+};
+
+use super::{dlsym::{query_dlsym_cache, DLSYM_CACHE}, glfw, registry, sdl3};
+use stellar_shared::log_debug;
 
 // types
 type EGLDisplay = *const c_void;
 type EGLSurface = *const c_void;
+type EGLConfig = *const c_void;
+type EGLBoolean = u32;
+type EGLint = i32;
+type EGLAttrib = isize;
+type EGLenum = u32;
+// an x window id on x11, a pointer on everything else, pointer sized either way
+type EGLNativeWindowType = usize;
+
+struct EglSurface {
+    display: usize,
+    window_id: usize,
+    // we registered the window for this surface, as opposed to reusing an xlib window we already knew
+    owned: bool,
+    // what the host was last told, swaps only go to the host when the surface size moves away from this
+    size: Option<(u32, u32)>,
+}
+
+lazy_static! {
+    // display to the platform it was opened for, None when it came from eglGetDisplay
+    static ref EGL_DISPLAYS: Mutex<HashMap<usize, Option<EGLenum>>> = Mutex::new(HashMap::new());
+    static ref EGL_SURFACES: Mutex<HashMap<usize, EglSurface>> = Mutex::new(HashMap::new());
+    // pbuffer to its display, they aren't windows and swapping one does nothing so their swaps are never captured
+    static ref EGL_PBUFFERS: Mutex<HashMap<usize, usize>> = Mutex::new(HashMap::new());
+}
+
+fn mark_egl_used() {
+    HOST.features.enable_egl();
+    HOST.suggest_graphics_api(GraphicsAPI::OpenGL);
+}
+
+// goes through the dlsym cache since the app may have dlopened libEGL privately
+unsafe fn query_surface_size(display: EGLDisplay, surface: EGLSurface) -> Option<(u32, u32)> {
+    let func = match query_dlsym_cache("eglQuerySurface") {
+        Some(func) => func.0,
+        // linked normally, goes through our dlsym and lands in the cache for next time
+        None => libc::dlsym(libc::RTLD_NEXT, b"eglQuerySurface\0".as_ptr() as *const c_char) as *const c_void,
+    };
+    if func.is_null() {
+        return None;
+    }
+    let query: unsafe extern "C" fn(EGLDisplay, EGLSurface, EGLint, *mut EGLint) -> EGLBoolean = std::mem::transmute(func);
+    let (mut width, mut height) = (0, 0);
+    if query(display, surface, EGL_WIDTH, &mut width) != EGL_TRUE || query(display, surface, EGL_HEIGHT, &mut height) != EGL_TRUE {
+        return None;
+    }
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    Some((width as u32, height as u32))
+}
 
-// extern void glXSwapBuffers( Display *dpy, GLXDrawable drawable );
-redhook::hook! {
-    unsafe fn eglSwapBuffers(name: EGLDisplay, surface: EGLSurface) => gl_x_swap_buffers {
-        if HOST.config.enable_x11 && HOST.config.enable_glx {
-            // HOST.test();
-            {
-                let mut features = HOST.features.lock().unwrap();
+// the x window behind a platform surface's native window pointer, gbm and wayland pointers have no id we'd know
+unsafe fn platform_native_window_id(display: EGLDisplay, native_window: *mut c_void) -> Option<usize> {
+    if native_window.is_null() {
+        return None;
+    }
+    let platform = EGL_DISPLAYS.lock().unwrap().get(&(display as usize)).copied().flatten();
+    match platform {
+        Some(EGL_PLATFORM_X11_KHR) => Some(*(native_window as *const c_ulong) as usize),
+        Some(EGL_PLATFORM_XCB_EXT) => Some(*(native_window as *const u32) as usize),
+        _ => None,
+    }
+}
+
+unsafe fn register_surface(display: EGLDisplay, surface: EGLSurface, native_window_id: Option<usize>) {
+    if surface.is_null() {
+        return;
+    }
+    mark_egl_used();
+    let size = query_surface_size(display, surface);
+    // on x11 the window usually went through our xlib hooks already, keep a single entry for it
+    let existing = native_window_id.filter(|id| HOST.get_behavior().get_window_info(*id).is_some());
+    let tracked = match existing {
+        // the xlib entry may be out of date, the first swap brings it in line with the surface
+        Some(window_id) => EglSurface { display: display as usize, window_id, owned: false, size: None },
+        None => {
+            // gbm, wayland or a window we never saw created
+            let window = Window::new(surface as usize, Library::EGL);
+            HOST.onWindowCreate(window, None, None, size.map(|s| s.0), size.map(|s| s.1));
+            EglSurface { display: display as usize, window_id: surface as usize, owned: true, size }
+        }
+    };
+    log_debug!(Hooks, "egl surface {} tracked as window {} ({:?})", surface as usize, tracked.window_id, size);
+    EGL_SURFACES.lock().unwrap().insert(surface as usize, tracked);
+}
+
+unsafe fn unregister_surface(surface: usize) {
+    EGL_PBUFFERS.lock().unwrap().remove(&surface);
+    let tracked = EGL_SURFACES.lock().unwrap().remove(&surface);
+    if let Some(tracked) = tracked {
+        if tracked.owned {
+            HOST.onWindowDestroy(tracked.window_id);
+        }
+    }
+}
+
+fn is_pbuffer(surface: EGLSurface) -> bool {
+    EGL_PBUFFERS.lock().unwrap().contains_key(&(surface as usize))
+}
+
+// returns the window id to hand to onFrameSwapBegin
+unsafe fn before_swap(display: EGLDisplay, surface: EGLSurface) -> Option<usize> {
+    mark_egl_used();
+    let (window_id, cached) = {
+        let surfaces = EGL_SURFACES.lock().unwrap();
+        let tracked = surfaces.get(&(surface as usize))?;
+        (tracked.window_id, tracked.size)
+    };
+    // native windows get resized behind our back, the surface always has the real size
+    if let Some((width, height)) = query_surface_size(display, surface) {
+        if cached != Some((width, height)) {
+            if let Some(tracked) = EGL_SURFACES.lock().unwrap().get_mut(&(surface as usize)) {
+                tracked.size = Some((width, height));
             }
+            HOST.onWindowResize(window_id, width, height);
+        }
+    }
+    Some(window_id)
+}
+
+// every swap entry point goes through here so the damage variants get the same guards as eglSwapBuffers
+unsafe fn capture_swap(display: EGLDisplay, surface: EGLSurface, name: &str, swap: impl FnOnce() -> EGLBoolean) -> EGLBoolean {
+    // glfwSwapBuffers and SDL3 capture the frame themselves
+    if HOST.config.enable_egl && !glfw::in_glfw_swap() && !sdl3::in_sdl3_swap() && !registry::is_disabled(&HOST.config, name) && !is_pbuffer(surface) {
+        let window_id = before_swap(display, surface);
+        HOST.onFrameSwapBegin(window_id);
+        let result = swap();
+        HOST.onFrameSwapEnd();
+        result
+    } else {
+        swap()
+    }
+}
 
-            HOST.onFrameSwapBegin();            
-            let result = redhook::real!(eglSwapBuffers)(name, surface);
-            HOST.onFrameSwapEnd();
-            result
-        } else {
-            if HOST.config.debug_mode {
-                // println!("Attempted to open {}", name);
+crate::hw_hooks! {
+    // eglGetProcAddress always goes through so the cache gets the extension pointers
+    library: Library::EGL, gate: egl_enabled;
+
+    unsafe fn eglGetDisplay(native_display: *mut c_void) -> EGLDisplay => egl_get_display_first {
+        crate::track_hook!("eglGetDisplay");
+        let display = real()(native_display);
+        if HOST.config.enable_egl && !display.is_null() {
+            EGL_DISPLAYS.lock().unwrap().entry(display as usize).or_insert(None);
+        }
+        display
+    }
+
+    unsafe fn eglGetPlatformDisplay(platform: EGLenum, native_display: *mut c_void, attrib_list: *const EGLAttrib) -> EGLDisplay => egl_get_platform_display_first {
+        crate::track_hook!("eglGetPlatformDisplay");
+        let display = real()(platform, native_display, attrib_list);
+        if HOST.config.enable_egl && !display.is_null() {
+            log_debug!(Hooks, "egl platform display {} for platform {:#x}", display as usize, platform);
+            EGL_DISPLAYS.lock().unwrap().insert(display as usize, Some(platform));
+        }
+        display
+    }
+
+    unsafe fn eglGetPlatformDisplayEXT(platform: EGLenum, native_display: *mut c_void, attrib_list: *const EGLint) -> EGLDisplay => egl_get_platform_display_ext_first {
+        crate::track_hook!("eglGetPlatformDisplayEXT");
+        let display = real()(platform, native_display, attrib_list);
+        if HOST.config.enable_egl && !display.is_null() {
+            EGL_DISPLAYS.lock().unwrap().insert(display as usize, Some(platform));
+        }
+        display
+    }
+
+    unsafe fn eglCreateWindowSurface(display: EGLDisplay, config: EGLConfig, native_window: EGLNativeWindowType, attrib_list: *const EGLint) -> EGLSurface => egl_create_window_surface_first {
+        crate::track_hook!("eglCreateWindowSurface");
+        let surface = real()(display, config, native_window, attrib_list);
+        if HOST.config.enable_egl {
+            // passed by value so on x11 this is the window id itself, looking it up is harmless on other platforms
+            register_surface(display, surface, Some(native_window));
+        }
+        surface
+    }
+
+    unsafe fn eglCreatePlatformWindowSurface(display: EGLDisplay, config: EGLConfig, native_window: *mut c_void, attrib_list: *const EGLAttrib) -> EGLSurface => egl_create_platform_window_surface_first {
+        crate::track_hook!("eglCreatePlatformWindowSurface");
+        let surface = real()(display, config, native_window, attrib_list);
+        if HOST.config.enable_egl {
+            register_surface(display, surface, platform_native_window_id(display, native_window));
+        }
+        surface
+    }
+
+    unsafe fn eglCreatePlatformWindowSurfaceEXT(display: EGLDisplay, config: EGLConfig, native_window: *mut c_void, attrib_list: *const EGLint) -> EGLSurface => egl_create_platform_window_surface_ext_first {
+        crate::track_hook!("eglCreatePlatformWindowSurfaceEXT");
+        let surface = real()(display, config, native_window, attrib_list);
+        if HOST.config.enable_egl {
+            register_surface(display, surface, platform_native_window_id(display, native_window));
+        }
+        surface
+    }
+
+    // only remembered so swaps on them can be told apart from swaps on windows we never saw created
+    unsafe fn eglCreatePbufferSurface(display: EGLDisplay, config: EGLConfig, attrib_list: *const EGLint) -> EGLSurface => egl_create_pbuffer_surface_first {
+        crate::track_hook!("eglCreatePbufferSurface");
+        let surface = real()(display, config, attrib_list);
+        if HOST.config.enable_egl && !surface.is_null() {
+            EGL_PBUFFERS.lock().unwrap().insert(surface as usize, display as usize);
+        }
+        surface
+    }
+
+    unsafe fn eglDestroySurface(display: EGLDisplay, surface: EGLSurface) -> EGLBoolean => egl_destroy_surface_first {
+        crate::track_hook!("eglDestroySurface");
+        if HOST.config.enable_egl {
+            unregister_surface(surface as usize);
+        }
+        real()(display, surface)
+    }
+
+    unsafe fn eglTerminate(display: EGLDisplay) -> EGLBoolean => egl_terminate_first {
+        crate::track_hook!("eglTerminate");
+        if HOST.config.enable_egl {
+            // terminating destroys every surface the app didn't
+            let surfaces: Vec<usize> = EGL_SURFACES.lock().unwrap().iter().filter(|(_, tracked)| tracked.display == display as usize).map(|(surface, _)| *surface).collect();
+            for surface in surfaces {
+                unregister_surface(surface);
             }
-            // std::ptr::null()
+            EGL_PBUFFERS.lock().unwrap().retain(|_, pbuffer_display| *pbuffer_display != display as usize);
+            EGL_DISPLAYS.lock().unwrap().remove(&(display as usize));
         }
+        real()(display)
+    }
+
+    unsafe fn eglSwapBuffers(display: EGLDisplay, surface: EGLSurface) -> EGLBoolean => egl_swap_buffers_first {
+        crate::track_hook!("eglSwapBuffers");
+        capture_swap(display, surface, "eglSwapBuffers", || real()(display, surface))
     }
+
+    unsafe fn eglSwapBuffersWithDamageKHR(display: EGLDisplay, surface: EGLSurface, rects: *const EGLint, n_rects: EGLint) -> EGLBoolean => egl_swap_buffers_with_damage_khr_first {
+        crate::track_hook!("eglSwapBuffersWithDamageKHR");
+        capture_swap(display, surface, "eglSwapBuffersWithDamageKHR", || real()(display, surface, rects, n_rects))
+    }
+
+    unsafe fn eglSwapBuffersWithDamageEXT(display: EGLDisplay, surface: EGLSurface, rects: *const EGLint, n_rects: EGLint) -> EGLBoolean => egl_swap_buffers_with_damage_ext_first {
+        crate::track_hook!("eglSwapBuffersWithDamageEXT");
+        capture_swap(display, surface, "eglSwapBuffersWithDamageEXT", || real()(display, surface, rects, n_rects))
+    }
+
+    // extension entry points (and some engines' core ones) only come from here
+    #[gate(registry::always)]
+    unsafe fn eglGetProcAddress(name: *const c_char) -> *mut c_void => egl_get_proc_address_first {
+        crate::track_hook!("eglGetProcAddress");
+        let func = real()(name);
+        if func.is_null() || name.is_null() {
+            return func;
+        }
+        let func_name = match CStr::from_ptr(name).to_str() {
+            Ok(func_name) => func_name,
+            Err(_) => return func,
+        };
+        // the _hw_direct lookups fall back to the cache for functions libEGL doesn't export
+        DLSYM_CACHE.lock().unwrap().insert(func_name.to_owned(), Pointer(func));
+        registry::lookup(func_name).unwrap_or(func)
+    }
+}
+
+fn egl_enabled(config: &Config) -> bool {
+    config.enable_egl
 }
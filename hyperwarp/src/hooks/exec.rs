@@ -0,0 +1,103 @@
+// hands HW_TARGET_PID to children of the first target through the environment they're started with,
+// setenv on ourselves isn't safe once the game has threads
+// only calls that reach us through the PLT or dlsym are seen, glibc's execl* and system() go straight to its own execve
+
+use libc::{c_char, c_int, pid_t, posix_spawn_file_actions_t, posix_spawnattr_t};
+
+use crate::{constants::Library, host::targeting};
+
+extern "C" {
+    static environ: *const *const c_char;
+}
+
+// envp plus our entry, the CString it points into lives in targeting
+fn with_target_pid(envp: *const *const c_char) -> Option<Vec<*const c_char>> {
+    let entry = targeting::child_target_env()?;
+    let mut variables = Vec::new();
+    if !envp.is_null() {
+        let mut index = 0;
+        loop {
+            let variable = unsafe { *envp.add(index) };
+            if variable.is_null() {
+                break;
+            }
+            // already set, either by us before an exec or by whoever started the child
+            if unsafe { std::ffi::CStr::from_ptr(variable) }.to_bytes().starts_with(targeting::TARGET_PID_ENV_PREFIX.as_bytes()) {
+                return None;
+            }
+            variables.push(variable);
+            index += 1;
+        }
+    }
+    variables.push(entry.as_ptr());
+    variables.push(std::ptr::null());
+    Some(variables)
+}
+
+crate::hw_hooks! {
+    library: Library::CNative, gate: targeting::tracks_process_tree;
+
+    unsafe fn execve(path: *const c_char, argv: *const *const c_char, envp: *const *const c_char) -> c_int => execve_first {
+        match with_target_pid(envp) {
+            Some(variables) => real()(path, argv, variables.as_ptr()),
+            None => real()(path, argv, envp),
+        }
+    }
+
+    unsafe fn execvpe(file: *const c_char, argv: *const *const c_char, envp: *const *const c_char) -> c_int => execvpe_first {
+        match with_target_pid(envp) {
+            Some(variables) => real()(file, argv, variables.as_ptr()),
+            None => real()(file, argv, envp),
+        }
+    }
+
+    unsafe fn fexecve(fd: c_int, argv: *const *const c_char, envp: *const *const c_char) -> c_int => fexecve_first {
+        match with_target_pid(envp) {
+            Some(variables) => real()(fd, argv, variables.as_ptr()),
+            None => real()(fd, argv, envp),
+        }
+    }
+
+    // the environ forms become their envp versions when there's something to add
+    unsafe fn execv(path: *const c_char, argv: *const *const c_char) -> c_int => execv_first {
+        match with_target_pid(environ) {
+            Some(variables) => crate::hw_real!(execve: unsafe extern "C" fn(*const c_char, *const *const c_char, *const *const c_char) -> c_int)(path, argv, variables.as_ptr()),
+            None => real()(path, argv),
+        }
+    }
+
+    unsafe fn execvp(file: *const c_char, argv: *const *const c_char) -> c_int => execvp_first {
+        match with_target_pid(environ) {
+            Some(variables) => crate::hw_real!(execvpe: unsafe extern "C" fn(*const c_char, *const *const c_char, *const *const c_char) -> c_int)(file, argv, variables.as_ptr()),
+            None => real()(file, argv),
+        }
+    }
+
+    unsafe fn posix_spawn(
+        pid: *mut pid_t,
+        path: *const c_char,
+        file_actions: *const posix_spawn_file_actions_t,
+        attrp: *const posix_spawnattr_t,
+        argv: *const *mut c_char,
+        envp: *const *mut c_char
+    ) -> c_int => posix_spawn_first {
+        match with_target_pid(envp as *const *const c_char) {
+            Some(variables) => real()(pid, path, file_actions, attrp, argv, variables.as_ptr() as *const *mut c_char),
+            None => real()(pid, path, file_actions, attrp, argv, envp),
+        }
+    }
+
+    unsafe fn posix_spawnp(
+        pid: *mut pid_t,
+        file: *const c_char,
+        file_actions: *const posix_spawn_file_actions_t,
+        attrp: *const posix_spawnattr_t,
+        argv: *const *mut c_char,
+        envp: *const *mut c_char
+    ) -> c_int => posix_spawnp_first {
+        match with_target_pid(envp as *const *const c_char) {
+            Some(variables) => real()(pid, file, file_actions, attrp, argv, variables.as_ptr() as *const *mut c_char),
+            None => real()(pid, file, file_actions, attrp, argv, envp),
+        }
+    }
+}
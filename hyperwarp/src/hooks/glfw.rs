@@ -1,12 +1,603 @@
-use libc::{c_char, c_int, c_void};
+use std::{cell::Cell, collections::{HashMap, HashSet}, ffi::CString, sync::Mutex};
 
-use crate::host::hosting::HOST;
+use lazy_static::lazy_static;
+use libc::{c_char, c_float, c_int, c_uchar, c_ulong, c_void};
+use stellar_protocol::protocol::{GraphicsAPI, InputEvent, InputEventPayload};
+
+use crate::{
+    constants::{
+        glfw::{GLFWcharfun, GLFWcursorposfun, GLFWgamepadstate, GLFWkeyfun, GLFWmonitor, GLFWmousebuttonfun, GLFWscrollfun, GLFWwindow, GLFW_FALSE, GLFW_KEY_LAST, GLFW_KEY_UNKNOWN, GLFW_MOUSE_BUTTON_LAST, GLFW_PRESS, GLFW_RELEASE, GLFW_TRUE},
+        Library, GAMEPAD_NAME,
+    },
+    host::{hosting::HOST, window::Window},
+    platform::glfw::{glfw_buttons_from_browser, glfw_char_for_key, glfw_gamepad_state_from_sdl, glfw_key_from_sdl_keycode, glfw_mods_from_sdl, glfw_scroll_from_browser},
+    utils::{self, config::Config},
+};
+
+use super::dlsym::query_dlsym_cache;
+use stellar_shared::log_debug;
+
+#[derive(Clone, Copy, Default)]
+struct GlfwCallbacks {
+    key: GLFWkeyfun,
+    char: GLFWcharfun,
+    cursor_pos: GLFWcursorposfun,
+    mouse_button: GLFWmousebuttonfun,
+    scroll: GLFWscrollfun,
+}
+
+struct GlfwWindow {
+    window_id: usize,
+    // we registered the window, as opposed to reusing the xlib window glfw made for it
+    owned: bool,
+    callbacks: GlfwCallbacks,
+}
+
+// what the app sees when it polls instead of using callbacks
+struct GlfwInputState {
+    keys: HashSet<i32>,
+    buttons: u8,
+    // in glfw screen coordinates, relative motion keeps going past the window edges like glfw's disabled cursor does
+    cursor: Option<(f64, f64)>,
+}
+
+// glfw hands out pointers that stay valid until the next query, these back the ones for our virtual gamepads
+#[derive(Default)]
+struct GlfwJoystickBuffers {
+    axes: Vec<f32>,
+    buttons: Vec<u8>,
+    name: CString,
+}
+
+lazy_static! {
+    static ref GLFW_WINDOWS: Mutex<HashMap<usize, GlfwWindow>> = Mutex::new(HashMap::new());
+    static ref GLFW_INPUT: Mutex<GlfwInputState> = Mutex::new(GlfwInputState { keys: HashSet::new(), buttons: 0, cursor: None });
+    static ref GLFW_JOYSTICKS: Mutex<HashMap<c_int, GlfwJoystickBuffers>> = Mutex::new(HashMap::new());
+}
+
+thread_local! {
+    static IN_GLFW_SWAP: Cell<bool> = Cell::new(false);
+}
+
+// glfwSwapBuffers already captured the frame, the glx and egl swaps under it should not capture again
+pub fn in_glfw_swap() -> bool {
+    IN_GLFW_SWAP.with(|flag| flag.get())
+}
+
+// glfw may be dlopened privately, same trick as the egl size query
+unsafe fn glfw_symbol(name: &str) -> Option<*const c_void> {
+    if let Some(func) = query_dlsym_cache(name) {
+        return Some(func.0);
+    }
+    let name_cstring = CString::new(name).unwrap();
+    let func = libc::dlsym(libc::RTLD_NEXT, name_cstring.as_ptr()) as *const c_void;
+    if func.is_null() {
+        None
+    } else {
+        Some(func)
+    }
+}
+
+unsafe fn query_size(name: &str, window: *mut GLFWwindow) -> Option<(u32, u32)> {
+    let func: unsafe extern "C" fn(*mut GLFWwindow, *mut c_int, *mut c_int) = std::mem::transmute(glfw_symbol(name)?);
+    let (mut width, mut height) = (0, 0);
+    func(window, &mut width, &mut height);
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    Some((width as u32, height as u32))
+}
+
+// pixels, which is what we capture
+unsafe fn framebuffer_size(window: *mut GLFWwindow) -> Option<(u32, u32)> {
+    query_size("glfwGetFramebufferSize", window)
+}
+
+// glfw screen coordinates over framebuffer pixels, not 1 on hidpi wayland and macos style scaling
+unsafe fn cursor_scale(window: *mut GLFWwindow) -> (f64, f64) {
+    match (query_size("glfwGetWindowSize", window), framebuffer_size(window)) {
+        (Some((width, height)), Some((fb_width, fb_height))) => (width as f64 / fb_width as f64, height as f64 / fb_height as f64),
+        _ => (1.0, 1.0),
+    }
+}
+
+unsafe fn x11_window_id(window: *mut GLFWwindow) -> Option<usize> {
+    // only there when glfw was built with x11 support
+    let func: unsafe extern "C" fn(*mut GLFWwindow) -> c_ulong = std::mem::transmute(glfw_symbol("glfwGetX11Window")?);
+    match func(window) {
+        0 => None,
+        id => Some(id as usize),
+    }
+}
+
+fn mark_glfw_used() {
+    HOST.features.enable_glfw();
+}
+
+unsafe fn register_window(window: *mut GLFWwindow, title: *const c_char) {
+    if window.is_null() {
+        return;
+    }
+    let size = framebuffer_size(window);
+    // on x11 our xlib hooks saw glfw create the window already, keep a single entry for it
+    let existing = x11_window_id(window).filter(|id| HOST.get_behavior().get_window_info(*id).is_some());
+    let tracked = match existing {
+        Some(window_id) => GlfwWindow { window_id, owned: false, callbacks: GlfwCallbacks::default() },
+        None => {
+            let glfw_window = Window::new(window as usize, Library::GLFW).with_title(utils::title_from_cstr(title));
+            HOST.onWindowCreate(glfw_window, None, None, size.map(|s| s.0), size.map(|s| s.1));
+            GlfwWindow { window_id: window as usize, owned: true, callbacks: GlfwCallbacks::default() }
+        }
+    };
+    log_debug!(Hooks, "glfw window {} tracked as window {} ({:?})", window as usize, tracked.window_id, size);
+    GLFW_WINDOWS.lock().unwrap().insert(window as usize, tracked);
+}
+
+unsafe fn unregister_window(window: *mut GLFWwindow) {
+    let tracked = GLFW_WINDOWS.lock().unwrap().remove(&(window as usize));
+    if let Some(tracked) = tracked {
+        if tracked.owned {
+            HOST.onWindowDestroy(tracked.window_id);
+        }
+    }
+}
+
+fn tracked_window_id(window: *mut GLFWwindow) -> Option<usize> {
+    GLFW_WINDOWS.lock().unwrap().get(&(window as usize)).map(|tracked| tracked.window_id)
+}
+
+// the framebuffer is what ends up in the capture, it changes under us when the wm resizes the window
+unsafe fn sync_window_size(window: *mut GLFWwindow) -> Option<usize> {
+    let window_id = tracked_window_id(window)?;
+    if let Some((width, height)) = framebuffer_size(window) {
+        let current = HOST.get_behavior().get_window_info(window_id).map(|info| info.size);
+        if current.is_some() && current != Some((width, height)) {
+            HOST.onWindowResize(window_id, width, height);
+        }
+    }
+    Some(window_id)
+}
+
+fn update_callbacks<T>(window: *mut GLFWwindow, update: impl FnOnce(&mut GlfwCallbacks) -> T) -> Option<T> {
+    GLFW_WINDOWS.lock().unwrap().get_mut(&(window as usize)).map(|tracked| update(&mut tracked.callbacks))
+}
+
+// injected input goes to the window we are capturing, or any glfw window if that isn't one of them
+fn input_target() -> Option<(*mut GLFWwindow, GlfwCallbacks)> {
+    let capture_id = HOST.get_behavior().get_capture_window().map(|window| window.id);
+    let windows = GLFW_WINDOWS.lock().unwrap();
+    windows.iter()
+        .find(|(_, tracked)| Some(tracked.window_id) == capture_id)
+        .or_else(|| windows.iter().next())
+        .map(|(window, tracked)| (*window as *mut GLFWwindow, tracked.callbacks))
+}
+
+fn has_pending_input() -> bool {
+    if !HOST.incoming_input.is_empty() {
+        return true;
+    }
+    let input_manager = HOST.input_manager.lock().unwrap();
+    !input_manager.event_queue.is_empty() || !input_manager.glfw_event_queue.is_empty()
+}
+
+// runs after glfw processed its own events, so ours land in the same frame as if they came from the window system
+unsafe fn deliver_injected_input() {
+    let events: Vec<InputEvent> = {
+        HOST.flush_input();
+        HOST.input_manager.lock().unwrap().take_glfw_events()
+    };
+    if events.is_empty() {
+        return;
+    }
+    let (window, callbacks) = match input_target() {
+        Some(target) => target,
+        None => return,
+    };
+    let (scale_x, scale_y) = cursor_scale(window);
+    for event in events {
+        let mods = glfw_mods_from_sdl(event.context.as_ref().map_or(0, |context| context.modifiers));
+        // no locks are held while the app's callbacks run, they usually poll us right back
+        match event.payload {
+            InputEventPayload::KeyEvent { key, state, .. } => {
+                let (glfw_key, scancode) = glfw_key_from_sdl_keycode(key);
+                if glfw_key == GLFW_KEY_UNKNOWN {
+                    continue;
+                }
+                {
+                    let mut input = GLFW_INPUT.lock().unwrap();
+                    if state {
+                        input.keys.insert(glfw_key);
+                    } else {
+                        input.keys.remove(&glfw_key);
+                    }
+                }
+                let action = if state { GLFW_PRESS } else { GLFW_RELEASE };
+                if let Some(key_callback) = callbacks.key {
+                    key_callback(window, glfw_key, scancode as c_int, action, mods);
+                }
+                if state {
+                    if let (Some(char_callback), Some(codepoint)) = (callbacks.char, glfw_char_for_key(glfw_key, mods)) {
+                        char_callback(window, codepoint);
+                    }
+                }
+            },
+            InputEventPayload::MouseMoveAbsolute(x, y, _, _) => {
+                let position = (x as f64 * scale_x, y as f64 * scale_y);
+                GLFW_INPUT.lock().unwrap().cursor = Some(position);
+                if let Some(cursor_pos_callback) = callbacks.cursor_pos {
+                    cursor_pos_callback(window, position.0, position.1);
+                }
+            },
+            InputEventPayload::MouseMoveRelative { x, y, x_absolute, y_absolute } => {
+                let position = {
+                    let mut input = GLFW_INPUT.lock().unwrap();
+                    let (current_x, current_y) = input.cursor.unwrap_or((x_absolute as f64 * scale_x, y_absolute as f64 * scale_y));
+                    let position = (current_x + x as f64 * scale_x, current_y + y as f64 * scale_y);
+                    input.cursor = Some(position);
+                    position
+                };
+                if let Some(cursor_pos_callback) = callbacks.cursor_pos {
+                    cursor_pos_callback(window, position.0, position.1);
+                }
+            },
+            InputEventPayload::MouseButtonsChange { change, state } => {
+                let buttons = glfw_buttons_from_browser(change);
+                {
+                    let mut input = GLFW_INPUT.lock().unwrap();
+                    if state {
+                        input.buttons |= change;
+                    } else {
+                        input.buttons &= !change;
+                    }
+                }
+                let action = if state { GLFW_PRESS } else { GLFW_RELEASE };
+                if let Some(mouse_button_callback) = callbacks.mouse_button {
+                    for button in buttons {
+                        mouse_button_callback(window, button, action, mods);
+                    }
+                }
+            },
+            InputEventPayload::MouseScroll { x, y } => {
+                let (offset_x, offset_y) = glfw_scroll_from_browser(x, y);
+                if let Some(scroll_callback) = callbacks.scroll {
+                    scroll_callback(window, offset_x, offset_y);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+fn virtual_gamepad_count() -> c_int {
+    HOST.input_manager.lock().unwrap().count_gamepads() as c_int
+}
+
+// virtual gamepads take the first joystick ids, real ones are shifted up behind them
+fn real_joystick_id(jid: c_int) -> c_int {
+    jid - virtual_gamepad_count()
+}
+
+unsafe fn virtual_gamepad_name(jid: c_int) -> Option<*const c_char> {
+    let name = {
+        let input_manager = HOST.input_manager.lock().unwrap();
+        if jid < 0 || jid as usize >= input_manager.count_gamepads() {
+            return None;
+        }
+        input_manager.get_gamepad(jid as usize).map(|gamepad| gamepad.name.clone()).unwrap_or(GAMEPAD_NAME.to_string())
+    };
+    let mut joysticks = GLFW_JOYSTICKS.lock().unwrap();
+    let buffers = joysticks.entry(jid).or_default();
+    if buffers.name.as_bytes() != name.as_bytes() {
+        buffers.name = CString::new(name).unwrap_or_else(|_| CString::new(GAMEPAD_NAME).unwrap());
+    }
+    Some(buffers.name.as_ptr())
+}
+
+crate::hw_hooks! {
+    library: Library::GLFW, gate: glfw_enabled;
 
-redhook::hook! {
     unsafe fn glfwInit() -> c_int => glfw_init_first {
-        if HOST.config.debug_mode {
-            println!("glfwInit called");
+        crate::track_hook!("glfwInit");
+        log_debug!(Hooks, "glfwInit called");
+        if HOST.config.enable_glfw {
+            mark_glfw_used();
+        }
+        real()()
+    }
+
+    unsafe fn glfwCreateWindow(width: c_int, height: c_int, title: *const c_char, monitor: *mut GLFWmonitor, share: *mut GLFWwindow) -> *mut GLFWwindow => glfw_create_window_first {
+        crate::track_hook!("glfwCreateWindow");
+        let window = real()(width, height, title, monitor, share);
+        if HOST.config.enable_glfw {
+            mark_glfw_used();
+            register_window(window, title);
+        }
+        window
+    }
+
+    unsafe fn glfwDestroyWindow(window: *mut GLFWwindow) => glfw_destroy_window_first {
+        crate::track_hook!("glfwDestroyWindow");
+        if HOST.config.enable_glfw {
+            unregister_window(window);
         }
-        redhook::real!(glfwInit)()
+        real()(window);
     }
+
+    unsafe fn glfwSetWindowSize(window: *mut GLFWwindow, width: c_int, height: c_int) => glfw_set_window_size_first {
+        crate::track_hook!("glfwSetWindowSize");
+        real()(window, width, height);
+        if HOST.config.enable_glfw {
+            // x11 applies this asynchronously, the next swap picks up whatever it ends up being
+            sync_window_size(window);
+        }
+    }
+
+    unsafe fn glfwSetWindowTitle(window: *mut GLFWwindow, title: *const c_char) => glfw_set_window_title_first {
+        crate::track_hook!("glfwSetWindowTitle");
+        real()(window, title);
+        if HOST.config.enable_glfw {
+            // the xlib hooks already prefix x11 titles, this only keeps our record in sync
+            if let Some(window_id) = tracked_window_id(window) {
+                HOST.onWindowTitleChange(window_id, utils::title_from_cstr(title));
+            }
+        }
+    }
+
+    unsafe fn glfwSwapBuffers(window: *mut GLFWwindow) => glfw_swap_buffers_first {
+        crate::track_hook!("glfwSwapBuffers");
+        if HOST.config.enable_glfw {
+            HOST.features.enable_glfw();
+            HOST.features.enable_gl();
+            HOST.suggest_graphics_api(GraphicsAPI::OpenGL);
+            let window_id = sync_window_size(window);
+            HOST.onFrameSwapBegin(window_id);
+            IN_GLFW_SWAP.with(|flag| flag.set(true));
+            real()(window);
+            IN_GLFW_SWAP.with(|flag| flag.set(false));
+            HOST.onFrameSwapEnd();
+        } else {
+            real()(window);
+        }
+    }
+
+    unsafe fn glfwPollEvents() => glfw_poll_events_first {
+        crate::track_hook!("glfwPollEvents");
+        real()();
+        if HOST.config.enable_glfw {
+            deliver_injected_input();
+        }
+    }
+
+    // input that arrives while the app is blocked in here only shows up once something else wakes it
+    unsafe fn glfwWaitEvents() => glfw_wait_events_first {
+        crate::track_hook!("glfwWaitEvents");
+        if HOST.config.enable_glfw && has_pending_input() {
+            crate::hw_real!(glfwPollEvents: unsafe extern "C" fn())();
+        } else {
+            real()();
+        }
+        if HOST.config.enable_glfw {
+            deliver_injected_input();
+        }
+    }
+
+    unsafe fn glfwWaitEventsTimeout(timeout: f64) => glfw_wait_events_timeout_first {
+        crate::track_hook!("glfwWaitEventsTimeout");
+        if HOST.config.enable_glfw && has_pending_input() {
+            crate::hw_real!(glfwPollEvents: unsafe extern "C" fn())();
+        } else {
+            real()(timeout);
+        }
+        if HOST.config.enable_glfw {
+            deliver_injected_input();
+        }
+    }
+
+    // callback setters, we keep a copy so injected input can be delivered through them
+
+    unsafe fn glfwSetKeyCallback(window: *mut GLFWwindow, callback: GLFWkeyfun) -> GLFWkeyfun => glfw_set_key_callback_first {
+        crate::track_hook!("glfwSetKeyCallback");
+        update_callbacks(window, |callbacks| callbacks.key = callback);
+        real()(window, callback)
+    }
+
+    unsafe fn glfwSetCharCallback(window: *mut GLFWwindow, callback: GLFWcharfun) -> GLFWcharfun => glfw_set_char_callback_first {
+        crate::track_hook!("glfwSetCharCallback");
+        update_callbacks(window, |callbacks| callbacks.char = callback);
+        real()(window, callback)
+    }
+
+    unsafe fn glfwSetCursorPosCallback(window: *mut GLFWwindow, callback: GLFWcursorposfun) -> GLFWcursorposfun => glfw_set_cursor_pos_callback_first {
+        crate::track_hook!("glfwSetCursorPosCallback");
+        update_callbacks(window, |callbacks| callbacks.cursor_pos = callback);
+        real()(window, callback)
+    }
+
+    unsafe fn glfwSetMouseButtonCallback(window: *mut GLFWwindow, callback: GLFWmousebuttonfun) -> GLFWmousebuttonfun => glfw_set_mouse_button_callback_first {
+        crate::track_hook!("glfwSetMouseButtonCallback");
+        update_callbacks(window, |callbacks| callbacks.mouse_button = callback);
+        real()(window, callback)
+    }
+
+    unsafe fn glfwSetScrollCallback(window: *mut GLFWwindow, callback: GLFWscrollfun) -> GLFWscrollfun => glfw_set_scroll_callback_first {
+        crate::track_hook!("glfwSetScrollCallback");
+        update_callbacks(window, |callbacks| callbacks.scroll = callback);
+        real()(window, callback)
+    }
+
+    // polling, injected state is merged on top of the real one
+
+    unsafe fn glfwGetKey(window: *mut GLFWwindow, key: c_int) -> c_int => glfw_get_key_first {
+        crate::track_hook!("glfwGetKey");
+        let state = real()(window, key);
+        if HOST.config.enable_glfw && state != GLFW_PRESS && key >= 0 && key <= GLFW_KEY_LAST && GLFW_INPUT.lock().unwrap().keys.contains(&key) {
+            GLFW_PRESS
+        } else {
+            state
+        }
+    }
+
+    unsafe fn glfwGetMouseButton(window: *mut GLFWwindow, button: c_int) -> c_int => glfw_get_mouse_button_first {
+        crate::track_hook!("glfwGetMouseButton");
+        let state = real()(window, button);
+        if HOST.config.enable_glfw && state != GLFW_PRESS && button >= 0 && button <= GLFW_MOUSE_BUTTON_LAST && GLFW_INPUT.lock().unwrap().buttons & (1 << button) != 0 {
+            GLFW_PRESS
+        } else {
+            state
+        }
+    }
+
+    unsafe fn glfwGetCursorPos(window: *mut GLFWwindow, x: *mut f64, y: *mut f64) => glfw_get_cursor_pos_first {
+        crate::track_hook!("glfwGetCursorPos");
+        real()(window, x, y);
+        if HOST.config.enable_glfw {
+            if let Some((cursor_x, cursor_y)) = GLFW_INPUT.lock().unwrap().cursor {
+                if !x.is_null() {
+                    *x = cursor_x;
+                }
+                if !y.is_null() {
+                    *y = cursor_y;
+                }
+            }
+        }
+    }
+
+    // joysticks
+
+    unsafe fn glfwJoystickPresent(jid: c_int) -> c_int => glfw_joystick_present_first {
+        crate::track_hook!("glfwJoystickPresent");
+        if !HOST.config.enable_glfw {
+            return real()(jid);
+        }
+        if jid >= 0 && jid < virtual_gamepad_count() {
+            GLFW_TRUE
+        } else {
+            real()(real_joystick_id(jid))
+        }
+    }
+
+    unsafe fn glfwJoystickIsGamepad(jid: c_int) -> c_int => glfw_joystick_is_gamepad_first {
+        crate::track_hook!("glfwJoystickIsGamepad");
+        if !HOST.config.enable_glfw {
+            return real()(jid);
+        }
+        if jid >= 0 && jid < virtual_gamepad_count() {
+            GLFW_TRUE
+        } else {
+            real()(real_joystick_id(jid))
+        }
+    }
+
+    unsafe fn glfwGetJoystickAxes(jid: c_int, count: *mut c_int) -> *const c_float => glfw_get_joystick_axes_first {
+        crate::track_hook!("glfwGetJoystickAxes");
+        if !HOST.config.enable_glfw {
+            return real()(jid, count);
+        }
+        let axes = {
+            let input_manager = HOST.input_manager.lock().unwrap();
+            if jid < 0 || jid as usize >= input_manager.count_gamepads() {
+                None
+            } else {
+                input_manager.get_gamepad(jid as usize).map(|gamepad| gamepad.state.axes.iter().map(|axis| *axis as f32).collect::<Vec<f32>>())
+            }
+        };
+        match axes {
+            Some(axes) => {
+                let mut joysticks = GLFW_JOYSTICKS.lock().unwrap();
+                let buffers = joysticks.entry(jid).or_default();
+                buffers.axes = axes;
+                *count = buffers.axes.len() as c_int;
+                buffers.axes.as_ptr()
+            },
+            None => real()(real_joystick_id(jid), count),
+        }
+    }
+
+    unsafe fn glfwGetJoystickButtons(jid: c_int, count: *mut c_int) -> *const c_uchar => glfw_get_joystick_buttons_first {
+        crate::track_hook!("glfwGetJoystickButtons");
+        if !HOST.config.enable_glfw {
+            return real()(jid, count);
+        }
+        let buttons = {
+            let input_manager = HOST.input_manager.lock().unwrap();
+            if jid < 0 || jid as usize >= input_manager.count_gamepads() {
+                None
+            } else {
+                input_manager.get_gamepad(jid as usize).map(|gamepad| gamepad.state.buttons.iter().map(|button| *button as u8).collect::<Vec<u8>>())
+            }
+        };
+        match buttons {
+            Some(buttons) => {
+                let mut joysticks = GLFW_JOYSTICKS.lock().unwrap();
+                let buffers = joysticks.entry(jid).or_default();
+                buffers.buttons = buttons;
+                *count = buffers.buttons.len() as c_int;
+                buffers.buttons.as_ptr()
+            },
+            None => real()(real_joystick_id(jid), count),
+        }
+    }
+
+    unsafe fn glfwGetJoystickHats(jid: c_int, count: *mut c_int) -> *const c_uchar => glfw_get_joystick_hats_first {
+        crate::track_hook!("glfwGetJoystickHats");
+        if HOST.config.enable_glfw && jid >= 0 && jid < virtual_gamepad_count() {
+            // the dpad is reported as buttons
+            *count = 0;
+            std::ptr::NonNull::<c_uchar>::dangling().as_ptr()
+        } else if HOST.config.enable_glfw {
+            real()(real_joystick_id(jid), count)
+        } else {
+            real()(jid, count)
+        }
+    }
+    unsafe fn glfwGetJoystickName(jid: c_int) -> *const c_char => glfw_get_joystick_name_first {
+        crate::track_hook!("glfwGetJoystickName");
+        if !HOST.config.enable_glfw {
+            return real()(jid);
+        }
+        match virtual_gamepad_name(jid) {
+            Some(name) => name,
+            None => real()(real_joystick_id(jid)),
+        }
+    }
+
+    unsafe fn glfwGetGamepadName(jid: c_int) -> *const c_char => glfw_get_gamepad_name_first {
+        crate::track_hook!("glfwGetGamepadName");
+        if !HOST.config.enable_glfw {
+            return real()(jid);
+        }
+        match virtual_gamepad_name(jid) {
+            Some(name) => name,
+            None => real()(real_joystick_id(jid)),
+        }
+    }
+
+    unsafe fn glfwGetGamepadState(jid: c_int, state: *mut GLFWgamepadstate) -> c_int => glfw_get_gamepad_state_first {
+        crate::track_hook!("glfwGetGamepadState");
+        if !HOST.config.enable_glfw {
+            return real()(jid, state);
+        }
+        let virtual_state = {
+            let input_manager = HOST.input_manager.lock().unwrap();
+            if jid < 0 || jid as usize >= input_manager.count_gamepads() {
+                None
+            } else {
+                input_manager.get_gamepad(jid as usize).map(|gamepad| glfw_gamepad_state_from_sdl(&gamepad.state))
+            }
+        };
+        match virtual_state {
+            Some(virtual_state) => {
+                if state.is_null() {
+                    return GLFW_FALSE;
+                }
+                *state = virtual_state;
+                GLFW_TRUE
+            },
+            None => real()(real_joystick_id(jid), state),
+        }
+    }
+}
+
+fn glfw_enabled(config: &Config) -> bool {
+    config.enable_glfw
 }
@@ -3,9 +3,10 @@ use std::collections::HashMap;
 use libc::{c_char, c_void, c_long, c_int};
 use stellar_protocol::protocol::GraphicsAPI;
 
-use crate::{host::hosting::HOST, utils::pointer::Pointer};
+use crate::{constants::Library, host::hosting::HOST, utils::pointer::Pointer};
 
-use super::dlsym::{query_dlsym_cache, DLSYM_CACHE};
+use super::{dlsym::{query_dlsym_cache, DLSYM_CACHE}, glfw, registry, sdl3};
+use stellar_shared::log_debug;
 
 // types
 type Display = *mut c_void;
@@ -21,11 +22,8 @@ pub extern "C" fn glXSwapBuffersShim(name: Display, drawble: GLXDrawable) {
 pub fn modify_pointers(name: &str, pointer: Pointer) -> Pointer{
     match name {
         "glXSwapBuffers" => {
-            println!("overrode glxswapbuffers");
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_glx();
-            }
+            log_debug!(Hooks, "overrode glxswapbuffers");
+            HOST.features.enable_glx();
             HOST.suggest_graphics_api(GraphicsAPI::OpenGL);
             // memorize the real glxSwapBuffers pointer and return our shim instead
             HOST.func_pointers.lock().unwrap().insert(name.to_string(), pointer);
@@ -38,65 +36,9 @@ pub fn modify_pointers(name: &str, pointer: Pointer) -> Pointer{
 
 // pub const getProcAddressOverrides: HashMap<String, *const c_void> = HashMap::new();
 
-redhook::hook! {
-    unsafe fn glXSwapBuffers_hw_direct(name: Display, drawble: GLXDrawable) => gl_x_swap_buffers_hw_direct {
-        // shim so I can run redhook::real on it
-    }
-}
-
-// extern void glXSwapBuffers( Display *dpy, GLXDrawable drawable );
-redhook::hook! {
-    unsafe fn glXSwapBuffers(name: Display, drawble: GLXDrawable) => gl_x_swap_buffers {
-        if HOST.config.enable_x11 && HOST.config.enable_glx {
-            // HOST.test();
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_glx();
-            }
-
-            HOST.onFrameSwapBegin();            
-            let result = redhook::real!(glXSwapBuffers_hw_direct)(name, drawble);
-            HOST.onFrameSwapEnd();
-            result
-        } else {
-            if HOST.config.debug_mode {
-                // println!("Attempted to open {}", name);
-            }
-            // std::ptr::null()
-        }
-    }
-}
-
-redhook::hook! {
-    unsafe fn glXSwapBuffersMscOML(name: Display, drawble: GLXDrawable, target_msc: c_long, divisor: c_long, remainder: c_long) => gl_x_swap_buffers_msc_oml {
-        if HOST.config.enable_x11 && HOST.config.enable_glx {
-            // HOST.test();
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_glx();
-            }
-
-            HOST.onFrameSwapBegin();            
-            let result = redhook::real!(glXSwapBuffersMscOML_hw_direct)(name, drawble, target_msc, divisor, remainder);
-            HOST.onFrameSwapEnd();
-            result
-        } else {
-            if HOST.config.debug_mode {
-                // println!("Attempted to open {}", name);
-            }
-            // std::ptr::null()
-        }
-    }
-}
-
-redhook::hook! {
-    unsafe fn glXSwapBuffersMscOML_hw_direct(name: Display, drawble: GLXDrawable, target_msc: c_long, divisor: c_long, remainder: c_long) => gl_x_swap_buffers_msc_oml_hw_direct {
-        // this is a shim so I can run redhook::real on it
-    }
-}
-
 #[no_mangle]
 pub extern "C" fn glXSwapBuffersPA(name: Display, drawble: GLXDrawable) {
+    crate::track_hook!("glXSwapBuffersPA");
     // println!("Entered glXSwapBuffersPA");
     let func_pointers = HOST.func_pointers.lock().unwrap();
     // println!("Locked pointer map");
@@ -107,7 +49,7 @@ pub extern "C" fn glXSwapBuffersPA(name: Display, drawble: GLXDrawable) {
 
             let func: extern "C" fn(name: Display, drawble: GLXDrawable) = unsafe { std::mem::transmute(func_ref) };
             
-            HOST.onFrameSwapBegin();
+            HOST.onFrameSwapBegin(Some(drawble as usize));
             func(name, drawble);
             HOST.onFrameSwapEnd();
 
@@ -118,42 +60,71 @@ pub extern "C" fn glXSwapBuffersPA(name: Display, drawble: GLXDrawable) {
     }
 }
 
-redhook::hook! {
-    unsafe fn glXSwapBuffersPA_hw_direct(name: Display, drawble: GLXDrawable) => gl_x_swap_buffers_pa_hw_direct {
-        // this is a shim so I can run redhook::real on it
-    }
-}
 
 fn glxGetProcAddrShim(name: String, origPointer: Pointer) -> Pointer{
     match name.as_ref() {
         "glXSwapBuffers" => {
             HOST.suggest_graphics_api(GraphicsAPI::OpenGL);
 
-            if HOST.config.debug_mode {
-                println!("overrode glxswapbuffers");
-            }
-            let mut features = HOST.features.lock().unwrap();
-            features.enable_glx();
+            log_debug!(Hooks, "overrode glxswapbuffers");
+            HOST.features.enable_glx();
             // return our above shim
             Pointer(gl_x_swap_buffers as *const c_void)
         },
-        _ => origPointer
+        _ => registry::lookup(&name).map_or(origPointer, |pointer| Pointer(pointer))
     }
 }
 
-// shim so we can call real from another
+crate::hw_hooks! {
+    // ENABLE_GLX drops swaps instead of passing them through so these always stay hooked
+    library: Library::GLX, gate: registry::always;
+
+    // extern void glXSwapBuffers( Display *dpy, GLXDrawable drawable );
+    unsafe fn glXSwapBuffers(name: Display, drawble: GLXDrawable) => gl_x_swap_buffers {
+        crate::track_hook!("glXSwapBuffers");
+        if glfw::in_glfw_swap() || sdl3::in_sdl3_swap() || registry::is_disabled(&HOST.config, "glXSwapBuffers") {
+            // glfwSwapBuffers or SDL3 already captured this frame, or a profile wants another swap hook to capture
+            real()(name, drawble)
+        } else if HOST.config.enable_x11 && HOST.config.enable_glx {
+            // HOST.test();
+            HOST.features.enable_glx();
 
-redhook::hook! {
-    unsafe fn glXGetProcAddress_hw_direct(name: *const c_char) -> c_func => gl_x_get_proc_address_hw_direct {
-        std::ptr::null()
+            HOST.onFrameSwapBegin(Some(drawble as usize));            
+            let result = real()(name, drawble);
+            HOST.onFrameSwapEnd();
+            result
+        } else {
+            if HOST.config.debug_mode {
+                // println!("Attempted to open {}", name);
+            }
+            // std::ptr::null()
+        }
     }
-}
 
-redhook::hook! {
+    unsafe fn glXSwapBuffersMscOML(name: Display, drawble: GLXDrawable, target_msc: c_long, divisor: c_long, remainder: c_long) => gl_x_swap_buffers_msc_oml {
+        crate::track_hook!("glXSwapBuffersMscOML");
+        if registry::is_disabled(&HOST.config, "glXSwapBuffersMscOML") {
+            real()(name, drawble, target_msc, divisor, remainder)
+        } else if HOST.config.enable_x11 && HOST.config.enable_glx {
+            // HOST.test();
+            HOST.features.enable_glx();
+
+            HOST.onFrameSwapBegin(Some(drawble as usize));            
+            let result = real()(name, drawble, target_msc, divisor, remainder);
+            HOST.onFrameSwapEnd();
+            result
+        } else {
+            if HOST.config.debug_mode {
+                // println!("Attempted to open {}", name);
+            }
+            // std::ptr::null()
+        }
+    }
     unsafe fn glXGetProcAddress(name: *const c_char) -> c_func => gl_x_get_proc_address {
-        let func = redhook::real!(glXGetProcAddress_hw_direct)(name);
+        crate::track_hook!("glXGetProcAddress");
+        let func = real()(name);
         let func_name = std::ffi::CStr::from_ptr(name).to_str().unwrap();
-        println!("glx get proc addr {}", func_name);
+        log_debug!(Hooks, "glx get proc addr {}", func_name);
         let origPointer = Pointer(func);
         // insert orig pointer
         HOST.func_pointers.lock().unwrap().insert(func_name.to_owned(), Pointer(func));
@@ -164,19 +135,12 @@ redhook::hook! {
 
         pointer.0
     }
-}
-
-redhook::hook! {
-    unsafe fn glXGetProcAddressARB_hw_direct(name: *const c_char) -> c_func => gl_x_get_proc_address_arb_hw_direct {
-        std::ptr::null() // exists so we can call real on it
-    }
-}
 
-redhook::hook! {
     unsafe fn glXGetProcAddressARB(name: *const c_char) -> c_func => gl_x_get_proc_address_arb {
-        let func = redhook::real!(glXGetProcAddressARB_hw_direct)(name);
+        crate::track_hook!("glXGetProcAddressARB");
+        let func = real()(name);
         let func_name = std::ffi::CStr::from_ptr(name).to_str().unwrap();
-        println!("glx get proc addr arb {}", func_name);
+        log_debug!(Hooks, "glx get proc addr arb {}", func_name);
         // insert orig pointer
         HOST.func_pointers.lock().unwrap().insert(func_name.to_owned(), Pointer(func));
         {
@@ -186,4 +150,4 @@ redhook::hook! {
         let pointer = glxGetProcAddrShim(func_name.to_owned(), origPointer); // we use the same since this func only switches funcs for stuff we're interested in
         pointer.0
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,247 @@
+// every symbol we hand out instead of the real one
+// dlsym, glXGetProcAddress, eglGetProcAddress and the SDL dynapi table all route through here
+// so adding a hook is writing it inside its module's hw_hooks! block, which also makes register_hooks
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use lazy_static::lazy_static;
+use libc::c_void;
+
+use crate::{constants::Library, host::hosting::HOST, utils::{config::Config, pointer::Pointer}};
+
+use super::{dlopen, dlsym, egl, exec, glfw, glx, sdl2, sdl3, xlib};
+use stellar_shared::log_info;
+
+// the library's own version of a hooked function, dlsym resolves the _hw_direct alias past our export
+#[macro_export]
+macro_rules! hw_real {
+    ($name:ident: $fn_type:ty) => {{
+        static REAL: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let mut pointer = REAL.load(std::sync::atomic::Ordering::Relaxed);
+        if pointer == 0 {
+            pointer = libc::dlsym(libc::RTLD_NEXT, concat!(stringify!($name), "_hw_direct\0").as_ptr() as *const libc::c_char) as usize;
+            assert!(pointer != 0, concat!("could not resolve the real ", stringify!($name)));
+            REAL.store(pointer, std::sync::atomic::Ordering::Relaxed);
+        }
+        std::mem::transmute::<usize, $fn_type>(pointer)
+    }};
+}
+
+// wraps a module's hooks, each `unsafe fn name(..) => handler { .. }` becomes the redhook export,
+// gets a real() inside its body for the library's version and is registered under library and gate,
+// #[gate(path)] in front of one overrides the gate, anything else in the block is passed through untouched
+#[macro_export]
+macro_rules! hw_hooks {
+    (library: $library:expr, gate: $gate:expr; $($rest:tt)*) => {
+        $crate::hw_hooks!(@munch ($library, $gate) [] $($rest)*);
+    };
+    (@munch $context:tt [$($registered:tt)*]
+        $(#[gate($hook_gate:path)])?
+        unsafe fn $name:ident ( $($v:ident : $t:ty),* ) $(-> $r:ty)? => $first:ident $body:block
+        $($rest:tt)*
+    ) => {
+        redhook::hook! {
+            unsafe fn $name($($v: $t),*) $(-> $r)? => $first {
+                #[allow(dead_code)]
+                unsafe fn real() -> unsafe extern "C" fn($($t),*) $(-> $r)? {
+                    $crate::hw_real!($name: unsafe extern "C" fn($($t),*) $(-> $r)?)
+                }
+                $body
+            }
+        }
+        $crate::hw_hooks!(@munch $context [$($registered)* ($name, $first, [$($hook_gate)?])] $($rest)*);
+    };
+    (@munch $context:tt [$($registered:tt)*] $item:item $($rest:tt)*) => {
+        $item
+        $crate::hw_hooks!(@munch $context [$($registered)*] $($rest)*);
+    };
+    (@munch ($library:expr, $gate:expr) [$(($name:ident, $first:ident, [$($hook_gate:path)?]))*]) => {
+        pub fn register_hooks(registry: &mut $crate::hooks::registry::HookRegistry) {
+            $(
+                registry.register(stringify!($name), $first as *const libc::c_void, $library, $crate::hw_hooks!(@gate [$($hook_gate)?] $gate));
+            )*
+        }
+    };
+    (@gate [$hook_gate:path] $gate:expr) => { $hook_gate };
+    (@gate [] $gate:expr) => { $gate };
+}
+
+pub struct HookEntry {
+    pub symbol: &'static str,
+    pub replacement: Pointer,
+    pub library: Library,
+    // checked on every lookup against the registry's config, a hook that's gated off hands out the real symbol instead
+    pub gate: fn(&Config) -> bool,
+}
+
+impl HookEntry {
+    pub fn is_enabled(&self, config: &Config) -> bool {
+        !is_disabled(config, self.symbol) && (self.gate)(config)
+    }
+}
+
+#[derive(Default)]
+pub struct HookRegistry {
+    entries: Vec<HookEntry>,
+    // the same name can be registered by more than one library (SDL2 and SDL3), first registered wins
+    by_symbol: HashMap<&'static str, Vec<usize>>,
+    // library_symbol_prefix of every library with a hook, a handful at most
+    cache_prefixes: Vec<&'static str>,
+    // HOST.config as of premain, so lookups from inside dlsym don't touch HOST
+    config: OnceLock<Config>,
+}
+
+impl HookRegistry {
+    pub fn register(&mut self, symbol: &'static str, replacement: *const c_void, library: Library, gate: fn(&Config) -> bool) {
+        self.by_symbol.entry(symbol).or_default().push(self.entries.len());
+        if let Some(prefix) = library_symbol_prefix(library) {
+            if !self.cache_prefixes.contains(&prefix) {
+                self.cache_prefixes.push(prefix);
+            }
+        }
+        self.entries.push(HookEntry {
+            symbol,
+            replacement: Pointer(replacement),
+            library,
+            gate,
+        });
+    }
+
+    pub fn entries(&self) -> &[HookEntry] {
+        &self.entries
+    }
+
+    pub fn is_registered(&self, symbol: &str) -> bool {
+        self.by_symbol.contains_key(symbol)
+    }
+
+    // only the first snapshot sticks, later config changes don't reach the gates
+    pub fn snapshot_config(&self, config: &Config) {
+        let _ = self.config.set(config.clone());
+    }
+
+    // None before premain, building HOST from inside dlsym would run its whole init at loader time
+    pub fn config(&self) -> Option<&Config> {
+        self.config.get()
+    }
+
+    pub fn should_cache(&self, symbol: &str) -> bool {
+        self.is_registered(symbol) || self.cache_prefixes.iter().any(|prefix| symbol.starts_with(prefix))
+    }
+
+    // the replacement to hand out for a symbol, None means use the real one
+    pub fn lookup(&self, symbol: &str) -> Option<*mut c_void> {
+        self.find(symbol, |_| true)
+    }
+
+    // same but only hooks from one library, the dynapi table knows which SDL it belongs to
+    pub fn lookup_library(&self, symbol: &str, library: Library) -> Option<*mut c_void> {
+        self.find(symbol, |entry| entry.library == library)
+    }
+
+    // anything looked up before premain gets the real symbol instead of building HOST at loader time
+    fn find(&self, symbol: &str, filter: impl Fn(&HookEntry) -> bool) -> Option<*mut c_void> {
+        let candidates = self.by_symbol.get(symbol)?;
+        let config = self.config()?;
+        candidates
+            .iter()
+            .map(|index| &self.entries[*index])
+            .find(|entry| filter(entry) && entry.is_enabled(config))
+            .map(|entry| entry.replacement.as_mut_func())
+    }
+}
+
+// symbols from these libraries get their real pointer cached on lookup so the _hw_direct binds can find it later
+pub fn library_symbol_prefix(library: Library) -> Option<&'static str> {
+    match library {
+        Library::SDL2 | Library::SDL3 => Some("SDL_"),
+        Library::GLFW => Some("glfw"),
+        Library::Xlib => Some("X"),
+        Library::EGL => Some("egl"),
+        Library::GLX => Some("glX"),
+        _ => None,
+    }
+}
+
+pub fn should_cache_symbol(symbol: &str) -> bool {
+    HOOKS.should_cache(symbol)
+}
+
+// disabled_hooks from a profile or HW_DISABLED_HOOKS, the swap hooks also check it since they're linked in directly
+pub fn is_disabled(config: &Config, symbol: &str) -> bool {
+    config.disabled_hooks.iter().any(|disabled| disabled == symbol)
+}
+
+pub fn always(_config: &Config) -> bool {
+    true
+}
+
+lazy_static! {
+    // registration order matters for shared names, SDL3 goes before SDL2
+    pub static ref HOOKS: HookRegistry = {
+        let mut registry = HookRegistry::default();
+        dlsym::register_hooks(&mut registry);
+        dlopen::register_hooks(&mut registry);
+        exec::register_hooks(&mut registry);
+        glx::register_hooks(&mut registry);
+        egl::register_hooks(&mut registry);
+        glfw::register_hooks(&mut registry);
+        xlib::register_hooks(&mut registry);
+        sdl3::register_hooks(&mut registry);
+        sdl2::register_hooks(&mut registry);
+        registry
+    };
+}
+
+pub fn lookup(symbol: &str) -> Option<*mut c_void> {
+    HOOKS.lookup(symbol)
+}
+
+pub fn print_hooks() {
+    // not called from dlsym, falling back to HOST is fine here
+    let config = HOOKS.config().unwrap_or(HOST.config.as_ref());
+    log_info!(Hooks, "{} hooks registered:", HOOKS.entries().len());
+    for entry in HOOKS.entries() {
+        log_info!(Hooks, "  {} ({:?}) {}", entry.symbol, entry.library, if entry.is_enabled(config) { "enabled" } else { "disabled" });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn stand_in() {}
+
+    fn registry() -> HookRegistry {
+        let mut registry = HookRegistry::default();
+        registry.register("SDL_GL_SwapWindow", stand_in as *const c_void, Library::SDL3, always);
+        registry.register("SDL_GL_SwapWindow", std::ptr::null(), Library::SDL2, always);
+        registry
+    }
+
+    #[test]
+    fn lookups_pass_through_until_premain() {
+        let registry = registry();
+        assert_eq!(registry.lookup("SDL_GL_SwapWindow"), None);
+        registry.snapshot_config(&Config::default());
+        assert_eq!(registry.lookup("SDL_GL_SwapWindow"), Some(stand_in as *mut c_void));
+        assert_eq!(registry.lookup_library("SDL_GL_SwapWindow", Library::SDL2), Some(std::ptr::null_mut()));
+        assert_eq!(registry.lookup("SDL_Init"), None);
+    }
+
+    #[test]
+    fn disabled_hooks_hand_out_the_real_symbol() {
+        let registry = registry();
+        registry.snapshot_config(&Config { disabled_hooks: vec!["SDL_GL_SwapWindow".to_string()], ..Config::default() });
+        assert_eq!(registry.lookup("SDL_GL_SwapWindow"), None);
+    }
+
+    #[test]
+    fn only_hooked_libraries_are_cached() {
+        let registry = registry();
+        assert!(registry.should_cache("SDL_GL_SwapWindow"));
+        assert!(registry.should_cache("SDL_CreateWindow"));
+        assert!(!registry.should_cache("glfwCreateWindow"));
+        assert!(!registry.should_cache("malloc"));
+    }
+}
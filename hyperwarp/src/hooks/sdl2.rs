@@ -1,53 +1,114 @@
 use std::ffi::c_short;
 
-use libc::{c_char, c_int, c_ushort, c_void};
+use libc::{c_char, c_int, c_ulong, c_ushort, c_void};
 use sdl2_sys_lite::bindings::{SDL_Event, SDL_EventType, SDL_Joystick, SDL_WindowEventID};
-use stellar_protocol::protocol::GraphicsAPI;
+use stellar_protocol::protocol::{GraphicsAPI, WindowIcon};
+use stellar_shared::vendor::sdl_bindings::SDL_Surface;
 
-use crate::constants::sdl2::SDL_FALSE;
+use crate::bind::sdl2_safe;
+use crate::constants::sdl2::{SDL_FALSE, SDL_WINDOW_FULLSCREEN, SDL_WINDOW_HIDDEN};
 
 use crate::constants::Library;
+use crate::constants::sdl3::{SDL3_Event, SDL3_Renderer, SDL3_Surface, SDL3_Window};
 use crate::host::window::Window;
+use crate::platform::sdl3::sdl3_flags_from_sdl2_args;
 use crate::utils::{self, format_window_title_prefix_cstr};
 use crate::utils::manual_types::sdl2::{SDL_Window, Uint32, SDL_Renderer};
 
 use crate::host::hosting::HOST;
 
+use super::registry;
+use super::sdl3;
+use stellar_shared::logging::{self, Level, Target};
+use stellar_shared::{log_debug, log_trace, log_warn};
+
 // Many of these hooks
 
 pub const SDL_DYNAPI_TABLE_MAX_SIZE: usize = 1024;
 
-redhook::hook! {
-    unsafe fn SDL_Init(flags: Uint32) -> c_int => sdl_init_first {
-        if HOST.config.debug_mode {
-            println!("SDL_Init called...");
-            let bt = backtrace::Backtrace::new();
-            println!("init backtrace {:?}", bt);
+pub fn SDL_track_window_event(event: &SDL_Event) {
+    unsafe {
+        if event.type_ != SDL_EventType::SDL_WINDOWEVENT as u32 {
+            return;
         }
-        if HOST.config.enable_sdl2 {
-            redhook::real!(SDL_Init_hw_direct)(flags)
+        let event_type_id = event.window.event;
+        let change: Option<(bool, bool)> = if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_SHOWN as u8 {
+            Some((false, true))
+        } else if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_HIDDEN as u8 {
+            Some((false, false))
+        } else if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_FOCUS_GAINED as u8 {
+            Some((true, true))
+        } else if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_FOCUS_LOST as u8 {
+            Some((true, false))
         } else {
-            SDL_FALSE
+            None
+        };
+        if let Some((is_focus, state)) = change {
+            // events only carry the SDL window id, SDL maps it back to the pointer we registered without us locking anything
+            let window_ptr = sdl2_safe::SDL_GetWindowFromID_safe(event.window.windowID);
+            if window_ptr.is_null() {
+                return;
+            }
+            if is_focus {
+                HOST.onWindowFocusChange(window_ptr as usize, state);
+            } else {
+                HOST.onWindowVisibilityChange(window_ptr as usize, state);
+            }
         }
     }
 }
 
-redhook::hook! {
-    unsafe fn SDL_Init_hw_direct(flags: Uint32) -> c_int => sdl_init_hw_direct {
-        // shim so I can run redhook::real on it
-        0
+pub fn SDL_should_allow_event(event: &SDL_Event) -> bool {
+    if !HOST.config.suppress_focus_loss {
+        return true;
+    }
+    unsafe {
+        if event.type_ == SDL_EventType::SDL_WINDOWEVENT as u32 {
+            let event_type_id = event.window.event;
+            if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_MINIMIZED as u8 {
+                return false;
+            } else if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_FOCUS_LOST as u8 {
+                return false;
+            } else if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_SHOWN as u8 {
+                return false;
+            }
+        }
     }
+    true
 }
 
-redhook::hook! {
-    unsafe fn SDL_CreateWindow(title: *const c_char, x: c_int, y: c_int, w: c_int, h: c_int, flags: Uint32) -> *const SDL_Window  => sdl_createwindow_first {
-        if HOST.config.debug_mode {
-            println!("SDL_CreateWindow called");
+crate::hw_hooks! {
+    // like xlib ENABLE_SDL2 denies instead of passing through, these also hand SDL3 apps to the sdl3 handlers
+    library: Library::SDL2, gate: registry::always;
+
+    unsafe fn SDL_Init(flags: Uint32) -> c_int => sdl_init_first {
+        crate::track_hook!("SDL_Init");
+        log_debug!(Hooks, "SDL_Init called...");
+        if logging::enabled(Target::Hooks, Level::Debug) {
+            let bt = backtrace::Backtrace::new();
+            log_debug!(Hooks, "init backtrace {:?}", bt);
         }
-        {
-            let mut features = HOST.features.lock().unwrap();
-            features.enable_sdl2();
+        if HOST.config.enable_sdl2 {
+            real()(flags)
+        } else {
+            SDL_FALSE
         }
+    }
+
+    // w is a whole register so SDL3's 64 bit flags survive, SDL2 callers only set the low 32 bits
+    unsafe fn SDL_CreateWindow(title: *const c_char, x: c_int, y: c_int, w: c_ulong, h: c_int, flags: Uint32) -> *const SDL_Window  => sdl_createwindow_first {
+        if sdl3::is_sdl3() {
+            // SDL3 is (title, w, h, flags) so the args land one slot early
+            return sdl3::sdl3_createwindow_first(title, x, y, sdl3_flags_from_sdl2_args(w, h)) as *const SDL_Window;
+        }
+        let w = w as c_int;
+        if sdl3::is_sdl2_compat() {
+            return real()(title, x, y, w, h, flags);
+        }
+        // SDL3 calls are counted by the sdl3 handler instead
+        crate::track_hook!("SDL_CreateWindow");
+        log_debug!(Hooks, "SDL_CreateWindow called");
+        HOST.features.enable_sdl2();
         let mut final_x: c_int = x;
         let mut final_y: c_int = y;
         let mut final_w: c_int = w;
@@ -63,197 +124,227 @@ redhook::hook! {
         let final_title = utils::format_window_title_prefix_cstr(title);
 
         let mut result = if HOST.config.enable_sdl2 {
-            redhook::real!(SDL_CreateWindow_hw_direct)(final_title, final_x, final_y, final_w, final_h, flags)
+            real()(final_title.as_ref().map_or(title, |t| t.as_ptr()), final_x, final_y, final_w, final_h, flags)
         } else {
             std::ptr::null()
         };
 
         // this should never panic
         // who would have a negative window size?
-        let window = Window::new(result as usize, Library::SDL2);
+        let mut window = Window::new(result as usize, Library::SDL2).with_title(utils::title_from_cstr(title));
+        window.visible = flags & SDL_WINDOW_HIDDEN == 0;
+        window.fullscreen = flags & SDL_WINDOW_FULLSCREEN != 0;
 
         HOST.onWindowCreate(window, Some(final_x), Some(final_y), Some(final_w.try_into().unwrap()), Some(final_h.try_into().unwrap()));
-
-        if HOST.config.debug_mode {
-            println!("SDL_CreateWindow called with x: {}, y: {}, w: {}, h: {}", final_x, final_y, final_w, final_h);
+        // the XCreateWindow hook already registered the X window SDL made for this, it's the same window
+        if let Some(x_window) = sdl2_safe::SDL_GetWindowX11Window_safe(result) {
+            HOST.link_native_window(result as usize, x_window);
         }
 
+        log_debug!(Hooks, "SDL_CreateWindow called with x: {}, y: {}, w: {}, h: {}", final_x, final_y, final_w, final_h);
+
         // TODO: maybe we should send a fake window focus here.
 
         result
     }
-}
 
-redhook::hook! {
-    unsafe fn SDL_CreateWindow_hw_direct(title: *const c_char, x: c_int, y: c_int, w: c_int, h: c_int, flags: Uint32) -> *const SDL_Window  => sdl_createwindow_hw_direct {
-        std::ptr::null() // Once again this is a shim so I can run redhook::real on it
-    }
-}
-
-redhook::hook! {
     unsafe fn SDL_GL_SwapBuffers() => sdl_gl_swapbuffers_first {
-        if HOST.config.debug_mode {
-            println!("SDL_GL_SwapBuffers called");
-        }
+        crate::track_hook!("SDL_GL_SwapBuffers");
+        log_debug!(Hooks, "SDL_GL_SwapBuffers called");
         if HOST.config.enable_sdl2 {
             HOST.suggest_graphics_api(GraphicsAPI::OpenGL);
-            HOST.onFrameSwapBegin();
-            let result = redhook::real!(SDL_GL_SwapBuffers_hw_direct)();
+            // SDL 1.2 only has the one window, its X window is what the xlib hooks registered
+            HOST.onFrameSwapBegin(sdl2_safe::SDL12_GetX11Window_safe());
+            let result = real()();
             HOST.onFrameSwapEnd();
             result
         } else {
             // std::ptr::null()
         }
     }
-}
-
-redhook::hook! {
-    unsafe fn SDL_GL_SwapBuffers_hw_direct() => sdl_gl_swapbuffers_hw_direct {
-        // shim so I can run redhook::real on it   
-    }
-}
 
-/*redhook::hook! {
-    unsafe fn SDL_GL_SwapWindow(display: *mut SDL_Window) => sdl_gl_swapwindow_first {
-        if HOST.config.tracing_mode {
-            println!("SDL_GL_SwapWindow called");
-        }
+    /*unsafe fn SDL_GL_SwapWindow(display: *mut SDL_Window) => sdl_gl_swapwindow_first {
+        log_trace!(Hooks, "SDL_GL_SwapWindow called");
         if HOST.config.enable_sdl2 {
-            HOST.onFrameSwapBegin();
+            HOST.onFrameSwapBegin(Some(display as usize));
             HOST.suggest_graphics_api(GraphicsAPI::OpenGL);
-            redhook::real!(SDL_GL_SwapWindow_hw_direct)(display);
+            real()(display);
             HOST.onFrameSwapEnd();
         }
-    }
-}
-
-redhook::hook! {
-    unsafe fn SDL_GL_SwapWindow_hw_direct(display: *mut SDL_Window) => sdl_gl_swapwindow_hw_direct {
-        // shim so I can run redhook::real on it   
-    }
-}*/
+    }*/
 
-redhook::hook! {
     unsafe fn SDL_RenderPresent(renderer: *mut SDL_Renderer) -> *const c_void => sdl_renderpresent_first {
-        if HOST.config.tracing_mode {
-            println!("SDL_RenderPresent called");
+        if sdl3::is_sdl3() {
+            return sdl3::sdl3_renderpresent_first(renderer as *mut SDL3_Renderer) as usize as *const c_void;
+        }
+        if sdl3::is_sdl2_compat() {
+            return real()(renderer);
         }
+        crate::track_hook!("SDL_RenderPresent");
+        log_trace!(Hooks, "SDL_RenderPresent called");
         if HOST.config.enable_sdl2 {
-            HOST.onFrameSwapBegin();
-            let result = redhook::real!(SDL_RenderPresent_hw_direct)(renderer);
+            // picks gl or SDL_RenderReadPixels depending on the renderer backend
+            HOST.onRenderPresent(renderer as usize);
+            let result = real()(renderer);
             HOST.onFrameSwapEnd();
             result
         } else {
             std::ptr::null()
         }
     }
-}
 
-redhook::hook! {
-    unsafe fn SDL_RenderPresent_hw_direct(renderer: *mut SDL_Renderer) -> *const c_void => sdl_renderpresent_hw_direct {
-        std::ptr::null()
-    }
-}
-
-redhook::hook! {
+    // void like SDL2, SDL3 apps calling through this export lose the bool, dlsym and dynapi hand them the SDL3 handler instead
     unsafe fn SDL_SetWindowTitle(display: *mut SDL_Window, title: *const c_char) => sdl_setwindowtitle_first {
-        if HOST.config.debug_mode {
-            println!("SDL_SetWindowTitle called");
+        if sdl3::is_sdl3() {
+            sdl3::sdl3_setwindowtitle_first(display as *mut SDL3_Window, title);
+            return;
+        }
+        if sdl3::is_sdl2_compat() {
+            return real()(display, title);
+        }
+        crate::track_hook!("SDL_SetWindowTitle");
+        if logging::enabled(Target::Hooks, Level::Debug) {
+            log_debug!(Hooks, "SDL_SetWindowTitle called");
             let bt = backtrace::Backtrace::new();
-            println!("init backtrace {:?}", bt);
-            let resolved = redhook::real!(SDL_SetWindowTitle_hw_direct);
-            println!("SDL_SetWindowTitle_hw_direct resolved to {}", resolved as usize);
+            log_debug!(Hooks, "init backtrace {:?}", bt);
+            let resolved = real();
+            log_debug!(Hooks, "real SDL_SetWindowTitle resolved to {}", resolved as usize);
         }
 
         if HOST.config.enable_sdl2 {
-            let result = redhook::real!(SDL_SetWindowTitle_hw_direct)(display, format_window_title_prefix_cstr(title));
-            result
+            // kept alive until SDL has made its own copy
+            let final_title = format_window_title_prefix_cstr(title);
+            real()(display, final_title.as_ref().map_or(title, |t| t.as_ptr()));
+            HOST.onWindowTitleChange(display as usize, utils::title_from_cstr(title));
         }
     }
-}
 
-redhook::hook! {
-    unsafe fn SDL_SetWindowTitle_hw_direct(display: *mut SDL_Window, title: *const c_char) => sdl_setwindowtitle_hw_direct {
-        // shim so I can run redhook::real on it   
+    unsafe fn SDL_SetWindowIcon(display: *mut SDL_Window, icon: *mut SDL_Surface) => sdl_setwindowicon_first {
+        if sdl3::is_sdl3() {
+            sdl3::sdl3_setwindowicon_first(display as *mut SDL3_Window, icon as *mut SDL3_Surface);
+            return;
+        }
+        if sdl3::is_sdl2_compat() {
+            return real()(display, icon);
+        }
+        crate::track_hook!("SDL_SetWindowIcon");
+        log_debug!(Hooks, "SDL_SetWindowIcon called");
+
+        if HOST.config.enable_sdl2 {
+            // read it before the app gets a chance to free the surface
+            let icon_data = sdl2_safe::SDL_Surface_to_rgba_safe(icon).map(|(width, height, rgba)| WindowIcon { width, height, rgba });
+            real()(display, icon);
+            HOST.onWindowIconChange(display as usize, icon_data);
+        }
+    }
+
+    unsafe fn SDL_ShowWindow(display: *mut SDL_Window) => sdl_showwindow_first {
+        if sdl3::is_sdl3() {
+            sdl3::sdl3_showwindow_first(display as *mut SDL3_Window);
+            return;
+        }
+        if sdl3::is_sdl2_compat() {
+            return real()(display);
+        }
+        crate::track_hook!("SDL_ShowWindow");
+        if HOST.config.enable_sdl2 {
+            real()(display);
+            HOST.onWindowVisibilityChange(display as usize, true);
+        }
+    }
+
+    unsafe fn SDL_HideWindow(display: *mut SDL_Window) => sdl_hidewindow_first {
+        if sdl3::is_sdl3() {
+            sdl3::sdl3_hidewindow_first(display as *mut SDL3_Window);
+            return;
+        }
+        if sdl3::is_sdl2_compat() {
+            return real()(display);
+        }
+        crate::track_hook!("SDL_HideWindow");
+        if HOST.config.enable_sdl2 {
+            real()(display);
+            HOST.onWindowVisibilityChange(display as usize, false);
+        }
+    }
+
+    unsafe fn SDL_SetWindowFullscreen(display: *mut SDL_Window, flags: Uint32) -> c_int => sdl_setwindowfullscreen_first {
+        if sdl3::is_sdl3() {
+            // SDL3 takes a bool, only the low byte is meaningful
+            return sdl3::sdl3_setwindowfullscreen_first(display as *mut SDL3_Window, flags & 0xff != 0) as c_int;
+        }
+        if sdl3::is_sdl2_compat() {
+            return real()(display, flags);
+        }
+        crate::track_hook!("SDL_SetWindowFullscreen");
+        if HOST.config.enable_sdl2 {
+            let result = real()(display, flags);
+            if result == 0 {
+                HOST.onWindowFullscreenChange(display as usize, flags & SDL_WINDOW_FULLSCREEN != 0);
+            }
+            result
+        } else {
+            -1
+        }
     }
-}
 
-// c_char is one way of getting a u8 array in c
-redhook::hook! {
+    // c_char is one way of getting a u8 array in c
     unsafe fn SDL_GetKeyboardState(count: *const c_int)-> *const c_char => sdl_getkeyboardstate_first {
+        crate::track_hook!("SDL_GetKeyboardState");
         if HOST.config.debug_mode {
 
         }
 
         if HOST.config.enable_sdl2 {
-            let result = redhook::real!(SDL_GetKeyboardState_hw_direct)(count);
+            let result = real()(count);
             std::mem::transmute(HOST.input_manager.lock().unwrap().keyboard.get_virt_array_ptr())
         } else {
             std::ptr::null()
         }
     }
-}
 
-redhook::hook! {
-    unsafe fn SDL_GetKeyboardState_hw_direct(count: *const c_int) -> *const c_char => sdl_getkeyboardstate_hw_direct {
-        // shim so I can run redhook::real on it   
-        std::ptr::null()
-    }
-}
-
-redhook::hook! {
     unsafe fn SDL_DestroyWindow(display: *mut SDL_Window) => sdl_destroywindow_first {
-        if HOST.config.debug_mode {
-            println!("SDL_DestroyWindow called");
+        if sdl3::is_sdl3() {
+            return sdl3::sdl3_destroywindow_first(display as *mut SDL3_Window);
         }
+        if sdl3::is_sdl2_compat() {
+            return real()(display);
+        }
+        crate::track_hook!("SDL_DestroyWindow");
+        log_debug!(Hooks, "SDL_DestroyWindow called");
 
         if HOST.config.enable_sdl2 {
             HOST.onWindowDestroy(display as usize);
-            let result = redhook::real!(SDL_DestroyWindow_hw_direct)(display);
+            let result = real()(display);
             result
         }
     }
-}
-
-redhook::hook! {
-    unsafe fn SDL_DestroyWindow_hw_direct(display: *mut SDL_Window) => sdl_destroywindow_hw_direct {
-        // shim so I can run redhook::real on it   
-    }
-}
-
-pub fn SDL_should_allow_event(event: &SDL_Event) -> bool {
-    unsafe {
-        if event.type_ == SDL_EventType::SDL_WINDOWEVENT as u32 {
-            let event_type_id = event.window.event;
-            if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_MINIMIZED as u8 {
-                return false;
-            } else if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_FOCUS_LOST as u8 {
-                return false;
-            } else if event_type_id == SDL_WindowEventID::SDL_WINDOWEVENT_SHOWN as u8 {
-                return false;
-            }
-        }
-    }
-    true
-}
-
-redhook::hook! {
     unsafe fn SDL_PollEvent(event: *mut SDL_Event) -> c_int => sdl_pollevent_first {
+        if sdl3::is_sdl3() {
+            // the event layout changed as well
+            return sdl3::sdl3_pollevent_first(event as *mut SDL3_Event) as c_int;
+        }
+        if sdl3::is_sdl2_compat() {
+            // sdl2-compat polls SDL3 underneath and that's where we filter
+            return real()(event);
+        }
+        crate::track_hook!("SDL_PollEvent");
         if HOST.config.debug_mode {
             // println!("SDL_PollEvent called");
         }
 
         if HOST.config.enable_sdl2 {
             // flush inputs here as well
-            HOST.input_manager.lock().unwrap().flush_queue();
+            HOST.flush_input();
             
-            let result = redhook::real!(SDL_PollEvent_hw_direct)(event);
+            let result = real()(event);
             if result != 0 {
                 let event_ref = event.as_ref().unwrap();
+                // track what the real window is doing before we possibly hide the event from the app
+                SDL_track_window_event(event_ref);
                 if !SDL_should_allow_event(event_ref) {
                     // hopefully nothing notices the event changed even if we return 0
-                    // TODO: remove this print when stabilized.
-                    println!("canceled event hack");
+                    log_trace!(Input, "canceled event hack");
                     return 0;
                 }
             }
@@ -262,78 +353,47 @@ redhook::hook! {
             0
         }
     }
-}
 
-redhook::hook! {
-    unsafe fn SDL_PollEvent_hw_direct(event: *mut SDL_Event) -> c_int => sdl_pollevent_hw_direct {
-        // shim so I can run redhook::real on it   
-        0
-    }
-}
+    // Joystick hooks
 
-// Joystick hooks
-
-redhook::hook! {
     unsafe fn SDL_NumJoysticks() -> c_int => sdl_numjoysticks_first {
-        if HOST.config.debug_mode {
-            println!("SDL_numjoysticks called");
-        }
+        crate::track_hook!("SDL_NumJoysticks");
+        log_debug!(Hooks, "SDL_numjoysticks called");
         if HOST.config.enable_sdl2 {
             /*if HOST.config.virtual_gamecontrollers {
                 HOST.input_manager.lock().unwrap().count_gamepads() as i32 // this would never overflow lol
             }else{
-                redhook::real!(SDL_NumJoysticks_hw_direct)()
+                real()()
             }*/
-            redhook::real!(SDL_NumJoysticks_hw_direct)()
+            real()()
         } else {
             0
         }
     }
-}
-
-redhook::hook! {
-    unsafe fn SDL_NumJoysticks_hw_direct() -> c_int => sdl_numjoysticks_hw_direct {
-        // shim so I can run redhook::real on it   
-        println!("WARN: unreachable code SDL_numjoysticks_hw_direct executed");
-        0
-    }
-}
 
-redhook::hook! {
     unsafe fn SDL_JoystickGetProduct(joystick: *mut SDL_Joystick) -> c_ushort => sdl_joystickgetproduct_first {
-        if HOST.config.debug_mode {
-            println!("SDL_JoystickGetProduct called");
-        }
+        crate::track_hook!("SDL_JoystickGetProduct");
+        log_debug!(Hooks, "SDL_JoystickGetProduct called");
 
         if HOST.config.enable_sdl2 {
             if let Some(gamepad) = HOST.input_manager.lock().unwrap().find_by_sdl(joystick as usize) {
                 gamepad.usb_id.product_id
             }else{
-                redhook::real!(SDL_JoystickGetProduct_hw_direct)(joystick)
+                real()(joystick)
             }
         } else {
             0
         }
     }
-}
-
-redhook::hook! {
-    unsafe fn SDL_JoystickGetProduct_hw_direct(joystick: *mut SDL_Joystick) -> c_ushort => sdl_joystickgetproduct_hw_direct {
-        // shim so I can run redhook::real on it   
-        0
-    }
-}
 
-redhook::hook! {
     unsafe fn SDL_JoystickGetVendor(joystick: *mut SDL_Joystick) -> c_ushort => sdl_joystickgetvendor_first {
-        if HOST.config.debug_mode {
-            println!("SDL_JoystickGetVendor called");
-        }
+        crate::track_hook!("SDL_JoystickGetVendor");
+        log_debug!(Hooks, "SDL_JoystickGetVendor called");
         if HOST.config.enable_sdl2 {
             if let Some(gamepad) = HOST.input_manager.lock().unwrap().find_by_sdl(joystick as usize) {
                 gamepad.usb_id.vendor_id
             }else{
-                redhook::real!(SDL_JoystickGetVendor_hw_direct)(joystick)
+                real()(joystick)
             }
             
         } else {
@@ -342,31 +402,4 @@ redhook::hook! {
     }
 }
 
-redhook::hook! {
-    unsafe fn SDL_JoystickGetVendor_hw_direct(joystick: *mut SDL_Joystick) -> c_ushort => sdl_joystickgetvendor_hw_direct {
-        // shim so I can run redhook::real on it   
-        0
-    }
-}
-
 // Game Controller hooks
-
-
-
-pub fn try_modify_symbol(symbol_name: &str) -> Option<*mut c_void> {
-    match symbol_name {
-        "SDL_Init" => Some(sdl_init_first as *mut c_void),
-        "SDL_CreateWindow" => Some(sdl_createwindow_first as *mut c_void),
-        "SDL_GL_SwapBuffers" => Some(sdl_gl_swapbuffers_first as *mut c_void),
-        // "SDL_GL_SwapWindow" => Some(sdl_gl_swapwindow_first as *mut c_void),
-        "SDL_RenderPresent" => Some(sdl_renderpresent_first as *mut c_void),
-        "SDL_SetWindowTitle" => Some(sdl_setwindowtitle_first as *mut c_void),
-        "SDL_GetKeyboardState" => Some(sdl_getkeyboardstate_first as *mut c_void),
-        "SDL_DestroyWindow" => Some(sdl_destroywindow_first as *mut c_void),
-        "SDL_PollEvent" => Some(sdl_pollevent_first as *mut c_void),
-        "SDL_NumJoysticks" => Some(sdl_numjoysticks_first as *mut c_void),
-        "SDL_JoystickGetProduct" => Some(sdl_joystickgetproduct_first as *mut c_void),
-        "SDL_JoystickGetVendor" => Some(sdl_joystickgetvendor_first as *mut c_void),
-        _ => None
-    }
-}
\ No newline at end of file
@@ -0,0 +1,316 @@
+// SDL3 reuses most SDL2 function names with new signatures, so nothing here is exported
+// apps linked against libSDL3 land in the SDL2 hooks which hand off to these, dlsym and dynapi get them directly
+
+use std::{cell::Cell, sync::atomic::{AtomicU8, Ordering}};
+
+use libc::{c_char, c_int, c_void};
+use stellar_protocol::protocol::{GraphicsAPI, WindowIcon};
+
+use crate::{
+    bind::sdl3_safe,
+    platform::sdl3::sdl3_window_state_from_flags,
+    constants::{sdl3::*, Library},
+    host::{hosting::HOST, window::Window},
+    utils::{self, config::Config, format_window_title_prefix_cstr},
+};
+
+use super::registry::HookRegistry;
+use stellar_shared::{log_debug, log_trace};
+
+const SDL3_UNKNOWN: u8 = 0;
+const SDL3_NO: u8 = 1;
+const SDL3_YES: u8 = 2;
+// libSDL2 is sdl2-compat running on top of libSDL3
+const SDL3_COMPAT: u8 = 3;
+
+static SDL3_DETECTED: AtomicU8 = AtomicU8::new(SDL3_UNKNOWN);
+
+thread_local! {
+    static IN_SDL3_SWAP: Cell<bool> = Cell::new(false);
+}
+
+// SDL_GL_SwapWindow already captured the frame, the glx and egl swaps under it should not capture again
+pub fn in_sdl3_swap() -> bool {
+    IN_SDL3_SWAP.with(|flag| flag.get())
+}
+
+pub fn mark_sdl3() {
+    if detect_sdl() != SDL3_COMPAT {
+        SDL3_DETECTED.store(SDL3_YES, Ordering::Relaxed);
+    }
+}
+
+unsafe fn library_loaded(name: &[u8]) -> bool {
+    let handle = libc::dlopen(name.as_ptr() as *const c_char, libc::RTLD_LAZY | libc::RTLD_NOLOAD);
+    if handle.is_null() {
+        false
+    } else {
+        libc::dlclose(handle);
+        true
+    }
+}
+
+// sdl2-compat reports its own version with a patch level of 50 and up, real SDL2 never got that far
+unsafe fn sdl2_is_compat() -> bool {
+    let handle = libc::dlopen(SDL2_LIBRARY_NAME.as_ptr() as *const c_char, libc::RTLD_LAZY | libc::RTLD_NOLOAD);
+    if handle.is_null() {
+        return false;
+    }
+    let get_version = libc::dlsym(handle, b"SDL_GetVersion\0".as_ptr() as *const c_char);
+    let mut version = [0u8; 3];
+    if !get_version.is_null() {
+        // SDL2's SDL_GetVersion fills in an SDL_version, three Uint8s
+        let get_version: unsafe extern "C" fn(*mut u8) = std::mem::transmute(get_version);
+        get_version(version.as_mut_ptr());
+    }
+    libc::dlclose(handle);
+    version[0] == 2 && version[2] >= SDL2_COMPAT_MIN_PATCH
+}
+
+// only settles once one of the two is loaded, a process with real SDL2 and SDL3 both loaded is treated as SDL2 like before
+fn detect_sdl() -> u8 {
+    match SDL3_DETECTED.load(Ordering::Relaxed) {
+        SDL3_UNKNOWN => {
+            let (sdl2_loaded, sdl3_loaded) = unsafe { (library_loaded(SDL2_LIBRARY_NAME), library_loaded(SDL3_LIBRARY_NAME)) };
+            if !sdl2_loaded && !sdl3_loaded {
+                return SDL3_UNKNOWN;
+            }
+            let detected = if !sdl2_loaded {
+                SDL3_YES
+            } else if sdl3_loaded && unsafe { sdl2_is_compat() } {
+                SDL3_COMPAT
+            } else {
+                SDL3_NO
+            };
+            log_debug!(Hooks, "sdl3 detected: {}, sdl2-compat: {}", detected == SDL3_YES, detected == SDL3_COMPAT);
+            SDL3_DETECTED.store(detected, Ordering::Relaxed);
+            detected
+        }
+        detected => detected,
+    }
+}
+
+pub fn is_sdl3() -> bool {
+    detect_sdl() == SDL3_YES
+}
+
+// the app talks SDL2 to sdl2-compat which calls into SDL3, we hook the SDL3 side so every call is only seen once
+pub fn is_sdl2_compat() -> bool {
+    detect_sdl() == SDL3_COMPAT
+}
+
+// the backbuffer is sized in pixels which only matches the window size at a pixel density of 1
+fn sync_window_size(window: *mut SDL3_Window) {
+    if let Some((width, height)) = sdl3_safe::SDL_GetWindowSizeInPixels_safe(window) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let size = (width as u32, height as u32);
+        let current = HOST.get_behavior().get_window_info(window as usize).map(|info| info.size);
+        if current.is_some() && current != Some(size) {
+            HOST.onWindowResize(window as usize, size.0, size.1);
+        }
+    }
+}
+
+pub unsafe extern "C" fn sdl3_createwindow_first(title: *const c_char, w: c_int, h: c_int, flags: u64) -> *mut SDL3_Window {
+    crate::track_hook!("SDL_CreateWindow (SDL3)");
+    log_debug!(Hooks, "SDL3 SDL_CreateWindow called");
+    if !HOST.config.enable_sdl3 {
+        return sdl3_createwindow_direct(title, w, h, flags);
+    }
+    HOST.features.enable_sdl3();
+    let final_w = HOST.config.window_width_override.map_or(w, |new_w| new_w as c_int);
+    let final_h = HOST.config.window_height_override.map_or(h, |new_h| new_h as c_int);
+
+    let final_title = format_window_title_prefix_cstr(title);
+    let result = sdl3_createwindow_direct(final_title.as_ref().map_or(title, |t| t.as_ptr()), final_w, final_h, flags);
+    if result.is_null() {
+        return result;
+    }
+
+    let mut window = Window::new(result as usize, Library::SDL3).with_title(utils::title_from_cstr(title));
+    (window.visible, window.fullscreen) = sdl3_window_state_from_flags(flags);
+    // track the pixel size, that's what gets captured
+    let (pixel_w, pixel_h) = sdl3_safe::SDL_GetWindowSizeInPixels_safe(result).unwrap_or((final_w, final_h));
+    HOST.onWindowCreate(window, None, None, Some(pixel_w.max(0) as u32), Some(pixel_h.max(0) as u32));
+
+    log_debug!(Hooks, "SDL3 SDL_CreateWindow called with w: {}, h: {}, {}x{} pixels", final_w, final_h, pixel_w, pixel_h);
+    result
+}
+
+unsafe fn sdl3_createwindow_direct(title: *const c_char, w: c_int, h: c_int, flags: u64) -> *mut SDL3_Window {
+    (crate::bind::sdl3::SDL_CreateWindow)(title, w, h, flags)
+}
+
+pub unsafe extern "C" fn sdl3_destroywindow_first(window: *mut SDL3_Window) {
+    crate::track_hook!("SDL_DestroyWindow (SDL3)");
+    if HOST.config.enable_sdl3 {
+        HOST.onWindowDestroy(window as usize);
+    }
+    (crate::bind::sdl3::SDL_DestroyWindow)(window)
+}
+
+pub unsafe extern "C" fn sdl3_setwindowtitle_first(window: *mut SDL3_Window, title: *const c_char) -> bool {
+    crate::track_hook!("SDL_SetWindowTitle (SDL3)");
+    if !HOST.config.enable_sdl3 {
+        return (crate::bind::sdl3::SDL_SetWindowTitle)(window, title);
+    }
+    // kept alive until SDL has made its own copy
+    let final_title = format_window_title_prefix_cstr(title);
+    let result = (crate::bind::sdl3::SDL_SetWindowTitle)(window, final_title.as_ref().map_or(title, |t| t.as_ptr()));
+    HOST.onWindowTitleChange(window as usize, utils::title_from_cstr(title));
+    result
+}
+
+pub unsafe extern "C" fn sdl3_setwindowicon_first(window: *mut SDL3_Window, icon: *mut SDL3_Surface) -> bool {
+    crate::track_hook!("SDL_SetWindowIcon (SDL3)");
+    if !HOST.config.enable_sdl3 {
+        return (crate::bind::sdl3::SDL_SetWindowIcon)(window, icon);
+    }
+    // read it before the app gets a chance to free the surface
+    let icon_data = sdl3_safe::SDL_Surface_to_rgba_safe(icon).map(|(width, height, rgba)| WindowIcon { width, height, rgba });
+    let result = (crate::bind::sdl3::SDL_SetWindowIcon)(window, icon);
+    HOST.onWindowIconChange(window as usize, icon_data);
+    result
+}
+
+pub unsafe extern "C" fn sdl3_showwindow_first(window: *mut SDL3_Window) -> bool {
+    crate::track_hook!("SDL_ShowWindow (SDL3)");
+    let result = (crate::bind::sdl3::SDL_ShowWindow)(window);
+    if HOST.config.enable_sdl3 && result {
+        HOST.onWindowVisibilityChange(window as usize, true);
+    }
+    result
+}
+
+pub unsafe extern "C" fn sdl3_hidewindow_first(window: *mut SDL3_Window) -> bool {
+    crate::track_hook!("SDL_HideWindow (SDL3)");
+    let result = (crate::bind::sdl3::SDL_HideWindow)(window);
+    if HOST.config.enable_sdl3 && result {
+        HOST.onWindowVisibilityChange(window as usize, false);
+    }
+    result
+}
+
+pub unsafe extern "C" fn sdl3_setwindowfullscreen_first(window: *mut SDL3_Window, fullscreen: bool) -> bool {
+    crate::track_hook!("SDL_SetWindowFullscreen (SDL3)");
+    let result = (crate::bind::sdl3::SDL_SetWindowFullscreen)(window, fullscreen);
+    if HOST.config.enable_sdl3 && result {
+        HOST.onWindowFullscreenChange(window as usize, fullscreen);
+    }
+    result
+}
+
+pub unsafe extern "C" fn sdl3_gl_swapwindow_first(window: *mut SDL3_Window) -> bool {
+    crate::track_hook!("SDL_GL_SwapWindow (SDL3)");
+    log_trace!(Hooks, "SDL3 SDL_GL_SwapWindow called");
+    if !HOST.config.enable_sdl3 {
+        return (crate::bind::sdl3::SDL_GL_SwapWindow)(window);
+    }
+    HOST.features.enable_sdl3();
+    HOST.features.enable_gl();
+    HOST.suggest_graphics_api(GraphicsAPI::OpenGL);
+    sync_window_size(window);
+    HOST.onFrameSwapBegin(Some(window as usize));
+    IN_SDL3_SWAP.with(|flag| flag.set(true));
+    let result = (crate::bind::sdl3::SDL_GL_SwapWindow)(window);
+    IN_SDL3_SWAP.with(|flag| flag.set(false));
+    HOST.onFrameSwapEnd();
+    result
+}
+
+pub unsafe extern "C" fn sdl3_renderpresent_first(renderer: *mut SDL3_Renderer) -> bool {
+    crate::track_hook!("SDL_RenderPresent (SDL3)");
+    log_trace!(Hooks, "SDL3 SDL_RenderPresent called");
+    if !HOST.config.enable_sdl3 {
+        return (crate::bind::sdl3::SDL_RenderPresent)(renderer);
+    }
+    let window = sdl3_safe::SDL_GetRenderWindow_safe(renderer);
+    if !window.is_null() {
+        sync_window_size(window);
+    }
+    // picks gl or SDL_RenderReadPixels depending on the renderer backend
+    HOST.onSdl3RenderPresent(renderer as usize);
+    IN_SDL3_SWAP.with(|flag| flag.set(true));
+    let result = (crate::bind::sdl3::SDL_RenderPresent)(renderer);
+    IN_SDL3_SWAP.with(|flag| flag.set(false));
+    HOST.onFrameSwapEnd();
+    result
+}
+
+pub fn SDL3_track_window_event(event: &SDL3_Event) {
+    unsafe {
+        let event_type = event.type_;
+        if !(SDL3_EVENT_WINDOW_SHOWN..=SDL3_EVENT_WINDOW_FOCUS_LOST).contains(&event_type) {
+            return;
+        }
+        let window = sdl3_safe::SDL_GetWindowFromID_safe(event.window.windowID);
+        if window.is_null() {
+            return;
+        }
+        let window_ptr = window as usize;
+        match event_type {
+            SDL3_EVENT_WINDOW_SHOWN => HOST.onWindowVisibilityChange(window_ptr, true),
+            SDL3_EVENT_WINDOW_HIDDEN => HOST.onWindowVisibilityChange(window_ptr, false),
+            SDL3_EVENT_WINDOW_FOCUS_GAINED => HOST.onWindowFocusChange(window_ptr, true),
+            SDL3_EVENT_WINDOW_FOCUS_LOST => HOST.onWindowFocusChange(window_ptr, false),
+            SDL3_EVENT_WINDOW_PIXEL_SIZE_CHANGED => {
+                // data1 and data2 are the new size in pixels
+                let (width, height) = (event.window.data1, event.window.data2);
+                if width > 0 && height > 0 && HOST.get_behavior().get_window_info(window_ptr).is_some() {
+                    HOST.onWindowResize(window_ptr, width as u32, height as u32);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+// same events the SDL2 hook hides from the app
+pub fn SDL3_should_allow_event(event: &SDL3_Event) -> bool {
+    if !HOST.config.suppress_focus_loss {
+        return true;
+    }
+    let event_type = unsafe { event.type_ };
+    !matches!(event_type, SDL3_EVENT_WINDOW_MINIMIZED | SDL3_EVENT_WINDOW_FOCUS_LOST | SDL3_EVENT_WINDOW_SHOWN)
+}
+
+pub unsafe extern "C" fn sdl3_pollevent_first(event: *mut SDL3_Event) -> bool {
+    crate::track_hook!("SDL_PollEvent (SDL3)");
+    if !HOST.config.enable_sdl3 {
+        return (crate::bind::sdl3::SDL_PollEvent)(event);
+    }
+    // flush inputs here as well
+    HOST.flush_input();
+
+    let result = (crate::bind::sdl3::SDL_PollEvent)(event);
+    // a null event only asks whether anything is pending
+    if result && !event.is_null() {
+        let event_ref = &*event;
+        // track what the real window is doing before we possibly hide the event from the app
+        SDL3_track_window_event(event_ref);
+        if !SDL3_should_allow_event(event_ref) {
+            return false;
+        }
+    }
+    result
+}
+
+fn sdl3_loaded(_config: &Config) -> bool {
+    is_sdl3() || is_sdl2_compat()
+}
+
+// same names as SDL2 so these are only handed out once libSDL3 is loaded, the handlers check ENABLE_SDL3 themselves
+pub fn register_hooks(registry: &mut HookRegistry) {
+    registry.register("SDL_CreateWindow", sdl3_createwindow_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_DestroyWindow", sdl3_destroywindow_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_SetWindowTitle", sdl3_setwindowtitle_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_SetWindowIcon", sdl3_setwindowicon_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_ShowWindow", sdl3_showwindow_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_HideWindow", sdl3_hidewindow_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_SetWindowFullscreen", sdl3_setwindowfullscreen_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_GL_SwapWindow", sdl3_gl_swapwindow_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_RenderPresent", sdl3_renderpresent_first as *const c_void, Library::SDL3, sdl3_loaded);
+    registry.register("SDL_PollEvent", sdl3_pollevent_first as *const c_void, Library::SDL3, sdl3_loaded);
+}
@@ -0,0 +1,136 @@
+// per hook call counters, so we can tell whether a game ever reaches a hook and which swap variant it uses
+// counting is always on and is just an atomic add, timing and first call backtraces are opt in
+
+use std::{sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Mutex}, time::Instant};
+
+use backtrace::Backtrace;
+use lazy_static::lazy_static;
+
+use crate::host::hosting::HOST;
+use stellar_protocol::protocol::HookCallStats;
+use stellar_shared::log_info;
+
+// bucket i counts calls that took under 2^i microseconds, the last one catches everything slower
+pub const HOOK_TIMING_BUCKETS: usize = 16;
+
+lazy_static! {
+    // every call site that has been hit at least once, in first call order
+    static ref HOOK_STATS: Mutex<Vec<&'static HookStats>> = Mutex::new(Vec::new());
+}
+
+pub struct HookStats {
+    pub name: &'static str,
+    seen: AtomicBool,
+    calls: AtomicU64,
+    timed_calls: AtomicU64,
+    total_ns: AtomicU64,
+    max_ns: AtomicU64,
+    buckets: [AtomicU64; HOOK_TIMING_BUCKETS],
+}
+
+impl HookStats {
+    pub const fn new(name: &'static str) -> HookStats {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        HookStats {
+            name,
+            seen: AtomicBool::new(false),
+            calls: AtomicU64::new(0),
+            timed_calls: AtomicU64::new(0),
+            total_ns: AtomicU64::new(0),
+            max_ns: AtomicU64::new(0),
+            buckets: [ZERO; HOOK_TIMING_BUCKETS],
+        }
+    }
+
+    pub fn enter(&'static self) -> HookCall {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if !self.seen.swap(true, Ordering::Relaxed) {
+            HOOK_STATS.lock().unwrap().push(self);
+            if HOST.config.log_first_hook_calls {
+                log_info!(Hooks, "first call to {} from {:?}", self.name, Backtrace::new());
+            }
+        }
+        HookCall {
+            stats: self,
+            start: if HOST.config.hook_timing { Some(Instant::now()) } else { None },
+        }
+    }
+
+    fn record(&self, elapsed_ns: u64) {
+        self.timed_calls.fetch_add(1, Ordering::Relaxed);
+        self.total_ns.fetch_add(elapsed_ns, Ordering::Relaxed);
+        self.max_ns.fetch_max(elapsed_ns, Ordering::Relaxed);
+        let micros = elapsed_ns / 1000;
+        let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(HOOK_TIMING_BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    pub fn to_protocol(&self) -> HookCallStats {
+        HookCallStats {
+            name: self.name.to_string(),
+            calls: self.calls(),
+            timed_calls: self.timed_calls.load(Ordering::Relaxed),
+            total_ns: self.total_ns.load(Ordering::Relaxed),
+            max_ns: self.max_ns.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let mut output = format!("{}: {} calls", self.name, self.calls());
+        let timed_calls = self.timed_calls.load(Ordering::Relaxed);
+        if timed_calls > 0 {
+            let total_ns = self.total_ns.load(Ordering::Relaxed);
+            output += &format!(", avg {:.3}ms, max {:.3}ms over {} timed",
+                total_ns as f64 / timed_calls as f64 / 1_000_000.0,
+                self.max_ns.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+                timed_calls);
+            let buckets: Vec<u64> = self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect();
+            output += &format!(", <2^n us histogram {:?}", buckets);
+        }
+        output
+    }
+}
+
+// held for the duration of a hook, records the time on drop when timing is on
+pub struct HookCall {
+    stats: &'static HookStats,
+    start: Option<Instant>,
+}
+
+impl Drop for HookCall {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            self.stats.record(start.elapsed().as_nanos() as u64);
+        }
+    }
+}
+
+// put at the top of a hook body, the guard has to stay alive until the hook returns
+#[macro_export]
+macro_rules! track_hook {
+    ($name:expr) => {
+        let _hook_call = {
+            static STATS: $crate::hooks::telemetry::HookStats = $crate::hooks::telemetry::HookStats::new($name);
+            STATS.enter()
+        };
+    };
+}
+
+// what goes out in DebugInfo, counters are atomics so this works from any thread
+pub fn hook_call_stats() -> Vec<HookCallStats> {
+    HOOK_STATS.lock().unwrap().iter().map(|hook| hook.to_protocol()).collect()
+}
+
+pub fn create_debug_output() -> String {
+    let stats = HOOK_STATS.lock().unwrap();
+    let mut output = format!("Hook Calls ({} hooks reached):\n", stats.len());
+    for hook in stats.iter() {
+        output += &format!("  {}\n", hook.describe());
+    }
+    output
+}
@@ -0,0 +1,888 @@
+use std::{collections::HashMap, ffi::CStr, sync::{Arc, Mutex}};
+
+use lazy_static::lazy_static;
+use libc::{c_char, c_void};
+use stellar_protocol::protocol::{FramePixelFormat, GraphicsAPI};
+
+use crate::{constants::vulkan::*, host::hosting::HOST};
+use stellar_shared::{log_debug, log_trace, log_warn};
+
+// vulkan apps don't go through anything we can LD_PRELOAD so this is a layer instead
+// the loader finds it through layer/hyperwarp_vk_layer.json (or VK_INSTANCE_LAYERS=VK_LAYER_STELLAR_hyperwarp with VK_LAYER_PATH)
+// and calls vkNegotiateLoaderLayerInterfaceVersion, from there everything goes through the two proc addr functions below
+pub const LAYER_NAME: &str = "VK_LAYER_STELLAR_hyperwarp";
+
+type PFN_vkCreateInstance = unsafe extern "system" fn(*const c_void, *const c_void, *mut VkDispatchable) -> VkResult;
+type PFN_vkDestroyInstance = unsafe extern "system" fn(VkDispatchable, *const c_void);
+type PFN_vkGetPhysicalDeviceMemoryProperties = unsafe extern "system" fn(VkDispatchable, *mut VkPhysicalDeviceMemoryProperties);
+type PFN_vkGetPhysicalDeviceQueueFamilyProperties = unsafe extern "system" fn(VkDispatchable, *mut u32, *mut VkQueueFamilyProperties);
+type PFN_vkCreateDevice = unsafe extern "system" fn(VkDispatchable, *const c_void, *const c_void, *mut VkDispatchable) -> VkResult;
+type PFN_vkCreateXlibSurfaceKHR = unsafe extern "system" fn(VkDispatchable, *const VkXlibSurfaceCreateInfoKHR, *const c_void, *mut VkHandle) -> VkResult;
+type PFN_vkCreateXcbSurfaceKHR = unsafe extern "system" fn(VkDispatchable, *const VkXcbSurfaceCreateInfoKHR, *const c_void, *mut VkHandle) -> VkResult;
+type PFN_vkDestroySurfaceKHR = unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void);
+
+// presents copy into a free slot and the copy gets picked up on a later present, so we never stall on the gpu
+const READBACK_RING_SIZE: usize = 3;
+// only used when the app tears a device down without idling it first
+const READBACK_TEARDOWN_TIMEOUT_NS: u64 = 1_000_000_000;
+
+lazy_static! {
+    // both keyed by the loader dispatch table pointer, which every child handle (physical devices, queues) shares with its parent
+    static ref VK_INSTANCES: Mutex<HashMap<usize, InstanceData>> = Mutex::new(HashMap::new());
+    // only locked to look a device up, everything after that goes through the device's own lock
+    static ref VK_DEVICES: Mutex<HashMap<usize, Arc<DeviceData>>> = Mutex::new(HashMap::new());
+    // the X window each surface was made for, presents use it to find the capture target
+    static ref VK_SURFACES: Mutex<HashMap<VkHandle, usize>> = Mutex::new(HashMap::new());
+}
+
+struct InstanceData {
+    instance: usize,
+    get_instance_proc_addr: PFN_vkGetInstanceProcAddr,
+    destroy_instance: PFN_vkDestroyInstance,
+    get_physical_device_memory_properties: PFN_vkGetPhysicalDeviceMemoryProperties,
+    get_physical_device_queue_family_properties: PFN_vkGetPhysicalDeviceQueueFamilyProperties,
+    // only there when the app enabled the matching surface extension
+    create_xlib_surface: Option<PFN_vkCreateXlibSurfaceKHR>,
+    create_xcb_surface: Option<PFN_vkCreateXcbSurfaceKHR>,
+    destroy_surface: Option<PFN_vkDestroySurfaceKHR>,
+}
+
+// the next layer's device functions
+struct DeviceDispatch {
+    get_device_proc_addr: PFN_vkGetDeviceProcAddr,
+    destroy_device: unsafe extern "system" fn(VkDispatchable, *const c_void),
+    get_device_queue: unsafe extern "system" fn(VkDispatchable, u32, u32, *mut VkDispatchable),
+    get_device_queue2: Option<unsafe extern "system" fn(VkDispatchable, *const VkDeviceQueueInfo2, *mut VkDispatchable)>,
+    create_command_pool: unsafe extern "system" fn(VkDispatchable, *const VkCommandPoolCreateInfo, *const c_void, *mut VkHandle) -> VkResult,
+    destroy_command_pool: unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void),
+    allocate_command_buffers: unsafe extern "system" fn(VkDispatchable, *const VkCommandBufferAllocateInfo, *mut VkDispatchable) -> VkResult,
+    begin_command_buffer: unsafe extern "system" fn(VkDispatchable, *const VkCommandBufferBeginInfo) -> VkResult,
+    end_command_buffer: unsafe extern "system" fn(VkDispatchable) -> VkResult,
+    reset_command_buffer: unsafe extern "system" fn(VkDispatchable, u32) -> VkResult,
+    cmd_pipeline_barrier: unsafe extern "system" fn(VkDispatchable, u32, u32, u32, u32, *const VkMemoryBarrier, u32, *const c_void, u32, *const VkImageMemoryBarrier),
+    cmd_copy_image_to_buffer: unsafe extern "system" fn(VkDispatchable, VkHandle, i32, VkHandle, u32, *const VkBufferImageCopy),
+    queue_submit: unsafe extern "system" fn(VkDispatchable, u32, *const VkSubmitInfo, VkHandle) -> VkResult,
+    create_fence: unsafe extern "system" fn(VkDispatchable, *const VkFenceCreateInfo, *const c_void, *mut VkHandle) -> VkResult,
+    destroy_fence: unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void),
+    get_fence_status: unsafe extern "system" fn(VkDispatchable, VkHandle) -> VkResult,
+    wait_for_fences: unsafe extern "system" fn(VkDispatchable, u32, *const VkHandle, u32, u64) -> VkResult,
+    reset_fences: unsafe extern "system" fn(VkDispatchable, u32, *const VkHandle) -> VkResult,
+    create_semaphore: unsafe extern "system" fn(VkDispatchable, *const VkSemaphoreCreateInfo, *const c_void, *mut VkHandle) -> VkResult,
+    destroy_semaphore: unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void),
+    create_buffer: unsafe extern "system" fn(VkDispatchable, *const VkBufferCreateInfo, *const c_void, *mut VkHandle) -> VkResult,
+    destroy_buffer: unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void),
+    get_buffer_memory_requirements: unsafe extern "system" fn(VkDispatchable, VkHandle, *mut VkMemoryRequirements),
+    allocate_memory: unsafe extern "system" fn(VkDispatchable, *const VkMemoryAllocateInfo, *const c_void, *mut VkHandle) -> VkResult,
+    free_memory: unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void),
+    bind_buffer_memory: unsafe extern "system" fn(VkDispatchable, VkHandle, VkHandle, u64) -> VkResult,
+    map_memory: unsafe extern "system" fn(VkDispatchable, VkHandle, u64, u64, u32, *mut *mut c_void) -> VkResult,
+    unmap_memory: unsafe extern "system" fn(VkDispatchable, VkHandle),
+    // only there when the app enabled VK_KHR_swapchain
+    create_swapchain: Option<unsafe extern "system" fn(VkDispatchable, *const VkSwapchainCreateInfoKHR, *const c_void, *mut VkHandle) -> VkResult>,
+    destroy_swapchain: Option<unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void)>,
+    get_swapchain_images: Option<unsafe extern "system" fn(VkDispatchable, VkHandle, *mut u32, *mut VkHandle) -> VkResult>,
+    queue_present: Option<unsafe extern "system" fn(VkDispatchable, *const VkPresentInfoKHR) -> VkResult>,
+}
+
+struct SwapchainData {
+    images: Vec<VkHandle>,
+    format: i32,
+    width: u32,
+    height: u32,
+    // None when the surface came from somewhere we don't hook (wayland, a layer above us)
+    window_id: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ReadbackFrame {
+    width: u32,
+    height: u32,
+    format: FramePixelFormat,
+    sequence: u64,
+}
+
+// one slot of the ring, a host visible buffer the presented image gets copied into plus what we need to record the copy
+struct Readback {
+    queue_family: u32,
+    command_pool: VkHandle,
+    command_buffer: usize,
+    fence: VkHandle,
+    // signalled by the copy, the real present waits on it instead of the app's semaphores
+    semaphore: VkHandle,
+    buffer: VkHandle,
+    memory: VkHandle,
+    size: u64,
+    mapped: usize,
+    // set while the copy is on the gpu, what the buffer holds once the fence signals
+    in_flight: Option<ReadbackFrame>,
+}
+
+// everything that changes after vkCreateDevice, behind the device's own lock
+struct DeviceState {
+    // queue handle to the family it came from
+    queue_families: HashMap<usize, u32>,
+    // only swapchains we could add transfer src usage to
+    swapchains: HashMap<VkHandle, SwapchainData>,
+    readbacks: Vec<Option<Readback>>,
+    // numbers submitted copies so a slow slot never gets delivered after a newer one
+    submitted: u64,
+    delivered: u64,
+}
+
+struct DeviceData {
+    device: usize,
+    dispatch: DeviceDispatch,
+    // from the loader's create info, gives command buffers we allocate the loader's dispatch table
+    set_device_loader_data: Option<PFN_vkSetDeviceLoaderData>,
+    memory_properties: VkPhysicalDeviceMemoryProperties,
+    // queue flags of every family on the physical device, indexed by family
+    queue_family_flags: Vec<u32>,
+    state: Mutex<DeviceState>,
+}
+
+unsafe fn dispatch_key(handle: VkDispatchable) -> usize {
+    *(handle as *const usize)
+}
+
+// clones the device out so the map isn't locked while we're in the driver
+unsafe fn device_data(handle: VkDispatchable) -> Option<Arc<DeviceData>> {
+    VK_DEVICES.lock().unwrap().get(&dispatch_key(handle)).cloned()
+}
+
+fn capture_enabled() -> bool {
+    HOST.config.enable_vulkan && HOST.config.capture_mode && !HOST.config.netural_mode
+}
+
+// swapchain alpha is whatever the app left there, the compositor ignores it so we do too
+fn pixel_format_for_vk_format(format: i32) -> Option<FramePixelFormat> {
+    match format {
+        VK_FORMAT_B8G8R8A8_UNORM | VK_FORMAT_B8G8R8A8_SRGB => Some(FramePixelFormat::Bgrx),
+        VK_FORMAT_R8G8B8A8_UNORM | VK_FORMAT_R8G8B8A8_SRGB => Some(FramePixelFormat::Rgbx),
+        _ => None,
+    }
+}
+
+// the loader puts its infos for us somewhere in the create info's pNext chain, function says which one we want
+unsafe fn find_layer_create_info(create_info: *const c_void, s_type: i32, function: i32) -> *mut VkLayerInstanceCreateInfo {
+    // instance and device infos share a layout up to the union
+    let mut current = (*(create_info as *const VkBaseInStructure)).p_next;
+    while !current.is_null() {
+        let info = current as *mut VkLayerInstanceCreateInfo;
+        if (*current).s_type == s_type && (*info).function == function {
+            return info;
+        }
+        current = (*current).p_next;
+    }
+    std::ptr::null_mut()
+}
+
+macro_rules! load_required {
+    ($gpa:expr, $handle:expr, $name:literal) => {
+        match $gpa($handle, concat!($name, "\0").as_ptr() as *const c_char) {
+            Some(func) => std::mem::transmute(func),
+            None => {
+                log_warn!(Hooks, "vulkan layer: next layer has no {}", $name);
+                return None;
+            }
+        }
+    };
+}
+
+macro_rules! load_optional {
+    ($gpa:expr, $handle:expr, $name:literal) => {
+        $gpa($handle, concat!($name, "\0").as_ptr() as *const c_char).map(|func| std::mem::transmute(func))
+    };
+}
+
+macro_rules! as_void_function {
+    ($func:expr) => {
+        Some(std::mem::transmute::<*const (), unsafe extern "system" fn()>($func as *const ()))
+    };
+}
+
+impl DeviceDispatch {
+    unsafe fn load(gdpa: PFN_vkGetDeviceProcAddr, device: VkDispatchable) -> Option<Self> {
+        Some(DeviceDispatch {
+            get_device_proc_addr: gdpa,
+            destroy_device: load_required!(gdpa, device, "vkDestroyDevice"),
+            get_device_queue: load_required!(gdpa, device, "vkGetDeviceQueue"),
+            get_device_queue2: load_optional!(gdpa, device, "vkGetDeviceQueue2"),
+            create_command_pool: load_required!(gdpa, device, "vkCreateCommandPool"),
+            destroy_command_pool: load_required!(gdpa, device, "vkDestroyCommandPool"),
+            allocate_command_buffers: load_required!(gdpa, device, "vkAllocateCommandBuffers"),
+            begin_command_buffer: load_required!(gdpa, device, "vkBeginCommandBuffer"),
+            end_command_buffer: load_required!(gdpa, device, "vkEndCommandBuffer"),
+            reset_command_buffer: load_required!(gdpa, device, "vkResetCommandBuffer"),
+            cmd_pipeline_barrier: load_required!(gdpa, device, "vkCmdPipelineBarrier"),
+            cmd_copy_image_to_buffer: load_required!(gdpa, device, "vkCmdCopyImageToBuffer"),
+            queue_submit: load_required!(gdpa, device, "vkQueueSubmit"),
+            create_fence: load_required!(gdpa, device, "vkCreateFence"),
+            destroy_fence: load_required!(gdpa, device, "vkDestroyFence"),
+            get_fence_status: load_required!(gdpa, device, "vkGetFenceStatus"),
+            wait_for_fences: load_required!(gdpa, device, "vkWaitForFences"),
+            reset_fences: load_required!(gdpa, device, "vkResetFences"),
+            create_semaphore: load_required!(gdpa, device, "vkCreateSemaphore"),
+            destroy_semaphore: load_required!(gdpa, device, "vkDestroySemaphore"),
+            create_buffer: load_required!(gdpa, device, "vkCreateBuffer"),
+            destroy_buffer: load_required!(gdpa, device, "vkDestroyBuffer"),
+            get_buffer_memory_requirements: load_required!(gdpa, device, "vkGetBufferMemoryRequirements"),
+            allocate_memory: load_required!(gdpa, device, "vkAllocateMemory"),
+            free_memory: load_required!(gdpa, device, "vkFreeMemory"),
+            bind_buffer_memory: load_required!(gdpa, device, "vkBindBufferMemory"),
+            map_memory: load_required!(gdpa, device, "vkMapMemory"),
+            unmap_memory: load_required!(gdpa, device, "vkUnmapMemory"),
+            create_swapchain: load_optional!(gdpa, device, "vkCreateSwapchainKHR"),
+            destroy_swapchain: load_optional!(gdpa, device, "vkDestroySwapchainKHR"),
+            get_swapchain_images: load_optional!(gdpa, device, "vkGetSwapchainImagesKHR"),
+            queue_present: load_optional!(gdpa, device, "vkQueuePresentKHR"),
+        })
+    }
+}
+
+impl DeviceData {
+    fn handle(&self) -> VkDispatchable {
+        self.device as VkDispatchable
+    }
+
+    fn find_memory_type(&self, type_bits: u32, flags: u32) -> Option<u32> {
+        (0..self.memory_properties.memory_type_count).find(|&index| {
+            type_bits & (1 << index) != 0 && self.memory_properties.memory_types[index as usize].property_flags & flags == flags
+        })
+    }
+
+    unsafe fn destroy_readbacks(&self, state: &mut DeviceState) {
+        for slot in state.readbacks.iter_mut() {
+            if let Some(readback) = slot.take() {
+                self.free_readback(readback);
+            }
+        }
+    }
+
+    // destroying null handles is a no-op so this also cleans up half built readbacks
+    unsafe fn free_readback(&self, readback: Readback) {
+        let device = self.handle();
+        let dispatch = &self.dispatch;
+        if readback.in_flight.is_some() {
+            // apps idle the device before tearing things down, this is for the ones that don't
+            (dispatch.wait_for_fences)(device, 1, &readback.fence, 1, READBACK_TEARDOWN_TIMEOUT_NS);
+        }
+        if readback.mapped != 0 {
+            (dispatch.unmap_memory)(device, readback.memory);
+        }
+        (dispatch.destroy_buffer)(device, readback.buffer, std::ptr::null());
+        (dispatch.free_memory)(device, readback.memory, std::ptr::null());
+        (dispatch.destroy_semaphore)(device, readback.semaphore, std::ptr::null());
+        (dispatch.destroy_fence)(device, readback.fence, std::ptr::null());
+        // frees the command buffer too
+        (dispatch.destroy_command_pool)(device, readback.command_pool, std::ptr::null());
+    }
+
+    unsafe fn create_readback(&self, queue_family: u32, size: u64) -> Result<Readback, String> {
+        let mut readback = Readback {
+            queue_family,
+            command_pool: 0,
+            command_buffer: 0,
+            fence: 0,
+            semaphore: 0,
+            buffer: 0,
+            memory: 0,
+            size,
+            mapped: 0,
+            in_flight: None,
+        };
+        match self.init_readback(&mut readback) {
+            Ok(()) => Ok(readback),
+            Err(err) => {
+                self.free_readback(readback);
+                Err(err)
+            }
+        }
+    }
+
+    unsafe fn init_readback(&self, readback: &mut Readback) -> Result<(), String> {
+        let device = self.handle();
+        let dispatch = &self.dispatch;
+        let set_device_loader_data = self.set_device_loader_data.ok_or_else(|| "the loader gave us no vkSetDeviceLoaderData".to_string())?;
+        let pool_info = VkCommandPoolCreateInfo {
+            s_type: VK_STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO,
+            p_next: std::ptr::null(),
+            flags: VK_COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER_BIT,
+            queue_family_index: readback.queue_family,
+        };
+        check((dispatch.create_command_pool)(device, &pool_info, std::ptr::null(), &mut readback.command_pool), "vkCreateCommandPool")?;
+        let alloc_info = VkCommandBufferAllocateInfo {
+            s_type: VK_STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
+            p_next: std::ptr::null(),
+            command_pool: readback.command_pool,
+            level: VK_COMMAND_BUFFER_LEVEL_PRIMARY,
+            command_buffer_count: 1,
+        };
+        let mut command_buffer: VkDispatchable = std::ptr::null_mut();
+        check((dispatch.allocate_command_buffers)(device, &alloc_info, &mut command_buffer), "vkAllocateCommandBuffers")?;
+        readback.command_buffer = command_buffer as usize;
+        // command buffers made below the loader don't have its dispatch table yet
+        check(set_device_loader_data(device, command_buffer), "vkSetDeviceLoaderData")?;
+
+        let fence_info = VkFenceCreateInfo { s_type: VK_STRUCTURE_TYPE_FENCE_CREATE_INFO, p_next: std::ptr::null(), flags: 0 };
+        check((dispatch.create_fence)(device, &fence_info, std::ptr::null(), &mut readback.fence), "vkCreateFence")?;
+        let semaphore_info = VkSemaphoreCreateInfo { s_type: VK_STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO, p_next: std::ptr::null(), flags: 0 };
+        check((dispatch.create_semaphore)(device, &semaphore_info, std::ptr::null(), &mut readback.semaphore), "vkCreateSemaphore")?;
+
+        let buffer_info = VkBufferCreateInfo {
+            s_type: VK_STRUCTURE_TYPE_BUFFER_CREATE_INFO,
+            p_next: std::ptr::null(),
+            flags: 0,
+            size: readback.size,
+            usage: VK_BUFFER_USAGE_TRANSFER_DST_BIT,
+            sharing_mode: VK_SHARING_MODE_EXCLUSIVE,
+            queue_family_index_count: 0,
+            p_queue_family_indices: std::ptr::null(),
+        };
+        check((dispatch.create_buffer)(device, &buffer_info, std::ptr::null(), &mut readback.buffer), "vkCreateBuffer")?;
+        let mut requirements = VkMemoryRequirements::default();
+        (dispatch.get_buffer_memory_requirements)(device, readback.buffer, &mut requirements);
+        let memory_type_index = self
+            .find_memory_type(requirements.memory_type_bits, VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT | VK_MEMORY_PROPERTY_HOST_COHERENT_BIT)
+            .ok_or_else(|| "no host visible coherent memory type".to_string())?;
+        let memory_info = VkMemoryAllocateInfo {
+            s_type: VK_STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
+            p_next: std::ptr::null(),
+            allocation_size: requirements.size,
+            memory_type_index,
+        };
+        check((dispatch.allocate_memory)(device, &memory_info, std::ptr::null(), &mut readback.memory), "vkAllocateMemory")?;
+        check((dispatch.bind_buffer_memory)(device, readback.buffer, readback.memory, 0), "vkBindBufferMemory")?;
+        let mut mapped: *mut c_void = std::ptr::null_mut();
+        check((dispatch.map_memory)(device, readback.memory, 0, readback.size, 0, &mut mapped), "vkMapMemory")?;
+        readback.mapped = mapped as usize;
+        Ok(())
+    }
+
+    // hands the newest finished copy to the host and frees every finished slot, never waits on the gpu
+    unsafe fn collect_readbacks(&self, state: &mut DeviceState) {
+        let device = self.handle();
+        let mut newest: Option<(usize, ReadbackFrame)> = None;
+        let mut finished = Vec::new();
+        for (index, slot) in state.readbacks.iter().enumerate() {
+            let (readback, frame) = match slot {
+                Some(readback) => match readback.in_flight {
+                    Some(frame) => (readback, frame),
+                    None => continue,
+                },
+                None => continue,
+            };
+            // VK_NOT_READY, or the device is lost and the slot stays stuck which is fine too
+            if (self.dispatch.get_fence_status)(device, readback.fence) != VK_SUCCESS {
+                continue;
+            }
+            finished.push(index);
+            if newest.is_none_or(|(_, newest)| frame.sequence > newest.sequence) {
+                newest = Some((index, frame));
+            }
+        }
+        if let Some((index, frame)) = newest {
+            if frame.sequence > state.delivered {
+                let readback = state.readbacks[index].as_ref().unwrap();
+                let data = std::slice::from_raw_parts(readback.mapped as *const u8, frame.width as usize * frame.height as usize * 4);
+                HOST.onVulkanFrame(frame.width, frame.height, frame.format, data);
+                state.delivered = frame.sequence;
+            }
+        }
+        for index in finished {
+            let readback = state.readbacks[index].as_mut().unwrap();
+            (self.dispatch.reset_fences)(device, 1, &readback.fence);
+            readback.in_flight = None;
+        }
+    }
+
+    // the first swapchain in the present that shows the capture target
+    unsafe fn pick_capture_swapchain(&self, state: &DeviceState, info: &VkPresentInfoKHR) -> Option<(VkHandle, u32)> {
+        let mut picked = None;
+        for index in 0..info.swapchain_count as usize {
+            let swapchain = *info.p_swapchains.add(index);
+            let data = match state.swapchains.get(&swapchain) {
+                Some(data) => data,
+                None => continue,
+            };
+            // this also marks the window as swapping so keep going after we found one
+            if HOST.onVulkanPresent(data.window_id) && picked.is_none() {
+                picked = Some((swapchain, *info.p_image_indices.add(index)));
+            }
+        }
+        picked
+    }
+
+    // records a copy of the presented image into a free slot and submits it after the app's wait semaphores
+    // returns the semaphore the real present has to wait on instead of those
+    unsafe fn capture_present(&self, state: &mut DeviceState, queue: VkDispatchable, swapchain: VkHandle, image_index: u32, info: &VkPresentInfoKHR) -> Result<VkHandle, String> {
+        let (image, format, width, height) = match state.swapchains.get(&swapchain) {
+            Some(data) => match data.images.get(image_index as usize) {
+                Some(image) => (*image, data.format, data.width, data.height),
+                None => return Err(format!("image index {} out of range", image_index)),
+            },
+            None => return Err("swapchain is not capturable".to_string()),
+        };
+        let pixel_format = pixel_format_for_vk_format(format).ok_or_else(|| format!("unsupported swapchain format {}", format))?;
+        let queue_family = *state.queue_families.get(&(queue as usize)).ok_or_else(|| "present queue never came through vkGetDeviceQueue".to_string())?;
+        // graphics and compute queues can always do transfers even when they don't say so
+        let queue_flags = self.queue_family_flags.get(queue_family as usize).copied().unwrap_or(0);
+        if queue_flags & (VK_QUEUE_GRAPHICS_BIT | VK_QUEUE_COMPUTE_BIT | VK_QUEUE_TRANSFER_BIT) == 0 {
+            return Err(format!("present queue family {} can't do transfers", queue_family));
+        }
+        let index = state
+            .readbacks
+            .iter()
+            .position(|slot| slot.as_ref().is_none_or(|readback| readback.in_flight.is_none()))
+            .ok_or_else(|| "every readback is still in flight".to_string())?;
+        let size = width as u64 * height as u64 * 4;
+        let reusable = matches!(&state.readbacks[index], Some(readback) if readback.queue_family == queue_family && readback.size >= size);
+        if !reusable {
+            if let Some(old) = state.readbacks[index].take() {
+                self.free_readback(old);
+            }
+            state.readbacks[index] = Some(self.create_readback(queue_family, size)?);
+        }
+
+        let dispatch = &self.dispatch;
+        let readback = state.readbacks[index].as_mut().unwrap();
+        let command_buffer = readback.command_buffer as VkDispatchable;
+        let color_range = VkImageSubresourceRange { aspect_mask: VK_IMAGE_ASPECT_COLOR_BIT, base_mip_level: 0, level_count: 1, base_array_layer: 0, layer_count: 1 };
+
+        check((dispatch.reset_command_buffer)(command_buffer, 0), "vkResetCommandBuffer")?;
+        let begin_info = VkCommandBufferBeginInfo {
+            s_type: VK_STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
+            p_next: std::ptr::null(),
+            flags: VK_COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT_BIT,
+            p_inheritance_info: std::ptr::null(),
+        };
+        check((dispatch.begin_command_buffer)(command_buffer, &begin_info), "vkBeginCommandBuffer")?;
+        let to_transfer = VkImageMemoryBarrier {
+            s_type: VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+            p_next: std::ptr::null(),
+            src_access_mask: 0,
+            dst_access_mask: VK_ACCESS_TRANSFER_READ_BIT,
+            old_layout: VK_IMAGE_LAYOUT_PRESENT_SRC_KHR,
+            new_layout: VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+            src_queue_family_index: VK_QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: VK_QUEUE_FAMILY_IGNORED,
+            image,
+            subresource_range: color_range,
+        };
+        (dispatch.cmd_pipeline_barrier)(command_buffer, VK_PIPELINE_STAGE_ALL_COMMANDS_BIT, VK_PIPELINE_STAGE_TRANSFER_BIT, 0, 0, std::ptr::null(), 0, std::ptr::null(), 1, &to_transfer);
+        let region = VkBufferImageCopy {
+            buffer_offset: 0,
+            // tightly packed
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: VkImageSubresourceLayers { aspect_mask: VK_IMAGE_ASPECT_COLOR_BIT, mip_level: 0, base_array_layer: 0, layer_count: 1 },
+            image_offset: VkOffset3D { x: 0, y: 0, z: 0 },
+            image_extent: VkExtent3D { width, height, depth: 1 },
+        };
+        (dispatch.cmd_copy_image_to_buffer)(command_buffer, image, VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL, readback.buffer, 1, &region);
+        let to_present = VkImageMemoryBarrier {
+            s_type: VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+            p_next: std::ptr::null(),
+            src_access_mask: VK_ACCESS_TRANSFER_READ_BIT,
+            dst_access_mask: VK_ACCESS_MEMORY_READ_BIT,
+            old_layout: VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+            new_layout: VK_IMAGE_LAYOUT_PRESENT_SRC_KHR,
+            src_queue_family_index: VK_QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: VK_QUEUE_FAMILY_IGNORED,
+            image,
+            subresource_range: color_range,
+        };
+        let to_host = VkMemoryBarrier {
+            s_type: VK_STRUCTURE_TYPE_MEMORY_BARRIER,
+            p_next: std::ptr::null(),
+            src_access_mask: VK_ACCESS_TRANSFER_WRITE_BIT,
+            dst_access_mask: VK_ACCESS_HOST_READ_BIT,
+        };
+        (dispatch.cmd_pipeline_barrier)(command_buffer, VK_PIPELINE_STAGE_TRANSFER_BIT, VK_PIPELINE_STAGE_ALL_COMMANDS_BIT | VK_PIPELINE_STAGE_HOST_BIT, 0, 1, &to_host, 0, std::ptr::null(), 1, &to_present);
+        check((dispatch.end_command_buffer)(command_buffer), "vkEndCommandBuffer")?;
+
+        let wait_stages = vec![VK_PIPELINE_STAGE_ALL_COMMANDS_BIT; info.wait_semaphore_count as usize];
+        let submit_info = VkSubmitInfo {
+            s_type: VK_STRUCTURE_TYPE_SUBMIT_INFO,
+            p_next: std::ptr::null(),
+            wait_semaphore_count: info.wait_semaphore_count,
+            p_wait_semaphores: info.p_wait_semaphores,
+            p_wait_dst_stage_mask: wait_stages.as_ptr(),
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer,
+            signal_semaphore_count: 1,
+            p_signal_semaphores: &readback.semaphore,
+        };
+        check((dispatch.queue_submit)(queue, 1, &submit_info, readback.fence), "vkQueueSubmit")?;
+        state.submitted += 1;
+        readback.in_flight = Some(ReadbackFrame { width, height, format: pixel_format, sequence: state.submitted });
+        Ok(readback.semaphore)
+    }
+}
+
+fn check(result: VkResult, what: &str) -> Result<(), String> {
+    if result == VK_SUCCESS {
+        Ok(())
+    } else {
+        Err(format!("{} failed with {}", what, result))
+    }
+}
+
+unsafe extern "system" fn hw_vkCreateInstance(create_info: *const c_void, allocator: *const c_void, instance: *mut VkDispatchable) -> VkResult {
+    crate::track_hook!("vkCreateInstance");
+    let layer_info = find_layer_create_info(create_info, VK_STRUCTURE_TYPE_LOADER_INSTANCE_CREATE_INFO, VK_LAYER_LINK_INFO);
+    if layer_info.is_null() {
+        return VK_ERROR_INITIALIZATION_FAILED;
+    }
+    let link = (*layer_info).u as *mut VkLayerInstanceLink;
+    let next_get_instance_proc_addr = (*link).pfn_next_get_instance_proc_addr;
+    // advance the chain so the next layer sees its own link
+    (*layer_info).u = (*link).p_next as *mut c_void;
+
+    let create: PFN_vkCreateInstance = match next_get_instance_proc_addr(std::ptr::null_mut(), b"vkCreateInstance\0".as_ptr() as *const c_char) {
+        Some(func) => std::mem::transmute(func),
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    let result = create(create_info, allocator, instance);
+    if result != VK_SUCCESS {
+        return result;
+    }
+    let load = || -> Option<InstanceData> {
+        Some(InstanceData {
+            instance: *instance as usize,
+            get_instance_proc_addr: next_get_instance_proc_addr,
+            destroy_instance: load_required!(next_get_instance_proc_addr, *instance, "vkDestroyInstance"),
+            get_physical_device_memory_properties: load_required!(next_get_instance_proc_addr, *instance, "vkGetPhysicalDeviceMemoryProperties"),
+            get_physical_device_queue_family_properties: load_required!(next_get_instance_proc_addr, *instance, "vkGetPhysicalDeviceQueueFamilyProperties"),
+            create_xlib_surface: load_optional!(next_get_instance_proc_addr, *instance, "vkCreateXlibSurfaceKHR"),
+            create_xcb_surface: load_optional!(next_get_instance_proc_addr, *instance, "vkCreateXcbSurfaceKHR"),
+            destroy_surface: load_optional!(next_get_instance_proc_addr, *instance, "vkDestroySurfaceKHR"),
+        })
+    };
+    match load() {
+        Some(data) => {
+            VK_INSTANCES.lock().unwrap().insert(dispatch_key(*instance), data);
+        },
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    }
+    if HOST.config.enable_vulkan {
+        HOST.features.enable_vulkan();
+        HOST.suggest_graphics_api(GraphicsAPI::Vulkan);
+    }
+    log_debug!(Hooks, "vulkan layer: instance {:?} created", *instance);
+    result
+}
+
+unsafe extern "system" fn hw_vkDestroyInstance(instance: VkDispatchable, allocator: *const c_void) {
+    crate::track_hook!("vkDestroyInstance");
+    if instance.is_null() {
+        return;
+    }
+    let data = VK_INSTANCES.lock().unwrap().remove(&dispatch_key(instance));
+    if let Some(data) = data {
+        (data.destroy_instance)(instance, allocator);
+    }
+}
+
+unsafe extern "system" fn hw_vkCreateXlibSurfaceKHR(instance: VkDispatchable, create_info: *const VkXlibSurfaceCreateInfoKHR, allocator: *const c_void, surface: *mut VkHandle) -> VkResult {
+    crate::track_hook!("vkCreateXlibSurfaceKHR");
+    let create = match VK_INSTANCES.lock().unwrap().get(&dispatch_key(instance)).and_then(|data| data.create_xlib_surface) {
+        Some(create) => create,
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    let result = create(instance, create_info, allocator, surface);
+    if result == VK_SUCCESS {
+        VK_SURFACES.lock().unwrap().insert(*surface, (*create_info).window as usize);
+    }
+    result
+}
+
+unsafe extern "system" fn hw_vkCreateXcbSurfaceKHR(instance: VkDispatchable, create_info: *const VkXcbSurfaceCreateInfoKHR, allocator: *const c_void, surface: *mut VkHandle) -> VkResult {
+    crate::track_hook!("vkCreateXcbSurfaceKHR");
+    let create = match VK_INSTANCES.lock().unwrap().get(&dispatch_key(instance)).and_then(|data| data.create_xcb_surface) {
+        Some(create) => create,
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    let result = create(instance, create_info, allocator, surface);
+    if result == VK_SUCCESS {
+        // xcb window ids are the same X ids the xlib hooks see
+        VK_SURFACES.lock().unwrap().insert(*surface, (*create_info).window as usize);
+    }
+    result
+}
+
+unsafe extern "system" fn hw_vkDestroySurfaceKHR(instance: VkDispatchable, surface: VkHandle, allocator: *const c_void) {
+    crate::track_hook!("vkDestroySurfaceKHR");
+    VK_SURFACES.lock().unwrap().remove(&surface);
+    let destroy = VK_INSTANCES.lock().unwrap().get(&dispatch_key(instance)).and_then(|data| data.destroy_surface);
+    if let Some(destroy) = destroy {
+        destroy(instance, surface, allocator);
+    }
+}
+
+unsafe extern "system" fn hw_vkCreateDevice(physical_device: VkDispatchable, create_info: *const c_void, allocator: *const c_void, device: *mut VkDispatchable) -> VkResult {
+    crate::track_hook!("vkCreateDevice");
+    let layer_info = find_layer_create_info(create_info, VK_STRUCTURE_TYPE_LOADER_DEVICE_CREATE_INFO, VK_LAYER_LINK_INFO);
+    if layer_info.is_null() {
+        return VK_ERROR_INITIALIZATION_FAILED;
+    }
+    let link = (*layer_info).u as *mut VkLayerDeviceLink;
+    let next_get_instance_proc_addr = (*link).pfn_next_get_instance_proc_addr;
+    let next_get_device_proc_addr = (*link).pfn_next_get_device_proc_addr;
+    (*layer_info).u = (*link).p_next as *mut c_void;
+    let loader_data_info = find_layer_create_info(create_info, VK_STRUCTURE_TYPE_LOADER_DEVICE_CREATE_INFO, VK_LOADER_DATA_CALLBACK);
+    let set_device_loader_data: Option<PFN_vkSetDeviceLoaderData> = if loader_data_info.is_null() || (*loader_data_info).u.is_null() {
+        None
+    } else {
+        Some(std::mem::transmute((*loader_data_info).u))
+    };
+
+    let (instance, get_memory_properties, get_queue_family_properties) = match VK_INSTANCES.lock().unwrap().get(&dispatch_key(physical_device)) {
+        Some(data) => (data.instance as VkDispatchable, data.get_physical_device_memory_properties, data.get_physical_device_queue_family_properties),
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    let create: PFN_vkCreateDevice = match next_get_instance_proc_addr(instance, b"vkCreateDevice\0".as_ptr() as *const c_char) {
+        Some(func) => std::mem::transmute(func),
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    let result = create(physical_device, create_info, allocator, device);
+    if result != VK_SUCCESS {
+        return result;
+    }
+    let dispatch = match DeviceDispatch::load(next_get_device_proc_addr, *device) {
+        Some(dispatch) => dispatch,
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    if set_device_loader_data.is_none() {
+        log_warn!(Hooks, "vulkan layer: the loader didn't pass vkSetDeviceLoaderData, device {:?} won't be captured", *device);
+    }
+    let mut memory_properties: VkPhysicalDeviceMemoryProperties = std::mem::zeroed();
+    get_memory_properties(physical_device, &mut memory_properties);
+    let mut family_count = 0;
+    get_queue_family_properties(physical_device, &mut family_count, std::ptr::null_mut());
+    let mut families = vec![std::mem::zeroed::<VkQueueFamilyProperties>(); family_count as usize];
+    get_queue_family_properties(physical_device, &mut family_count, families.as_mut_ptr());
+    families.truncate(family_count as usize);
+    VK_DEVICES.lock().unwrap().insert(dispatch_key(*device), Arc::new(DeviceData {
+        device: *device as usize,
+        dispatch,
+        set_device_loader_data,
+        memory_properties,
+        queue_family_flags: families.iter().map(|family| family.queue_flags).collect(),
+        state: Mutex::new(DeviceState {
+            queue_families: HashMap::new(),
+            swapchains: HashMap::new(),
+            readbacks: (0..READBACK_RING_SIZE).map(|_| None).collect(),
+            submitted: 0,
+            delivered: 0,
+        }),
+    }));
+    log_debug!(Hooks, "vulkan layer: device {:?} created", *device);
+    result
+}
+
+unsafe extern "system" fn hw_vkDestroyDevice(device: VkDispatchable, allocator: *const c_void) {
+    crate::track_hook!("vkDestroyDevice");
+    if device.is_null() {
+        return;
+    }
+    let data = VK_DEVICES.lock().unwrap().remove(&dispatch_key(device));
+    if let Some(data) = data {
+        data.destroy_readbacks(&mut data.state.lock().unwrap());
+        (data.dispatch.destroy_device)(device, allocator);
+    }
+}
+
+unsafe extern "system" fn hw_vkGetDeviceQueue(device: VkDispatchable, queue_family_index: u32, queue_index: u32, queue: *mut VkDispatchable) {
+    crate::track_hook!("vkGetDeviceQueue");
+    if let Some(data) = device_data(device) {
+        (data.dispatch.get_device_queue)(device, queue_family_index, queue_index, queue);
+        if !(*queue).is_null() {
+            data.state.lock().unwrap().queue_families.insert(*queue as usize, queue_family_index);
+        }
+    }
+}
+
+unsafe extern "system" fn hw_vkGetDeviceQueue2(device: VkDispatchable, queue_info: *const VkDeviceQueueInfo2, queue: *mut VkDispatchable) {
+    crate::track_hook!("vkGetDeviceQueue2");
+    if let Some(data) = device_data(device) {
+        if let Some(get_device_queue2) = data.dispatch.get_device_queue2 {
+            get_device_queue2(device, queue_info, queue);
+            if !(*queue).is_null() {
+                data.state.lock().unwrap().queue_families.insert(*queue as usize, (*queue_info).queue_family_index);
+            }
+        }
+    }
+}
+
+unsafe extern "system" fn hw_vkCreateSwapchainKHR(device: VkDispatchable, create_info: *const VkSwapchainCreateInfoKHR, allocator: *const c_void, swapchain: *mut VkHandle) -> VkResult {
+    crate::track_hook!("vkCreateSwapchainKHR");
+    let data = match device_data(device) {
+        Some(data) => data,
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    let create = match data.dispatch.create_swapchain {
+        Some(create) => create,
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    if !HOST.config.enable_vulkan {
+        return create(device, create_info, allocator, swapchain);
+    }
+    // we need to be able to copy out of the images
+    let mut modified = *create_info;
+    modified.image_usage |= VK_IMAGE_USAGE_TRANSFER_SRC_BIT;
+    let result = create(device, &modified, allocator, swapchain);
+    if result != VK_SUCCESS {
+        log_warn!(Hooks, "vulkan layer: swapchain creation with transfer src failed ({}), this swapchain won't be captured", result);
+        return create(device, create_info, allocator, swapchain);
+    }
+    if let Some(get_images) = data.dispatch.get_swapchain_images {
+        let mut count = 0;
+        get_images(device, *swapchain, &mut count, std::ptr::null_mut());
+        let mut images = vec![0; count as usize];
+        if get_images(device, *swapchain, &mut count, images.as_mut_ptr()) == VK_SUCCESS {
+            images.truncate(count as usize);
+            let window_id = VK_SURFACES.lock().unwrap().get(&modified.surface).copied();
+            log_debug!(Hooks, "vulkan layer: swapchain {} for window {:?} is {}x{} format {} with {} images", *swapchain, window_id, modified.image_extent.width, modified.image_extent.height, modified.image_format, images.len());
+            data.state.lock().unwrap().swapchains.insert(*swapchain, SwapchainData {
+                images,
+                format: modified.image_format,
+                width: modified.image_extent.width,
+                height: modified.image_extent.height,
+                window_id,
+            });
+        }
+    }
+    result
+}
+
+unsafe extern "system" fn hw_vkDestroySwapchainKHR(device: VkDispatchable, swapchain: VkHandle, allocator: *const c_void) {
+    crate::track_hook!("vkDestroySwapchainKHR");
+    if let Some(data) = device_data(device) {
+        data.state.lock().unwrap().swapchains.remove(&swapchain);
+        if let Some(destroy) = data.dispatch.destroy_swapchain {
+            destroy(device, swapchain, allocator);
+        }
+    }
+}
+
+unsafe extern "system" fn hw_vkQueuePresentKHR(queue: VkDispatchable, present_info: *const VkPresentInfoKHR) -> VkResult {
+    crate::track_hook!("vkQueuePresentKHR");
+    // queues share their device's dispatch table
+    let data = match device_data(queue) {
+        Some(data) => data,
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    let present = match data.dispatch.queue_present {
+        Some(present) => present,
+        None => return VK_ERROR_INITIALIZATION_FAILED,
+    };
+    if !capture_enabled() || HOST.capture_helper.is_none() || !HOST.has_frame_consumers() {
+        return present(queue, present_info);
+    }
+    let mut info = *present_info;
+    let mut copy_done: VkHandle = 0;
+    {
+        let start = std::time::Instant::now();
+        let mut state = data.state.lock().unwrap();
+        data.collect_readbacks(&mut state);
+        if let Some((swapchain, image_index)) = data.pick_capture_swapchain(&state, &info) {
+            match data.capture_present(&mut state, queue, swapchain, image_index, &info) {
+                Ok(semaphore) => {
+                    copy_done = semaphore;
+                    log_trace!(Hooks, "vulkan present capture took {:?}", start.elapsed());
+                },
+                Err(err) => {
+                    log_debug!(Hooks, "vulkan layer: could not capture present: {}", err);
+                }
+            }
+        }
+    }
+    // our submit consumed the app's wait semaphores, the present waits for the copy instead
+    if copy_done != 0 {
+        info.wait_semaphore_count = 1;
+        info.p_wait_semaphores = &copy_done;
+    }
+    present(queue, &info)
+}
+
+unsafe fn intercepted_device_function(name: &CStr) -> PFN_vkVoidFunction {
+    match name.to_bytes() {
+        b"vkGetDeviceProcAddr" => as_void_function!(hw_vkGetDeviceProcAddr as PFN_vkGetDeviceProcAddr),
+        b"vkDestroyDevice" => as_void_function!(hw_vkDestroyDevice as unsafe extern "system" fn(VkDispatchable, *const c_void)),
+        b"vkGetDeviceQueue" => as_void_function!(hw_vkGetDeviceQueue as unsafe extern "system" fn(VkDispatchable, u32, u32, *mut VkDispatchable)),
+        b"vkGetDeviceQueue2" => as_void_function!(hw_vkGetDeviceQueue2 as unsafe extern "system" fn(VkDispatchable, *const VkDeviceQueueInfo2, *mut VkDispatchable)),
+        b"vkCreateSwapchainKHR" => as_void_function!(hw_vkCreateSwapchainKHR as unsafe extern "system" fn(VkDispatchable, *const VkSwapchainCreateInfoKHR, *const c_void, *mut VkHandle) -> VkResult),
+        b"vkDestroySwapchainKHR" => as_void_function!(hw_vkDestroySwapchainKHR as unsafe extern "system" fn(VkDispatchable, VkHandle, *const c_void)),
+        b"vkQueuePresentKHR" => as_void_function!(hw_vkQueuePresentKHR as unsafe extern "system" fn(VkDispatchable, *const VkPresentInfoKHR) -> VkResult),
+        _ => None,
+    }
+}
+
+unsafe fn intercepted_instance_function(name: &CStr) -> PFN_vkVoidFunction {
+    match name.to_bytes() {
+        b"vkGetInstanceProcAddr" => as_void_function!(hw_vkGetInstanceProcAddr as PFN_vkGetInstanceProcAddr),
+        b"vkCreateInstance" => as_void_function!(hw_vkCreateInstance as PFN_vkCreateInstance),
+        b"vkDestroyInstance" => as_void_function!(hw_vkDestroyInstance as PFN_vkDestroyInstance),
+        b"vkCreateDevice" => as_void_function!(hw_vkCreateDevice as PFN_vkCreateDevice),
+        _ => intercepted_device_function(name),
+    }
+}
+
+// only handed out when the next layer has them, apps probe these to see if the extension is enabled
+unsafe fn intercepted_surface_function(instance: VkDispatchable, name: &CStr) -> PFN_vkVoidFunction {
+    let instances = VK_INSTANCES.lock().unwrap();
+    let data = instances.get(&dispatch_key(instance))?;
+    match name.to_bytes() {
+        b"vkCreateXlibSurfaceKHR" if data.create_xlib_surface.is_some() => as_void_function!(hw_vkCreateXlibSurfaceKHR as PFN_vkCreateXlibSurfaceKHR),
+        b"vkCreateXcbSurfaceKHR" if data.create_xcb_surface.is_some() => as_void_function!(hw_vkCreateXcbSurfaceKHR as PFN_vkCreateXcbSurfaceKHR),
+        b"vkDestroySurfaceKHR" if data.destroy_surface.is_some() => as_void_function!(hw_vkDestroySurfaceKHR as PFN_vkDestroySurfaceKHR),
+        _ => None,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "system" fn hw_vkGetInstanceProcAddr(instance: VkDispatchable, name: *const c_char) -> PFN_vkVoidFunction {
+    if name.is_null() {
+        return None;
+    }
+    let name = CStr::from_ptr(name);
+    let func = intercepted_instance_function(name);
+    if func.is_some() || instance.is_null() {
+        return func;
+    }
+    let func = intercepted_surface_function(instance, name);
+    if func.is_some() {
+        return func;
+    }
+    let next = VK_INSTANCES.lock().unwrap().get(&dispatch_key(instance)).map(|data| data.get_instance_proc_addr);
+    next.and_then(|get_instance_proc_addr| get_instance_proc_addr(instance, name.as_ptr()))
+}
+
+#[no_mangle]
+pub unsafe extern "system" fn hw_vkGetDeviceProcAddr(device: VkDispatchable, name: *const c_char) -> PFN_vkVoidFunction {
+    if name.is_null() {
+        return None;
+    }
+    let func = intercepted_device_function(CStr::from_ptr(name));
+    if func.is_some() || device.is_null() {
+        return func;
+    }
+    let next = device_data(device).map(|data| data.dispatch.get_device_proc_addr);
+    next.and_then(|get_device_proc_addr| get_device_proc_addr(device, name))
+}
+
+// loader interface version 2, we don't do anything with physical device functions
+#[no_mangle]
+pub unsafe extern "system" fn vkNegotiateLoaderLayerInterfaceVersion(version_struct: *mut VkNegotiateLayerInterface) -> VkResult {
+    if version_struct.is_null() || (*version_struct).s_type != LAYER_NEGOTIATE_INTERFACE_STRUCT {
+        return VK_ERROR_INITIALIZATION_FAILED;
+    }
+    if (*version_struct).loader_layer_interface_version > 2 {
+        (*version_struct).loader_layer_interface_version = 2;
+    }
+    (*version_struct).pfn_get_instance_proc_addr = Some(hw_vkGetInstanceProcAddr);
+    (*version_struct).pfn_get_device_proc_addr = Some(hw_vkGetDeviceProcAddr);
+    (*version_struct).pfn_get_physical_device_proc_addr = std::ptr::null_mut();
+    log_debug!(Hooks, "{} negotiated loader interface version {}", LAYER_NAME, (*version_struct).loader_layer_interface_version);
+    VK_SUCCESS
+}
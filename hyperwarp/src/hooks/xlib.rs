@@ -6,6 +6,9 @@ use libc::{c_char, c_void};
 
 use crate::{constants::{xlib::XWindowChanges, Library}, host::hosting::HOST};
 
+use super::registry;
+use stellar_shared::log_debug;
+
 // types
 // TODO: convert them to without the pointer stuffs
 pub type Display = *const c_void;
@@ -13,17 +16,18 @@ pub type Window = *const c_void;
 pub type Visual = *const c_void;
 pub type XSetWindowAttributes = *const c_void;
 
-redhook::hook! {
+crate::hw_hooks! {
+    // ENABLE_X11 denies windows instead of passing them through so these always stay hooked
+    library: Library::Xlib, gate: registry::always;
+
     unsafe fn XOpenDisplay(name: *const c_char) -> *mut Display => x_open_display_first {
+        crate::track_hook!("XOpenDisplay");
         if HOST.config.enable_x11 {
             // HOST.test();
             
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_x11();
-            }
+            HOST.features.enable_x11();
 
-            redhook::real!(XOpenDisplay_hw_direct)(name)
+            real()(name)
         } else {
             if HOST.config.debug_mode {
                 // println!("Attempted to open {}", CStr::from_ptr(name).to_str().unwrap());
@@ -31,16 +35,7 @@ redhook::hook! {
             std::ptr::null_mut()
         }
     }
-}
-
-// hooking target
-redhook::hook! {
-    unsafe fn XOpenDisplay_hw_direct(name: *const c_char) -> *mut Display => x_open_display_hw_direct {
-        std::ptr::null_mut()
-    }
-}
 
-redhook::hook! {
     unsafe fn XCreateWindow(
         display: Display,
         parent: Window,
@@ -55,16 +50,13 @@ redhook::hook! {
         value_mask: libc::c_ulong,
         attributes: XSetWindowAttributes
     ) -> Window => x_create_window_first {
+        crate::track_hook!("XCreateWindow");
         if HOST.config.enable_x11 {
             // HOST.test();
             
-            // TODO: various feature flags locks are held longer than they should be, I should be adding more blocks to limit the lock time
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_x11();
-            }
+            HOST.features.enable_x11();
 
-            let result = redhook::real!(XCreateWindow_hw_direct)(
+            let result = real()(
                 display,
                 parent,
                 x,
@@ -78,46 +70,22 @@ redhook::hook! {
                 value_mask,
                 attributes,
             );
-            if HOST.config.debug_mode {
-                println!("XCreateWindow: {}", result as u64);
-            }
+            log_debug!(Hooks, "XCreateWindow: {}", result as u64);
 
-            let window = crate::host::window::Window::new(((result) as *const c_void) as usize, Library::Xlib);
+            let mut window = crate::host::window::Window::new(((result) as *const c_void) as usize, Library::Xlib);
+            // x windows stay hidden until XMapWindow
+            window.visible = false;
 
             // WARNING: implicit tick
             HOST.onWindowCreate(window, Some(x), Some(y), Some(width), Some(height));
             
             result
         } else {
-            if HOST.config.debug_mode {
-                println!("Attempted to create window, denied by config");
-            }
+            log_debug!(Hooks, "Attempted to create window, denied by config");
             std::ptr::null()
         }
     }
-}
-
-redhook::hook! {
-    unsafe fn XCreateWindow_hw_direct(
-        display: Display,
-        parent: Window,
-        x: libc::c_int,
-        y: libc::c_int,
-        width: libc::c_uint,
-        height: libc::c_uint,
-        border_width: libc::c_uint,
-        depth: libc::c_int,
-        class: libc::c_uint,
-        visual: Visual,
-        value_mask: libc::c_ulong,
-        attributes: XSetWindowAttributes
-    ) -> Window => x_create_window_hw_direct {
-        std::ptr::null()
-    }
-}
-
 
-redhook::hook! {
     unsafe fn XCreateSimpleWindow(
         display: Display,
         parent: Window,
@@ -129,15 +97,13 @@ redhook::hook! {
         border: libc::c_ulong,
         background: libc::c_ulong
     ) -> Window => x_create_simple_window_first {
+        crate::track_hook!("XCreateSimpleWindow");
         if HOST.config.enable_x11 {
             // HOST.test();
 
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_x11();
-            }
+            HOST.features.enable_x11();
 
-            let result = redhook::real!(XCreateSimpleWindow_hw_direct)(
+            let result = real()(
                 display,
                 parent,
                 x,
@@ -148,50 +114,28 @@ redhook::hook! {
                 border,
                 background,
             );
-            if HOST.config.debug_mode {
-                println!("XCreateSimpleWindow: {}", result as u64);
-            }
+            log_debug!(Hooks, "XCreateSimpleWindow: {}", result as u64);
 
-            let window = crate::host::window::Window::new(((result) as *const c_void) as usize, Library::Xlib);
+            let mut window = crate::host::window::Window::new(((result) as *const c_void) as usize, Library::Xlib);
+            // x windows stay hidden until XMapWindow
+            window.visible = false;
 
             HOST.onWindowCreate(window, Some(x), Some(y), Some(width), Some(height));
             
             result
         } else {
-            if HOST.config.debug_mode {
-                println!("Attempted to create window, denied by config");
-            }
+            log_debug!(Hooks, "Attempted to create window, denied by config");
             std::ptr::null()
         }
     }
-}
 
-redhook::hook! {
-    unsafe fn XCreateSimpleWindow_hw_direct(
-        display: Display,
-        parent: Window,
-        x: libc::c_int,
-        y: libc::c_int,
-        width: libc::c_uint,
-        height: libc::c_uint,
-        border_width: libc::c_uint,
-        border: libc::c_ulong,
-        background: libc::c_ulong
-    ) -> Window => x_create_simple_window_hw_direct {
-        std::ptr::null()
-    }
-}
-
-redhook::hook! {
     unsafe fn XConfigureWindow(display: Display, window: Window, value_mask: libc::c_ulong, values: *const XWindowChanges) => x_configure_window_first {
+        crate::track_hook!("XConfigureWindow");
         if HOST.config.enable_x11 {
             // HOST.test();
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_x11();
-            }
+            HOST.features.enable_x11();
 
-            redhook::real!(XConfigureWindow_hw_direct)(
+            real()(
                 display,
                 window,
                 value_mask,
@@ -199,37 +143,26 @@ redhook::hook! {
             );
 
             let configuration = values.as_ref().unwrap();
-            println!("reconfigure: {:#?}", configuration);
+            log_debug!(Hooks, "reconfigure: {:#?}", configuration);
 
             // HOST.onWindowConfigure(window, values);
         } else {
-            if HOST.config.debug_mode {
-                println!("Attempted to configure window, denied by config");
-            }
+            log_debug!(Hooks, "Attempted to configure window, denied by config");
         }
     }
-}
-
-redhook::hook! {
-    unsafe fn XConfigureWindow_hw_direct(display: Display, window: Window, value_mask: libc::c_ulong, values: *const XWindowChanges) => x_configure_window_hw_direct {
-    }
-}
 
-// https://tronche.com/gui/x/xlib/window/XResizeWindow.html
-redhook::hook! {
+    // https://tronche.com/gui/x/xlib/window/XResizeWindow.html
     unsafe fn XResizeWindow(display: Display, window: Window, width: libc::c_uint, height: libc::c_uint) => x_resize_window_first {
-        println!("XResizeWindow called");
+        crate::track_hook!("XResizeWindow");
+        log_debug!(Hooks, "XResizeWindow called");
         if HOST.config.enable_x11 {
             // HOST.test();
 
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_x11();
-            }
+            HOST.features.enable_x11();
 
-            println!("XResizeWindow called {}x{}", width, height);
+            log_debug!(Hooks, "XResizeWindow called {}x{}", width, height);
 
-            redhook::real!(XResizeWindow_hw_direct)(
+            real()(
                 display,
                 window,
                 width,
@@ -239,55 +172,93 @@ redhook::hook! {
             HOST.onWindowResize(window as usize, width, height);
 
         } else {
-            if HOST.config.debug_mode {
-                println!("Attempted to resize window, denied by config");
-            }
+            log_debug!(Hooks, "Attempted to resize window, denied by config");
         }
     }
-}
 
-redhook::hook! {
-    unsafe fn XResizeWindow_hw_direct(display: Display, window: Window, width: libc::c_uint, height: libc::c_uint) => x_resize_window_hw_direct {
-    }
-}
-
-redhook::hook! {
     unsafe fn XDestroyWindow(display: Display, window: Window) => x_destroy_window_first {
+        crate::track_hook!("XDestroyWindow");
         if HOST.config.enable_x11 {
             // HOST.test();
 
-            {
-                let mut features = HOST.features.lock().unwrap();
-                features.enable_x11();
-            }
+            HOST.features.enable_x11();
 
-            redhook::real!(XDestroyWindow_hw_direct)(
+            real()(
                 display,
                 window,
             );
 
             HOST.onWindowDestroy(window as usize);
         } else {
-            if HOST.config.debug_mode {
-                println!("Attempted to destroy window, denied by config");
-            }
+            log_debug!(Hooks, "Attempted to destroy window, denied by config");
         }
     }
-}
 
-redhook::hook! {
-    unsafe fn XDestroyWindow_hw_direct(display: Display, window: Window) => x_destroy_window_hw_direct {
+    // https://tronche.com/gui/x/xlib/ICC/client-to-window-manager/XStoreName.html
+    unsafe fn XStoreName(display: Display, window: Window, window_name: *const c_char) -> libc::c_int => x_store_name_first {
+        crate::track_hook!("XStoreName");
+        if HOST.config.enable_x11 {
+            let final_name = crate::utils::format_window_title_prefix_cstr(window_name);
+            let result = real()(display, window, final_name.as_ref().map_or(window_name, |name| name.as_ptr()));
+            HOST.onWindowTitleChange(window as usize, crate::utils::title_from_cstr(window_name));
+            result
+        } else {
+            log_debug!(Hooks, "Attempted to name window, denied by config");
+            0
+        }
+    }
+
+    unsafe fn XMapWindow(display: Display, window: Window) -> libc::c_int => x_map_window_first {
+        crate::track_hook!("XMapWindow");
+        if HOST.config.enable_x11 {
+            let result = real()(display, window);
+            HOST.onWindowVisibilityChange(window as usize, true);
+            result
+        } else {
+            log_debug!(Hooks, "Attempted to map window, denied by config");
+            0
+        }
+    }
+
+    unsafe fn XUnmapWindow(display: Display, window: Window) -> libc::c_int => x_unmap_window_first {
+        crate::track_hook!("XUnmapWindow");
+        if HOST.config.enable_x11 {
+            let result = real()(display, window);
+            HOST.onWindowVisibilityChange(window as usize, false);
+            result
+        } else {
+            log_debug!(Hooks, "Attempted to unmap window, denied by config");
+            0
+        }
     }
-}
 
-pub fn try_modify_symbol(symbol_name: &str) -> Option<*mut c_void> {
-    match symbol_name {
-        "XOpenDisplay" => Some(x_open_display_first as *mut c_void),
-        "XCreateWindow" => Some(x_create_window_first as *mut c_void),
-        "XCreateSimpleWindow" => Some(x_create_simple_window_first as *mut c_void),
-        "XConfigureWindow" => Some(x_configure_window_first as *mut c_void),
-        "XResizeWindow" => Some(x_resize_window_first as *mut c_void),
-        "XDestroyWindow" => Some(x_destroy_window_first as *mut c_void),
-        _ => None
+    // https://tronche.com/gui/x/xlib/graphics/XPutImage.html
+    unsafe fn XPutImage(
+        display: Display,
+        drawable: Window,
+        gc: *const c_void,
+        image: *const c_void,
+        src_x: libc::c_int,
+        src_y: libc::c_int,
+        dest_x: libc::c_int,
+        dest_y: libc::c_int,
+        width: libc::c_uint,
+        height: libc::c_uint
+    ) -> libc::c_int => x_put_image_first {
+        crate::track_hook!("XPutImage");
+        let result = real()(display, drawable, gc, image, src_x, src_y, dest_x, dest_y, width, height);
+        if HOST.config.enable_x11 {
+            HOST.onXlibDraw(false);
+        }
+        result
     }
-}
\ No newline at end of file
+
+    unsafe fn XFlush(display: Display) -> libc::c_int => x_flush_first {
+        crate::track_hook!("XFlush");
+        let result = real()(display);
+        if HOST.config.enable_x11 {
+            HOST.onXlibDraw(true);
+        }
+        result
+    }
+}
@@ -2,4 +2,11 @@ pub mod input;
 pub mod host_behavior;
 pub mod hosting;
 pub mod window;
-pub mod feature_flags;
\ No newline at end of file
+pub mod feature_flags;
+pub mod frame_slots;
+pub mod frame_handoff;
+pub mod frame_stream;
+pub mod damage;
+pub mod xlib_capture;
+pub mod targeting;
+pub mod transport;
@@ -0,0 +1,208 @@
+use std::{
+    hash::{DefaultHasher, Hasher},
+    str::FromStr,
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
+    time::{Duration, Instant},
+};
+
+// how the writer thread decides a frame is the same as the last one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageDetection {
+    Off,
+    // hash a grid of small samples, cheap but can miss tiny changes (the keepalive covers those eventually)
+    Sampled,
+    // memcmp against a copy of the last written frame
+    Full,
+}
+
+impl FromStr for DamageDetection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" | "none" | "false" | "0" => Ok(DamageDetection::Off),
+            "sampled" | "tiles" => Ok(DamageDetection::Sampled),
+            "full" => Ok(DamageDetection::Full),
+            _ => Err(format!("unknown damage detection mode {}", s)),
+        }
+    }
+}
+
+// tiles per axis for sampled mode
+const SAMPLE_GRID: usize = 32;
+// pixels read from the middle row of each tile
+const SAMPLE_PIXELS: usize = 8;
+
+pub struct DamageTracker {
+    mode: DamageDetection,
+    keepalive: Duration,
+    last_fingerprint: Option<u64>,
+    last_frame: Vec<u8>,
+    last_written: Option<Instant>,
+    // shared so debug info can read it while the writer thread owns the tracker
+    skipped_frames: Arc<AtomicU64>,
+}
+
+impl DamageTracker {
+    pub fn new(mode: DamageDetection, keepalive: Duration) -> Self {
+        DamageTracker {
+            mode,
+            keepalive,
+            last_fingerprint: None,
+            last_frame: Vec::new(),
+            last_written: None,
+            skipped_frames: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn skipped_counter(&self) -> Arc<AtomicU64> {
+        self.skipped_frames.clone()
+    }
+
+    // returns false if the frame is unchanged and the keepalive hasn't expired yet
+    pub fn should_write(&mut self, fb: &[u8], width: u32, height: u32) -> bool {
+        let changed = match self.mode {
+            DamageDetection::Off => true,
+            DamageDetection::Sampled => {
+                let fingerprint = sample_fingerprint(fb, width as usize, height as usize);
+                let changed = self.last_fingerprint != Some(fingerprint);
+                self.last_fingerprint = Some(fingerprint);
+                changed
+            },
+            DamageDetection::Full => {
+                let changed = self.last_frame.as_slice() != fb;
+                if changed {
+                    self.last_frame.clear();
+                    self.last_frame.extend_from_slice(fb);
+                }
+                changed
+            },
+        };
+
+        let keepalive_due = match self.last_written {
+            Some(last_written) => last_written.elapsed() >= self.keepalive,
+            None => true,
+        };
+
+        if changed || keepalive_due {
+            self.last_written = Some(Instant::now());
+            true
+        } else {
+            self.skipped_frames.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+}
+
+fn sample_fingerprint(fb: &[u8], width: usize, height: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_usize(fb.len());
+    if width == 0 || height == 0 || fb.len() < width * height * 4 {
+        hasher.write(fb);
+        return hasher.finish();
+    }
+    let tile_width = (width / SAMPLE_GRID).max(1);
+    let tile_height = (height / SAMPLE_GRID).max(1);
+    let mut y = tile_height / 2;
+    while y < height {
+        let mut x = 0;
+        while x < width {
+            let start = (y * width + x) * 4;
+            let end = (start + SAMPLE_PIXELS.min(width - x) * 4).min(fb.len());
+            hasher.write(&fb[start..end]);
+            x += tile_width;
+        }
+        y += tile_height;
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONG: Duration = Duration::from_secs(3600);
+
+    fn frame(width: u32, height: u32, fill: u8) -> Vec<u8> {
+        vec![fill; (width * height * 4) as usize]
+    }
+
+    fn skipped(tracker: &DamageTracker) -> u64 {
+        tracker.skipped_counter().load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("off".parse::<DamageDetection>(), Ok(DamageDetection::Off));
+        assert_eq!("0".parse::<DamageDetection>(), Ok(DamageDetection::Off));
+        assert_eq!("tiles".parse::<DamageDetection>(), Ok(DamageDetection::Sampled));
+        assert_eq!("full".parse::<DamageDetection>(), Ok(DamageDetection::Full));
+        assert!("sometimes".parse::<DamageDetection>().is_err());
+    }
+
+    #[test]
+    fn off_writes_everything() {
+        let mut tracker = DamageTracker::new(DamageDetection::Off, LONG);
+        let fb = frame(16, 16, 1);
+        assert!(tracker.should_write(&fb, 16, 16));
+        assert!(tracker.should_write(&fb, 16, 16));
+        assert_eq!(skipped(&tracker), 0);
+    }
+
+    #[test]
+    fn full_skips_identical_frames() {
+        let mut tracker = DamageTracker::new(DamageDetection::Full, LONG);
+        let mut fb = frame(16, 16, 1);
+        assert!(tracker.should_write(&fb, 16, 16));
+        assert!(!tracker.should_write(&fb, 16, 16));
+        assert_eq!(skipped(&tracker), 1);
+        // a single byte anywhere counts
+        fb[7] = 2;
+        assert!(tracker.should_write(&fb, 16, 16));
+        assert!(!tracker.should_write(&fb, 16, 16));
+        assert_eq!(skipped(&tracker), 2);
+    }
+
+    #[test]
+    fn sampled_sees_changes_in_sampled_pixels() {
+        let mut tracker = DamageTracker::new(DamageDetection::Sampled, LONG);
+        let mut fb = frame(64, 64, 0);
+        assert!(tracker.should_write(&fb, 64, 64));
+        assert!(!tracker.should_write(&fb, 64, 64));
+        // first pixel of the middle row of the first tile
+        let tile_height = 64 / SAMPLE_GRID;
+        let offset = (tile_height / 2) * 64 * 4;
+        fb[offset] = 255;
+        assert!(tracker.should_write(&fb, 64, 64));
+    }
+
+    #[test]
+    fn resize_always_writes() {
+        let mut tracker = DamageTracker::new(DamageDetection::Sampled, LONG);
+        assert!(tracker.should_write(&frame(16, 16, 0), 16, 16));
+        assert!(tracker.should_write(&frame(32, 16, 0), 32, 16));
+        let mut tracker = DamageTracker::new(DamageDetection::Full, LONG);
+        assert!(tracker.should_write(&frame(16, 16, 0), 16, 16));
+        assert!(tracker.should_write(&frame(32, 16, 0), 32, 16));
+    }
+
+    #[test]
+    fn keepalive_writes_unchanged_frames() {
+        let mut tracker = DamageTracker::new(DamageDetection::Full, Duration::ZERO);
+        let fb = frame(16, 16, 3);
+        assert!(tracker.should_write(&fb, 16, 16));
+        assert!(tracker.should_write(&fb, 16, 16));
+        assert_eq!(skipped(&tracker), 0);
+    }
+
+    #[test]
+    fn short_buffers_are_hashed_whole() {
+        let mut tracker = DamageTracker::new(DamageDetection::Sampled, LONG);
+        // claims to be bigger than it is, must not index out of bounds
+        let mut fb = vec![0u8; 12];
+        assert!(tracker.should_write(&fb, 64, 64));
+        assert!(!tracker.should_write(&fb, 64, 64));
+        fb[11] = 1;
+        assert!(tracker.should_write(&fb, 64, 64));
+    }
+}
@@ -1,40 +1,102 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 // which features have we detected
-#[derive(Clone,Copy,Debug)]
+// atomics so hooks on the game thread never wait on anyone else to set or read one, flags only ever turn on
+#[derive(Debug)]
 pub struct FeatureFlags {
-    pub x11_enabled: bool,
-    pub gl_enabled: bool,
-    pub glfw_enabled: bool,
-    pub glx_enabled: bool,
-    pub sdl2_enabled: bool,
-    pub egl_enabled: bool,
+    x11_enabled: AtomicBool,
+    gl_enabled: AtomicBool,
+    glfw_enabled: AtomicBool,
+    glx_enabled: AtomicBool,
+    sdl2_enabled: AtomicBool,
+    sdl3_enabled: AtomicBool,
+    egl_enabled: AtomicBool,
+    vulkan_enabled: AtomicBool,
 }
 
 impl FeatureFlags {
     pub fn new() -> Self {
         FeatureFlags {
-            x11_enabled: false,
-            gl_enabled: false,
-            glfw_enabled: false,
-            glx_enabled: false,
-            sdl2_enabled: false,
-            egl_enabled: false,
+            x11_enabled: AtomicBool::new(false),
+            gl_enabled: AtomicBool::new(false),
+            glfw_enabled: AtomicBool::new(false),
+            glx_enabled: AtomicBool::new(false),
+            sdl2_enabled: AtomicBool::new(false),
+            sdl3_enabled: AtomicBool::new(false),
+            egl_enabled: AtomicBool::new(false),
+            vulkan_enabled: AtomicBool::new(false),
         }
     }
 
-    pub fn enable_x11(&mut self) {
-        self.x11_enabled = true;
+    pub fn x11_enabled(&self) -> bool {
+        self.x11_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn gl_enabled(&self) -> bool {
+        self.gl_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn glfw_enabled(&self) -> bool {
+        self.glfw_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn glx_enabled(&self) -> bool {
+        self.glx_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn sdl2_enabled(&self) -> bool {
+        self.sdl2_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn sdl3_enabled(&self) -> bool {
+        self.sdl3_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn egl_enabled(&self) -> bool {
+        self.egl_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn vulkan_enabled(&self) -> bool {
+        self.vulkan_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn enable_x11(&self) {
+        self.x11_enabled.store(true, Ordering::Relaxed);
     }
 
-    pub fn enable_glx(&mut self) {
-        self.glx_enabled = true;
+    pub fn enable_glx(&self) {
+        self.glx_enabled.store(true, Ordering::Relaxed);
         // this implies x11
-        self.x11_enabled = true;
+        self.x11_enabled.store(true, Ordering::Relaxed);
         // this implies gl
-        self.gl_enabled = true;
+        self.gl_enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn enable_gl(&self) {
+        self.gl_enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn enable_glfw(&self) {
+        self.glfw_enabled.store(true, Ordering::Relaxed);
+        // glfw can be used for vulkan too, gl gets enabled once it swaps buffers
     }
 
-    pub fn enable_sdl2(&mut self) {
-        self.sdl2_enabled = true;
+    pub fn enable_sdl2(&self) {
+        self.sdl2_enabled.store(true, Ordering::Relaxed);
         // maybe imply x11?
     }
-}
\ No newline at end of file
+
+    pub fn enable_sdl3(&self) {
+        self.sdl3_enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn enable_egl(&self) {
+        self.egl_enabled.store(true, Ordering::Relaxed);
+        // egl contexts are always gl(es) for us
+        self.gl_enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn enable_vulkan(&self) {
+        self.vulkan_enabled.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::{atomic::{AtomicU64, Ordering}, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use stellar_protocol::protocol::FrameLayout;
+
+// hands finished readbacks to the writer thread without either side waiting on the other's work
+// there are three buffers: the one readback fills, the one parked here and the one the writer is copying out
+// publishing and taking only swap vecs under the lock, the file IO happens with nothing held
+// if the writer hasn't taken the parked frame by the next publish it gets replaced and counts as dropped
+
+#[derive(Debug)]
+pub struct HandoffFrame {
+    // counts every publish from 1, a gap between two takes is how many were dropped in between
+    pub sequence: u64,
+    pub width: u32,
+    pub height: u32,
+    pub layout: FrameLayout,
+}
+
+#[derive(Debug)]
+struct Parked {
+    buffer: Vec<u8>,
+    frame: Option<HandoffFrame>,
+    stopped: bool,
+}
+
+#[derive(Debug)]
+pub struct FrameHandoff {
+    parked: Mutex<Parked>,
+    ready: Condvar,
+    published: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl FrameHandoff {
+    pub fn new() -> Self {
+        FrameHandoff {
+            parked: Mutex::new(Parked {
+                buffer: Vec::new(),
+                frame: None,
+                stopped: false,
+            }),
+            ready: Condvar::new(),
+            published: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    // render thread, back comes back holding an older buffer resized to match so the next readback can go straight in
+    pub fn publish(&self, back: &mut Vec<u8>, width: u32, height: u32, layout: FrameLayout) {
+        let len = back.len();
+        let sequence = self.published.fetch_add(1, Ordering::Relaxed) + 1;
+        {
+            let mut parked = self.parked.lock().unwrap();
+            if parked.frame.is_some() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            std::mem::swap(&mut parked.buffer, back);
+            parked.frame = Some(HandoffFrame { sequence, width, height, layout });
+        }
+        self.ready.notify_one();
+        // only allocates after a resize or for the first couple of frames
+        back.resize(len, 0);
+    }
+
+    // writer thread, blocks until there's a frame and swaps it into buffer, None once stopped
+    pub fn take(&self, buffer: &mut Vec<u8>) -> Option<HandoffFrame> {
+        let mut parked = self.parked.lock().unwrap();
+        loop {
+            if parked.stopped {
+                return None;
+            }
+            if let Some(frame) = parked.frame.take() {
+                std::mem::swap(&mut parked.buffer, buffer);
+                return Some(frame);
+            }
+            parked = self.ready.wait(parked).unwrap();
+        }
+    }
+
+    // take that gives up after timeout, Some(None) when nothing came in time and None once stopped
+    pub fn take_timeout(&self, buffer: &mut Vec<u8>, timeout: Duration) -> Option<Option<HandoffFrame>> {
+        let deadline = Instant::now() + timeout;
+        let mut parked = self.parked.lock().unwrap();
+        loop {
+            if parked.stopped {
+                return None;
+            }
+            if let Some(frame) = parked.frame.take() {
+                std::mem::swap(&mut parked.buffer, buffer);
+                return Some(Some(frame));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Some(None);
+            }
+            parked = self.ready.wait_timeout(parked, deadline - now).unwrap().0;
+        }
+    }
+
+    pub fn stop(&self) {
+        self.parked.lock().unwrap().stopped = true;
+        self.ready.notify_all();
+    }
+
+    pub fn published(&self) -> u64 {
+        self.published.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_frames_leave_gaps_in_the_sequence() {
+        let handoff = FrameHandoff::new();
+        let mut back = vec![1u8; 4];
+        let mut taken = Vec::new();
+        handoff.publish(&mut back, 1, 1, FrameLayout::default());
+        assert_eq!(handoff.take(&mut taken).unwrap().sequence, 1);
+        handoff.publish(&mut back, 1, 1, FrameLayout::default());
+        handoff.publish(&mut back, 1, 1, FrameLayout::default());
+        assert_eq!(handoff.take(&mut taken).unwrap().sequence, 3);
+        assert_eq!((handoff.published(), handoff.dropped()), (3, 1));
+        assert_eq!(taken.len(), 4);
+    }
+
+    #[test]
+    fn take_timeout_tells_timeouts_from_stopping() {
+        let handoff = FrameHandoff::new();
+        let mut buffer = Vec::new();
+        assert!(matches!(handoff.take_timeout(&mut buffer, Duration::from_millis(10)), Some(None)));
+        handoff.publish(&mut vec![0u8; 4], 1, 1, FrameLayout::default());
+        assert!(matches!(handoff.take_timeout(&mut buffer, Duration::from_millis(10)), Some(Some(_))));
+        handoff.stop();
+        assert!(handoff.take_timeout(&mut buffer, Duration::from_millis(10)).is_none());
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Mutex;
+
+use stellar_protocol::protocol::{frame_slot_id, frame_slot_index, FRAME_SLOT_INDEX_BITS};
+
+use super::transport::Peer;
+
+// the shimg file is split into this many frame sized slots so streamerd can hold onto one while we write the next
+pub const FRAME_SLOT_COUNT: usize = 4;
+
+// generations wrap before they'd run out of bits in the slot id
+const GENERATION_MASK: u32 = (1 << (32 - FRAME_SLOT_INDEX_BITS)) - 1;
+
+#[derive(Default)]
+struct SlotState {
+    generation: u32,
+    busy: bool,
+    // transports that were signalled about this frame and haven't handed it to their subscribers yet
+    pending: usize,
+    // subscribers that got the frame and still have to release it
+    holders: Vec<Peer>,
+}
+
+impl SlotState {
+    fn free_if_unheld(&mut self) {
+        if self.pending == 0 && self.holders.is_empty() {
+            self.busy = false;
+        }
+    }
+}
+
+// tracks which slots of the shimg file are being read and by whom
+// a slot only goes back to the writer once every subscriber it was handed to released it or went away
+pub struct FrameSlots {
+    slots: Mutex<Vec<SlotState>>,
+}
+
+impl FrameSlots {
+    pub fn new(count: usize) -> Self {
+        FrameSlots {
+            slots: Mutex::new((0..count).map(|_| SlotState::default()).collect()),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.slots.lock().unwrap().len()
+    }
+
+    // claim a free slot, None means readers are holding all of them and the frame should be dropped
+    pub fn acquire(&self) -> Option<u32> {
+        let mut slots = self.slots.lock().unwrap();
+        let (index, slot) = slots.iter_mut().enumerate().find(|(_, slot)| !slot.busy)?;
+        slot.generation = slot.generation.wrapping_add(1) & GENERATION_MASK;
+        slot.busy = true;
+        slot.pending = 0;
+        slot.holders.clear();
+        Some(frame_slot_id(index, slot.generation))
+    }
+
+    fn with_current<F: FnOnce(&mut SlotState)>(&self, slot_id: u32, f: F) {
+        let mut slots = self.slots.lock().unwrap();
+        let index = frame_slot_index(slot_id);
+        if let Some(slot) = slots.get_mut(index) {
+            // a stale id is from before the slot was reused or reset, it has nothing left to say about it
+            if slot.busy && frame_slot_id(index, slot.generation) == slot_id {
+                f(slot);
+                slot.free_if_unheld();
+            }
+        }
+    }
+
+    // writer thread, about to signal this many transports, each of them calls hand_out exactly once
+    pub fn expect_handouts(&self, slot_id: u32, transports: usize) {
+        self.with_current(slot_id, |slot| slot.pending = transports);
+    }
+
+    // a transport passed the frame to these subscribers, empty when they all went away in the meantime
+    pub fn hand_out(&self, slot_id: u32, holders: &[Peer]) {
+        self.with_current(slot_id, |slot| {
+            slot.pending = slot.pending.saturating_sub(1);
+            slot.holders.extend_from_slice(holders);
+        });
+    }
+
+    // the writer couldn't fill the slot, nobody was told about it
+    pub fn abandon(&self, slot_id: u32) {
+        self.with_current(slot_id, |slot| slot.pending = 0);
+    }
+
+    pub fn release(&self, slot_id: u32, peer: Peer) {
+        self.with_current(slot_id, |slot| slot.holders.retain(|holder| *holder != peer));
+    }
+
+    // a peer that disconnected or expired, whatever it still held is never coming back
+    pub fn release_peer(&self, peer: Peer) {
+        let mut slots = self.slots.lock().unwrap();
+        for slot in slots.iter_mut().filter(|slot| slot.busy) {
+            slot.holders.retain(|holder| *holder != peer);
+            slot.free_if_unheld();
+        }
+    }
+
+    // the slot size changed so every handed out frame points at the old layout
+    // bumping the generations turns the releases still on their way into no-ops
+    pub fn reset(&self) {
+        let mut slots = self.slots.lock().unwrap();
+        for slot in slots.iter_mut() {
+            slot.generation = slot.generation.wrapping_add(1) & GENERATION_MASK;
+            slot.busy = false;
+            slot.pending = 0;
+            slot.holders.clear();
+        }
+    }
+
+    pub fn count_busy(&self) -> usize {
+        self.slots.lock().unwrap().iter().filter(|slot| slot.busy).count()
+    }
+}
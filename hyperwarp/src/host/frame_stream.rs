@@ -0,0 +1,214 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use stellar_protocol::auth::{self, AuthToken, SessionCipher, Side};
+use stellar_protocol::frame_stream::{self, FrameHeader, TICKET_LEN};
+use stellar_protocol::protocol::{FrameCompression, FrameLayout};
+use stellar_shared::{log_debug, log_info, log_warn};
+
+use super::frame_handoff::FrameHandoff;
+
+// frames over tcp for a streamerd on another machine, the shm slots keep working next to it
+// every connection gets its own sender thread behind a FrameHandoff so a slow link drops frames instead of stalling the writer
+
+// a ticket nobody connected with by then is thrown away
+const TICKET_TTL: Duration = Duration::from_secs(30);
+// how long a new connection gets to send its ticket
+const TICKET_READ_TIMEOUT: Duration = Duration::from_secs(5);
+// connections still owing us a ticket, each one holds a thread until it sends it or times out
+const MAX_PENDING_CONNECTIONS: usize = 16;
+// a peer that takes longer than this to take a frame is assumed gone
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Ticket {
+    ticket: Vec<u8>,
+    compression: FrameCompression,
+    // set when the control connection asking for it was sealed
+    cipher: Option<SessionCipher>,
+    issued: Instant,
+}
+
+struct FrameStream {
+    peer: SocketAddr,
+    handoff: FrameHandoff,
+    sent: AtomicU64,
+}
+
+pub struct FrameStreamServer {
+    port: u16,
+    tickets: Mutex<Vec<Ticket>>,
+    streams: Mutex<Vec<Arc<FrameStream>>>,
+    awaiting_ticket: AtomicUsize,
+}
+
+impl FrameStreamServer {
+    pub fn start(addr: SocketAddr) -> io::Result<Arc<FrameStreamServer>> {
+        let listener = TcpListener::bind(addr)?;
+        let server = Arc::new(FrameStreamServer {
+            port: listener.local_addr()?.port(),
+            tickets: Mutex::new(Vec::new()),
+            streams: Mutex::new(Vec::new()),
+            awaiting_ticket: AtomicUsize::new(0),
+        });
+        let accepting = server.clone();
+        thread::Builder::new().name("hw-frame-stream".to_string()).spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => accepting.spawn_accept(stream),
+                    Err(err) => log_warn!(Net, "frame stream accept failed: {}", err),
+                }
+            }
+        })?;
+        Ok(server)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    // handed out over an authenticated control connection, good for one frame stream connection
+    // seal_with is the token when that connection is sealed, the frames get sealed too then
+    pub fn issue_ticket(&self, compression: FrameCompression, seal_with: Option<&AuthToken>) -> Vec<u8> {
+        let ticket = auth::random_bytes(TICKET_LEN);
+        let cipher = seal_with.map(|token| SessionCipher::frame_stream(token, &ticket, Side::Host));
+        let mut tickets = self.tickets.lock().unwrap();
+        tickets.retain(|pending| pending.issued.elapsed() < TICKET_TTL);
+        tickets.push(Ticket { ticket: ticket.clone(), compression, cipher, issued: Instant::now() });
+        ticket
+    }
+
+    fn redeem(&self, ticket: &[u8]) -> Option<(FrameCompression, Option<SessionCipher>)> {
+        let mut tickets = self.tickets.lock().unwrap();
+        tickets.retain(|pending| pending.issued.elapsed() < TICKET_TTL);
+        let index = tickets.iter().position(|pending| pending.ticket == ticket)?;
+        let redeemed = tickets.remove(index);
+        Some((redeemed.compression, redeemed.cipher))
+    }
+
+    // the ticket read happens on its own thread so one idle connection can't hold up the accept loop
+    fn spawn_accept(self: &Arc<Self>, stream: TcpStream) {
+        if self.awaiting_ticket.fetch_add(1, Ordering::Relaxed) >= MAX_PENDING_CONNECTIONS {
+            self.awaiting_ticket.fetch_sub(1, Ordering::Relaxed);
+            log_warn!(Net, "too many frame stream connections waiting on a ticket, closing {:?}", stream.peer_addr());
+            return;
+        }
+        let server = self.clone();
+        let spawned = thread::Builder::new().name("hw-frame-send".to_string()).spawn(move || server.accept(stream));
+        if let Err(err) = spawned {
+            self.awaiting_ticket.fetch_sub(1, Ordering::Relaxed);
+            log_warn!(Net, "could not start a frame stream thread: {}", err);
+        }
+    }
+
+    // becomes the sender once the ticket checks out
+    fn accept(&self, mut stream: TcpStream) {
+        let redeemed = self.read_ticket(&mut stream);
+        self.awaiting_ticket.fetch_sub(1, Ordering::Relaxed);
+        let (peer, compression, mut cipher) = match redeemed {
+            Some(redeemed) => redeemed,
+            None => return,
+        };
+        let _ = stream.set_nodelay(true);
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        log_info!(Net, "streaming frames to {} ({}{})", peer, compression, if cipher.is_some() { ", sealed" } else { "" });
+
+        let frame_stream = Arc::new(FrameStream {
+            peer,
+            handoff: FrameHandoff::new(),
+            sent: AtomicU64::new(0),
+        });
+        self.streams.lock().unwrap().push(frame_stream.clone());
+        if let Err(err) = send_frames(&frame_stream, &mut stream, compression, cipher.as_mut()) {
+            log_info!(Net, "frame stream to {} closed: {}", peer, err);
+        }
+        self.streams.lock().unwrap().retain(|other| !Arc::ptr_eq(other, &frame_stream));
+    }
+
+    fn read_ticket(&self, stream: &mut TcpStream) -> Option<(SocketAddr, FrameCompression, Option<SessionCipher>)> {
+        let peer = stream.peer_addr().ok()?;
+        let mut ticket = [0u8; TICKET_LEN];
+        let _ = stream.set_read_timeout(Some(TICKET_READ_TIMEOUT));
+        if let Err(err) = stream.read_exact(&mut ticket) {
+            log_debug!(Net, "frame stream from {} never sent a ticket: {}", peer, err);
+            return None;
+        }
+        match self.redeem(&ticket) {
+            Some((compression, cipher)) => Some((peer, compression, cipher)),
+            None => {
+                log_warn!(Net, "frame stream from {} had no valid ticket, closing it", peer);
+                None
+            }
+        }
+    }
+
+    // writer thread, fb comes back holding some older buffer like FrameHandoff::publish
+    pub fn publish(&self, fb: &mut Vec<u8>, width: u32, height: u32, layout: FrameLayout) {
+        let streams = self.streams.lock().unwrap();
+        if let Some((last, rest)) = streams.split_last() {
+            for stream in rest {
+                let mut copy = fb.clone();
+                stream.handoff.publish(&mut copy, width, height, layout);
+            }
+            last.handoff.publish(fb, width, height, layout);
+        }
+    }
+
+    pub fn has_streams(&self) -> bool {
+        !self.streams.lock().unwrap().is_empty()
+    }
+
+    pub fn create_debug_output(&self) -> String {
+        let mut output = format!("Frame Stream: port {}\n", self.port);
+        for stream in self.streams.lock().unwrap().iter() {
+            output += &format!("  {}: {} frames sent, {} dropped\n", stream.peer, stream.sent.load(Ordering::Relaxed), stream.handoff.dropped());
+        }
+        output
+    }
+}
+
+fn send_frames(frame_stream: &FrameStream, stream: &mut TcpStream, compression: FrameCompression, mut cipher: Option<&mut SessionCipher>) -> io::Result<()> {
+    let mut raw: Vec<u8> = Vec::new();
+    let mut payload: Vec<u8> = Vec::new();
+    let mut sequence = 0;
+    while let Some(frame) = frame_stream.handoff.take_timeout(&mut raw, frame_stream::KEEPALIVE_INTERVAL) {
+        let header = match frame {
+            Some(frame) => {
+                frame_stream::compress(compression, &raw, &mut payload);
+                // numbered by the handoff so frames it dropped for a slow link show up as gaps on the other end
+                sequence = frame.sequence;
+                FrameHeader {
+                    sequence,
+                    width: frame.width,
+                    height: frame.height,
+                    compression,
+                    layout: frame.layout,
+                    raw_len: raw.len() as u32,
+                    payload_len: payload.len() as u32,
+                }
+            }
+            // a static screen sends nothing, this tells the other end we're still here
+            None => {
+                payload.clear();
+                FrameHeader::keepalive(sequence, compression)
+            }
+        };
+        match cipher.as_deref_mut() {
+            Some(cipher) => {
+                stream.write_all(&cipher.seal(&header.encode()))?;
+                stream.write_all(&cipher.seal(&payload))?;
+            }
+            None => {
+                stream.write_all(&header.encode())?;
+                stream.write_all(&payload)?;
+            }
+        }
+        if !header.is_keepalive() {
+            frame_stream.sent.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    Ok(())
+}
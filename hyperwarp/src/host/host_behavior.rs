@@ -1,28 +1,36 @@
 use std::{
+    collections::HashMap,
     fs::File,
     io::{Seek, SeekFrom, Write},
+    os::unix::fs::FileExt,
     path::{Path, PathBuf},
-    sync::{mpsc, Arc, Mutex},
+    sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex},
     thread::{JoinHandle, Thread},
     time::{Instant, UNIX_EPOCH},
 };
 
 use backtrace::Backtrace;
 use gl::{RGBA, UNSIGNED_BYTE};
+use libc::c_void;
+use regex::Regex;
+use stellar_protocol::protocol::{frame_slot_index, CaptureTarget, FrameLayout, FramePixelFormat, WindowInfo};
 
 use crate::{
     bind::{
         gl::{K_GL_RGBA, K_GL_UNSIGNED_BYTE},
         gl_safe::glReadPixelsSafe,
         sdl2_safe,
-    }, constants::Library, utils::{config::Config, manual_types::sdl2, utils::convert_header_to_u8}
+        sdl3_safe,
+    }, constants::{sdl3::SDL3_Renderer, Library}, hooks::{dlopen, telemetry}, platform::sdl2::sdl2_pixel_format_for, utils::{config::Config, manual_types::sdl2, profiles, utils::convert_header_to_u8}
 };
 
-use super::{hosting::HOST, window::Window};
+use super::{damage::DamageTracker, frame_handoff::FrameHandoff, frame_slots::FrameSlots, hosting::HOST, window::Window, xlib_capture::{XlibCapture, XlibCaptureMode}};
 
 use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
+use stellar_shared::logging::{self, Level, Target};
+use stellar_shared::{log_debug, log_error, log_info, log_trace, log_warn};
 
 // for now we only handle a single window
 // TODO: make casing consistent
@@ -68,16 +76,48 @@ pub struct DefaultHostBehavior {
     fb_width: Option<u32>,
     fb_height: Option<u32>,
     fb_enabled: bool,
+    // readback target, swapped out to the writer thread on every frame so it's never read while being written
     pub fb: Vec<u8>,
-    pub tx: Option<mpsc::Sender<FrameWriterThreadMessage>>,
+    pub frame_handoff: Option<Arc<FrameHandoff>>,
+    // unchanged frames the writer thread didn't write, the tracker itself lives on that thread
+    damage_skipped: Option<Arc<AtomicU64>>,
     pub windows: Vec<Window>,
+    pub capture_target: CaptureTarget,
+    // capture window the last published WindowChanged messages agree on
+    published_capture_id: Option<usize>,
+    capture_title_regex: Option<Regex>,
+    // byte order of what's currently in fb
+    pub fb_format: FramePixelFormat,
+    // only glReadPixels leaves the rows bottom up, every other path reads top down
+    fb_bottom_up: bool,
+    pub capture_path: CapturePath,
+    // SDL renderer pointer to backend name, None if SDL wouldn't tell us
+    renderer_names: HashMap<usize, Option<String>>,
+    // plain xlib capture, opened on first use
+    xlib_capture: Option<XlibCapture>,
+    xlib_capture_failed: bool,
+    xlib_dirty: bool,
+    last_xlib_capture: Option<Instant>,
+}
+
+// how the last frame got into fb, shown in debug info
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapturePath {
+    None,
+    GlReadPixels,
+    SdlRenderReadPixels(String),
+    XShmGetImage,
+    XGetImage,
+    VulkanPresent,
 }
 
 impl DefaultHostBehavior {
     fn setup_framebuffer(&mut self, width: u32, height: u32) {
-        println!("Create fb: {}x{}", width, height);
-        let bt = Backtrace::new();
-        println!("Create fb backtrace: {:?}", bt);
+        log_info!(Capture, "Create fb: {}x{}", width, height);
+        if logging::enabled(Target::Capture, Level::Debug) {
+            let bt = Backtrace::new();
+            log_debug!(Capture, "Create fb backtrace: {:?}", bt);
+        }
         self.fb_width = Some(width);
         self.fb_height = Some(height);
         self.fb = vec![0; (width * height * 4) as usize];
@@ -92,35 +132,80 @@ impl DefaultHostBehavior {
 
     pub fn onWindowCreate(
         &mut self,
-        win: Window,
+        mut win: Window,
         x: Option<i32>,
         y: Option<i32>,
         width: Option<u32>,
         height: Option<u32>,
     ) {
+        if let (Some(width), Some(height)) = (width, height) {
+            win.resize(width, height);
+        }
+        if let (Some(x), Some(y)) = (x, y) {
+            win.position(x, y);
+        }
         self.windows.push(win);
         if let Some(width) = width {
             if let Some(height) = height {
-                if HOST.config.debug_mode {
-                    println!("Create fb (from window creation): {}x{}", width, height);
-                }
+                log_debug!(Capture, "Create fb (from window creation): {}x{}", width, height);
                 self.setup_framebuffer(width, height);
             }
         }
     }
 
+    // an X window that belongs to a toolkit window resolves to the toolkit window, anything else is itself
+    pub fn resolve_window_id(&self, win_id: usize) -> usize {
+        self.windows.iter().find(|w| w.native_id == Some(win_id)).map_or(win_id, |w| w.id)
+    }
+
+    // win_id draws into native_id, fold the entry the xlib hooks made for it into win_id so it's one window
+    // returns true if there was a separate entry to drop
+    pub fn link_native_window(&mut self, win_id: usize, native_id: usize) -> bool {
+        if win_id == native_id || !self.windows.iter().any(|w| w.id == win_id) {
+            return false;
+        }
+        let before = self.windows.len();
+        self.windows.retain(|w| w.id != native_id);
+        if let Some(window) = self.windows.iter_mut().find(|w| w.id == win_id) {
+            window.native_id = Some(native_id);
+        }
+        self.windows.len() != before
+    }
+
     pub fn onWindowResize(&mut self, win_id: usize, width: u32, height: u32) {
-        self.windows.iter_mut().find(|w| w.id == win_id).unwrap().resize(width, height);
+        let win_id = self.resolve_window_id(win_id);
+        match self.windows.iter_mut().find(|w| w.id == win_id) {
+            Some(window) => window.resize(width, height),
+            None => log_warn!(Capture, "resize for unknown window {}", win_id),
+        }
     }
 
     pub fn onWindowDestroy(&mut self, win_id: usize) {
+        let win_id = self.resolve_window_id(win_id);
         let matched_count = self.windows.iter().filter(|w| w.id == win_id).count();
         if matched_count == 0 {
-            println!("warn: window of id {} not found", win_id);
+            log_warn!(Capture, "window of id {} not found", win_id);
         }
         self.windows.retain(|w| w.id != win_id);
     }
 
+    pub fn get_window_info(&self, win_id: usize) -> Option<WindowInfo> {
+        let win_id = self.resolve_window_id(win_id);
+        let capture_id = self.get_capture_window().map(|w| w.id);
+        self.windows.iter().find(|w| w.id == win_id).map(|w| w.to_info(Some(w.id) == capture_id))
+    }
+
+    // apply a metadata change to a window, returns the new info if the closure reports something actually changed
+    pub fn update_window<F: FnOnce(&mut Window) -> bool>(&mut self, win_id: usize, update: F) -> Option<WindowInfo> {
+        let win_id = self.resolve_window_id(win_id);
+        let window = self.windows.iter_mut().find(|w| w.id == win_id)?;
+        if update(window) {
+            self.get_window_info(win_id)
+        } else {
+            None
+        }
+    }
+
     pub fn get_largest_window(&self) -> Option<(u32, u32)> {
         let mut largest_area = 0;
         let mut largest_width = 0;
@@ -137,15 +222,86 @@ impl DefaultHostBehavior {
     }
 
 
-    pub fn onFrameSwapBegin(&mut self) {
+    pub fn set_capture_target(&mut self, target: CaptureTarget) -> Result<(), String> {
+        // compile once here instead of every swap
+        self.capture_title_regex = match &target {
+            CaptureTarget::TitleRegex(pattern) => Some(Regex::new(pattern).map_err(|err| format!("bad title regex {}: {}", pattern, err))?),
+            _ => None,
+        };
+        log_debug!(Capture, "capture target set to {}", target);
+        self.capture_target = target;
+        Ok(())
+    }
+
+    // the window we should be reading pixels from according to the capture target
+    pub fn get_capture_window(&self) -> Option<&Window> {
+        match &self.capture_target {
+            CaptureTarget::Largest => {
+                // first largest wins so ties go to the earliest created window like before
+                let mut largest: Option<&Window> = None;
+                for window in self.windows.iter() {
+                    if largest.map_or(true, |current| window.area() > current.area()) {
+                        largest = Some(window);
+                    }
+                }
+                largest
+            },
+            CaptureTarget::MostRecentlySwapped => self.windows.iter().filter(|w| w.last_swap.is_some()).max_by_key(|w| w.last_swap),
+            CaptureTarget::WindowId(id) => {
+                let id = self.resolve_window_id(*id as usize);
+                self.windows.iter().find(|w| w.id == id)
+            },
+            CaptureTarget::TitleRegex(_) => {
+                let regex = self.capture_title_regex.as_ref()?;
+                self.windows.iter().find(|w| w.title.as_ref().map_or(false, |title| regex.is_match(title)))
+            },
+        }
+    }
+
+    // swaps on drawables we don't know about (or when the hook can't tell us) are always captured
+    pub fn should_capture_swap(&self, window_id: Option<usize>) -> bool {
+        let window_id = match window_id {
+            Some(window_id) => self.resolve_window_id(window_id),
+            None => return true,
+        };
+        if !self.windows.iter().any(|w| w.id == window_id) {
+            return true;
+        }
+        self.get_capture_window().map_or(false, |w| w.id == window_id)
+    }
+
+    // when the capture window moved to another window both of them have a stale capturing flag out there
+    pub fn take_capture_change(&mut self) -> Vec<WindowInfo> {
+        let capture_id = self.get_capture_window().map(|w| w.id);
+        if capture_id == self.published_capture_id {
+            return Vec::new();
+        }
+        let previous = std::mem::replace(&mut self.published_capture_id, capture_id);
+        [previous, capture_id].into_iter().flatten().filter_map(|id| self.get_window_info(id)).collect()
+    }
+
+    pub fn list_windows(&self) -> Vec<WindowInfo> {
+        let capture_id = self.get_capture_window().map(|w| w.id);
+        self.windows.iter().map(|w| w.to_info(Some(w.id) == capture_id)).collect()
+    }
+
+    pub fn onFrameSwapBegin(&mut self, window_id: Option<usize>) {
         // HOST.tick();
         let start = Instant::now();
-        if HOST.config.capture_mode {
-            let features = HOST.features.lock().unwrap();
-            if features.gl_enabled {
+        let window_id = window_id.map(|window_id| self.resolve_window_id(window_id));
+        if let Some(window_id) = window_id {
+            if let Some(window) = self.windows.iter_mut().find(|w| w.id == window_id) {
+                window.last_swap = Some(start);
+            }
+        }
+        if HOST.config.capture_mode && !self.should_capture_swap(window_id) {
+            log_trace!(Capture, "skipping capture for swap on non target window {:?}", window_id);
+        } else if HOST.config.capture_mode {
+            let features = &HOST.features;
+            if features.gl_enabled() {
                 // let wh = sdl2_safe::SDL_GetWindowSize_safe();
                 // surely no one uses both sdl2 and something else
-                if features.sdl2_enabled {
+                if features.sdl2_enabled() {
                     self.get_largest_sdl2_window(); // this forces a sync of the window sizes for SDL
                 }
                 /*if features.sdl2_enabled {
@@ -153,9 +309,7 @@ impl DefaultHostBehavior {
                         if self.fb_width != Some(width.try_into().unwrap())
                             || self.fb_height != Some(height.try_into().unwrap())
                         {
-                            if HOST.config.debug_mode {
-                                println!("resize fb {}x{}", width, height);
-                            }
+                            log_debug!(Capture, "resize fb {}x{}", width, height);
                             self.setup_framebuffer(
                                 width.try_into().unwrap(),
                                 height.try_into().unwrap(),
@@ -165,14 +319,12 @@ impl DefaultHostBehavior {
                         //  println!("sdl2 window not found");
                     }
                 }*/
-                let largest_dims = self.get_largest_window();
-                if let Some((width, height)) = largest_dims {
+                let target_dims = self.get_capture_window().map(|w| (w.width, w.height)).filter(|(w, h)| *w > 0 && *h > 0);
+                if let Some((width, height)) = target_dims {
                     if self.fb_width != Some(width.try_into().unwrap())
                         || self.fb_height != Some(height.try_into().unwrap())
                     {
-                        if HOST.config.debug_mode {
-                            println!("resize fb {}x{}", width, height);
-                        }
+                        log_debug!(Capture, "resize fb {}x{}", width, height);
                         self.setup_framebuffer(
                             width.try_into().unwrap(),
                             height.try_into().unwrap(),
@@ -191,26 +343,232 @@ impl DefaultHostBehavior {
                             UNSIGNED_BYTE,
                             self.fb.as_mut_ptr(),
                         );
+                        self.fb_format = FramePixelFormat::Rgba;
+                        self.fb_bottom_up = true;
+                        self.capture_path = CapturePath::GlReadPixels;
                         // pov: you are a rustacean and you are reading this code (copilot wrote this and the comment)
                         // println!("a sample of captured pixels {}", self.fb[std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos() as usize % (self.fb.len() - 1)]);
                         // artifical lag debug
                         // sleep(Duration::from_millis(150));
-                        if let Some(sender) = &self.tx {
-                            sender.send(FrameWriterThreadMessage::NewFrame).unwrap();
-                        }
+                        self.publish_frame();
                     }
                 } else {
-                    if HOST.config.debug_mode {
-                        println!("unknown framebuffer dimensions");
-                    }
+                    log_debug!(Capture, "unknown framebuffer dimensions");
                 }
             } else {
                 // println!("gl not enabled");
             }
         }
-        if HOST.config.tracing_mode {
-            println!("onFrameSwapBegin took {:?}", start.elapsed());
+        log_trace!(Capture, "onFrameSwapBegin took {:?}", start.elapsed());
+    }
+
+    fn get_renderer_name(&mut self, renderer: usize, lib: Library) -> Option<String> {
+        self.renderer_names.entry(renderer).or_insert_with(|| {
+            let name = if lib == Library::SDL3 {
+                sdl3_safe::SDL_GetRendererName_safe(renderer as *mut SDL3_Renderer)
+            } else {
+                sdl2_safe::SDL_GetRendererName_safe(renderer as *mut c_void)
+            };
+            log_debug!(Capture, "SDL renderer {} uses backend {:?}", renderer, name);
+            name
+        }).clone()
+    }
+
+    // SDL_RenderPresent, called before the real present so the backbuffer is still intact
+    pub fn onRenderPresent(&mut self, renderer: usize) {
+        let window_id = sdl2_safe::SDL_RenderGetWindow_safe(renderer as *mut c_void) as usize;
+        let window_id = if window_id == 0 { None } else { Some(window_id) };
+        let name = self.get_renderer_name(renderer, Library::SDL2);
+        let gl_backed = name.as_ref().map_or(false, |name| name.starts_with("opengl")) && HOST.features.gl_enabled();
+        if gl_backed {
+            // the gl path can read the backbuffer just fine
+            self.onFrameSwapBegin(window_id);
+            return;
+        }
+
+        let start = Instant::now();
+        if let Some(window_id) = window_id {
+            if let Some(window) = self.windows.iter_mut().find(|w| w.id == window_id) {
+                window.last_swap = Some(start);
+            }
+        }
+        if !HOST.config.capture_mode || HOST.capture_helper.is_none() {
+            return;
+        }
+        if !self.should_capture_swap(window_id) {
+            log_trace!(Capture, "skipping renderer capture for non target window {:?}", window_id);
+            return;
+        }
+        let (width, height) = match sdl2_safe::SDL_GetRendererOutputSize_safe(renderer as *mut c_void) {
+            Some((width, height)) if width > 0 && height > 0 => (width as u32, height as u32),
+            _ => {
+                log_debug!(Capture, "could not get renderer output size");
+                return;
+            }
+        };
+        if self.fb_width != Some(width) || self.fb_height != Some(height) {
+            log_debug!(Capture, "resize fb {}x{} (renderer)", width, height);
+            self.setup_framebuffer(width, height);
+        }
+        let format = HOST.config.sdl_render_read_format;
+        let pitch = (width * 4) as i32;
+        let result = sdl2_safe::SDL_RenderReadPixels_safe(renderer as *mut c_void, sdl2_pixel_format_for(format), &mut self.fb, pitch);
+        if result != 0 {
+            log_debug!(Capture, "SDL_RenderReadPixels failed: {}", sdl2_safe::SDL_GetError_safe());
+            return;
+        }
+        // renderer reads come out top down, the frame says so and streamerd leaves it alone
+        self.fb_format = format;
+        self.fb_bottom_up = false;
+        self.capture_path = CapturePath::SdlRenderReadPixels(name.unwrap_or_else(|| "unknown".to_string()));
+        self.publish_frame();
+        log_trace!(Capture, "onRenderPresent took {:?}", start.elapsed());
+    }
+
+    // SDL3 reads pixels into a new surface instead of our buffer, window sizes are already synced by the hook
+    pub fn onSdl3RenderPresent(&mut self, renderer: usize) {
+        let window_id = sdl3_safe::SDL_GetRenderWindow_safe(renderer as *mut SDL3_Renderer) as usize;
+        let window_id = if window_id == 0 { None } else { Some(window_id) };
+        let name = self.get_renderer_name(renderer, Library::SDL3);
+        let gl_backed = name.as_ref().map_or(false, |name| name.starts_with("opengl")) && HOST.features.gl_enabled();
+        if gl_backed {
+            self.onFrameSwapBegin(window_id);
+            return;
+        }
+
+        let start = Instant::now();
+        if let Some(window_id) = window_id {
+            if let Some(window) = self.windows.iter_mut().find(|w| w.id == window_id) {
+                window.last_swap = Some(start);
+            }
+        }
+        if !HOST.config.capture_mode || HOST.capture_helper.is_none() {
+            return;
         }
+        if !self.should_capture_swap(window_id) {
+            log_trace!(Capture, "skipping renderer capture for non target window {:?}", window_id);
+            return;
+        }
+        let format = HOST.config.sdl_render_read_format;
+        let (width, height, pixels) = match sdl3_safe::SDL_RenderReadPixels_safe(renderer as *mut SDL3_Renderer, sdl2_pixel_format_for(format)) {
+            Some(frame) => frame,
+            None => {
+                log_debug!(Capture, "SDL3 SDL_RenderReadPixels failed: {}", sdl3_safe::SDL_GetError_safe());
+                return;
+            }
+        };
+        if self.fb_width != Some(width) || self.fb_height != Some(height) {
+            log_debug!(Capture, "resize fb {}x{} (sdl3 renderer)", width, height);
+            self.setup_framebuffer(width, height);
+        }
+        let size = (width * height * 4) as usize;
+        self.fb[..size].copy_from_slice(&pixels[..size]);
+        self.fb_format = format;
+        self.fb_bottom_up = false;
+        self.capture_path = CapturePath::SdlRenderReadPixels(name.unwrap_or_else(|| "unknown".to_string()));
+        self.publish_frame();
+        log_trace!(Capture, "onSdl3RenderPresent took {:?}", start.elapsed());
+    }
+
+    pub fn mark_xlib_dirty(&mut self) {
+        self.xlib_dirty = true;
+    }
+
+    // plain xlib apps never swap so frames get pulled from the server instead
+    // force skips the dirty check (timer mode), the interval applies either way
+    pub fn onXlibCapture(&mut self, force: bool) {
+        if !HOST.config.capture_mode || HOST.capture_helper.is_none() || HOST.config.xlib_capture == XlibCaptureMode::Off {
+            return;
+        }
+        if !force && !self.xlib_dirty {
+            return;
+        }
+        if HOST.features.gl_enabled() {
+            // gl swaps already produce frames
+            return;
+        }
+        if let Some(last_xlib_capture) = self.last_xlib_capture {
+            if last_xlib_capture.elapsed() < Duration::from_millis(HOST.config.xlib_capture_interval_ms) {
+                return;
+            }
+        }
+        let window_id = match self.get_capture_window() {
+            Some(window) if window.lib == Library::Xlib && window.visible => window.id,
+            _ => return,
+        };
+        if self.xlib_capture.is_none() {
+            if self.xlib_capture_failed {
+                return;
+            }
+            match XlibCapture::open() {
+                Ok(capture) => {
+                    log_debug!(Capture, "xlib capture opened, using shm: {}", capture.uses_shm());
+                    self.xlib_capture = Some(capture);
+                },
+                Err(err) => {
+                    log_warn!(Capture, "xlib capture unavailable: {}", err);
+                    self.xlib_capture_failed = true;
+                    return;
+                }
+            }
+        }
+        let start = Instant::now();
+        self.last_xlib_capture = Some(start);
+        self.xlib_dirty = false;
+
+        let (width, height) = match self.xlib_capture.as_ref().unwrap().get_window_size(window_id as u64) {
+            Some(size) => size,
+            None => return,
+        };
+        if self.fb_width != Some(width) || self.fb_height != Some(height) {
+            log_debug!(Capture, "resize fb {}x{} (xlib)", width, height);
+            self.setup_framebuffer(width, height);
+        }
+        let capture = self.xlib_capture.as_mut().unwrap();
+        match capture.grab(window_id as u64, width, height, &mut self.fb) {
+            Ok(()) => {
+                // 24/32 bit truecolor ZPixmaps are bgrx in memory on little endian
+                self.fb_format = FramePixelFormat::Bgrx;
+                self.fb_bottom_up = false;
+                self.capture_path = if capture.uses_shm() { CapturePath::XShmGetImage } else { CapturePath::XGetImage };
+                self.publish_frame();
+            },
+            Err(err) => {
+                log_debug!(Capture, "xlib capture of window {} failed: {}", window_id, err);
+            }
+        }
+        log_trace!(Capture, "onXlibCapture took {:?}", start.elapsed());
+    }
+
+    // the vulkan layer asks before it records a copy, the frame itself comes later through onVulkanFrame
+    pub fn onVulkanPresent(&mut self, window_id: Option<usize>) -> bool {
+        let window_id = window_id.map(|window_id| self.resolve_window_id(window_id));
+        if let Some(window_id) = window_id {
+            if let Some(window) = self.windows.iter_mut().find(|w| w.id == window_id) {
+                window.last_swap = Some(Instant::now());
+            }
+        }
+        self.should_capture_swap(window_id)
+    }
+
+    // vkQueuePresentKHR through the vulkan layer, data is the presented image tightly packed and already top down
+    pub fn onVulkanFrame(&mut self, width: u32, height: u32, format: FramePixelFormat, data: &[u8]) {
+        if !HOST.config.capture_mode || HOST.capture_helper.is_none() {
+            return;
+        }
+        if self.fb_width != Some(width) || self.fb_height != Some(height) {
+            log_debug!(Capture, "resize fb {}x{} (vulkan)", width, height);
+            self.setup_framebuffer(width, height);
+        }
+        let size = self.fb.len();
+        if data.len() < size {
+            return;
+        }
+        self.fb.copy_from_slice(&data[..size]);
+        self.fb_format = format;
+        self.fb_bottom_up = false;
+        self.capture_path = CapturePath::VulkanPresent;
+        self.publish_frame();
     }
 
     pub fn onFrameSwapEnd(&mut self) {
@@ -221,6 +579,13 @@ impl DefaultHostBehavior {
         Some(self.fb.as_ref())
     }
 
+    // after a readback, fb comes back as an older buffer of the same size
+    fn publish_frame(&mut self) {
+        if let (Some(handoff), Some(width), Some(height)) = (&self.frame_handoff, self.fb_width, self.fb_height) {
+            handoff.publish(&mut self.fb, width, height, FrameLayout { format: self.fb_format, bottom_up: self.fb_bottom_up });
+        }
+    }
+
     pub fn tick(&mut self) {}
 
     pub fn get_fb_size(&self) -> Option<(u32, u32)> {
@@ -234,22 +599,32 @@ impl DefaultHostBehavior {
 
     pub fn create_debug_output(&self) -> String {
         let mut output = "Debug Info (b):\n".to_string();
+        output += &format!("Config: {:#?}\n", HOST.config);
         output += &format!("FB Size: {:?}\n", self.get_fb_size());
         output += &format!("Shimg Path: {:?}\n", self.get_shimg_path(&HOST.config));
+        output += &format!("Capture Target: {} (window {:?})\n", self.capture_target, self.get_capture_window().map(|w| w.id));
+        output += &format!("Capture Path: {:?} ({})\n", self.capture_path, self.fb_format);
+        if let Some(handoff) = &self.frame_handoff {
+            output += &format!("Frame Writer: {} frames published, {} dropped\n", handoff.published(), handoff.dropped());
+        }
+        if let Some(skipped) = &self.damage_skipped {
+            output += &format!("Damage Detection: {:?}, {} unchanged frames skipped\n", HOST.config.damage_detection, skipped.load(Ordering::Relaxed));
+        }
+        for (renderer, name) in self.renderer_names.iter() {
+            output += &format!("SDL Renderer: {} {:?}\n", renderer, name);
+        }
         for window in self.windows.iter() {
             output += &format!("Window: {:?}\n", window);
         }
+        output += &profiles::create_debug_output();
+        output += &dlopen::create_debug_output();
+        output += &telemetry::create_debug_output();
         output
     }
 }
 
 impl DefaultHostBehavior {}
 
-pub enum FrameWriterThreadMessage {
-    Stop,
-    NewFrame,
-}
-
 impl DefaultHostBehavior {
     pub fn new() -> Self {
         DefaultHostBehavior {
@@ -257,8 +632,20 @@ impl DefaultHostBehavior {
             fb_height: None,
             fb_enabled: false,
             fb: Vec::new(),
-            tx: None,
-            windows: Vec::new()
+            frame_handoff: None,
+            damage_skipped: None,
+            published_capture_id: None,
+            windows: Vec::new(),
+            capture_target: CaptureTarget::default(),
+            capture_title_regex: None,
+            fb_format: FramePixelFormat::Rgba,
+            fb_bottom_up: false,
+            capture_path: CapturePath::None,
+            renderer_names: HashMap::new(),
+            xlib_capture: None,
+            xlib_capture_failed: false,
+            xlib_dirty: false,
+            last_xlib_capture: None,
         }
     }
 
@@ -313,41 +700,105 @@ impl DefaultHostBehavior {
         Some(chosen)
     }
 
-    pub fn spawn_writer_thread(&mut self, config: &Config) -> JoinHandle<()> {
+    // the SDL3 hooks keep these sizes in pixels so no need to ask SDL
+    pub fn get_largest_sdl3_window(&self) -> Option<usize> {
+        self.windows.iter()
+            .filter(|w| w.lib == Library::SDL3)
+            .fold(None, |largest: Option<&Window>, w| if largest.map_or(true, |l| w.area() > l.area()) { Some(w) } else { largest })
+            .map(|w| w.id)
+    }
+
+    pub fn spawn_writer_thread(&mut self, config: &Config, frame_slots: Arc<FrameSlots>) -> JoinHandle<()> {
         let base_loc = Path::new("/dev/shm");
         let file_loc = base_loc.join(format!("{}{}", config.session_id, ".raw"));
 
-        println!("file_loc: {}", file_loc.display());
+        log_info!(Capture, "file_loc: {}", file_loc.display());
 
-        let (tx, rx) = mpsc::channel::<FrameWriterThreadMessage>(); // we use to signal to the thread to dump the frames.
+        let handoff = Arc::new(FrameHandoff::new());
+        self.frame_handoff = Some(handoff.clone());
 
-        self.tx = Some(tx);
+        let mut damage_tracker = DamageTracker::new(config.damage_detection, Duration::from_millis(config.frame_keepalive_ms));
+        self.damage_skipped = Some(damage_tracker.skipped_counter());
 
         thread::spawn(move || {
-            let mut file = File::create(file_loc).unwrap();
-            loop {
-                match rx.recv() {
-                    Ok(FrameWriterThreadMessage::Stop) => {
-                        break;
-                    }
-                    Ok(FrameWriterThreadMessage::NewFrame) => {
-                        // write the frame to the file
-                        if HOST.config.debug_mode {
-                            // println!("new frame writing");
+            // without the shm file frames can still go out over the frame stream
+            let file = match File::create(&file_loc) {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    log_error!(Capture, "couldn't create {}, frames won't reach streamerd through shm: {}", file_loc.display(), err);
+                    None
+                }
+            };
+            let mut slot_size: usize = 0;
+            let mut file_size: usize = 0;
+            // the writer's own buffer, traded for the parked one on every take
+            let mut fb: Vec<u8> = Vec::new();
+            let mut last_drop_report = Instant::now();
+            let mut reported_dropped = 0;
+            let mut last_error_report: Option<Instant> = None;
+            // kept apart from slot_size, which only moves when there's a shm file to write
+            let mut last_shape: Option<(u32, u32, FrameLayout)> = None;
+            while let Some(frame) = handoff.take(&mut fb) {
+                // let start = Instant::now();
+                // always feed the tracker so it has the new size on record after a resize
+                // a new size or layout always goes out, with or without the shm file
+                let shape = (frame.width, frame.height, frame.layout);
+                let same_shape = last_shape == Some(shape);
+                last_shape = Some(shape);
+                if !damage_tracker.should_write(&fb, frame.width, frame.height) && same_shape {
+                    // unchanged, streamerd keeps showing the last frame it got
+                    continue;
+                }
+                if let Some(file) = &file {
+                    // a full or read only /dev/shm drops the frame instead of taking the game down, the next one tries again
+                    if let Err(err) = write_shm_frame(file, &fb, &frame_slots, &mut slot_size, &mut file_size, frame.layout) {
+                        if last_error_report.map_or(true, |last| last.elapsed() >= Duration::from_secs(1)) {
+                            log_error!(Capture, "couldn't write a frame to {}, dropping it: {}", file_loc.display(), err);
+                            last_error_report = Some(Instant::now());
                         }
-                        // let start = Instant::now();
-                        let behavior = HOST.get_behavior();
-                        file.write(behavior.getFramebufferForCapture().unwrap())
-                            .unwrap();
-                        file.seek(SeekFrom::Start(0)).unwrap();
-                        // println!("shm write took {:?}", start.elapsed());
-                    }
-                    Err(e) => {
-                        println!("Error in thread: {:?}", e);
-                        break;
                     }
                 }
+                // after the shm write since this trades fb away for an older buffer
+                if let Some(frame_stream) = &HOST.frame_stream {
+                    frame_stream.publish(&mut fb, frame.width, frame.height, frame.layout);
+                }
+                // frames replaced before we got to them, at most one report a second so a slow disk doesn't also flood the log
+                let dropped = handoff.dropped();
+                if dropped != reported_dropped && last_drop_report.elapsed() >= Duration::from_secs(1) {
+                    log_warn!(Capture, "frame writer fell behind, dropped {} frames ({} total)", dropped - reported_dropped, dropped);
+                    reported_dropped = dropped;
+                    last_drop_report = Instant::now();
+                }
             }
+            log_debug!(Capture, "frame writer stopped");
         })
     }
 }
+
+// one frame into the next free slot of the shimg file, growing the file when the frame size changed
+fn write_shm_frame(file: &File, fb: &[u8], frame_slots: &FrameSlots, slot_size: &mut usize, file_size: &mut usize, layout: FrameLayout) -> std::io::Result<()> {
+    if fb.len() != *slot_size {
+        let needed = fb.len() * frame_slots.count();
+        // never shrink, streamerd may still have the old size mapped and would SIGBUS
+        if needed > *file_size {
+            file.set_len(needed as u64)?;
+            *file_size = needed;
+        }
+        // resolution changed, anything streamerd still has mapped is stale
+        *slot_size = fb.len();
+        frame_slots.reset();
+    }
+    let slot = match frame_slots.acquire() {
+        Some(slot) => slot,
+        None => {
+            log_debug!(Capture, "all frame slots busy, dropping frame");
+            return Ok(());
+        }
+    };
+    if let Err(err) = file.write_all_at(fb, (frame_slot_index(slot) * *slot_size) as u64) {
+        frame_slots.abandon(slot);
+        return Err(err);
+    }
+    HOST.notify_frame_slot(slot, *slot_size, layout);
+    Ok(())
+}
@@ -1,103 +1,133 @@
 use backtrace::Backtrace;
-use message_io::adapters::unix_socket::{create_null_socketaddr, UnixSocketListenConfig};
-use message_io::network::{Endpoint, NetEvent, Transport, TransportListen};
-use message_io::node::{self, NodeEvent, NodeHandler, NodeListener};
 
+use stellar_protocol::auth::AuthToken;
 use stellar_protocol::deserialize;
-use stellar_protocol::protocol::{get_all_channels, DebugInfo, GraphicsAPI, Handshake, HostInfo, InputEvent, StellarChannel, StellarDirectControlMessage, StellarMessage, Synchornization};
+use stellar_protocol::session::{self, SessionDescriptor};
+use stellar_protocol::protocol::{DebugInfo, GraphicsAPI, Handshake, HostInfo, InputEvent, StellarChannel, StellarDirectControlMessage, StellarMessage, Synchornization, CaptureTarget, FrameLayout, FramePixelFormat, WindowIcon};
 
 use crossbeam_queue::SegQueue;
+use stellar_shared::logging::{self, Level, Target};
 
-use std::any::Any;
 use std::path::PathBuf;
 use std::sync::RwLock;
 use std::{
     collections::HashMap,
-    fs::File,
+    fs::{self, File, OpenOptions},
     io::Write,
+    os::unix::fs::OpenOptionsExt,
     os::unix::net::UnixStream,
     sync::{Arc, Mutex, MutexGuard},
 };
 
 use std::thread; // for test func
+use std::time::Duration;
 
-use crate::constants::{BLACKLISTED_PROCESS_NAMES, GAMEPAD_NAME};
+use crate::constants::GAMEPAD_NAME;
 use crate::hooks::dlsym::check_cache_integrity;
+use crate::hooks::{dlopen, registry, telemetry};
 use crate::platform::sdl2::{calc_axes_for_virtual_gamepad, calc_btns_for_virtual_gamepad};
 use crate::{bind, shim};
 use crate::utils::{config::Config, pointer::Pointer};
 use lazy_static::lazy_static;
 
 use super::feature_flags;
-use super::input::{Gamepad, GamepadInitializationSpecs, InputManager, Timestampable};
+use super::frame_slots::{FrameSlots, FRAME_SLOT_COUNT};
+use super::frame_stream::FrameStreamServer;
+use super::input::{Gamepad, GamepadInitializationSpecs, InputManager};
+use super::targeting::{self, ProcessInfo};
+use super::transport::{CommandQueues, Peer, Transport, TransportAddr, TransportShared};
 use super::window::Window;
+use super::xlib_capture::XlibCaptureMode;
 use super::{
     feature_flags::FeatureFlags,
     host_behavior::{DefaultHostBehavior},
 };
+use stellar_shared::{log_debug, log_error, log_info, log_trace, log_warn};
 
 pub struct CaptureHelper {
     pub frameFile: Option<Mutex<File>>,
 }
 
+// what the io threads hand to the game thread, already validated
 pub enum MainTickMessage {
-    RequestResolutionBroadcast(Endpoint),
-    RequestShImgPath(Endpoint),
-    RequestHandshake(Endpoint),
-    RequestDebugInfoV2(Endpoint),
-    ProcessDirectMessage(Endpoint, String, StellarDirectControlMessage),
+    RequestResolutionBroadcast(Peer),
+    RequestShImgPath(Peer),
+    RequestHandshake(Peer),
+    RequestDebugInfoV2(Peer),
+    RequestWindowList(Peer),
+    SetCaptureTarget(CaptureTarget),
+    ProcessDirectMessage(Peer, String, StellarDirectControlMessage),
 }
 
+// so a burst of requests can't stall a frame, the rest wait for the next tick
+const MAX_COMMANDS_PER_TICK: usize = 8;
+
 pub struct LastSentState {
     pub resolution: (u32, u32),
     pub graphics_api: GraphicsAPI,
+    pub pixel_format: FramePixelFormat,
 }
 
 pub struct ApplicationHost {
     pub config: Arc<Config>,
-    pub features: Mutex<FeatureFlags>,
+    pub features: FeatureFlags,
     pub behavior: Arc<Mutex<DefaultHostBehavior>>,
     pub func_pointers: Mutex<HashMap<String, Pointer>>,
     pub capture_helper: Option<CaptureHelper>,
-    pub messaging_handler: Option<Arc<Mutex<NodeHandler<InternalSignals>>>>,
-    pub command_queue: Arc<SegQueue<MainTickMessage>>,
+    // one per listening transport, a Peer's transport is an index into this
+    pub transports: Vec<Arc<Transport>>,
+    pub commands: Arc<CommandQueues>,
     pub last_sent_state: Arc<RwLock<LastSentState>>, // TODO: remove this arc rwlock if perf is hit hard enough here, may be able to unsafe it
     pub host_info: RwLock<HostInfo>,
-    pub input_manager: Arc<Mutex<InputManager>>,
+    // only the game thread locks this, the networking thread queues into incoming_input instead
+    pub input_manager: Mutex<InputManager>,
+    pub incoming_input: Arc<SegQueue<InputEvent>>,
+    pub frame_slots: Arc<FrameSlots>,
+    // frames over tcp for streamerds on other machines, only when HW_FRAME_STREAM_ADDR is set
+    pub frame_stream: Option<Arc<FrameStreamServer>>,
+    // what we published to the session dir, removed again at exit
+    pub session: Mutex<Option<SessionDescriptor>>,
+    // held while the descriptor file is rewritten so two rewrites never race on the temp file
+    session_write: Mutex<()>,
 }
 
 #[derive(Debug)]
 pub enum InternalSignals {
     TestSignal,
     TracingSignal,
-    NewFrameSignal,
-    SendToChannelSignal(StellarChannel, StellarMessage),
+    NewFrameSignal(u32, u64, FrameLayout),
+    // serialized once no matter how many transports and subscribers it goes out to
+    SendToChannelSignal(StellarChannel, Arc<Vec<u8>>),
+    // each transport checks for quiet peers on a timer
+    ExpirePeersSignal,
 }
 
 impl ApplicationHost {
     pub fn new(config: Config) -> Self {
         let mut default_behavior = DefaultHostBehavior::new();
-        if config.debug_mode {
-            println!("Spawning writer thread...");
-        }
+        let frame_slots = Arc::new(FrameSlots::new(FRAME_SLOT_COUNT));
+        log_debug!(Capture, "Spawning writer thread...");
         if !config.netural_mode {
-            let handle = default_behavior.spawn_writer_thread(&config);
-            if config.debug_mode {
-                println!("Default behavior thread handle: {:?}", handle);
-            }
+            let handle = default_behavior.spawn_writer_thread(&config, frame_slots.clone());
+            log_debug!(Capture, "Default behavior thread handle: {:?}", handle);
         }
         let host_info = HostInfo::default();
         let host = ApplicationHost {
             config: Arc::new(config),
-            features: Mutex::new(FeatureFlags::new()),
+            features: FeatureFlags::new(),
             behavior: Arc::new(Mutex::new(default_behavior)),
             func_pointers: Mutex::new(HashMap::new()),
             capture_helper: None,
-            messaging_handler: None,
-            command_queue: Arc::new(SegQueue::new()),
-            last_sent_state: Arc::new(RwLock::new(LastSentState { resolution: (0, 0), graphics_api: host_info.graphics_api })),
+            transports: Vec::new(),
+            commands: Arc::new(CommandQueues::new()),
+            last_sent_state: Arc::new(RwLock::new(LastSentState { resolution: (0, 0), graphics_api: host_info.graphics_api, pixel_format: FramePixelFormat::default() })),
             host_info: RwLock::new(host_info),
-            input_manager: Arc::new(Mutex::new(InputManager::new())),
+            input_manager: Mutex::new(InputManager::new()),
+            incoming_input: Arc::new(SegQueue::new()),
+            frame_slots,
+            frame_stream: None,
+            session: Mutex::new(None),
+            session_write: Mutex::new(()),
         };
         return host;
     }
@@ -108,6 +138,33 @@ impl ApplicationHost {
         path
     }
 
+    pub fn get_auth_token_path(&self) -> PathBuf {
+        stellar_protocol::util::runtime_dir().join(format!("{}.token", self.config.session_id))
+    }
+
+    // the configured token, or a fresh one written to our runtime dir for whoever sets up streamerd
+    fn resolve_auth_token(&self) -> AuthToken {
+        if let Some(token) = &self.config.auth_token {
+            return token.clone();
+        }
+        let token = AuthToken::generate();
+        let path = self.get_auth_token_path();
+        // create_new so a file or symlink someone put there first is never written through
+        let create = || OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path);
+        let written = path.parent().map_or(Ok(()), stellar_protocol::util::ensure_private_dir)
+            .and_then(|_| match create() {
+                // left by an earlier run with the same session id, the dir is ours so it's safe to replace
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => fs::remove_file(&path).and_then(|_| create()),
+                result => result,
+            })
+            .and_then(|mut file| file.write_all(token.as_str().as_bytes()));
+        match written {
+            Ok(()) => log_info!(Net, "auth token for network control written to {}", path.display()),
+            Err(err) => log_error!(Net, "could not write auth token to {}: {}", path.display(), err),
+        }
+        token
+    }
+
     pub fn get_handshake(&self) -> Handshake {
         let resolution = self.get_behavior().get_fb_size().or_else(|| Some((0,0))).unwrap();
         let shimg_path = self.get_behavior().get_shimg_path(&self.config);
@@ -115,6 +172,9 @@ impl ApplicationHost {
             resolution: resolution,
             shimg_path: shimg_path,
             graphics_api: self.host_info.read().unwrap().graphics_api,
+            frame_slots: self.frame_slots.count() as u32,
+            pixel_format: self.get_behavior().fb_format,
+            frame_stream_port: self.frame_stream.as_ref().map(|frame_stream| frame_stream.port()),
         };
         handshake
     }
@@ -131,26 +191,66 @@ impl ApplicationHost {
 
     pub fn set_graphics_api(&self, api: GraphicsAPI) {
         self.host_info.write().unwrap().graphics_api = api;
+        // the descriptor usually goes out before the game picks an api
+        // this is often the render thread so only the field changes here, the file is rewritten on a thread of its own
+        let changed = match self.session.lock().unwrap().as_mut() {
+            Some(descriptor) if descriptor.graphics_api != api => {
+                descriptor.graphics_api = api;
+                true
+            }
+            _ => false,
+        };
+        if changed {
+            if let Err(err) = thread::Builder::new().name("hw-session".to_string()).spawn(|| HOST.rewrite_session()) {
+                log_warn!(Net, "could not start the session descriptor update: {}", err);
+            }
+        }
     }
 
-    pub fn tick(&self) {
-        self.get_behavior().tick();
-
-        // process commands from queue
-        if self.config.tracing_mode {
-            println!("tick()");
+    // each rewrite writes whatever the descriptor is once it gets its turn, so the last one always has the newest state
+    fn rewrite_session(&self) {
+        let _writing = self.session_write.lock().unwrap();
+        let descriptor = self.session.lock().unwrap().clone();
+        if let Some(descriptor) = descriptor {
+            if let Err(err) = descriptor.write() {
+                log_warn!(Net, "could not update session descriptor: {}", err);
+            }
         }
+    }
 
-        if self.get_behavior().get_fb_size().is_none() {
-            return;
+    // so streamerd --list-sessions/--attach can find us without guessing socket paths
+    fn publish_session(&self, socket_path: PathBuf) {
+        let descriptor = SessionDescriptor {
+            pid: std::process::id(),
+            exe: std::env::current_exe().map(|exe| exe.display().to_string()).unwrap_or_else(|_| stellar_protocol::util::prog().unwrap_or_default()),
+            session_id: self.config.session_id.clone(),
+            socket_path,
+            shm_path: self.get_behavior().get_shimg_path(&self.config),
+            graphics_api: self.host_info.read().unwrap().graphics_api,
+            started_at: session::now(),
+        };
+        match descriptor.write() {
+            Ok(path) => {
+                log_info!(Net, "session descriptor written to {}", path.display());
+                *self.session.lock().unwrap() = Some(descriptor);
+                unsafe {
+                    libc::atexit(remove_session_on_exit);
+                }
+            }
+            Err(err) => log_error!(Net, "could not write session descriptor to {}: {}", descriptor.path().display(), err),
         }
+    }
 
-        // compute changes in state
+    // sends a sync to subscribers if the resolution, graphics api or pixel format moved since last time
+    pub fn sync_if_changed(&self) {
+        let fb_size = match self.get_behavior().get_fb_size() {
+            Some(fb_size) => fb_size,
+            None => return,
+        };
         let mut state_changed = false;
         {
             let mut last_sent_state = self.last_sent_state.write().unwrap();
             let host_info = self.host_info.read().unwrap();
-            let fb_size = self.get_behavior().get_fb_size().unwrap();
             if last_sent_state.resolution != fb_size {
                 state_changed = true;
                 last_sent_state.resolution = fb_size;
@@ -160,100 +260,43 @@ impl ApplicationHost {
                 state_changed = true;
                 last_sent_state.graphics_api = host_info.graphics_api;
             }
+
+            let fb_format = self.get_behavior().fb_format;
+            if last_sent_state.pixel_format != fb_format {
+                state_changed = true;
+                last_sent_state.pixel_format = fb_format;
+            }
         }
 
         if state_changed {
             // send a sync message
-            println!("changes detected, doing sync signal");
+            log_debug!(Net, "changes detected, doing sync signal");
             self.sync();
         }
+    }
 
-        match self.command_queue.pop() {
-            Some(command) => match command {
-                MainTickMessage::RequestResolutionBroadcast(endpoint) => {
-                    if self.config.debug_mode {
-                        println!("Responding to resolution request from {:?} with {:?}", endpoint.addr(), self.get_behavior().get_fb_size());
-                    }
-                    self.send_to(endpoint, &StellarMessage::ResolutionBroadcastResponse(self.get_behavior().get_fb_size()));
-                    if self.config.debug_mode {
-                        println!("Resolution response sent!");
-                    }
-                },
-                MainTickMessage::RequestShImgPath(endpoint) => {
-                    let path = self.get_behavior().get_shimg_path(&self.config);
-                    if self.config.debug_mode {
-                        println!("Responding to shimg path request from {:?} with {:?}", endpoint.addr(), path);
-                    }
-                    let path_copy = path.clone();
-                    self.send_to(endpoint.clone(), &StellarMessage::ShImgPathResponseStruct(path));
-                    self.send_to(endpoint.clone(), &StellarMessage::ShImgPathResponse(path_copy.display().to_string()));
-                },
-                MainTickMessage::RequestHandshake(endpoint) => {
-                    if self.config.debug_mode {
-                        println!("Responding to handshake request from {:?}", endpoint.addr());
-                    }
-                    let handshake = self.get_handshake();
-                    self.send_to(endpoint, &StellarMessage::HandshakeResponse(handshake));
-                    self.sync();
-                },
-                MainTickMessage::RequestDebugInfoV2(endpoint) => {
-                    // prepare debug info that can be sent without waiting for tick
-                    let mut output = "Debug Info:\n".to_string();
-                    output += &format!("Features: {:#?}", HOST.features.lock().unwrap());
-
-                    check_cache_integrity();
-
-                    output += &HOST.get_behavior().create_debug_output();
-
-                    let debug_info_v2 = DebugInfo {
-                        message: output,
-                    };
-                    self.send_to(endpoint.clone(), &StellarMessage::DebugInfoResponseV2(debug_info_v2, "main".to_string()));
-                },
-                MainTickMessage::ProcessDirectMessage(endpoint, source, message) => {
-                    match message {
-                        StellarDirectControlMessage::AddGamepad { local_id, product_type, axes, buttons, hats } => {
-                            if axes < 16 && buttons < 32 && hats == 0{
-                                let mut input_manager_locked = self.input_manager.lock().unwrap();
-                                // TODO; fail when we have unreasonable amount of axes/buttons
-                                let internal_axes = calc_axes_for_virtual_gamepad(axes as u8);
-                                let internal_buttons = calc_btns_for_virtual_gamepad(buttons as u8);
-                                let gamepad = Gamepad::from_product_type(GAMEPAD_NAME.to_string(), product_type, GamepadInitializationSpecs { axes: internal_axes as i32, buttons: internal_buttons as i32, hats });
-                                let chosen_id = gamepad.id.clone();
-                                println!("adding gamepad {}", chosen_id);
-                                let index = input_manager_locked.add_gamepad(gamepad);
-                                let added_message = format!("Added gamepad {}", index + 1);
-                                let direct_message = StellarDirectControlMessage::AddGamepadReply { local_id, remote_id: chosen_id, success: true, message: added_message };
-                                self.send_to(endpoint, &StellarMessage::ReplyDataChannelMessage(source, "reliable".to_string(), direct_message));
-                            } else {
-                                let direct_message = StellarDirectControlMessage::AddGamepadReply { local_id, remote_id: "".to_string(), success: false, message: format!("Could not add gamepad, too many axes/buttons/hats") };
-                                self.send_to(endpoint, &StellarMessage::ReplyDataChannelMessage(source, "reliable".to_string(), direct_message));
-                            }
-                        }
-                        StellarDirectControlMessage::RemoveGamepad { remote_id } => {
-                            {
-                                let mut input_manager_locked = self.input_manager.lock().unwrap();
-                                if let Some(gamepad) = input_manager_locked.remove_gamepad(&remote_id) {
-                                    println!("Removed gamepad {}", gamepad.id);
-                                    let direct_message = StellarDirectControlMessage::RemoveGamepadReply { remote_id, success: true, message: format!("Removed gamepad {}", gamepad.id) };
-                                    self.send_to(endpoint, &StellarMessage::BroadcastDataChannelMessage("reliable".to_string(), direct_message));
-                                } else {
-                                    println!("Gamepad removal failed");
-                                    let direct_message = StellarDirectControlMessage::RemoveGamepadReply { remote_id: remote_id.clone(), success: false, message: format!("Could not find gamepad {}, perhaps it was removed earlier?", &remote_id) };
-                                    self.send_to(endpoint, &StellarMessage::ReplyDataChannelMessage(source, "reliable".to_string(), direct_message));
-                                }
-                            }
-                        },
-                        _ => {
-                            println!("Unhandled direct control message (on main) {:?} from {:?}", message, endpoint.addr());
-                        }
-                    }
-                }
-            },
-            None => {}
+    pub fn tick(&self) {
+        self.get_behavior().tick();
+        self.flush_log_lines();
+        self.publish_capture_change();
+
+        // process commands from queue
+        log_trace!(General, "tick()");
+
+        if self.get_behavior().get_fb_size().is_none() {
+            return;
         }
 
-        self.input_manager.lock().unwrap().flush_queue();
+        self.sync_if_changed();
+
+        for _ in 0..MAX_COMMANDS_PER_TICK {
+            match self.commands.pop() {
+                Some(command) => self.handle_command(command),
+                None => break,
+            }
+        }
+
+        self.flush_input();
 
         {
             // let feature_flags = HOST.features.lock().unwrap();
@@ -262,18 +305,154 @@ impl ApplicationHost {
                 unsafe {
                     let error = bind::sdl2_safe::SDL_GetError_safe();
                     if error.len() > 0 {
-                        println!("SDL error: {}", error);
+                        log_warn!(General, "SDL error: {}", error);
                     }
                 }
             }*/
         }
     }
 
+    // game thread side of the requests the io threads queued up
+    fn handle_command(&self, command: MainTickMessage) {
+        match command {
+            MainTickMessage::RequestResolutionBroadcast(peer) => {
+                log_debug!(Net, "Responding to resolution request from {:?} with {:?}", peer.addr(), self.get_behavior().get_fb_size());
+                self.send_to(peer, &StellarMessage::ResolutionBroadcastResponse(self.get_behavior().get_fb_size()));
+                log_debug!(Net, "Resolution response sent!");
+            },
+            MainTickMessage::RequestShImgPath(peer) => {
+                let path = self.get_behavior().get_shimg_path(&self.config);
+                log_debug!(Net, "Responding to shimg path request from {:?} with {:?}", peer.addr(), path);
+                let path_copy = path.clone();
+                self.send_to(peer, &StellarMessage::ShImgPathResponseStruct(path));
+                self.send_to(peer, &StellarMessage::ShImgPathResponse(path_copy.display().to_string()));
+            },
+            MainTickMessage::RequestHandshake(peer) => {
+                log_debug!(Net, "Responding to handshake request from {:?}", peer.addr());
+                let handshake = self.get_handshake();
+                self.send_to(peer, &StellarMessage::HandshakeResponse(handshake));
+                self.sync();
+            },
+            MainTickMessage::RequestDebugInfoV2(peer) => {
+                // prepare debug info that can be sent without waiting for tick
+                let mut output = "Debug Info:\n".to_string();
+                output += &format!("Features: {:#?}", HOST.features);
+
+                check_cache_integrity();
+
+                output += &HOST.get_behavior().create_debug_output();
+
+                let debug_info_v2 = DebugInfo {
+                    message: output,
+                    hook_calls: telemetry::hook_call_stats(),
+                };
+                self.send_to(peer, &StellarMessage::DebugInfoResponseV2(debug_info_v2, "main".to_string()));
+            },
+            MainTickMessage::RequestWindowList(peer) => {
+                let windows = self.get_behavior().list_windows();
+                log_debug!(Net, "Responding to window list request from {:?} with {} windows", peer.addr(), windows.len());
+                self.send_to(peer, &StellarMessage::WindowListResponse(windows));
+                // icons are too big to live in the list so send them right after
+                let icons: Vec<(u64, WindowIcon)> = self.get_behavior().windows.iter().filter_map(|w| w.icon.clone().map(|icon| (w.id as u64, icon))).collect();
+                for (id, icon) in icons {
+                    self.send_to(peer, &StellarMessage::WindowIconChanged(id, icon));
+                }
+            },
+            MainTickMessage::SetCaptureTarget(target) => {
+                if let Err(err) = self.get_behavior().set_capture_target(target) {
+                    log_warn!(Capture, "Failed to set capture target: {}", err);
+                }
+            },
+            MainTickMessage::ProcessDirectMessage(peer, source, message) => {
+                match message {
+                    StellarDirectControlMessage::AddGamepad { local_id, product_type, axes, buttons, hats } => {
+                        // the io thread already turns these down, this just keeps the casts below honest
+                        if (0..16).contains(&axes) && (0..32).contains(&buttons) && hats == 0 {
+                            let mut input_manager_locked = self.input_manager.lock().unwrap();
+                            let internal_axes = calc_axes_for_virtual_gamepad(axes as u8);
+                            let internal_buttons = calc_btns_for_virtual_gamepad(buttons as u8);
+                            let gamepad = Gamepad::from_product_type(GAMEPAD_NAME.to_string(), product_type, GamepadInitializationSpecs { axes: internal_axes as i32, buttons: internal_buttons as i32, hats });
+                            let chosen_id = gamepad.id.clone();
+                            log_info!(Input, "adding gamepad {}", chosen_id);
+                            let index = input_manager_locked.add_gamepad(gamepad);
+                            let added_message = format!("Added gamepad {}", index + 1);
+                            let direct_message = StellarDirectControlMessage::AddGamepadReply { local_id, remote_id: chosen_id, success: true, message: added_message };
+                            self.send_to(peer, &StellarMessage::ReplyDataChannelMessage(source, "reliable".to_string(), direct_message));
+                        } else {
+                            let direct_message = StellarDirectControlMessage::AddGamepadReply { local_id, remote_id: "".to_string(), success: false, message: format!("Could not add gamepad, too many axes/buttons/hats") };
+                            self.send_to(peer, &StellarMessage::ReplyDataChannelMessage(source, "reliable".to_string(), direct_message));
+                        }
+                    }
+                    StellarDirectControlMessage::RemoveGamepad { remote_id } => {
+                        {
+                            let mut input_manager_locked = self.input_manager.lock().unwrap();
+                            if let Some(gamepad) = input_manager_locked.remove_gamepad(&remote_id) {
+                                log_info!(Input, "Removed gamepad {}", gamepad.id);
+                                let direct_message = StellarDirectControlMessage::RemoveGamepadReply { remote_id, success: true, message: format!("Removed gamepad {}", gamepad.id) };
+                                self.send_to(peer, &StellarMessage::BroadcastDataChannelMessage("reliable".to_string(), direct_message));
+                            } else {
+                                log_warn!(Input, "Gamepad removal failed");
+                                let direct_message = StellarDirectControlMessage::RemoveGamepadReply { remote_id: remote_id.clone(), success: false, message: format!("Could not find gamepad {}, perhaps it was removed earlier?", &remote_id) };
+                                self.send_to(peer, &StellarMessage::ReplyDataChannelMessage(source, "reliable".to_string(), direct_message));
+                            }
+                        }
+                    },
+                    StellarDirectControlMessage::RequestTitle => {
+                        let (title, icon) = {
+                            let behavior = self.get_behavior();
+                            let window = behavior.get_capture_window();
+                            (window.and_then(|w| w.title.clone()), window.and_then(|w| w.icon.clone()))
+                        };
+                        if let Some(title) = title {
+                            self.send_to(peer, &StellarMessage::ReplyDataChannelMessage(source.clone(), "reliable".to_string(), StellarDirectControlMessage::UpdateWindowTitle { title }));
+                        }
+                        if let Some(icon) = icon {
+                            self.send_to(peer, &StellarMessage::ReplyDataChannelMessage(source, "reliable".to_string(), StellarDirectControlMessage::UpdateWindowIcon { width: icon.width, height: icon.height, rgba: icon.rgba }));
+                        }
+                    },
+                    _ => {
+                        log_warn!(Net, "Unhandled direct control message (on main) {:?} from {:?}", message, peer.addr());
+                    }
+                }
+            }
+        }
+    }
+
     pub fn sync(&self){
-        if let Some(handler) = &self.messaging_handler {
-            let handler = handler.lock().unwrap();
-            let sync_details = self.get_sync(); // this is here so it is more up to date
-            handler.signals().send(InternalSignals::SendToChannelSignal(StellarChannel::Synchornizations, StellarMessage::SynchronizationEvent(sync_details)));
+        let sync_details = self.get_sync(); // this is here so it is more up to date
+        self.broadcast(StellarChannel::Synchornizations, &StellarMessage::SynchronizationEvent(sync_details));
+    }
+
+    // sends what forward_log_line queued up, from the app's thread since a log call can happen with the handler locked
+    pub fn flush_log_lines(&self) {
+        if LOG_QUEUE.is_empty() || self.transports.is_empty() {
+            return;
+        }
+        while let Some(message) = LOG_QUEUE.pop() {
+            self.broadcast(StellarChannel::Logs, &message);
+        }
+    }
+
+    // capturing is only true on one window, republish the one that lost it along with the one that got it
+    fn publish_capture_change(&self) {
+        let changed = self.get_behavior().take_capture_change();
+        for info in changed {
+            self.publish_window_change(StellarMessage::WindowChanged(info));
+        }
+    }
+
+    pub fn publish_window_change(&self, message: StellarMessage) {
+        self.broadcast(StellarChannel::WindowChanges, &message);
+    }
+
+    // hands a message to every transport's io thread to send to whoever subscribed there
+    pub fn broadcast(&self, channel: StellarChannel, message: &StellarMessage) {
+        if self.transports.is_empty() {
+            return;
+        }
+        let data = Arc::new(stellar_protocol::serialize(message));
+        for transport in self.transports.iter() {
+            transport.signal(InternalSignals::SendToChannelSignal(channel, data.clone()));
         }
     }
 
@@ -282,21 +461,17 @@ impl ApplicationHost {
         Synchornization {
             resolution: self.get_behavior().get_fb_size(),
             graphics_api: Some(host_info.graphics_api),
+            pixel_format: Some(self.get_behavior().fb_format),
         }
     }
 
     pub fn premain(&self) {
         // println!("I became alive inside {}", stellar_protocol::util::prog().expect("Could not get program name"));
-    }
-
-    pub fn check_should_use_netural_mode() -> bool {
-        if let Some(name) = stellar_protocol::util::prog() {
-            // println!("'{}' is blacklisted {}", name, BLACKLISTED_PROCESS_NAMES.contains(&name.as_str()));
-            if BLACKLISTED_PROCESS_NAMES.contains(&name.as_str()){ // weird thing here
-                return true;
-            }
+        registry::HOOKS.snapshot_config(&self.config);
+        if self.config.list_hooks {
+            registry::print_hooks();
         }
-        false
+        dlopen::on_host_ready();
     }
 
     pub fn start_server(&mut self) {
@@ -304,295 +479,105 @@ impl ApplicationHost {
             return;
         }
 
-        let (handler, listener) = node::split::<InternalSignals>();
-
         // bind unix always
         let unix_socket_path = match self.config.unix_socket_path.clone() {
             Some(path) => path.into(),
             None => self.get_unix_socket_path(),
         };
-
-        if self.config.debug_mode {
-            println!("Listening on unix socket: {}", unix_socket_path.display());
-        }
-
-        handler
-            .network()
-            .listen_with(
-                TransportListen::UnixDatagramSocket(UnixSocketListenConfig::new(unix_socket_path)),
-                create_null_socketaddr(),
-            )
-            .expect("Opening unix control socket failed.");
+        let mut addrs = vec![TransportAddr::Unix(unix_socket_path.clone())];
 
         if let Some(bind_type) = &self.config.bind_type {
             let addr = self.config.bind_addr.expect("bind address not set");
-            if self.config.debug_mode {
-                println!("Binding to address: {} (proto: {})", addr, bind_type);
-            }
-            match bind_type.as_str() {
-                "udp" => {
-                    handler.network().listen(Transport::Udp, addr).unwrap();
-                }
-                "tcp" => {
-                    handler.network().listen(Transport::Tcp, addr).unwrap();
-                }
-                _ => {
-                    panic!("unknown bind type");
+            for kind in bind_type.split(',') {
+                match TransportAddr::parse_bind(kind, addr) {
+                    Ok(transport_addr) => addrs.push(transport_addr),
+                    Err(err) => log_error!(Net, "Not binding {}: {}", addr, err),
                 }
             }
         }
 
-        let config = self.config.clone();
-
-        let handler_wrapper = Arc::new(Mutex::new(handler));
-        let handler_wrapper_2 = handler_wrapper.clone();
-        let handler_wrapper_3 = handler_wrapper.clone();
-        let handler_wrapper_instant_responses = handler_wrapper.clone();
-        let handler_signals = handler_wrapper.clone();
-
-        let command_queue = self.command_queue.clone();
-
-        let input_manager = self.input_manager.clone();
-
-        std::thread::spawn(move || {
-            // let mut frame_sub_endpoints: Vec<Endpoint> = vec![];
-            let mut pubsub: HashMap<StellarChannel, Vec<Endpoint>> = HashMap::new();
-            for channel in stellar_protocol::protocol::get_all_channels() {
-                pubsub.insert(channel, vec![]);
-            }
-
-            let check_subscribers = |subscribers: &mut Vec<Endpoint>| {
-                let handler_locked = handler_wrapper_3.lock().unwrap();
-                subscribers.retain_mut(|target_endpoint| {
-                    handler_locked
-                        .network()
-                        .is_ready(target_endpoint.resource_id())
-                        .is_some()
-                });
-            };
+        // anything off the unix socket has to prove it knows the token first
+        let token = if addrs.iter().any(|addr| !matches!(addr, TransportAddr::Unix(_))) {
+            Some(self.resolve_auth_token())
+        } else {
+            None
+        };
 
-            let check_all = || {
-                for channel in stellar_protocol::protocol::get_all_channels() {
-                    check_subscribers(pubsub.get_mut(&channel).unwrap());
+        if let Some(frame_stream_addr) = self.config.frame_stream_addr {
+            match FrameStreamServer::start(frame_stream_addr) {
+                Ok(frame_stream) => {
+                    log_info!(Net, "Frame stream listening on {}", frame_stream_addr);
+                    self.frame_stream = Some(frame_stream);
                 }
-            };
-
-            let send_main_tick_request = |message: MainTickMessage| {
-                command_queue.push(message);
-            };
+                Err(err) => log_error!(Net, "Frame stream on {} failed: {}", frame_stream_addr, err),
+            }
+        }
 
-            listener.for_each(move |event| {
-                // println!("got event: {:?}", event);
-                match event {
-                    NodeEvent::Network(netevent) => {
-                        match netevent {
-                            NetEvent::Connected(endpoint, ready) => {
-                                if !ready {
-                                    if config.debug_mode {
-                                        println!("One client did not successfully ready. {}", endpoint.addr());
-                                    }
-                                }
-                                if config.debug_mode {
-                                    println!("connection from {}", endpoint.addr());
-                                }
-                            }
-                            NetEvent::Accepted(_endpoint, _listener) => {}
-                            NetEvent::Message(endpoint, data) => {
-                                if config.debug_mode {
-                                    // println!("I got a message from {:?}", endpoint.addr());
-                                }
-                                match stellar_protocol::deserialize_safe(data) { 
-                                    Some(message) => {
-                                        match message {
-                                            StellarMessage::ResolutionRequest => {
-                                                if config.debug_mode {
-                                                    println!("Attempting to fufill resolution request from {:?}", endpoint.addr());
-                                                }
-                                                send_main_tick_request(MainTickMessage::RequestResolutionBroadcast(endpoint));
-                                            },
-                                            StellarMessage::ShImgPathRequest => {
-                                                if config.debug_mode {
-                                                    println!("Attempting to fufill shimg path request from {:?}", endpoint.addr());
-                                                }
-                                                send_main_tick_request(MainTickMessage::RequestShImgPath(endpoint));
-                                            },
-                                            StellarMessage::HandshakeRequest => {
-                                                if config.debug_mode {
-                                                    println!("Attempting to fufill handshake request from {:?}", endpoint.addr());
-                                                }
-                                                send_main_tick_request(MainTickMessage::RequestHandshake(endpoint));
-                                            },
-                                            StellarMessage::Hello => {
-                                                if config.debug_mode {
-                                                    println!("Hello message received from {:?}", endpoint.addr());
-                                                }
-                                            },
-                                            StellarMessage::SubscribeChannel(channel) => {
-                                                if config.debug_mode {
-                                                    println!("Subscribing to channel {:?} from {:?}", channel, endpoint.addr());
-                                                }
-                                                pubsub.get_mut(&channel).unwrap().push(endpoint.clone());
-                                            },
-                                            StellarMessage::UserInputEvent(input_event) => {
-                                                if config.debug_mode {
-                                                    // println!("User input event received from {:?} {:?}", endpoint.addr(), input_event);
-                                                }
-                                                {
-                                                    let mut input_event = input_event.clone();
-                                                    input_event.metadata.timestamp(); // TODO: timestamp this more accurately
-                                                    let mut input_manager_locked = input_manager.lock().unwrap();
-                                                    input_manager_locked.process_event(input_event);
-                                                }
-                                               
-                                            },
-                                            StellarMessage::DebugInfoRequest => {
-                                                let mut output = "Debug Info:\n".to_string();
-                                                output += &format!("Features: {:#?}", HOST.features.lock().unwrap());
-                                                handler_wrapper_instant_responses.lock().unwrap().network().send(endpoint, &stellar_protocol::serialize(&StellarMessage::DebugInfoResponse(DebugInfo { message: output })));
-                                            },
-                                            StellarMessage::DebugInfoRequestV2 => {
-                                                // prepare debug info that can be sent without waiting for tick
-                                                let mut output = "Debug Info:\n".to_string();
-                                                output += &format!("Features: {:#?}", HOST.features.lock().unwrap());
-                                                let debug_info_v2 = DebugInfo {
-                                                    message: output,
-                                                };
-                                                handler_wrapper_instant_responses.lock().unwrap().network().send(endpoint.clone(), &stellar_protocol::serialize(&StellarMessage::DebugInfoResponseV2(debug_info_v2, "networking".to_string())));
-                                                send_main_tick_request(MainTickMessage::RequestDebugInfoV2(endpoint.clone()));
-                                            },
-                                            StellarMessage::ForwardedDataChannelMessage(source_socket_id, message) => {
-                                                if HOST.config.debug_mode {
-                                                    println!("Forwarded data channel message {:?} from socket id {:?}", message, source_socket_id);
-                                                }
-                                                match message {
-                                                    stellar_protocol::protocol::StellarDirectControlMessage::AddGamepad { .. } => {
-                                                        send_main_tick_request(MainTickMessage::ProcessDirectMessage(endpoint, source_socket_id, message));
-                                                    },
-                                                    stellar_protocol::protocol::StellarDirectControlMessage::RequestTitle => {
-                                                        
-                                                    },
-                                                    StellarDirectControlMessage::UpdateGamepad { remote_id, axes, buttons, hats } => {
-                                                        {
-                                                            let mut input_manager_locked = input_manager.lock().unwrap();
-                                                            // input_manager_locked.update_gamepad(remote_id, axes, buttons, hats);
-                                                            input_manager_locked.process_event(InputEvent::new(stellar_protocol::protocol::InputEventPayload::JoystickBrowserUpdate { id: remote_id, axis: axes, buttons: buttons }))   
-                                                        }
-                                                    },
-                                                    StellarDirectControlMessage::RemoveGamepad { .. } => {
-                                                        send_main_tick_request(MainTickMessage::ProcessDirectMessage(endpoint, source_socket_id, message));
-                                                    },
-                                                    _ => {
-                                                        println!("Unhandled forwarded data channel message {:?} from socket id {:?}", message, source_socket_id);
-                                                    }
-                                                }
-                                            },
-                                            _ => {
-                                                if config.debug_mode {
-                                                    println!("Unhandled message: {:?}", message);
-                                                }
-                                            }
-                                        }
-                                    },
-                                    None => {
-                                        if config.debug_mode {
-                                            // redeserialize to get error
-                                            if let Err(err) = stellar_protocol::deserialize_result(data) {
-                                                println!("malformed message error: {:?}", err);
-                                            } else {
-                                                println!("Error deserializing message (malformed?), for unknown reason.");
-                                            }
-                                        }
-                                    }
-                                }
-
-                                // ex. reply
-                                // old echo test
-                                /*handler_wrapper
-                                    .lock()
-                                    .unwrap()
-                                    .network()
-                                    .send(endpoint, data);*/
-                            }
-                            NetEvent::Disconnected(_endpoint) => {
-                                if config.debug_mode {
-                                    println!("One client disconnected. {}", _endpoint.addr());
-                                }
-                            }
-                        }
-                    }
-                    NodeEvent::Signal(signal) => {
-                        match signal {
-                            // WARNING: DO NOT SEND A SIGNAL HERE CAUSE IT'LL BE DEADLOCKED I THINK
-                            // APPARENTLY NOT
-                            InternalSignals::TestSignal => {}
-                            InternalSignals::TracingSignal => {}
-                            InternalSignals::NewFrameSignal => {
-                                check_subscribers(&mut pubsub.get_mut(&StellarChannel::Frame).unwrap());
-                                for subscriber in pubsub.get_mut(&StellarChannel::Frame).unwrap() {
-                                    handler_wrapper
-                                        .lock()
-                                        .unwrap()
-                                        .network()
-                                        .send(subscriber.clone(), &stellar_protocol::serialize(&StellarMessage::NewFrame));
-                                }
-                            },
-                            InternalSignals::SendToChannelSignal(channel, message) => {
-                                if let Some(subscribers) = pubsub.get_mut(&channel) {
-                                    for subscriber in subscribers {
-                                        handler_wrapper
-                                            .lock()
-                                            .unwrap()
-                                            .network()
-                                            .send(subscriber.clone(), &stellar_protocol::serialize(&message));
-                                    }
-                                }
-                            },
-                            _ => {
-                                // log unhandled signal
-                                if config.debug_mode {
-                                    println!("Unhandled signal: {:?}", signal.type_id());
-                                }
-                            }
-                        }
-                    }
-                }
-            });
-        });
+        let shared = TransportShared {
+            commands: self.commands.clone(),
+            incoming_input: self.incoming_input.clone(),
+            frame_slots: self.frame_slots.clone(),
+            frame_stream: self.frame_stream.clone(),
+            config: self.config.clone(),
+        };
 
-        self.messaging_handler = Some(handler_wrapper_2);
+        for addr in addrs {
+            log_debug!(Net, "Listening on {}", addr);
+            let is_unix = matches!(addr, TransportAddr::Unix(_));
+            let transport_token = if is_unix { None } else { token.clone() };
+            match Transport::spawn(self.transports.len(), addr.clone(), transport_token, shared.clone()) {
+                Ok(transport) => self.transports.push(transport),
+                Err(err) if is_unix => panic!("Opening unix control socket failed: {}", err),
+                Err(err) => log_error!(Net, "Listening on {} failed: {}", addr, err),
+            }
+        }
 
-        // handler.signals().send(event)
-        // self.messaging_pair = Some((handler, listener));
+        self.publish_session(unix_socket_path);
     }
 
-    pub fn notify_frame(&self) {
-        if let Some(handler) = &self.messaging_handler {
-            let handler = handler.lock().unwrap();
-            handler.signals().send(InternalSignals::NewFrameSignal);
+    // called by the writer thread once a frame has landed in the shimg file
+    pub fn notify_frame_slot(&self, slot: u32, slot_size: usize, layout: FrameLayout) {
+        let transports: Vec<&Arc<Transport>> = self.transports.iter().filter(|transport| transport.has_frame_subscribers()).collect();
+        // with nobody to tell this frees the slot right away
+        self.frame_slots.expect_handouts(slot, transports.len());
+        for transport in transports {
+            transport.signal(InternalSignals::NewFrameSignal(slot, slot_size as u64, layout));
         }
     }
 
-    pub fn send_to(&self, endpoint: Endpoint, message: &StellarMessage) -> bool {
-        if let Some(handler) = &self.messaging_handler {
-            let handler = handler.lock().unwrap();
-            handler
-                .network()
-                .send(endpoint, &stellar_protocol::serialize(message));
-            return true;
+    // false when a captured frame would go nowhere, capture paths with a real cost can skip the work then
+    pub fn has_frame_consumers(&self) -> bool {
+        self.transports.iter().any(|transport| transport.has_frame_subscribers())
+            || self.frame_stream.as_ref().is_some_and(|frame_stream| frame_stream.has_streams())
+    }
+
+    pub fn send_to(&self, peer: Peer, message: &StellarMessage) -> bool {
+        match self.transports.get(peer.transport) {
+            Some(transport) => {
+                transport.send(peer.endpoint, message);
+                true
+            }
+            None => false,
         }
-        false
     }
 
     pub fn start(&mut self) {
         if !self.config.disable_control {
             self.start_server();
         } else {
-            if self.config.debug_mode {
-                println!("Control disabled. Not starting server.");
-            }
+            log_debug!(Net, "Control disabled. Not starting server.");
         }
         if self.config.capture_mode {
             self.capture_helper = Some(CaptureHelper { frameFile: None });
+            if self.config.xlib_capture == XlibCaptureMode::Timer && !self.config.netural_mode {
+                let interval = Duration::from_millis(self.config.xlib_capture_interval_ms.max(1));
+                thread::spawn(move || {
+                    loop {
+                        thread::sleep(interval);
+                        HOST.onXlibCaptureTimer();
+                    }
+                });
+            }
         }
     }
 
@@ -615,21 +600,99 @@ impl ApplicationHost {
         self.behavior.lock().unwrap()
     }
 
+    // applies what the networking thread queued and pushes the results into the app, game thread only
+    pub fn flush_input(&self) {
+        let mut input_manager = self.input_manager.lock().unwrap();
+        while let Some(event) = self.incoming_input.pop() {
+            input_manager.process_event(event);
+        }
+        input_manager.flush_queue();
+    }
+
     pub fn test(&self) {
         // TODO: supress test func in non-debug mode
         // println!("test func called on thread {:?}", thread::current().id());
     }
 
-    pub fn onFrameSwapBegin(&self) {
-        self.get_behavior().onFrameSwapBegin();
+    // window_id is the native window/drawable being swapped if the hook knows it
+    pub fn onFrameSwapBegin(&self, window_id: Option<usize>) {
+        if self.config.netural_mode {
+            return;
+        }
+        self.get_behavior().onFrameSwapBegin(window_id);
+        self.tick();
+    }
+
+    // XPutImage (flushed = false) and XFlush (flushed = true) from plain xlib apps
+    pub fn onXlibDraw(&self, flushed: bool) {
+        if self.config.netural_mode {
+            return;
+        }
+        {
+            // libX11 can call back into our hooks while our own capture connection is being used with the behavior locked
+            let mut behavior = match self.behavior.try_lock() {
+                Ok(behavior) => behavior,
+                Err(_) => return,
+            };
+            if self.config.xlib_capture == XlibCaptureMode::Hooks {
+                // a put from a previous draw has most likely reached the server by now
+                behavior.onXlibCapture(false);
+                if !flushed {
+                    behavior.mark_xlib_dirty();
+                }
+            }
+        }
+        self.tick();
+    }
+
+    pub fn onXlibCaptureTimer(&self) {
+        self.get_behavior().onXlibCapture(true);
+        // tick() belongs to the app's thread so only do the sync part here
+        self.sync_if_changed();
+    }
+
+    pub fn onRenderPresent(&self, renderer: usize) {
+        if self.config.netural_mode {
+            return;
+        }
+        self.get_behavior().onRenderPresent(renderer);
+        self.tick();
+    }
+
+    pub fn onSdl3RenderPresent(&self, renderer: usize) {
+        if self.config.netural_mode {
+            return;
+        }
+        self.get_behavior().onSdl3RenderPresent(renderer);
+        self.tick();
+    }
+
+    // returns whether this present's swapchain should be copied out
+    pub fn onVulkanPresent(&self, window_id: Option<usize>) -> bool {
+        if self.config.netural_mode {
+            return false;
+        }
+        self.get_behavior().onVulkanPresent(window_id)
+    }
+
+    pub fn onVulkanFrame(&self, width: u32, height: u32, format: FramePixelFormat, data: &[u8]) {
+        if self.config.netural_mode {
+            return;
+        }
+        // the frames say they're top down, this is for the graphics api reported to streamerd and the session
+        if self.host_info.read().unwrap().graphics_api != GraphicsAPI::Vulkan {
+            self.set_graphics_api(GraphicsAPI::Vulkan);
+        }
+        self.get_behavior().onVulkanFrame(width, height, format, data);
         self.tick();
     }
 
     pub fn onFrameSwapEnd(&self) {
+        if self.config.netural_mode {
+            return;
+        }
         self.get_behavior().onFrameSwapEnd();
         self.tick();
-        self.notify_frame();
-        self.tick();
     }
 
     pub fn onWindowCreate(
@@ -644,35 +707,149 @@ impl ApplicationHost {
             // ignore SDL opengl test
             return;
         }
-        self.get_behavior().onWindowCreate(win, x, y, width, height);
+        let win_id = win.id;
+        let info = {
+            let mut behavior = self.get_behavior();
+            behavior.onWindowCreate(win, x, y, width, height);
+            behavior.get_window_info(win_id)
+        };
+        if let Some(info) = info {
+            self.publish_window_change(StellarMessage::WindowChanged(info));
+        }
         self.tick();
     }
 
+    // SDL and the like create their own X window underneath, it's reported as part of their window from now on
+    pub fn link_native_window(&self, win_id: usize, native_id: usize) {
+        let dropped = self.get_behavior().link_native_window(win_id, native_id);
+        if dropped {
+            log_debug!(General, "window {} is X window {}", win_id, native_id);
+            self.publish_window_change(StellarMessage::WindowDestroyed(native_id as u64));
+        }
+    }
+
     pub fn onWindowDestroy(&self, win_id: usize) {
-        self.get_behavior().onWindowDestroy(win_id);
+        let win_id = {
+            let mut behavior = self.get_behavior();
+            let win_id = behavior.resolve_window_id(win_id);
+            behavior.onWindowDestroy(win_id);
+            win_id
+        };
+        self.publish_window_change(StellarMessage::WindowDestroyed(win_id as u64));
         self.tick();
     }
 
     pub fn onWindowResize(&self, win_id: usize, width: u32, height: u32) {
-        self.get_behavior().onWindowResize(win_id, width, height);
+        let info = {
+            let mut behavior = self.get_behavior();
+            behavior.onWindowResize(win_id, width, height);
+            behavior.get_window_info(win_id)
+        };
+        if let Some(info) = info {
+            self.publish_window_change(StellarMessage::WindowChanged(info));
+        }
         self.tick();
     }
+
+    fn update_window<F: FnOnce(&mut Window) -> bool>(&self, win_id: usize, update: F) {
+        let info = self.get_behavior().update_window(win_id, update);
+        if let Some(info) = info {
+            log_debug!(General, "window {} changed: {:?}", win_id, info);
+            self.publish_window_change(StellarMessage::WindowChanged(info));
+        }
+    }
+
+    pub fn onWindowTitleChange(&self, win_id: usize, title: Option<String>) {
+        self.update_window(win_id, |window| {
+            let changed = window.title != title;
+            window.title = title;
+            changed
+        });
+    }
+
+    pub fn onWindowIconChange(&self, win_id: usize, icon: Option<WindowIcon>) {
+        let published_icon = icon.clone();
+        let mut changed = false;
+        self.update_window(win_id, |window| {
+            changed = window.icon != icon;
+            window.icon = icon;
+            changed
+        });
+        if changed {
+            if let Some(icon) = published_icon {
+                self.publish_window_change(StellarMessage::WindowIconChanged(win_id as u64, icon));
+            }
+        }
+    }
+
+    pub fn onWindowVisibilityChange(&self, win_id: usize, visible: bool) {
+        self.update_window(win_id, |window| {
+            let changed = window.visible != visible;
+            window.visible = visible;
+            changed
+        });
+    }
+
+    pub fn onWindowFullscreenChange(&self, win_id: usize, fullscreen: bool) {
+        self.update_window(win_id, |window| {
+            let changed = window.fullscreen != fullscreen;
+            window.fullscreen = fullscreen;
+            changed
+        });
+    }
+
+    pub fn onWindowFocusChange(&self, win_id: usize, focused: bool) {
+        self.update_window(win_id, |window| {
+            let changed = window.focused != focused;
+            window.focused = focused;
+            changed
+        });
+    }
+}
+
+// log lines wait here for the next tick when the log output is the socket, the oldest go once it's full
+const LOG_QUEUE_LIMIT: usize = 1024;
+
+lazy_static! {
+    static ref LOG_QUEUE: SegQueue<StellarMessage> = SegQueue::new();
+}
+
+fn forward_log_line(target: Target, level: Level, message: &str) {
+    if LOG_QUEUE.len() >= LOG_QUEUE_LIMIT {
+        LOG_QUEUE.pop();
+    }
+    LOG_QUEUE.push(StellarMessage::LogLine(level.name().to_string(), target.name().to_string(), message.to_string()));
+}
+
+fn init_logging(config: &mut Config) {
+    if let Err(err) = logging::init("hyperwarp", &config.log, config.log_fallback_level(), config.log_output.clone(), Some(forward_log_line)) {
+        config.config_errors.push(format!("log_output {}", err));
+    }
 }
 
 fn create_host() -> ApplicationHost {
     let mut config = Config::from_env();
-    // println!("i became alive inside {}", stellar_protocol::util::prog().expect("Could not get program name"));
-    if ApplicationHost::check_should_use_netural_mode() {
-        // println!("engaging netural mode");
+    init_logging(&mut config);
+    // log_info!(General, "i became alive inside {}", stellar_protocol::util::prog().expect("Could not get program name"));
+    let process = ProcessInfo::current();
+    if let Some(reason) = targeting::non_target_reason(&config, &process) {
+        log_debug!(General, "not hosting in {}, {}", targeting::describe_process(&process), reason);
         config.debug_mode = false;
         config.tracing_mode = false;
         config.netural_mode = true;
+        // quiet from here on unless HW_LOG asks otherwise
+        init_logging(&mut config);
+    } else if !config.netural_mode {
+        targeting::claim_process_tree(&config);
+    }
+    // errors show at the default level, a typo in the config file shouldn't need DEBUG_HW to notice
+    for error in config.config_errors.iter() {
+        log_error!(General, "config error: {}", error);
     }
-    if config.debug_mode {
-        // println!("Selected Connection type: {}", config.connection_type);
-        println!("Host config: {:?}", config);
+    log_debug!(General, "Host config: {:?}", config);
+    if logging::enabled(Target::General, Level::Debug) {
         let bt = Backtrace::new();
-        println!("Startup backtrace: {:?}", bt);
+        log_debug!(General, "Startup backtrace: {:?}", bt);
     }
     let host = {
         let mut host = ApplicationHost::new(config);
@@ -680,10 +857,21 @@ fn create_host() -> ApplicationHost {
         host
     };
 
-    host.log();
+    if !host.config.netural_mode {
+        host.log();
+    }
     host
 }
 
+// try_lock since whatever exited may have been holding it, a leftover descriptor gets cleaned up by the next listing anyways
+extern "C" fn remove_session_on_exit() {
+    if let Ok(session) = HOST.session.try_lock() {
+        if let Some(descriptor) = session.as_ref() {
+            descriptor.remove();
+        }
+    }
+}
+
 lazy_static! {
     // so look here, this might be unsafe yk, but all the important things are behind mutexes
     pub static ref HOST: ApplicationHost = create_host();
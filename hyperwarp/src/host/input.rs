@@ -6,9 +6,10 @@ use stellar_protocol::protocol::{InputContext, InputEvent, InputEventPayload, In
 use stellar_shared::constants::sdl2::*;
 use stellar_shared::vendor::sdl_bindings::SDL_KeyCode;
 
-use crate::{bind::{self, sdl2::SDL_JoystickClose, sdl2_safe::{self, SDL_GetScancodeFromKey_safe, SDL_GetTicks_safe, SDL_PushEvent_safe}}, constants::sdl2::SDL_OUR_FAKE_MOUSEID, hooks::dlsym::check_cache_integrity, platform::sdl2::{calc_axes_for_virtual_gamepad, calc_btns_for_virtual_gamepad, convert_update_to_sdl_form, sdl2_translate_gamecontroller_axis_value_for_trigger, sdl2_translate_joystick_axis_value, SDL_JOYSTICK_MIN_AXIS_VALUE}};
+use crate::{bind::{self, sdl2::SDL_JoystickClose, sdl2_safe::{self, SDL_GetScancodeFromKey_safe, SDL_GetTicks_safe, SDL_PushEvent_safe}, sdl3_safe}, constants::{sdl2::SDL_OUR_FAKE_MOUSEID, sdl3::SDL3_Joystick}, hooks::dlsym::check_cache_integrity, platform::{sdl2::{calc_axes_for_virtual_gamepad, calc_btns_for_virtual_gamepad, convert_update_to_sdl_form, sdl2_translate_gamecontroller_axis_value_for_trigger, sdl2_translate_joystick_axis_value, SDL_JOYSTICK_MIN_AXIS_VALUE}, sdl3::{sdl3_keyboard_event, sdl3_mouse_button_event, sdl3_mouse_motion_event, sdl3_mouse_wheel_event, sdl3_push_event, Sdl3EventTarget}}};
 
 use super::{feature_flags, hosting::HOST};
+use stellar_shared::{log_debug, log_error, log_warn};
 
 // abstraction for data
 pub struct Mouse {
@@ -67,7 +68,7 @@ impl Keyboard {
             if sdl_scancode_u32 < self.sdl2_virt_array.len() as u32 {
                 self.sdl2_virt_array[sdl_scancode_u32 as usize] = state as u8; // 1 means pressed, 0 means released
             } else {
-                println!("uh oh, sdl2 scancode out of bounds {}, impossible?", sdl_scancode_u32);
+                log_warn!(Input, "uh oh, sdl2 scancode out of bounds {}, impossible?", sdl_scancode_u32);
             }
         }
 
@@ -190,12 +191,16 @@ pub trait Timestampable {
 impl Timestampable for InputMetadata {
     fn timestamp(&mut self) {
         {
-            let feature_flags = HOST.features.lock().unwrap();
-            if feature_flags.sdl2_enabled {
+            let feature_flags = &HOST.features;
+            if feature_flags.sdl2_enabled() {
                 self.sdl2_timestamp_ticks = Some(SDL_GetTicks_safe());
                 self.sdl2_timestamp_ticks_u64 = Some(SDL_GetTicks_safe() as u64); // GetTicks64 not avali in some versions of sdl2, this is sad
             }
-            // TODO: sdl3
+            if feature_flags.sdl3_enabled() {
+                self.sdl3_timestamp_ticks = Some(sdl3_safe::SDL_GetTicks_safe() as u32);
+                // SDL3 event timestamps are in nanoseconds
+                self.sdl3_timestamp_ticks_u64 = Some(sdl3_safe::SDL_GetTicksNS_safe());
+            }
         }
     }
 }
@@ -243,6 +248,8 @@ pub struct InputManager {
     pub gamepads_locked: bool,
     pub event_queue: Vec<InputEvent>,
     pub event_queue_joystick_metaops: Vec<InputEvent>,
+    // glfw has no event queue we can push into, the glfwPollEvents hook drains this and calls the app's callbacks
+    pub glfw_event_queue: Vec<InputEvent>,
 }
 
 impl InputManager {
@@ -331,14 +338,15 @@ impl InputManager {
             event_queue: Vec::new(),
             gamepads_locked: false,
             event_queue_joystick_metaops: Vec::new(),
+            glfw_event_queue: Vec::new(),
         }
     }
 
     pub fn add_gamepad(&mut self, mut gamepad: Gamepad) -> usize {
         let index = self.gamepads.len();
-        let feature_flags = HOST.features.lock().unwrap();
+        let feature_flags = &HOST.features;
         let init_specs = gamepad.get_init_specs();
-        if feature_flags.sdl2_enabled {
+        if feature_flags.sdl2_enabled() {
             if gamepad.sdl_id.is_none() {
                 // Allocate virtual controller
                 let sdl_device_index = unsafe {
@@ -366,7 +374,7 @@ impl InputManager {
                         SetLED: None,
                         SendEffect: None,
                     };
-                    println!("attach begin");
+                    log_debug!(Input, "attach begin");
                     bind::sdl2::SDL_JoystickAttachVirtualEx(&desc as *const sdl2_sys_lite::bindings::SDL_VirtualJoystickDesc)*/
                 };
                 // println!("didn't die after attaching");
@@ -395,6 +403,30 @@ impl InputManager {
                 };
                 gamepad.sdl_instance_id = Some(sdl_joystick_id);
             }
+        } else if feature_flags.sdl3_enabled() {
+            if gamepad.sdl_id.is_none() {
+                // SDL3 only has the desc version of attaching, the ids are instance ids now
+                let btns_count = calc_btns_for_virtual_gamepad(init_specs.buttons as u8);
+                let axes_count = calc_axes_for_virtual_gamepad(init_specs.axes as u8);
+                match sdl3_safe::SDL_AttachVirtualGamepad_safe(&gamepad.name, gamepad.usb_id.vendor_id, gamepad.usb_id.product_id, axes_count as u16, btns_count as u16, init_specs.hats as u16) {
+                    Some(instance_id) => {
+                        let sdl_joystick_ref = sdl3_safe::SDL_OpenJoystick_safe(instance_id);
+                        // reset everything
+                        for i in 0..gamepad.state.axes.len() {
+                            let value = if i == 4 || i == 5 { SDL_JOYSTICK_MIN_AXIS_VALUE } else { 0 };
+                            sdl3_safe::SDL_SetJoystickVirtualAxis_safe(sdl_joystick_ref, i as i32, value);
+                        }
+                        for i in 0..gamepad.state.buttons.len() {
+                            sdl3_safe::SDL_SetJoystickVirtualButton_safe(sdl_joystick_ref, i as i32, false);
+                        }
+                        gamepad.sdl_id = Some(sdl_joystick_ref as usize);
+                        gamepad.sdl_instance_id = Some(instance_id as i32);
+                    },
+                    None => {
+                        log_error!(Input, "uh oh, SDL_AttachVirtualJoystick failed: {}", sdl3_safe::SDL_GetError_safe());
+                    }
+                }
+            }
         }
         self.gamepads.push(gamepad);
         // TODO: emit events
@@ -414,9 +446,7 @@ impl InputManager {
         {
             if let Some(gamepad) = self.gamepads.iter_mut().find(|gamepad| gamepad.id == id) {
                 if axes.len() != gamepad.state.axes.len() || buttons.len() != gamepad.state.buttons.len() {
-                    if HOST.config.debug_mode {
-                        println!("uh oh, gamepad update axes/buttons length mismatch {} {} {} {}", axes.len(), gamepad.state.axes.len(), buttons.len(), gamepad.state.buttons.len());
-                    }
+                    log_debug!(Input, "uh oh, gamepad update axes/buttons length mismatch {} {} {} {}", axes.len(), gamepad.state.axes.len(), buttons.len(), gamepad.state.buttons.len());
                     return;
                 }
 
@@ -469,11 +499,11 @@ impl InputManager {
 
     pub fn remove_gamepad(&mut self, id: &str) -> Option<Gamepad> {
         // TODO: emit events
-        let features = HOST.features.lock().unwrap();
+        let features = &HOST.features;
         match self.gamepads.iter().position(|gamepad| gamepad.id == id) {
             Some(index) => {
                 let mut gamepad = self.gamepads.remove(index);
-                if features.sdl2_enabled {
+                if features.sdl2_enabled() {
                     if let Some(sdl_inst_id) = gamepad.sdl_instance_id {
                         let joystick_id = sdl_inst_id as SDL_JoystickID;
                         if let Some(joystick_index) = bind::sdl2_safe::find_device_index_by_instance_id(joystick_id) {
@@ -482,11 +512,17 @@ impl InputManager {
                                 bind::sdl2::SDL_JoystickDetachVirtual(joystick_index);
                             }
                         } else {
-                            println!("uh oh, couldn't find joystick index for joystick instance id {} to close", joystick_id);
+                            log_warn!(Input, "uh oh, couldn't find joystick index for joystick instance id {} to close", joystick_id);
                         }
                     }
                     gamepad.sdl_id = None;
                     gamepad.sdl_instance_id = None;
+                } else if features.sdl3_enabled() {
+                    if let (Some(sdl_id), Some(sdl_inst_id)) = (gamepad.sdl_id, gamepad.sdl_instance_id) {
+                        sdl3_safe::SDL_CloseVirtualJoystick_safe(sdl_id as *mut SDL3_Joystick, sdl_inst_id as u32);
+                    }
+                    gamepad.sdl_id = None;
+                    gamepad.sdl_instance_id = None;
                 }
                 Some(gamepad)
             },
@@ -497,14 +533,28 @@ impl InputManager {
     }
 
     pub fn flush_queue(&mut self) {
-        let feature_flags = HOST.features.lock().unwrap();
+        let feature_flags = &HOST.features;
+        // which window SDL3 events go to and how to scale our pixel coordinates for it
+        let sdl3_target = if feature_flags.sdl3_enabled() && !self.event_queue.is_empty() {
+            Some(Sdl3EventTarget::current())
+        } else {
+            None
+        };
         for event in self.event_queue.drain(..) {
+            if feature_flags.glfw_enabled() {
+                match event.payload {
+                    InputEventPayload::KeyEvent { .. } | InputEventPayload::MouseMoveRelative { .. } | InputEventPayload::MouseMoveAbsolute(..) | InputEventPayload::MouseButtonsChange { .. } | InputEventPayload::MouseScroll { .. } => {
+                        self.glfw_event_queue.push(event.clone());
+                    },
+                    _ => {}
+                }
+            }
             match event.payload {
                 // type confusion note: no sdl enum key values are negative yet
                 InputEventPayload::KeyEvent { key, scancode, state, modifiers } => {
                     // we're going to ignore scancode for now
                     // let start_time = Instant::now();
-                    if feature_flags.sdl2_enabled {
+                    if feature_flags.sdl2_enabled() {
                         let event_type = if state { sdl2_sys_lite::bindings::SDL_EventType::SDL_KEYDOWN } else { sdl2_sys_lite::bindings::SDL_EventType::SDL_KEYUP };
                         let sdl_state = if state { sdl2_sys_lite::bindings::SDL_PRESSED } else { sdl2_sys_lite::bindings::SDL_RELEASED };
                         // println!("Resolving keycode enum of {}", key);
@@ -548,19 +598,25 @@ impl InputManager {
                             // println!("pushing event in {}ms", start_time.elapsed().as_millis());
                             if result_ok != 1 {
                                 let error_str = sdl2_safe::SDL_GetError_safe();
-                                if HOST.config.debug_mode {
-                                    println!("uh oh event push error: {}, {} {}", error_str, wid, timestamp);
-                                }
+                                log_debug!(Input, "uh oh event push error: {}, {} {}", error_str, wid, timestamp);
                             }
                         }
 
                         // println!("pushed event new kbd event");
                     }
+                    if let Some(target) = &sdl3_target {
+                        let timestamp = event.metadata.sdl3_timestamp_ticks_u64.unwrap_or(0);
+                        sdl3_push_event(sdl3_keyboard_event(target, timestamp, key, modifiers, state));
+                    }
                     // println!("pushed event new kbd event in {}ms", start_time.elapsed().as_millis());
                 },
                 InputEventPayload::MouseMoveRelative { x, y, x_absolute, y_absolute } => {
                     // println!("mouse move relative");
-                    if feature_flags.sdl2_enabled {
+                    if let (Some(target), Some(context)) = (&sdl3_target, &event.context) {
+                        let timestamp = event.metadata.sdl3_timestamp_ticks_u64.unwrap_or(0);
+                        sdl3_push_event(sdl3_mouse_motion_event(target, timestamp, context.buttons, x_absolute, y_absolute, x, y));
+                    }
+                    if feature_flags.sdl2_enabled() {
                         if let Some(context) = event.context {
                             let wid = HOST.get_behavior().get_largest_sdl2_window_id().unwrap_or(0);
                             let timestamp = event.metadata.sdl2_timestamp_ticks.unwrap_or(0);
@@ -585,19 +641,21 @@ impl InputManager {
                                 // println!("pushing event in {}ms", start_time.elapsed().as_millis());
                                 if result_ok != 1 {
                                     let error_str = sdl2_safe::SDL_GetError_safe();
-                                    if HOST.config.debug_mode {
-                                        println!("uh oh event push error: {}, {} {}", error_str, wid, timestamp);
-                                    }
+                                    log_debug!(Input, "uh oh event push error: {}, {} {}", error_str, wid, timestamp);
                                 }
                             }
                         }else{
-                            println!("no context for mouse move relative");
+                            log_debug!(Input, "no context for mouse move relative");
                         }
                     }
                 },
                 InputEventPayload::MouseMoveAbsolute(x, y, rel_x, rel_y) => {
                     // println!("mouse move absolute");
-                    if feature_flags.sdl2_enabled {
+                    if let (Some(target), Some(context)) = (&sdl3_target, &event.context) {
+                        let timestamp = event.metadata.sdl3_timestamp_ticks_u64.unwrap_or(0);
+                        sdl3_push_event(sdl3_mouse_motion_event(target, timestamp, context.buttons, x, y, rel_x, rel_y));
+                    }
+                    if feature_flags.sdl2_enabled() {
                         if let Some(context) = event.context {
                             let wid = HOST.get_behavior().get_largest_sdl2_window_id().unwrap_or(0);
                             let timestamp = event.metadata.sdl2_timestamp_ticks.unwrap_or(0);
@@ -622,19 +680,21 @@ impl InputManager {
                                 // println!("pushing event in {}ms", start_time.elapsed().as_millis());
                                 if result_ok != 1 {
                                     let error_str = sdl2_safe::SDL_GetError_safe();
-                                    if HOST.config.debug_mode {
-                                        println!("uh oh event push error: {}, {} {}", error_str, wid, timestamp);
-                                    }
+                                    log_debug!(Input, "uh oh event push error: {}, {} {}", error_str, wid, timestamp);
                                 }
                             }
                         }else{
-                            println!("no context for mouse move absolute");
+                            log_debug!(Input, "no context for mouse move absolute");
                         }
                     }
 
                 },
                 InputEventPayload::MouseButtonsChange { change, state } => {
-                    if feature_flags.sdl2_enabled {
+                    if let (Some(target), Some(context)) = (&sdl3_target, &event.context) {
+                        let timestamp = event.metadata.sdl3_timestamp_ticks_u64.unwrap_or(0);
+                        sdl3_push_event(sdl3_mouse_button_event(target, timestamp, change, state, context.mouse_x, context.mouse_y));
+                    }
+                    if feature_flags.sdl2_enabled() {
                         if let Some(context) = event.context {
                             let event_type = if state { sdl2_sys_lite::bindings::SDL_EventType::SDL_MOUSEBUTTONDOWN } else { sdl2_sys_lite::bindings::SDL_EventType::SDL_MOUSEBUTTONUP };
                             let sdl_state = if state { sdl2_sys_lite::bindings::SDL_PRESSED } else { sdl2_sys_lite::bindings::SDL_RELEASED };
@@ -663,47 +723,64 @@ impl InputManager {
                                 // println!("pushing event in {}ms", start_time.elapsed().as_millis());
                                 if result_ok != 1 {
                                     let error_str = sdl2_safe::SDL_GetError_safe();
-                                    if HOST.config.debug_mode {
-                                        println!("uh oh event push error: {}, {} {}", error_str, wid, timestamp);
-                                    }
+                                    log_debug!(Input, "uh oh event push error: {}, {} {}", error_str, wid, timestamp);
                                 }
                             }
                         }else{
-                            println!("no context for mouse button set");
+                            log_debug!(Input, "no context for mouse button set");
                         }
                     }
                 },
+                InputEventPayload::MouseScroll { x, y } => {
+                    // glfw and SDL3 pick these up for now
+                    if let (Some(target), Some(context)) = (&sdl3_target, &event.context) {
+                        let timestamp = event.metadata.sdl3_timestamp_ticks_u64.unwrap_or(0);
+                        sdl3_push_event(sdl3_mouse_wheel_event(target, timestamp, x, y, context.mouse_x, context.mouse_y));
+                    }
+                },
                 InputEventPayload::JoystickAxis { id, axis, value } => {
                     if let Some(gamepad) = self.gamepads.iter_mut().find(|gamepad| gamepad.id == id) {
-                        if feature_flags.sdl2_enabled {
+                        if feature_flags.sdl2_enabled() {
                             let sdl_value = sdl2_translate_joystick_axis_value(value);
                             unsafe {
                                 // println!("virtual axis {} set to {} which scales to {}", axis, value, sdl_value);
                                 bind::sdl2::SDL_JoystickSetVirtualAxis(gamepad.sdl_id.unwrap() as *mut SDL_Joystick, axis as i32, sdl_value);
                             }
                             // println!("setting joystick axis");
+                        } else if feature_flags.sdl3_enabled() {
+                            if let Some(sdl_id) = gamepad.sdl_id {
+                                sdl3_safe::SDL_SetJoystickVirtualAxis_safe(sdl_id as *mut SDL3_Joystick, axis as i32, sdl2_translate_joystick_axis_value(value));
+                            }
                         }
                     }
                 },
                 InputEventPayload::JoystickButton { id, button, pressed } => {
                     if let Some(gamepad) = self.gamepads.iter_mut().find(|gamepad| gamepad.id == id) {
-                        if feature_flags.sdl2_enabled {
+                        if feature_flags.sdl2_enabled() {
                             unsafe {
                                 let pressed_sdl2 = if pressed { SDL_PRESSED } else { SDL_RELEASED };
                                 // println!("virtual button {} set to {} which is {}", button, pressed, pressed_sdl2);
                                 bind::sdl2::SDL_JoystickSetVirtualButton(gamepad.sdl_id.unwrap() as *mut SDL_Joystick, button as i32, pressed_sdl2 as i8);
                             }
                             // println!("setting joystick button");
+                        } else if feature_flags.sdl3_enabled() {
+                            if let Some(sdl_id) = gamepad.sdl_id {
+                                sdl3_safe::SDL_SetJoystickVirtualButton_safe(sdl_id as *mut SDL3_Joystick, button as i32, pressed);
+                            }
                         }
                     }
                 },
                 _ => {
-                    println!("unhandled event in queue: {:?}", event);
+                    log_warn!(Input, "unhandled event in queue: {:?}", event);
                 }
             }
         }
     }
 
+    pub fn take_glfw_events(&mut self) -> Vec<InputEvent> {
+        std::mem::take(&mut self.glfw_event_queue)
+    }
+
     pub fn push_event(&mut self, event: InputEvent) {
         self.event_queue.push(event);
     }
@@ -730,14 +807,15 @@ impl InputManager {
                 let new_buttons = self.calculate_change(change, state);
                 self.set_mouse_buttons(new_buttons);
             },
+            InputEventPayload::MouseScroll { .. } => {
+                self.event_queue.push(new_event.with_input_manager(self));
+            },
             InputEventPayload::JoystickBrowserUpdate { .. } => {
                 let (id, updated_state) = convert_update_to_sdl_form(&event); 
                 self.update_gamepad_state(id, updated_state);
             },
             _ => {
-                if HOST.config.debug_mode {
-                    println!("unhandled event in processing: {:?}", event);
-                }
+                log_debug!(Input, "unhandled event in processing: {:?}", event);
                 self.event_queue.push(new_event);
             },
         }
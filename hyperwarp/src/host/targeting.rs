@@ -0,0 +1,247 @@
+// which processes we actually host in, anything else LD_PRELOAD reaches (crash reporters, shell wrappers, launchers)
+// goes neutral, no sockets, no shm file and the hooks just pass through
+// rules are checked in order and the first match decides, a process no rule matches is a target
+// unless there's an allow rule, then the rules are an allow-list and anything they don't allow is denied
+
+use std::{env, ffi::CString, fmt, fs, str::FromStr, sync::OnceLock};
+
+use regex::Regex;
+
+use crate::utils::{config::Config, profiles::{glob_matches, PROFILES}};
+
+// set by the first target when target_first_only is on, hooks/exec.rs adds it to its children's environment
+pub const TARGET_PID_ENV: &str = "HW_TARGET_PID";
+pub const TARGET_PID_ENV_PREFIX: &str = "HW_TARGET_PID=";
+
+// "HW_TARGET_PID=<our pid>" once we've claimed the tree
+static CHILD_TARGET_ENV: OnceLock<CString> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetAction {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone)]
+pub struct TargetRule {
+    pub action: TargetAction,
+    // globs like the profile ones, on our program name and the parent's
+    pub name: Option<String>,
+    pub parent: Option<String>,
+    // regex over the arguments joined with spaces, argv[0] included
+    pub argv: Option<Regex>,
+    text: String,
+}
+
+impl fmt::Display for TargetRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl FromStr for TargetRule {
+    type Err = String;
+
+    // "deny name=crashpad_handler", "allow parent=steam argv=--game\s", a bare "deny" matches everything
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let action = match words.next() {
+            Some("allow") => TargetAction::Allow,
+            Some("deny") => TargetAction::Deny,
+            other => return Err(format!("rule {:?} has to start with allow or deny, not {:?}", s, other)),
+        };
+        let mut rule = TargetRule {
+            action,
+            name: None,
+            parent: None,
+            argv: None,
+            text: s.trim().to_string(),
+        };
+        for word in words {
+            match word.split_once('=') {
+                Some(("name", glob)) => rule.name = Some(glob.to_string()),
+                Some(("parent", glob)) => rule.parent = Some(glob.to_string()),
+                Some(("argv", pattern)) => rule.argv = Some(Regex::new(pattern).map_err(|err| format!("rule {:?}: {}", s, err))?),
+                _ => return Err(format!("rule {:?}: expected name=, parent= or argv=, got {:?}", s, word)),
+            }
+        }
+        Ok(rule)
+    }
+}
+
+// the HW_TARGET_RULES form, rules separated by ; or one per line (json arrays come in one per line)
+#[derive(Debug, Clone, Default)]
+pub struct TargetRules(pub Vec<TargetRule>);
+
+impl FromStr for TargetRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split([';', '\n'])
+            .filter(|rule| !rule.trim().is_empty())
+            .map(TargetRule::from_str)
+            .collect::<Result<Vec<TargetRule>, String>>()
+            .map(TargetRules)
+    }
+}
+
+pub struct ProcessInfo {
+    pub name: Option<String>,
+    pub parent: Option<String>,
+    pub argv: String,
+}
+
+fn parent_name() -> Option<String> {
+    let ppid = unsafe { libc::getppid() };
+    // comm is cut off at 15 characters so prefer the exe
+    fs::read_link(format!("/proc/{}/exe", ppid)).ok()
+        .and_then(|exe| exe.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .or_else(|| fs::read_to_string(format!("/proc/{}/comm", ppid)).ok().map(|comm| comm.trim_end().to_string()))
+}
+
+impl ProcessInfo {
+    pub fn current() -> ProcessInfo {
+        ProcessInfo {
+            name: stellar_protocol::util::prog(),
+            parent: parent_name(),
+            argv: env::args_os().map(|arg| arg.to_string_lossy().into_owned()).collect::<Vec<String>>().join(" "),
+        }
+    }
+}
+
+impl TargetRule {
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        let glob = |pattern: &Option<String>, value: &Option<String>| match (pattern, value) {
+            (None, _) => true,
+            (Some(pattern), Some(value)) => glob_matches(pattern, value),
+            (Some(_), None) => false,
+        };
+        glob(&self.name, &process.name)
+            && glob(&self.parent, &process.parent)
+            && self.argv.as_ref().map_or(true, |argv| argv.is_match(&process.argv))
+    }
+}
+
+// None means host in this process, otherwise why not
+pub fn non_target_reason(config: &Config, process: &ProcessInfo) -> Option<String> {
+    if let Some(matched) = PROFILES.matched.iter().find(|matched| matched.profile.neutral) {
+        return Some(format!("neutral profile {} from {}", matched.profile.name, matched.origin));
+    }
+    if let Some(rule) = config.target_rules.0.iter().find(|rule| rule.matches(process)) {
+        if rule.action == TargetAction::Deny {
+            return Some(format!("denied by target rule {:?}", rule.to_string()));
+        }
+        // an allow still has to be the first process when that's asked for
+    } else if config.target_rules.0.iter().any(|rule| rule.action == TargetAction::Allow) {
+        return Some("no target rule allowed it".to_string());
+    }
+    if config.target_first_only {
+        if let Some(target_pid) = env::var(TARGET_PID_ENV).ok().and_then(|pid| pid.parse::<u32>().ok()) {
+            // exec keeps the pid, so a target that execs into the game stays the target
+            if target_pid != std::process::id() {
+                return Some(format!("not the first process in the tree, that was pid {}", target_pid));
+            }
+        }
+    }
+    None
+}
+
+pub fn tracks_process_tree(config: &Config) -> bool {
+    config.target_first_only
+}
+
+// marks us as the first target for any children, only matters with target_first_only
+// our own environment is left alone, the exec hooks add it to what children start with
+pub fn claim_process_tree(config: &Config) {
+    if tracks_process_tree(config) && env::var_os(TARGET_PID_ENV).is_none() {
+        let _ = CHILD_TARGET_ENV.set(CString::new(format!("{}{}", TARGET_PID_ENV_PREFIX, std::process::id())).unwrap());
+    }
+}
+
+pub fn child_target_env() -> Option<&'static CString> {
+    CHILD_TARGET_ENV.get()
+}
+
+pub fn describe_process(process: &ProcessInfo) -> String {
+    format!("{} (parent {}, pid {})",
+        process.name.as_deref().unwrap_or("unknown"),
+        process.parent.as_deref().unwrap_or("unknown"),
+        std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(name: &str, parent: &str, argv: &str) -> ProcessInfo {
+        ProcessInfo {
+            name: Some(name.to_string()),
+            parent: Some(parent.to_string()),
+            argv: argv.to_string(),
+        }
+    }
+
+    fn config(rules: &str) -> Config {
+        Config {
+            target_rules: rules.parse().unwrap(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn parses_rules() {
+        let rule: TargetRule = "allow name=game* parent=steam argv=--game\\s".parse().unwrap();
+        assert_eq!(rule.action, TargetAction::Allow);
+        assert_eq!(rule.name.as_deref(), Some("game*"));
+        assert_eq!(rule.parent.as_deref(), Some("steam"));
+        assert_eq!(rule.argv.as_ref().map(Regex::as_str), Some("--game\\s"));
+        assert_eq!(rule.to_string(), "allow name=game* parent=steam argv=--game\\s");
+
+        let rule: TargetRule = "  deny ".parse().unwrap();
+        assert_eq!(rule.action, TargetAction::Deny);
+        assert!(rule.name.is_none() && rule.parent.is_none() && rule.argv.is_none());
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert!("".parse::<TargetRule>().is_err());
+        assert!("maybe name=game".parse::<TargetRule>().is_err());
+        assert!("deny exe=game".parse::<TargetRule>().is_err());
+        assert!("deny name".parse::<TargetRule>().is_err());
+        assert!("deny argv=(".parse::<TargetRule>().is_err());
+        assert!("deny name=a; maybe".parse::<TargetRules>().is_err());
+    }
+
+    #[test]
+    fn splits_rule_lists() {
+        let rules: TargetRules = "deny name=crashpad_handler;\nallow parent=steam; ".parse().unwrap();
+        assert_eq!(rules.0.len(), 2);
+        assert_eq!(rules.0[1].action, TargetAction::Allow);
+        assert!("".parse::<TargetRules>().unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn matches_every_part_that_is_set() {
+        let rule: TargetRule = "deny name=crash* parent=game argv=--minidump".parse().unwrap();
+        assert!(rule.matches(&process("crashpad_handler", "game", "crashpad_handler --minidump x")));
+        assert!(!rule.matches(&process("crashpad_handler", "steam", "crashpad_handler --minidump x")));
+        assert!(!rule.matches(&process("crashpad_handler", "game", "crashpad_handler")));
+        assert!(!rule.matches(&ProcessInfo { name: None, parent: Some("game".to_string()), argv: "--minidump".to_string() }));
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let config = config("deny name=game-helper; allow name=game*");
+        assert!(non_target_reason(&config, &process("game-helper", "game", "")).is_some());
+        assert!(non_target_reason(&config, &process("game", "steam", "")).is_none());
+    }
+
+    #[test]
+    fn allow_rules_deny_everything_else() {
+        assert!(non_target_reason(&config("allow name=game"), &process("crashpad_handler", "game", "")).is_some());
+        assert!(non_target_reason(&config("allow name=game"), &process("game", "steam", "")).is_none());
+        // only deny rules, anything they don't catch is a target
+        assert!(non_target_reason(&config("deny name=crashpad_handler"), &process("launcher", "steam", "")).is_none());
+        assert!(non_target_reason(&config(""), &process("launcher", "steam", "")).is_none());
+    }
+}
@@ -0,0 +1,732 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_queue::SegQueue;
+use message_io::adapters::unix_socket::{create_null_socketaddr, UnixSocketListenConfig};
+use message_io::network::{Endpoint, NetEvent, Transport as NetTransport, TransportListen};
+use message_io::node::{self, NodeEvent, NodeHandler, NodeListener};
+use regex::Regex;
+
+use stellar_protocol::auth::{self, AuthToken, SessionCipher, Side, NONCE_LEN, PROOF_LEN};
+use stellar_protocol::protocol::{frame_slot_index, get_all_channels, CaptureTarget, DebugInfo, InputEvent, InputEventPayload, StellarChannel, StellarDirectControlMessage, StellarMessage};
+use stellar_shared::{log_debug, log_info, log_trace, log_warn};
+
+use crate::hooks::telemetry;
+use crate::utils::config::Config;
+
+use super::frame_slots::{FrameSlots, FRAME_SLOT_COUNT};
+use super::frame_stream::FrameStreamServer;
+use super::hosting::{InternalSignals, MainTickMessage, HOST};
+use super::input::Timestampable;
+
+// every transport gets its own message-io node and thread that parses, validates and rate limits what comes in
+// the game thread only sees what made it through, commands in the priority queues and input in incoming_input
+
+// anything bigger than this is dropped before we try to parse it
+pub const MAX_MESSAGE_SIZE: usize = 1 << 20;
+const MAX_STRING_LEN: usize = 4096;
+const MAX_TITLE_REGEX_LEN: usize = 1024;
+// browsers report way less than this, it's just so nobody can hand us a giant vec
+const MAX_GAMEPAD_INPUTS: usize = 64;
+// how often each io thread looks for peers that went quiet, see net_peer_timeout_ms
+const PEER_EXPIRY_CHECK: Duration = Duration::from_secs(1);
+// challenges a udp source can leave lying around, a spoofed one never answers so the oldest makes room
+const MAX_PENDING_AUTH: usize = 64;
+const AUTH_CHALLENGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub enum TransportAddr {
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+    Udp(SocketAddr),
+}
+
+impl TransportAddr {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransportAddr::Unix(_) => "unix",
+            TransportAddr::Tcp(_) => "tcp",
+            TransportAddr::Udp(_) => "udp",
+        }
+    }
+
+    // bind_type from the config, comma separated so tcp and udp can share the bind address
+    pub fn parse_bind(kind: &str, addr: SocketAddr) -> Result<TransportAddr, String> {
+        match kind.trim() {
+            "tcp" => Ok(TransportAddr::Tcp(addr)),
+            "udp" => Ok(TransportAddr::Udp(addr)),
+            other => Err(format!("unknown bind type {:?}", other)),
+        }
+    }
+
+    fn listen(&self, handler: &NodeHandler<InternalSignals>) -> io::Result<()> {
+        match self {
+            TransportAddr::Unix(path) => handler
+                .network()
+                .listen_with(TransportListen::UnixDatagramSocket(UnixSocketListenConfig::new(path.clone())), create_null_socketaddr())
+                .map(|_| ()),
+            // framed so messages keep their boundaries, plain tcp hands us whatever chunks the stream had
+            TransportAddr::Tcp(addr) => handler.network().listen(NetTransport::FramedTcp, *addr).map(|_| ()),
+            TransportAddr::Udp(addr) => handler.network().listen(NetTransport::Udp, *addr).map(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for TransportAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+            TransportAddr::Tcp(addr) => write!(f, "tcp:{}", addr),
+            TransportAddr::Udp(addr) => write!(f, "udp:{}", addr),
+        }
+    }
+}
+
+// who to answer, endpoints only mean something to the node they came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Peer {
+    pub transport: usize,
+    pub endpoint: Endpoint,
+}
+
+impl Peer {
+    pub fn addr(&self) -> SocketAddr {
+        self.endpoint.addr()
+    }
+}
+
+// the game thread drains these in order so input related work never waits behind debug requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    Input,
+    Control,
+    Debug,
+}
+
+const PRIORITY_COUNT: usize = 3;
+
+pub struct CommandQueues {
+    queues: [SegQueue<MainTickMessage>; PRIORITY_COUNT],
+}
+
+impl CommandQueues {
+    pub fn new() -> Self {
+        CommandQueues {
+            queues: [SegQueue::new(), SegQueue::new(), SegQueue::new()],
+        }
+    }
+
+    pub fn push(&self, priority: Priority, message: MainTickMessage) {
+        self.queues[priority as usize].push(message);
+    }
+
+    pub fn pop(&self) -> Option<MainTickMessage> {
+        self.queues.iter().find_map(|queue| queue.pop())
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn full(limit: f64) -> Self {
+        Bucket { tokens: limit, last: Instant::now() }
+    }
+
+    fn take(&mut self, limit: f64) -> bool {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * limit).min(limit);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// token bucket per endpoint and priority, lets a second's worth through in a burst, 0 means no limit
+// peers that haven't authenticated share one bucket so spoofed sources can't grow the map
+struct RateLimiter {
+    input_limit: u32,
+    limit: u32,
+    buckets: HashMap<(Endpoint, Priority), Bucket>,
+    unauthenticated: Bucket,
+}
+
+impl RateLimiter {
+    fn new(config: &Config) -> Self {
+        RateLimiter {
+            input_limit: config.net_input_rate_limit,
+            limit: config.net_rate_limit,
+            buckets: HashMap::new(),
+            unauthenticated: Bucket::full(config.net_rate_limit as f64),
+        }
+    }
+
+    fn allow(&mut self, endpoint: Endpoint, priority: Priority) -> bool {
+        let limit = match priority {
+            Priority::Input => self.input_limit,
+            Priority::Control | Priority::Debug => self.limit,
+        };
+        if limit == 0 {
+            return true;
+        }
+        let limit = limit as f64;
+        self.buckets.entry((endpoint, priority)).or_insert_with(|| Bucket::full(limit)).take(limit)
+    }
+
+    fn allow_unauthenticated(&mut self) -> bool {
+        self.limit == 0 || self.unauthenticated.take(self.limit as f64)
+    }
+
+    fn forget(&mut self, endpoint: Endpoint) {
+        self.buckets.retain(|(bucket_endpoint, _), _| *bucket_endpoint != endpoint);
+    }
+}
+
+pub fn parse(data: &[u8]) -> Result<StellarMessage, String> {
+    if data.len() > MAX_MESSAGE_SIZE {
+        return Err(format!("message too big ({} bytes)", data.len()));
+    }
+    stellar_protocol::deserialize_result(data).map_err(|err| format!("malformed message: {:?}", err))
+}
+
+fn check_string(what: &str, value: &str) -> Result<(), String> {
+    if value.len() > MAX_STRING_LEN {
+        return Err(format!("{} too long ({} bytes)", what, value.len()));
+    }
+    Ok(())
+}
+
+fn check_gamepad(id: &str, axes: &[f64], buttons: usize) -> Result<(), String> {
+    check_string("gamepad id", id)?;
+    if axes.len() > MAX_GAMEPAD_INPUTS || buttons > MAX_GAMEPAD_INPUTS {
+        return Err(format!("gamepad update with {} axes and {} buttons", axes.len(), buttons));
+    }
+    if axes.iter().any(|axis| !axis.is_finite()) {
+        return Err("gamepad axis is not a number".to_string());
+    }
+    Ok(())
+}
+
+fn validate_input(payload: &InputEventPayload) -> Result<(), String> {
+    match payload {
+        InputEventPayload::MouseScroll { x, y } => {
+            if !x.is_finite() || !y.is_finite() {
+                return Err("scroll delta is not a number".to_string());
+            }
+        }
+        InputEventPayload::JoystickBrowserUpdate { id, axis, buttons } => check_gamepad(id, axis, buttons.len())?,
+        InputEventPayload::JoystickAxis { id, value, .. } => {
+            check_string("gamepad id", id)?;
+            if !value.is_finite() {
+                return Err("gamepad axis is not a number".to_string());
+            }
+        }
+        InputEventPayload::JoystickButton { id, .. } => check_string("gamepad id", id)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn validate_direct(source: &str, message: &StellarDirectControlMessage) -> Result<Priority, String> {
+    check_string("data channel source", source)?;
+    match message {
+        StellarDirectControlMessage::UpdateGamepad { remote_id, axes, buttons, .. } => {
+            check_gamepad(remote_id, axes, buttons.len())?;
+            Ok(Priority::Input)
+        }
+        // too many axes gets a proper reply so it's not checked here
+        StellarDirectControlMessage::AddGamepad { local_id, .. } => {
+            check_string("gamepad id", local_id)?;
+            Ok(Priority::Input)
+        }
+        StellarDirectControlMessage::RemoveGamepad { remote_id } => {
+            check_string("gamepad id", remote_id)?;
+            Ok(Priority::Input)
+        }
+        StellarDirectControlMessage::RequestTitle => Ok(Priority::Control),
+        _ => Err(format!("unexpected data channel message {:?}", message)),
+    }
+}
+
+// checks something a client sent before anything acts on it and picks what queue and rate limit it falls under
+pub fn validate(message: &StellarMessage) -> Result<Priority, String> {
+    match message {
+        StellarMessage::UserInputEvent(event) => {
+            validate_input(&event.payload)?;
+            Ok(Priority::Input)
+        }
+        StellarMessage::ReleaseFrameSlot(slot) => {
+            if frame_slot_index(*slot) >= FRAME_SLOT_COUNT {
+                return Err(format!("frame slot {} out of range", slot));
+            }
+            Ok(Priority::Input)
+        }
+        StellarMessage::ForwardedDataChannelMessage(source, direct) => validate_direct(source, direct),
+        StellarMessage::SetCaptureTarget(CaptureTarget::TitleRegex(pattern)) => {
+            if pattern.len() > MAX_TITLE_REGEX_LEN {
+                return Err(format!("title regex too long ({} bytes)", pattern.len()));
+            }
+            Regex::new(pattern).map_err(|err| format!("bad title regex {}: {}", pattern, err))?;
+            Ok(Priority::Control)
+        }
+        StellarMessage::SetCaptureTarget(_)
+        | StellarMessage::SubscribeChannel(_)
+        | StellarMessage::UnsubscribeChannel(_)
+        | StellarMessage::ResolutionRequest
+        | StellarMessage::ShImgPathRequest
+        | StellarMessage::HandshakeRequest
+        | StellarMessage::WindowListRequest
+        | StellarMessage::FrameStreamRequest(_)
+        | StellarMessage::Heartbeat => Ok(Priority::Control),
+        StellarMessage::HelloName(name) => {
+            check_string("hello name", name)?;
+            Ok(Priority::Debug)
+        }
+        StellarMessage::AuthResponse(client_nonce, proof, _) => {
+            if client_nonce.len() != NONCE_LEN || proof.len() != PROOF_LEN {
+                return Err("auth response with the wrong nonce or proof length".to_string());
+            }
+            Ok(Priority::Control)
+        }
+        StellarMessage::Hello | StellarMessage::Test | StellarMessage::DebugInfoRequest | StellarMessage::DebugInfoRequestV2 => Ok(Priority::Debug),
+        _ => Err(format!("not a request {:?}", message)),
+    }
+}
+
+// what every io thread needs to hand work to the game thread
+#[derive(Clone)]
+pub struct TransportShared {
+    pub commands: Arc<CommandQueues>,
+    pub incoming_input: Arc<SegQueue<InputEvent>>,
+    pub frame_slots: Arc<FrameSlots>,
+    pub frame_stream: Option<Arc<FrameStreamServer>>,
+    pub config: Arc<Config>,
+}
+
+enum PeerAuth {
+    // server nonce we're waiting on a proof for and when it went out
+    Challenged(Vec<u8>, Instant),
+    Authenticated(Option<SessionCipher>),
+}
+
+pub struct Transport {
+    pub addr: TransportAddr,
+    pub handler: Mutex<NodeHandler<InternalSignals>>,
+    // None trusts everyone, only the unix socket since the filesystem already decides who gets to it
+    token: Option<AuthToken>,
+    require_encryption: bool,
+    // locked before handler when both are needed
+    peers: Mutex<HashMap<Endpoint, PeerAuth>>,
+    // so new frames only wake transports someone is watching on
+    frame_subscribers: AtomicUsize,
+    invalid: AtomicU64,
+    rate_limited: AtomicU64,
+    unauthenticated: AtomicU64,
+}
+
+impl Transport {
+    // binds and starts the io thread, index is what peers from this transport carry around
+    pub fn spawn(index: usize, addr: TransportAddr, token: Option<AuthToken>, shared: TransportShared) -> io::Result<Arc<Transport>> {
+        let (handler, listener) = node::split::<InternalSignals>();
+        if let Err(err) = addr.listen(&handler) {
+            handler.stop();
+            return Err(err);
+        }
+        // a udp peer is only known by its source address, which anyone can spoof, so it has to seal everything
+        let require_encryption = shared.config.net_require_encryption || matches!(addr, TransportAddr::Udp(_));
+        let transport = Arc::new(Transport {
+            addr,
+            handler: Mutex::new(handler),
+            token,
+            require_encryption,
+            peers: Mutex::new(HashMap::new()),
+            frame_subscribers: AtomicUsize::new(0),
+            invalid: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            unauthenticated: AtomicU64::new(0),
+        });
+        let thread_transport = transport.clone();
+        thread::Builder::new()
+            .name(format!("hw-net-{}", transport.addr.name()))
+            .spawn(move || thread_transport.run(index, listener, shared))?;
+        Ok(transport)
+    }
+
+    pub fn has_frame_subscribers(&self) -> bool {
+        self.frame_subscribers.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn signal(&self, signal: InternalSignals) {
+        self.handler.lock().unwrap().signals().send(signal);
+    }
+
+    pub fn send(&self, endpoint: Endpoint, message: &StellarMessage) {
+        self.send_raw(endpoint, &stellar_protocol::serialize(message));
+    }
+
+    fn send_raw(&self, endpoint: Endpoint, data: &[u8]) {
+        // peers stays locked until it's sent so sealed messages leave in counter order
+        let mut peers = self.peers.lock().unwrap();
+        match peers.get_mut(&endpoint) {
+            Some(PeerAuth::Authenticated(Some(cipher))) => {
+                let sealed = cipher.seal(data);
+                self.handler.lock().unwrap().network().send(endpoint, &sealed);
+            }
+            _ => {
+                self.handler.lock().unwrap().network().send(endpoint, data);
+            }
+        }
+    }
+
+    // undoes the session encryption if this peer has any
+    fn open<'a>(&self, endpoint: Endpoint, data: &'a [u8]) -> Result<Cow<'a, [u8]>, String> {
+        match self.peers.lock().unwrap().get_mut(&endpoint) {
+            Some(PeerAuth::Authenticated(Some(cipher))) => cipher.open(data).map(Cow::Owned),
+            _ => Ok(Cow::Borrowed(data)),
+        }
+    }
+
+    fn is_authenticated(&self, endpoint: Endpoint) -> bool {
+        self.token.is_none() || matches!(self.peers.lock().unwrap().get(&endpoint), Some(PeerAuth::Authenticated(_)))
+    }
+
+    fn is_sealed(&self, endpoint: Endpoint) -> bool {
+        matches!(self.peers.lock().unwrap().get(&endpoint), Some(PeerAuth::Authenticated(Some(_))))
+    }
+
+    // a new challenge for endpoint, dropping ones that were never answered so they stay bounded
+    fn challenge(&self, endpoint: Endpoint, nonce: Vec<u8>) {
+        let mut peers = self.peers.lock().unwrap();
+        let now = Instant::now();
+        peers.retain(|_, auth| !matches!(auth, PeerAuth::Challenged(_, issued) if now.duration_since(*issued) > AUTH_CHALLENGE_TIMEOUT));
+        let pending: Vec<(Endpoint, Instant)> = peers.iter()
+            .filter_map(|(endpoint, auth)| match auth {
+                PeerAuth::Challenged(_, issued) => Some((*endpoint, *issued)),
+                PeerAuth::Authenticated(_) => None,
+            })
+            .collect();
+        if pending.len() >= MAX_PENDING_AUTH && !peers.contains_key(&endpoint) {
+            if let Some((oldest, _)) = pending.iter().min_by_key(|(_, issued)| *issued) {
+                peers.remove(oldest);
+            }
+        }
+        peers.insert(endpoint, PeerAuth::Challenged(nonce, now));
+    }
+
+    // everything from a peer lands here until it proves it has the token, true once it has
+    fn authenticate(&self, endpoint: Endpoint, message: StellarMessage) -> bool {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return true,
+        };
+        match message {
+            StellarMessage::Hello | StellarMessage::HelloName(_) => {
+                let nonce = auth::random_nonce();
+                self.challenge(endpoint, nonce.clone());
+                self.send(endpoint, &StellarMessage::AuthChallenge(nonce));
+                false
+            }
+            StellarMessage::AuthResponse(client_nonce, proof, encrypt) => {
+                let server_nonce = match self.peers.lock().unwrap().get(&endpoint) {
+                    Some(PeerAuth::Challenged(nonce, _)) => nonce.clone(),
+                    _ => {
+                        log_debug!(Net, "auth response from {} without a challenge", endpoint.addr());
+                        return false;
+                    }
+                };
+                let rejection = if !auth::verify(token, &server_nonce, &client_nonce, &proof) {
+                    Some("wrong token")
+                } else if self.require_encryption && !encrypt {
+                    Some(match self.addr {
+                        TransportAddr::Udp(_) => "udp connections have to be encrypted",
+                        _ => "this host only takes encrypted connections",
+                    })
+                } else {
+                    None
+                };
+                match rejection {
+                    None => {
+                        log_info!(Net, "{} authenticated on {}{}", endpoint.addr(), self.addr, if encrypt { " (encrypted)" } else { "" });
+                        // the result goes out in the clear, everything after it is sealed
+                        self.send(endpoint, &StellarMessage::AuthResult(true, "authenticated".to_string()));
+                        let cipher = if encrypt { Some(SessionCipher::new(token, &server_nonce, &client_nonce, Side::Host)) } else { None };
+                        self.peers.lock().unwrap().insert(endpoint, PeerAuth::Authenticated(cipher));
+                        true
+                    }
+                    Some(reason) => {
+                        self.unauthenticated.fetch_add(1, Ordering::Relaxed);
+                        log_warn!(Net, "rejecting {} on {}: {}", endpoint.addr(), self.addr, reason);
+                        self.peers.lock().unwrap().remove(&endpoint);
+                        self.send(endpoint, &StellarMessage::AuthResult(false, reason.to_string()));
+                        if let TransportAddr::Tcp(_) = self.addr {
+                            self.handler.lock().unwrap().network().remove(endpoint.resource_id());
+                        }
+                        false
+                    }
+                }
+            }
+            // streamerd starts these as soon as it's connected, they only count once it's through
+            StellarMessage::Heartbeat => false,
+            _ => {
+                self.unauthenticated.fetch_add(1, Ordering::Relaxed);
+                log_debug!(Net, "dropping {:?} from unauthenticated {}", message, endpoint.addr());
+                false
+            }
+        }
+    }
+
+    pub fn create_debug_output(&self) -> String {
+        format!(
+            "Transport {}: {} frame subscribers, {} invalid, {} rate limited, {} unauthenticated{}\n",
+            self.addr,
+            self.frame_subscribers.load(Ordering::Relaxed),
+            self.invalid.load(Ordering::Relaxed),
+            self.rate_limited.load(Ordering::Relaxed),
+            self.unauthenticated.load(Ordering::Relaxed),
+            if self.token.is_some() { " (token required)" } else { "" }
+        )
+    }
+
+    fn run(&self, index: usize, listener: NodeListener<InternalSignals>, shared: TransportShared) {
+        let mut pubsub: HashMap<StellarChannel, Vec<Endpoint>> = get_all_channels().into_iter().map(|channel| (channel, vec![])).collect();
+        let mut limiter = RateLimiter::new(&shared.config);
+        // last time anything came from each peer, a datagram peer that went away never disconnects so this is how we notice
+        let mut last_seen: HashMap<Endpoint, Instant> = HashMap::new();
+        let peer_timeout = Duration::from_millis(shared.config.net_peer_timeout_ms);
+        if !peer_timeout.is_zero() {
+            self.handler.lock().unwrap().signals().send_with_timer(InternalSignals::ExpirePeersSignal, PEER_EXPIRY_CHECK);
+        }
+
+        listener.for_each(move |event| match event {
+            NodeEvent::Network(netevent) => match netevent {
+                NetEvent::Connected(endpoint, ready) => {
+                    if !ready {
+                        log_debug!(Net, "One client did not successfully ready. {}", endpoint.addr());
+                    }
+                    log_debug!(Net, "connection from {} on {}", endpoint.addr(), self.addr);
+                }
+                NetEvent::Accepted(_endpoint, _listener) => {}
+                NetEvent::Message(endpoint, data) => {
+                    let checked = self
+                        .open(endpoint, data)
+                        .and_then(|data| parse(&data))
+                        .and_then(|message| validate(&message).map(|priority| (message, priority)));
+                    let (message, priority) = match checked {
+                        Ok(checked) => checked,
+                        Err(err) => {
+                            self.invalid.fetch_add(1, Ordering::Relaxed);
+                            log_debug!(Net, "dropping message from {} on {}: {}", endpoint.addr(), self.addr, err);
+                            return;
+                        }
+                    };
+                    // nothing is kept per endpoint until it has authenticated
+                    let authenticated = self.is_authenticated(endpoint);
+                    let allowed = if authenticated { limiter.allow(endpoint, priority) } else { limiter.allow_unauthenticated() };
+                    if !allowed {
+                        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                        log_trace!(Net, "rate limited {:?} message from {}", priority, endpoint.addr());
+                        return;
+                    }
+                    if !authenticated {
+                        if self.authenticate(endpoint, message) {
+                            last_seen.insert(endpoint, Instant::now());
+                        }
+                        return;
+                    }
+                    last_seen.insert(endpoint, Instant::now());
+                    self.handle_message(Peer { transport: index, endpoint }, priority, message, &mut pubsub, &shared);
+                }
+                NetEvent::Disconnected(endpoint) => {
+                    log_debug!(Net, "One client disconnected. {}", endpoint.addr());
+                    limiter.forget(endpoint);
+                    last_seen.remove(&endpoint);
+                    self.forget_peer(Peer { transport: index, endpoint }, &mut pubsub, &shared);
+                }
+            },
+            NodeEvent::Signal(signal) => match signal {
+                InternalSignals::TestSignal => {}
+                InternalSignals::TracingSignal => {}
+                InternalSignals::NewFrameSignal(slot, slot_size, layout) => {
+                    self.check_subscribers(index, pubsub.get_mut(&StellarChannel::Frame).unwrap(), &shared);
+                    self.update_frame_subscribers(&pubsub);
+                    let subscribers = &pubsub[&StellarChannel::Frame];
+                    // every subscriber has to release it, with none left the slot is free again
+                    let holders: Vec<Peer> = subscribers.iter().map(|endpoint| Peer { transport: index, endpoint: *endpoint }).collect();
+                    shared.frame_slots.hand_out(slot, &holders);
+                    let data = stellar_protocol::serialize(&StellarMessage::NewFrameSlot(slot, slot_size, layout));
+                    for subscriber in subscribers {
+                        self.send_raw(*subscriber, &data);
+                    }
+                }
+                InternalSignals::SendToChannelSignal(channel, data) => {
+                    if let Some(subscribers) = pubsub.get(&channel) {
+                        for subscriber in subscribers {
+                            self.send_raw(*subscriber, &data);
+                        }
+                    }
+                }
+                InternalSignals::ExpirePeersSignal => {
+                    let expired: Vec<Endpoint> = last_seen.iter().filter(|(_, seen)| seen.elapsed() > peer_timeout).map(|(endpoint, _)| *endpoint).collect();
+                    for endpoint in expired {
+                        log_info!(Net, "{} on {} went quiet for {:?}, dropping it", endpoint.addr(), self.addr, peer_timeout);
+                        limiter.forget(endpoint);
+                        last_seen.remove(&endpoint);
+                        self.forget_peer(Peer { transport: index, endpoint }, &mut pubsub, &shared);
+                        if let TransportAddr::Tcp(_) = self.addr {
+                            self.handler.lock().unwrap().network().remove(endpoint.resource_id());
+                        }
+                    }
+                    self.handler.lock().unwrap().signals().send_with_timer(InternalSignals::ExpirePeersSignal, PEER_EXPIRY_CHECK);
+                }
+            },
+        });
+    }
+
+    // a peer that disconnected or expired, the frame slots it still held are never coming back
+    fn forget_peer(&self, peer: Peer, pubsub: &mut HashMap<StellarChannel, Vec<Endpoint>>, shared: &TransportShared) {
+        self.peers.lock().unwrap().remove(&peer.endpoint);
+        for subscribers in pubsub.values_mut() {
+            subscribers.retain(|subscriber| *subscriber != peer.endpoint);
+        }
+        self.update_frame_subscribers(pubsub);
+        shared.frame_slots.release_peer(peer);
+    }
+
+    fn check_subscribers(&self, index: usize, subscribers: &mut Vec<Endpoint>, shared: &TransportShared) {
+        let handler = self.handler.lock().unwrap();
+        subscribers.retain(|endpoint| {
+            let ready = handler.network().is_ready(endpoint.resource_id()).is_some();
+            if !ready {
+                shared.frame_slots.release_peer(Peer { transport: index, endpoint: *endpoint });
+            }
+            ready
+        });
+    }
+
+    fn update_frame_subscribers(&self, pubsub: &HashMap<StellarChannel, Vec<Endpoint>>) {
+        self.frame_subscribers.store(pubsub[&StellarChannel::Frame].len(), Ordering::Relaxed);
+    }
+
+    // everything here already passed validate
+    fn handle_message(&self, peer: Peer, priority: Priority, message: StellarMessage, pubsub: &mut HashMap<StellarChannel, Vec<Endpoint>>, shared: &TransportShared) {
+        let endpoint = peer.endpoint;
+        let queue = |command: MainTickMessage| shared.commands.push(priority, command);
+        match message {
+            StellarMessage::ResolutionRequest => {
+                log_debug!(Net, "Attempting to fufill resolution request from {:?}", endpoint.addr());
+                queue(MainTickMessage::RequestResolutionBroadcast(peer));
+            }
+            StellarMessage::ShImgPathRequest => {
+                log_debug!(Net, "Attempting to fufill shimg path request from {:?}", endpoint.addr());
+                queue(MainTickMessage::RequestShImgPath(peer));
+            }
+            StellarMessage::HandshakeRequest => {
+                log_debug!(Net, "Attempting to fufill handshake request from {:?}", endpoint.addr());
+                queue(MainTickMessage::RequestHandshake(peer));
+            }
+            StellarMessage::Hello => {
+                log_debug!(Net, "Hello message received from {:?}", endpoint.addr());
+            }
+            StellarMessage::Heartbeat => {
+                // last_seen is already updated, the echo lets streamerd notice when we're gone
+                self.send(endpoint, &StellarMessage::Heartbeat);
+            }
+            StellarMessage::SubscribeChannel(channel) => {
+                log_debug!(Net, "Subscribing to channel {:?} from {:?}", channel, endpoint.addr());
+                let subscribers = pubsub.get_mut(&channel).unwrap();
+                if !subscribers.contains(&endpoint) {
+                    subscribers.push(endpoint);
+                }
+                self.update_frame_subscribers(pubsub);
+            }
+            StellarMessage::UnsubscribeChannel(channel) => {
+                log_debug!(Net, "Unsubscribing from channel {:?} from {:?}", channel, endpoint.addr());
+                pubsub.get_mut(&channel).unwrap().retain(|subscriber| *subscriber != endpoint);
+                self.update_frame_subscribers(pubsub);
+            }
+            StellarMessage::ReleaseFrameSlot(slot) => {
+                shared.frame_slots.release(slot, peer);
+            }
+            StellarMessage::UserInputEvent(mut input_event) => {
+                input_event.metadata.timestamp(); // TODO: timestamp this more accurately
+                shared.incoming_input.push(input_event);
+            }
+            StellarMessage::DebugInfoRequest => {
+                let mut output = "Debug Info:\n".to_string();
+                output += &format!("Features: {:#?}", HOST.features);
+                self.send(endpoint, &StellarMessage::DebugInfoResponse(DebugInfo { message: output, hook_calls: telemetry::hook_call_stats() }));
+            }
+            StellarMessage::DebugInfoRequestV2 => {
+                // prepare debug info that can be sent without waiting for tick
+                let mut output = "Debug Info:\n".to_string();
+                output += &format!("Features: {:#?}", HOST.features);
+                // counters are atomics so these still show up if the main thread never ticks
+                output += &format!("\n{}", telemetry::create_debug_output());
+                output += &self.create_debug_output();
+                output += &format!("Queued commands: {}\n", shared.commands.queued());
+                if let Some(frame_stream) = &shared.frame_stream {
+                    output += &frame_stream.create_debug_output();
+                }
+                let debug_info_v2 = DebugInfo {
+                    message: output,
+                    hook_calls: telemetry::hook_call_stats(),
+                };
+                self.send(endpoint, &StellarMessage::DebugInfoResponseV2(debug_info_v2, "networking".to_string()));
+                queue(MainTickMessage::RequestDebugInfoV2(peer));
+            }
+            StellarMessage::WindowListRequest => {
+                queue(MainTickMessage::RequestWindowList(peer));
+            }
+            StellarMessage::FrameStreamRequest(compression) => {
+                // the ticket only goes out over a connection that already authenticated, None tells them to use shm
+                // frames are sealed exactly when this connection is, streamerd goes by its own link to know which
+                let seal_with = if self.is_sealed(endpoint) { self.token.as_ref() } else { None };
+                let ticket = shared.frame_stream.as_ref().map(|frame_stream| frame_stream.issue_ticket(compression, seal_with));
+                log_debug!(Net, "Frame stream request ({}) from {:?}, issued: {}", compression, endpoint.addr(), ticket.is_some());
+                self.send(endpoint, &StellarMessage::FrameStreamTicket(ticket));
+            }
+            StellarMessage::SetCaptureTarget(target) => {
+                queue(MainTickMessage::SetCaptureTarget(target));
+            }
+            StellarMessage::ForwardedDataChannelMessage(source_socket_id, message) => {
+                log_debug!(Net, "Forwarded data channel message {:?} from socket id {:?}", message, source_socket_id);
+                match message {
+                    StellarDirectControlMessage::AddGamepad { local_id, axes, buttons, hats, .. } if !(0..16).contains(&axes) || !(0..32).contains(&buttons) || hats != 0 => {
+                        let direct_message = StellarDirectControlMessage::AddGamepadReply { local_id, remote_id: "".to_string(), success: false, message: "Could not add gamepad, too many axes/buttons/hats".to_string() };
+                        self.send(endpoint, &StellarMessage::ReplyDataChannelMessage(source_socket_id, "reliable".to_string(), direct_message));
+                    }
+                    StellarDirectControlMessage::UpdateGamepad { remote_id, axes, buttons, .. } => {
+                        shared.incoming_input.push(InputEvent::new(InputEventPayload::JoystickBrowserUpdate { id: remote_id, axis: axes, buttons }));
+                    }
+                    StellarDirectControlMessage::AddGamepad { .. } | StellarDirectControlMessage::RemoveGamepad { .. } | StellarDirectControlMessage::RequestTitle => {
+                        queue(MainTickMessage::ProcessDirectMessage(peer, source_socket_id, message));
+                    }
+                    _ => {
+                        log_warn!(Net, "Unhandled forwarded data channel message {:?} from socket id {:?}", message, source_socket_id);
+                    }
+                }
+            }
+            _ => {
+                log_debug!(Net, "Unhandled message: {:?}", message);
+            }
+        }
+    }
+}
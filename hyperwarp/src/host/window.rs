@@ -1,4 +1,7 @@
+use std::time::Instant;
+
 use libc::c_void;
+use stellar_protocol::protocol::{WindowIcon, WindowInfo};
 
 use crate::constants::Library;
 
@@ -6,9 +9,17 @@ use crate::constants::Library;
 pub struct Window {
     pub id: usize,
     pub lib: Library,
+    // the X window a toolkit window draws into, swaps and x events on it count as this window
+    pub native_id: Option<usize>,
     pub width: u32,
     pub height: u32,
     pub position: Option<(i32, i32)>,
+    pub title: Option<String>,
+    pub icon: Option<WindowIcon>,
+    pub visible: bool,
+    pub fullscreen: bool,
+    pub focused: bool,
+    pub last_swap: Option<Instant>,
 }
 
 impl Window {
@@ -22,9 +33,17 @@ impl Window {
         Window {
             id,
             lib,
+            native_id: None,
             width: 0,
             height: 0,
             position: None,
+            title: None,
+            icon: None,
+            // most libs show windows on creation unless asked not to
+            visible: true,
+            fullscreen: false,
+            focused: false,
+            last_swap: None,
         }
     }
 
@@ -36,5 +55,28 @@ impl Window {
     pub fn position(&mut self, x: i32, y: i32) {
         self.position = Some((x, y));
     }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title;
+        self
+    }
+
+    pub fn area(&self) -> u32 {
+        self.width * self.height
+    }
+
+    pub fn to_info(&self, capturing: bool) -> WindowInfo {
+        WindowInfo {
+            id: self.id as u64,
+            library: format!("{:?}", self.lib),
+            title: self.title.clone(),
+            size: (self.width, self.height),
+            capturing,
+            visible: self.visible,
+            fullscreen: self.fullscreen,
+            focused: self.focused,
+            has_icon: self.icon.is_some(),
+        }
+    }
 }
 
@@ -0,0 +1,277 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use crate::{
+    bind::xlib as xlib_bind,
+    constants::xlib::{XErrorEvent, XImage, XShmSegmentInfo, ALL_PLANES, BAD_DRAWABLE, BAD_MATCH, BAD_WINDOW, Z_PIXMAP},
+};
+use stellar_shared::{log_debug, log_warn};
+
+// when plain Xlib windows get read back, only used when nothing is swapping gl buffers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XlibCaptureMode {
+    Off,
+    // grab on a background thread every interval
+    Timer,
+    // grab after the app draws with XPutImage or flushes
+    Hooks,
+}
+
+impl FromStr for XlibCaptureMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" | "none" | "false" | "0" => Ok(XlibCaptureMode::Off),
+            "timer" => Ok(XlibCaptureMode::Timer),
+            "hooks" | "draw" => Ok(XlibCaptureMode::Hooks),
+            _ => Err(format!("unknown xlib capture mode {}", s)),
+        }
+    }
+}
+
+// the default xlib error handler exits the process, and the window we capture can go away at any time
+// so every request on our display runs with trap_error installed and errors come back as a failed frame
+static TRAPPED_DISPLAY: AtomicUsize = AtomicUsize::new(0);
+static TRAPPED_ERROR: AtomicU8 = AtomicU8::new(0);
+static PREVIOUS_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" fn trap_error(display: *mut libc::c_void, event: *mut XErrorEvent) -> libc::c_int {
+    if display as usize == TRAPPED_DISPLAY.load(Ordering::SeqCst) {
+        TRAPPED_ERROR.store((*event).error_code, Ordering::SeqCst);
+        return 0;
+    }
+    // the app's own display errored while our trap was up, that's still theirs to handle
+    let previous = PREVIOUS_HANDLER.load(Ordering::SeqCst);
+    if previous == 0 {
+        return 0;
+    }
+    let previous: unsafe extern "C" fn(*mut libc::c_void, *mut XErrorEvent) -> libc::c_int = std::mem::transmute(previous);
+    previous(display, event)
+}
+
+// why a grab failed, only shm trouble turns shm off
+enum GrabError {
+    Window(String),
+    Shm(String),
+}
+
+// a shm backed XImage we keep around between grabs
+#[derive(Debug)]
+struct ShmImage {
+    image: *mut XImage,
+    info: Box<XShmSegmentInfo>,
+    width: u32,
+    height: u32,
+}
+
+// reads window contents through a display connection of our own so we never touch the app's Display from another thread
+#[derive(Debug)]
+pub struct XlibCapture {
+    display: *mut libc::c_void,
+    visual: *mut libc::c_void,
+    depth: i32,
+    shm_supported: bool,
+    shm_image: Option<ShmImage>,
+}
+
+// the display connection is only used while the behavior lock is held
+unsafe impl Send for XlibCapture {}
+
+impl XlibCapture {
+    pub fn open() -> Result<Self, String> {
+        unsafe {
+            let display = xlib_bind::XOpenDisplay(std::ptr::null());
+            if display.is_null() {
+                return Err("could not open a display connection for capture".to_string());
+            }
+            let screen = xlib_bind::XDefaultScreen(display);
+            let visual = xlib_bind::XDefaultVisual(display, screen);
+            let depth = xlib_bind::XDefaultDepth(display, screen);
+            let shm_supported = match *xlib_bind::XShmQueryExtension {
+                Some(query) => query(display) != 0 && xlib_bind::XShmCreateImage.is_some() && xlib_bind::XShmAttach.is_some() && xlib_bind::XShmGetImage.is_some(),
+                None => false,
+            };
+            Ok(XlibCapture { display, visual, depth, shm_supported, shm_image: None })
+        }
+    }
+
+    pub fn uses_shm(&self) -> bool {
+        self.shm_supported
+    }
+
+    // runs f with errors on our display trapped, syncs so everything f sent has been answered and puts the old handler back
+    fn with_error_trap<T>(&self, f: impl FnOnce() -> T) -> (T, Option<u8>) {
+        unsafe {
+            TRAPPED_ERROR.store(0, Ordering::SeqCst);
+            TRAPPED_DISPLAY.store(self.display as usize, Ordering::SeqCst);
+            let previous = xlib_bind::XSetErrorHandler(Some(trap_error));
+            PREVIOUS_HANDLER.store(previous.map_or(0, |handler| handler as usize), Ordering::SeqCst);
+            let result = f();
+            xlib_bind::XSync(self.display, 0);
+            xlib_bind::XSetErrorHandler(previous);
+            TRAPPED_DISPLAY.store(0, Ordering::SeqCst);
+            let error = TRAPPED_ERROR.swap(0, Ordering::SeqCst);
+            (result, if error == 0 { None } else { Some(error) })
+        }
+    }
+
+    pub fn get_window_size(&self, window: u64) -> Option<(u32, u32)> {
+        let mut root = 0;
+        let (mut x, mut y) = (0, 0);
+        let (mut width, mut height, mut border, mut depth) = (0, 0, 0, 0);
+        let (status, error) = self.with_error_trap(|| unsafe {
+            xlib_bind::XGetGeometry(self.display, window as libc::c_ulong, &mut root, &mut x, &mut y, &mut width, &mut height, &mut border, &mut depth)
+        });
+        if status == 0 || error.is_some() || width == 0 || height == 0 {
+            None
+        } else {
+            Some((width, height))
+        }
+    }
+
+    // copies the window into out as tightly packed bgrx rows, out must be width * height * 4 bytes
+    pub fn grab(&mut self, window: u64, width: u32, height: u32, out: &mut [u8]) -> Result<(), String> {
+        if out.len() < (width * height * 4) as usize {
+            return Err("output buffer too small".to_string());
+        }
+        if self.shm_supported {
+            match self.grab_shm(window, width, height, out) {
+                Ok(()) => return Ok(()),
+                Err(GrabError::Window(err)) => return Err(err),
+                Err(GrabError::Shm(err)) => {
+                    // some servers advertise shm but can't share with us (different ipc namespace), stop trying
+                    log_warn!(Capture, "XShmGetImage failed ({}), falling back to XGetImage", err);
+                    self.destroy_shm_image();
+                    self.shm_supported = false;
+                }
+            }
+        }
+        self.grab_plain(window, width, height, out)
+    }
+
+    fn grab_plain(&mut self, window: u64, width: u32, height: u32, out: &mut [u8]) -> Result<(), String> {
+        let (image, error) = self.with_error_trap(|| unsafe {
+            xlib_bind::XGetImage(self.display, window as libc::c_ulong, 0, 0, width, height, ALL_PLANES, Z_PIXMAP)
+        });
+        unsafe {
+            if let Some(error) = error {
+                if !image.is_null() {
+                    destroy_image(image);
+                }
+                return Err(format!("XGetImage failed with X error {}", error));
+            }
+            if image.is_null() {
+                return Err("XGetImage returned nothing".to_string());
+            }
+            let result = copy_image(&*image, width, height, out);
+            destroy_image(image);
+            result
+        }
+    }
+
+    fn grab_shm(&mut self, window: u64, width: u32, height: u32, out: &mut [u8]) -> Result<(), GrabError> {
+        if self.shm_image.as_ref().map_or(true, |shm| shm.width != width || shm.height != height) {
+            self.destroy_shm_image();
+            self.shm_image = Some(self.create_shm_image(width, height).map_err(GrabError::Shm)?);
+        }
+        let shm_image = self.shm_image.as_ref().unwrap().image;
+        let (status, error) = self.with_error_trap(|| unsafe {
+            xlib_bind::XShmGetImage.unwrap()(self.display, window as libc::c_ulong, shm_image, 0, 0, ALL_PLANES)
+        });
+        match error {
+            Some(BAD_WINDOW | BAD_DRAWABLE | BAD_MATCH) => return Err(GrabError::Window(format!("XShmGetImage failed with X error {}", error.unwrap()))),
+            Some(error) => return Err(GrabError::Shm(format!("X error {}", error))),
+            None => {}
+        }
+        if status == 0 {
+            return Err(GrabError::Shm("XShmGetImage returned false".to_string()));
+        }
+        copy_image(unsafe { &*shm_image }, width, height, out).map_err(GrabError::Window)
+    }
+
+    fn create_shm_image(&self, width: u32, height: u32) -> Result<ShmImage, String> {
+        unsafe {
+            let mut info = Box::new(XShmSegmentInfo { shmseg: 0, shmid: -1, shmaddr: std::ptr::null_mut(), read_only: 0 });
+            let image = xlib_bind::XShmCreateImage.unwrap()(self.display, self.visual, self.depth as libc::c_uint, Z_PIXMAP, std::ptr::null_mut(), info.as_mut(), width, height);
+            if image.is_null() {
+                return Err("XShmCreateImage failed".to_string());
+            }
+            let size = ((*image).bytes_per_line * (*image).height) as usize;
+            info.shmid = libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600);
+            if info.shmid < 0 {
+                destroy_image(image);
+                return Err(format!("shmget failed: {}", std::io::Error::last_os_error()));
+            }
+            let addr = libc::shmat(info.shmid, std::ptr::null(), 0);
+            // marking it for removal now means it goes away even if we crash, it stays alive while attached
+            libc::shmctl(info.shmid, libc::IPC_RMID, std::ptr::null_mut());
+            if addr as isize == -1 {
+                destroy_image(image);
+                return Err(format!("shmat failed: {}", std::io::Error::last_os_error()));
+            }
+            info.shmaddr = addr as *mut libc::c_char;
+            (*image).data = info.shmaddr;
+            // the trap syncs, so the server has attached (or refused to) before anything else happens
+            let info_ptr: *mut XShmSegmentInfo = info.as_mut();
+            let (status, error) = self.with_error_trap(|| xlib_bind::XShmAttach.unwrap()(self.display, info_ptr));
+            if status == 0 || error.is_some() {
+                libc::shmdt(addr);
+                (*image).data = std::ptr::null_mut();
+                destroy_image(image);
+                return Err(format!("XShmAttach failed{}", error.map(|error| format!(" with X error {}", error)).unwrap_or_default()));
+            }
+            Ok(ShmImage { image, info, width, height })
+        }
+    }
+
+    fn destroy_shm_image(&mut self) {
+        if let Some(mut shm) = self.shm_image.take() {
+            unsafe {
+                if let Some(detach) = *xlib_bind::XShmDetach {
+                    let info_ptr: *mut XShmSegmentInfo = shm.info.as_mut();
+                    let (_, error) = self.with_error_trap(|| detach(self.display, info_ptr));
+                    if let Some(error) = error {
+                        log_debug!(Capture, "XShmDetach failed with X error {}", error);
+                    }
+                }
+                libc::shmdt(shm.info.shmaddr as *const libc::c_void);
+                // the data pointer belongs to shm, not malloc
+                (*shm.image).data = std::ptr::null_mut();
+                destroy_image(shm.image);
+            }
+        }
+    }
+}
+
+impl Drop for XlibCapture {
+    fn drop(&mut self) {
+        self.destroy_shm_image();
+        unsafe {
+            xlib_bind::XCloseDisplay(self.display);
+        }
+    }
+}
+
+unsafe fn destroy_image(image: *mut XImage) {
+    // XDestroyImage is a macro around this
+    if let Some(destroy) = (*image).f.destroy_image {
+        destroy(image);
+    }
+}
+
+fn copy_image(image: &XImage, width: u32, height: u32, out: &mut [u8]) -> Result<(), String> {
+    if image.bits_per_pixel != 32 {
+        return Err(format!("unsupported bits per pixel {}", image.bits_per_pixel));
+    }
+    if image.data.is_null() || (image.width as u32) < width || (image.height as u32) < height {
+        return Err("image smaller than requested".to_string());
+    }
+    let row_size = (width * 4) as usize;
+    let stride = image.bytes_per_line as usize;
+    for row in 0..height as usize {
+        let src = unsafe { std::slice::from_raw_parts((image.data as *const u8).add(row * stride), row_size) };
+        out[row * row_size..(row + 1) * row_size].copy_from_slice(src);
+    }
+    Ok(())
+}
@@ -3,6 +3,7 @@ use libc::c_char;
 use libc::c_void;
 
 use crate::host::hosting::HOST;
+use stellar_shared::log_debug;
 
 pub mod constants;
 pub mod hooks;
@@ -13,17 +14,10 @@ pub mod standalone;
 pub mod shim;
 pub mod platform;
 
-// I forgot what this is for. 
-fn main() {
-    println!("Hello, world!");
-}
-
 redhook::hook! {
     unsafe fn premain_plugin() => premain_plugin_first {
         setup_panic!();
         HOST.premain(); // this will trigger initalization of host as lazy_static likes it
-        if HOST.config.debug_mode { 
-            println!("Premain starting. Please wait. ");
-        }
+        log_debug!(General, "Premain starting. Please wait. ");
     }
 }
\ No newline at end of file
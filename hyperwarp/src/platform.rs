@@ -1 +1,3 @@
-pub mod sdl2;
\ No newline at end of file
+pub mod sdl2;
+pub mod sdl3;
+pub mod glfw;
\ No newline at end of file
@@ -0,0 +1,146 @@
+use stellar_shared::constants::sdl2::{map_key_code_to_scancode_cursed_u32, KMOD_ALT, KMOD_CTRL, KMOD_SHIFT};
+
+use crate::{constants::glfw::{GLFWgamepadstate, GLFW_GAMEPAD_AXIS_COUNT, GLFW_GAMEPAD_BUTTON_COUNT, GLFW_KEY_UNKNOWN, GLFW_MOD_ALT, GLFW_MOD_CONTROL, GLFW_MOD_SHIFT, GLFW_MOUSE_BUTTON_LAST}, host::input::GamepadState};
+
+// browsers report roughly this much per wheel notch, glfw wants notches
+pub const GLFW_SCROLL_PIXELS_PER_STEP: f64 = 100.0;
+
+// glfw keys are named after the us layout so going through the sdl scancode is the closest match
+pub fn glfw_key_from_sdl_scancode(scancode: u32) -> i32 {
+    match scancode {
+        4..=29 => 65 + (scancode as i32 - 4), // a-z
+        30..=38 => 49 + (scancode as i32 - 30), // 1-9
+        39 => 48, // 0
+        40 => 257, // enter
+        41 => 256, // escape
+        42 => 259, // backspace
+        43 => 258, // tab
+        44 => 32, // space
+        45 => 45, // minus
+        46 => 61, // equal
+        47 => 91, // left bracket
+        48 => 93, // right bracket
+        49 => 92, // backslash
+        51 => 59, // semicolon
+        52 => 39, // apostrophe
+        53 => 96, // grave
+        54 => 44, // comma
+        55 => 46, // period
+        56 => 47, // slash
+        57 => 280, // caps lock
+        58..=69 => 290 + (scancode as i32 - 58), // f1-f12
+        70 => 283, // print screen
+        71 => 281, // scroll lock
+        72 => 284, // pause
+        73 => 260, // insert
+        74 => 268, // home
+        75 => 266, // page up
+        76 => 261, // delete
+        77 => 269, // end
+        78 => 267, // page down
+        79 => 262, // right
+        80 => 263, // left
+        81 => 264, // down
+        82 => 265, // up
+        83 => 282, // num lock
+        84 => 331, // kp divide
+        85 => 332, // kp multiply
+        86 => 333, // kp subtract
+        87 => 334, // kp add
+        88 => 335, // kp enter
+        89..=97 => 321 + (scancode as i32 - 89), // kp 1-9
+        98 => 320, // kp 0
+        99 => 330, // kp decimal
+        101 => 348, // menu
+        103 => 336, // kp equal
+        224 => 341, // left control
+        225 => 340, // left shift
+        226 => 342, // left alt
+        227 => 343, // left super
+        228 => 345, // right control
+        229 => 344, // right shift
+        230 => 346, // right alt
+        231 => 347, // right super
+        _ => GLFW_KEY_UNKNOWN,
+    }
+}
+
+pub fn glfw_key_from_sdl_keycode(keycode: u32) -> (i32, u32) {
+    let scancode = map_key_code_to_scancode_cursed_u32(keycode);
+    (glfw_key_from_sdl_scancode(scancode), scancode)
+}
+
+pub fn glfw_mods_from_sdl(modifiers: u16) -> i32 {
+    let mut mods = 0;
+    if modifiers & KMOD_SHIFT != 0 {
+        mods |= GLFW_MOD_SHIFT;
+    }
+    if modifiers & KMOD_CTRL != 0 {
+        mods |= GLFW_MOD_CONTROL;
+    }
+    if modifiers & KMOD_ALT != 0 {
+        mods |= GLFW_MOD_ALT;
+    }
+    mods
+}
+
+// what the char callback should see for a key press, only the plain us layout for now
+pub fn glfw_char_for_key(key: i32, mods: i32) -> Option<u32> {
+    if mods & (GLFW_MOD_CONTROL | GLFW_MOD_ALT) != 0 {
+        return None;
+    }
+    let shift = mods & GLFW_MOD_SHIFT != 0;
+    let c = match key {
+        65..=90 if shift => key as u8,
+        65..=90 => key as u8 + 32,
+        32 => b' ',
+        39..=57 | 59 | 61 | 91..=93 | 96 if !shift => key as u8,
+        39..=93 | 96 => match key as u8 {
+            b'1' => b'!', b'2' => b'@', b'3' => b'#', b'4' => b'$', b'5' => b'%',
+            b'6' => b'^', b'7' => b'&', b'8' => b'*', b'9' => b'(', b'0' => b')',
+            b'-' => b'_', b'=' => b'+', b'[' => b'{', b']' => b'}', b'\\' => b'|',
+            b';' => b':', b'\'' => b'"', b'`' => b'~', b',' => b'<', b'.' => b'>', b'/' => b'?',
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(c as u32)
+}
+
+// browser wheel deltas to glfw offsets, glfw has positive y as up and positive x as left
+pub fn glfw_scroll_from_browser(x: f32, y: f32) -> (f64, f64) {
+    (-(x as f64) / GLFW_SCROLL_PIXELS_PER_STEP, -(y as f64) / GLFW_SCROLL_PIXELS_PER_STEP)
+}
+
+// browser button bits are left, right, middle which is also glfw's order
+// a change can carry several buttons or none at all, glfw wants a callback per button
+pub fn glfw_buttons_from_browser(change: u8) -> Vec<i32> {
+    (0..=GLFW_MOUSE_BUTTON_LAST).filter(|button| change & (1 << button) != 0).collect()
+}
+
+// sdl game controller button index for each glfw gamepad button
+const GLFW_GAMEPAD_BUTTONS_FROM_SDL: [usize; GLFW_GAMEPAD_BUTTON_COUNT] = [
+    0, 1, 2, 3, // a b x y
+    9, 10, // bumpers
+    4, 6, 5, // back start guide
+    7, 8, // thumbs
+    11, 14, 12, 13, // up right down left
+];
+
+// our gamepad state is kept in sdl game controller order, see convert_update_to_sdl_form
+pub fn glfw_gamepad_state_from_sdl(state: &GamepadState) -> GLFWgamepadstate {
+    let mut glfw_state = GLFWgamepadstate::default();
+    for (i, sdl_button) in GLFW_GAMEPAD_BUTTONS_FROM_SDL.iter().enumerate() {
+        glfw_state.buttons[i] = *state.buttons.get(*sdl_button).unwrap_or(&false) as u8;
+    }
+    for i in 0..GLFW_GAMEPAD_AXIS_COUNT {
+        let value = *state.axes.get(i).unwrap_or(&0.0);
+        glfw_state.axes[i] = if i >= 4 {
+            // triggers rest at -1 in glfw
+            (value.clamp(0.0, 1.0) * 2.0 - 1.0) as f32
+        } else {
+            value as f32
+        };
+    }
+    glfw_state
+}
@@ -1,14 +1,24 @@
 use std::{cmp::max, collections::HashMap};
 
 use sdl2_sys_lite::bindings::{SDL_Event, SDL_EventType, SDL_GameControllerAxis, SDL_GameControllerButton, SDL_Joystick, SDL_MouseMotionEvent};
-use stellar_protocol::protocol::{InputEvent, InputEventPayload};
+use stellar_protocol::protocol::{FramePixelFormat, InputEvent, InputEventPayload};
 
-use crate::{bind::sdl2_safe::SDL_PushEvent_safe, host::{hosting::HOST, input::{GamepadInitializationSpecs, GamepadState}}};
+use crate::{constants::sdl2::{SDL_PIXELFORMAT_ABGR8888, SDL_PIXELFORMAT_ARGB8888, SDL_PIXELFORMAT_XBGR8888, SDL_PIXELFORMAT_XRGB8888}, bind::sdl2_safe::SDL_PushEvent_safe, host::{hosting::HOST, input::{GamepadInitializationSpecs, GamepadState}}};
 
 pub fn process_event(input_event: &InputEvent) {
     // TODO: actually use? it will be when we need to modularize input manager
 }
 
+// SDL names packed formats by the integer layout so the byte order is reversed on little endian
+pub fn sdl2_pixel_format_for(format: FramePixelFormat) -> u32 {
+    match format {
+        FramePixelFormat::Rgba => SDL_PIXELFORMAT_ABGR8888,
+        FramePixelFormat::Rgbx => SDL_PIXELFORMAT_XBGR8888,
+        FramePixelFormat::Bgra => SDL_PIXELFORMAT_ARGB8888,
+        FramePixelFormat::Bgrx => SDL_PIXELFORMAT_XRGB8888,
+    }
+}
+
 pub fn sdl2_translate_mouse_state(state: u8) -> u8 {
     state // don't need to atm?
 }
@@ -0,0 +1,188 @@
+use libc::{c_int, c_ulong};
+use stellar_shared::constants::sdl2::map_key_code_to_scancode_cursed_u32;
+
+use crate::{bind::sdl3_safe, constants::{sdl2::SDL_OUR_FAKE_MOUSEID, sdl3::*}, host::hosting::HOST};
+use stellar_shared::log_debug;
+
+// browsers report roughly this much per wheel notch, SDL wants notches
+pub const SDL3_SCROLL_PIXELS_PER_STEP: f32 = 100.0;
+
+// where injected events go, the window id plus points per pixel since SDL3 events are in window coordinates
+pub struct Sdl3EventTarget {
+    pub window_id: SDL3_WindowID,
+    pub scale_x: f32,
+    pub scale_y: f32,
+}
+
+impl Sdl3EventTarget {
+    pub fn current() -> Sdl3EventTarget {
+        let window = HOST.get_behavior().get_largest_sdl3_window();
+        let mut target = Sdl3EventTarget { window_id: 0, scale_x: 1.0, scale_y: 1.0 };
+        if let Some(window) = window {
+            let window = window as *mut SDL3_Window;
+            target.window_id = sdl3_safe::SDL_GetWindowID_safe(window);
+            // our coordinates are framebuffer pixels
+            if let (Some((w, h)), Some((pw, ph))) = (sdl3_safe::SDL_GetWindowSize_safe(window), sdl3_safe::SDL_GetWindowSizeInPixels_safe(window)) {
+                if w > 0 && h > 0 && pw > 0 && ph > 0 {
+                    target.scale_x = w as f32 / pw as f32;
+                    target.scale_y = h as f32 / ph as f32;
+                }
+            }
+        }
+        target
+    }
+}
+
+// SDL3's CreateWindow flags come in through the SDL2 export's w, plus h on 32 bit where a Uint64 takes two stack slots
+pub fn sdl3_flags_from_sdl2_args(w: c_ulong, h: c_int) -> u64 {
+    if std::mem::size_of::<c_ulong>() >= 8 {
+        w as u64
+    } else {
+        (w as u32 as u64) | ((h as u32 as u64) << 32)
+    }
+}
+
+// (visible, fullscreen) for a window created with these SDL_WindowFlags
+pub fn sdl3_window_state_from_flags(flags: u64) -> (bool, bool) {
+    (flags & SDL3_WINDOW_HIDDEN == 0, flags & SDL3_WINDOW_FULLSCREEN != 0)
+}
+
+// browser button bits are left, right, middle, back, forward
+pub fn sdl3_button_from_browser(change: u8) -> u8 {
+    match change {
+        1 => SDL3_BUTTON_LEFT,
+        2 => SDL3_BUTTON_RIGHT,
+        4 => SDL3_BUTTON_MIDDLE,
+        8 => SDL3_BUTTON_X1,
+        16 => SDL3_BUTTON_X2,
+        _ => change.ilog2() as u8 + 1,
+    }
+}
+
+// SDL_BUTTON_MASK(button) for every held browser button
+pub fn sdl3_button_state_from_browser(buttons: u8) -> u32 {
+    let mut state = 0;
+    for i in 0..8 {
+        let mask = 1u8 << i;
+        if buttons & mask != 0 {
+            state |= 1 << (sdl3_button_from_browser(mask) - 1);
+        }
+    }
+    state
+}
+
+// keycodes kept the SDL2 values, the scancode mask bit included
+pub fn sdl3_keyboard_event(target: &Sdl3EventTarget, timestamp: u64, key: u32, modifiers: u16, down: bool) -> SDL3_Event {
+    let mut event = SDL3_Event::zeroed();
+    event.key = SDL3_KeyboardEvent {
+        type_: if down { SDL3_EVENT_KEY_DOWN } else { SDL3_EVENT_KEY_UP },
+        reserved: 0,
+        timestamp,
+        windowID: target.window_id,
+        which: 0,
+        scancode: map_key_code_to_scancode_cursed_u32(key),
+        key,
+        mod_: modifiers,
+        raw: 0,
+        down,
+        repeat: false,
+    };
+    event
+}
+
+pub fn sdl3_mouse_motion_event(target: &Sdl3EventTarget, timestamp: u64, buttons: u8, x: i32, y: i32, xrel: i32, yrel: i32) -> SDL3_Event {
+    let mut event = SDL3_Event::zeroed();
+    event.motion = SDL3_MouseMotionEvent {
+        type_: SDL3_EVENT_MOUSE_MOTION,
+        reserved: 0,
+        timestamp,
+        windowID: target.window_id,
+        which: SDL_OUR_FAKE_MOUSEID,
+        state: sdl3_button_state_from_browser(buttons),
+        x: x as f32 * target.scale_x,
+        y: y as f32 * target.scale_y,
+        xrel: xrel as f32 * target.scale_x,
+        yrel: yrel as f32 * target.scale_y,
+    };
+    event
+}
+
+pub fn sdl3_mouse_button_event(target: &Sdl3EventTarget, timestamp: u64, change: u8, down: bool, x: i32, y: i32) -> SDL3_Event {
+    let mut event = SDL3_Event::zeroed();
+    event.button = SDL3_MouseButtonEvent {
+        type_: if down { SDL3_EVENT_MOUSE_BUTTON_DOWN } else { SDL3_EVENT_MOUSE_BUTTON_UP },
+        reserved: 0,
+        timestamp,
+        windowID: target.window_id,
+        which: SDL_OUR_FAKE_MOUSEID,
+        button: sdl3_button_from_browser(change),
+        down,
+        clicks: 1,
+        padding: 0,
+        x: x as f32 * target.scale_x,
+        y: y as f32 * target.scale_y,
+    };
+    event
+}
+
+// browser deltas have positive y scrolling down, SDL has positive y scrolling up
+pub fn sdl3_mouse_wheel_event(target: &Sdl3EventTarget, timestamp: u64, scroll_x: f32, scroll_y: f32, x: i32, y: i32) -> SDL3_Event {
+    let mut event = SDL3_Event::zeroed();
+    event.wheel = SDL3_MouseWheelEvent {
+        type_: SDL3_EVENT_MOUSE_WHEEL,
+        reserved: 0,
+        timestamp,
+        windowID: target.window_id,
+        which: SDL_OUR_FAKE_MOUSEID,
+        x: scroll_x / SDL3_SCROLL_PIXELS_PER_STEP,
+        y: -scroll_y / SDL3_SCROLL_PIXELS_PER_STEP,
+        direction: SDL3_MOUSEWHEEL_NORMAL,
+        mouse_x: x as f32 * target.scale_x,
+        mouse_y: y as f32 * target.scale_y,
+    };
+    event
+}
+
+pub fn sdl3_push_event(mut event: SDL3_Event) {
+    if !sdl3_safe::SDL_PushEvent_safe(&mut event) {
+        log_debug!(Input, "uh oh sdl3 event push error: {}", sdl3_safe::SDL_GetError_safe());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn createwindow_flags_keep_the_high_bits() {
+        let flags = (1u64 << 40) | SDL3_WINDOW_HIDDEN;
+        assert_eq!(sdl3_flags_from_sdl2_args(flags as c_ulong, 0), flags);
+        assert_eq!(sdl3_flags_from_sdl2_args(SDL3_WINDOW_FULLSCREEN as c_ulong, 720), SDL3_WINDOW_FULLSCREEN);
+    }
+
+    #[test]
+    fn window_state_from_flags() {
+        assert_eq!(sdl3_window_state_from_flags(0), (true, false));
+        assert_eq!(sdl3_window_state_from_flags(SDL3_WINDOW_HIDDEN), (false, false));
+        assert_eq!(sdl3_window_state_from_flags(SDL3_WINDOW_FULLSCREEN), (true, true));
+        // bits above 32 don't get mistaken for anything we look at
+        assert_eq!(sdl3_window_state_from_flags((1u64 << 40) | SDL3_WINDOW_FULLSCREEN | SDL3_WINDOW_HIDDEN), (false, true));
+    }
+
+    #[test]
+    fn browser_buttons_map_to_sdl3_buttons() {
+        assert_eq!(sdl3_button_from_browser(1), SDL3_BUTTON_LEFT);
+        assert_eq!(sdl3_button_from_browser(2), SDL3_BUTTON_RIGHT);
+        assert_eq!(sdl3_button_from_browser(4), SDL3_BUTTON_MIDDLE);
+        assert_eq!(sdl3_button_from_browser(8), SDL3_BUTTON_X1);
+        assert_eq!(sdl3_button_from_browser(16), SDL3_BUTTON_X2);
+    }
+
+    #[test]
+    fn browser_button_state_is_an_sdl3_mask() {
+        assert_eq!(sdl3_button_state_from_browser(0), 0);
+        // left and right held, SDL_BUTTON_LMASK | SDL_BUTTON_RMASK
+        assert_eq!(sdl3_button_state_from_browser(1 | 2), 1 | 4);
+        assert_eq!(sdl3_button_state_from_browser(4), 2);
+    }
+}
@@ -1,12 +1,13 @@
 use crate::host::hosting::HOST;
+use stellar_shared::{log_debug, log_error};
 
 redhook::hook! {
     unsafe fn rust_launch() -> i32 => rust_launch_first {
-        println!("Test success");
+        log_debug!(General, "Test success");
         // we are still using the host lazy static since it simplifies config loading
         // sanity!
         if HOST.config.capture_mode {
-            println!("Can't capture when being run through shim. ");
+            log_error!(General, "Can't capture when being run through shim. ");
             return 1;
         }
         // do stuffs
@@ -7,6 +7,7 @@ use crate::{constants::LIBRARY_NAME, host::hosting::HOST};
 
 
 pub mod config;
+pub mod profiles;
 // TODO: autogen types
 pub mod manual_types;
 
@@ -18,18 +19,25 @@ pub mod redhook_modif;
 
 pub mod sdl2_dynapi;
 pub mod sdl2_dynapi_helper;
+pub mod sdl3_dynapi;
 
-pub fn format_window_title_prefix_cstr(c_string: *const c_char) -> *const c_char {
-    if !HOST.config.retitle_windows {
-        return c_string;
+// returns the retitled string if retitling is on, the caller has to keep it alive until the real function returns
+// (SDL copies the title so it can be dropped right after)
+pub fn format_window_title_prefix_cstr(c_string: *const c_char) -> Option<CString> {
+    if !HOST.config.retitle_windows || c_string.is_null() {
+        return None;
     }
     
     let c_str = unsafe { CStr::from_ptr(c_string) };
-    let rust_str = c_str.to_str().expect("Bad C String");
+    let rust_str = c_str.to_string_lossy();
     // println!("orig cstring {}", rust_str);
     let formatted = format!("{} ({})", rust_str, LIBRARY_NAME); // TODO: put crate version here
-    // let c_str = CString::new(c_string).unwrap();
-    let formatted_c_str = CString::new(formatted).unwrap();
-    // formatted_c_str.as_ptr() as *const c_char
-    formatted_c_str.into_raw()
-}
\ No newline at end of file
+    CString::new(formatted).ok()
+}
+
+pub fn title_from_cstr(c_string: *const c_char) -> Option<String> {
+    if c_string.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(c_string) }.to_string_lossy().into_owned())
+}
@@ -1,6 +1,12 @@
-use std::{env, net::SocketAddr, str::FromStr};
+use std::{collections::{HashMap, HashSet}, env, fmt::Display, fs, net::SocketAddr, str::FromStr};
 
-use super::utils::generate_random_id;
+use stellar_protocol::auth::AuthToken;
+use stellar_protocol::protocol::FramePixelFormat;
+use stellar_shared::logging::{Level, LogOutput, LogSpec};
+
+use crate::host::{damage::DamageDetection, targeting::TargetRules, xlib_capture::XlibCaptureMode};
+
+use super::{profiles::PROFILES, utils::generate_random_id};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -9,9 +15,31 @@ pub struct Config {
     pub enable_glfw: bool,
     pub enable_glx: bool,
     pub enable_sdl2: bool,
+    pub enable_sdl3: bool,
+    pub enable_egl: bool,
+    // capture from the vulkan layer when it's loaded
+    pub enable_vulkan: bool,
+    // raise every log target not named in log to debug and trace
     pub debug_mode: bool,
     pub tracing_mode: bool,
+    // levels per target like "info,hooks=debug", and where the lines go (stdout, stderr, file:<path>, socket or off)
+    pub log: LogSpec,
+    pub log_output: LogOutput,
+    // print every registered hook and whether it's on at startup
+    pub list_hooks: bool,
+    // time every hooked call into a histogram, counting is always on
+    pub hook_timing: bool,
+    // print a backtrace the first time each hook gets called
+    pub log_first_hook_calls: bool,
     pub capture_mode: bool,
+    // skipping unchanged frames
+    pub damage_detection: DamageDetection,
+    pub frame_keepalive_ms: u64,
+    // format SDL_RenderReadPixels converts to for non gl renderers
+    pub sdl_render_read_format: FramePixelFormat,
+    // reading back plain xlib windows
+    pub xlib_capture: XlibCaptureMode,
+    pub xlib_capture_interval_ms: u64,
     // windowing
     pub window_width_override: Option<u32>,
     pub window_height_override: Option<u32>,
@@ -26,87 +54,244 @@ pub struct Config {
     pub unix_socket_path: Option<String>,
     pub bind_addr: Option<SocketAddr>, 
     pub bind_type: Option<String>,
+    // messages per second each peer gets per priority before the io thread starts dropping, 0 turns it off
+    pub net_input_rate_limit: u32,
+    pub net_rate_limit: u32,
+    // peers that send nothing, not even a heartbeat, for this long lose their subscriptions, 0 keeps them forever
+    pub net_peer_timeout_ms: u64,
+    // what tcp/udp peers have to prove they know, generated and written to $XDG_RUNTIME_DIR/hyperwarp/<session>.token (/tmp/hyperwarp-<uid> without one) when unset
+    pub auth_token: Option<AuthToken>,
+    // turn away network peers that authenticate without asking for encryption
+    pub net_require_encryption: bool,
+    // where streamerds that can't see our shm file connect for frames, off when unset
+    pub frame_stream_addr: Option<SocketAddr>,
     pub disable_control: bool,
     pub retitle_windows: bool,
     pub netural_mode: bool,
     pub virtual_gamecontrollers: bool,
+    // hide focus lost, minimize and shown events from SDL apps so they keep running in the background, some games need to see them
+    pub suppress_focus_loss: bool,
+    // hooks that hand out the real symbol and don't capture, for games that only work with a specific swap hook
+    pub disabled_hooks: Vec<String>,
+    // allow/deny rules for which processes we host in, see host/targeting.rs
+    pub target_rules: TargetRules,
+    // only the first target in a process tree hosts, children it starts go neutral
+    pub target_first_only: bool,
+    // HW_CONFIG, env vars override anything in it
+    pub config_file: Option<String>,
+    // names of the profiles that matched this executable, in the order they were applied
+    pub profiles: Vec<String>,
+    // bad values and unknown keys, reported at startup and in debug info
+    pub config_errors: Vec<String>,
 }
 
-fn get<T: FromStr>(key: &str, default: T) -> T {
-    match env::var(key) {
-        Ok(val) => val.parse::<T>().unwrap_or(default),
-        Err(_) => default,
-    }
+// where a value came from, env vars win over the file and the file wins over matching profiles
+pub struct ConfigSource {
+    file_path: Option<String>,
+    // (value, where it came from)
+    file_values: HashMap<String, (String, String)>,
+    used_keys: HashSet<String>,
+    profiles: Vec<String>,
+    errors: Vec<String>,
 }
 
-fn try_get<T: FromStr>(key: &str) -> Option<T> {
-    match env::var(key) {
-        Ok(val) => match val.parse::<T>() {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        },
-        Err(_) => None,
+// json values come in as the same strings an env var would hold, arrays become one item per line
+fn flatten_json_value(value: serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(value) => Some(value),
+        serde_json::Value::Null => None,
+        serde_json::Value::Array(values) => Some(values.into_iter().filter_map(flatten_json_value).collect::<Vec<String>>().join("\n")),
+        other => Some(other.to_string()),
     }
 }
 
-fn intify(key: &str, default: i32) -> i32 {
-    match env::var(key) {
-        Ok(val) => val.parse::<i32>().unwrap_or(default),
-        Err(_) => default,
+// same for toml, tables have no env var form so they're left for the unknown key check to report
+fn flatten_toml_value(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value),
+        toml::Value::Array(values) => Some(values.into_iter().filter_map(flatten_toml_value).collect::<Vec<String>>().join("\n")),
+        toml::Value::Table(_) => None,
+        other => Some(other.to_string()),
     }
 }
 
-fn u_intify(key: &str, default: u32) -> u32 {
-    match env::var(key) {
-        Ok(val) => val.parse::<u32>().unwrap_or(default),
-        Err(_) => default,
+// picked by extension, anything that isn't .toml is read as json like before
+fn parse_config_file(path: &str, text: &str) -> Result<Vec<(String, String)>, String> {
+    if path.ends_with(".toml") {
+        let values = text.parse::<toml::Table>().map_err(|err| err.to_string())?;
+        return Ok(values.into_iter().filter_map(|(key, value)| flatten_toml_value(value).map(|value| (key, value))).collect());
+    }
+    match serde_json::from_str::<serde_json::Value>(text).map_err(|err| err.to_string())? {
+        serde_json::Value::Object(values) => Ok(values.into_iter().filter_map(|(key, value)| flatten_json_value(value).map(|value| (key, value))).collect()),
+        _ => Err("expected a json object at the top level".to_string()),
     }
 }
 
-fn booleanify(key: &str, default: bool) -> bool {
-    match env::var(key) {
-        Ok(value) => match value.as_str() {
-            "true" => true,
-            "false" => false,
-            "1" => true,
-            "0" => false,
-            "yes" => true,
-            "no" => false,
-            _ => default,
-        },
-        Err(_) => default,
+impl ConfigSource {
+    // matching profiles first, then the json or toml file HW_CONFIG points at, if any
+    pub fn load() -> ConfigSource {
+        let mut source = ConfigSource {
+            file_path: env::var("HW_CONFIG").ok().filter(|path| !path.is_empty()),
+            file_values: HashMap::new(),
+            used_keys: HashSet::new(),
+            profiles: PROFILES.names(),
+            errors: PROFILES.errors.clone(),
+        };
+        for matched in PROFILES.matched.iter() {
+            for (key, value) in matched.profile.config.iter() {
+                if let Some(value) = flatten_json_value(value.clone()) {
+                    let origin = format!("profile {} ({}) key {}", matched.profile.name, matched.origin, key);
+                    source.file_values.insert(key.clone(), (value, origin));
+                }
+            }
+        }
+        if let Some(path) = source.file_path.clone() {
+            match fs::read_to_string(&path).map_err(|err| err.to_string()).and_then(|text| parse_config_file(&path, &text)) {
+                Ok(values) => {
+                    for (key, value) in values {
+                        let origin = format!("{} key {}", path, key);
+                        source.file_values.insert(key, (value, origin));
+                    }
+                },
+                Err(err) => source.errors.push(format!("{}: {}", path, err)),
+            }
+        }
+        source
+    }
+
+    // (value, where it came from)
+    fn raw(&mut self, key: &str, env_key: &str) -> Option<(String, String)> {
+        self.used_keys.insert(key.to_string());
+        if let Ok(value) = env::var(env_key) {
+            return Some((value, format!("env {}", env_key)));
+        }
+        self.file_values.get(key).cloned()
+    }
+
+    pub fn try_get<T: FromStr>(&mut self, key: &str, env_key: &str) -> Option<T> where T::Err: Display {
+        let (value, origin) = self.raw(key, env_key)?;
+        match value.parse::<T>() {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.errors.push(format!("{}: couldn't parse {:?} ({}), ignoring it", origin, value, err));
+                None
+            }
+        }
+    }
+
+    pub fn get<T: FromStr>(&mut self, key: &str, env_key: &str, default: T) -> T where T::Err: Display {
+        self.try_get(key, env_key).unwrap_or(default)
+    }
+
+    // comma separated (or a json array), empty entries dropped
+    pub fn list(&mut self, key: &str, env_key: &str) -> Vec<String> {
+        self.raw(key, env_key).map(|(value, _)| {
+            value.split([',', '\n']).map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect()
+        }).unwrap_or_default()
+    }
+
+    pub fn booleanify(&mut self, key: &str, env_key: &str, default: bool) -> bool {
+        let (value, origin) = match self.raw(key, env_key) {
+            Some(raw) => raw,
+            None => return default,
+        };
+        match value.as_str() {
+            "true" | "1" | "yes" => true,
+            "false" | "0" | "no" => false,
+            _ => {
+                self.errors.push(format!("{}: expected true/false/1/0/yes/no, got {:?}, using {}", origin, value, default));
+                default
+            }
+        }
+    }
+
+    // anything in the file or a profile we never asked for is probably a typo
+    pub fn finish(mut self) -> (Option<String>, Vec<String>, Vec<String>) {
+        let mut unknown: Vec<&String> = self.file_values.iter()
+            .filter(|(key, _)| !self.used_keys.contains(*key))
+            .map(|(_, (_, origin))| origin)
+            .collect();
+        unknown.sort();
+        let unknown_errors: Vec<String> = unknown.iter().map(|origin| format!("{}: unknown key", origin)).collect();
+        self.errors.extend(unknown_errors);
+        (self.file_path, self.profiles, self.errors)
     }
 }
 
 impl Config {
+    // what targets the log spec leaves out get
+    pub fn log_fallback_level(&self) -> Level {
+        if self.netural_mode {
+            Level::Off
+        } else if self.tracing_mode {
+            Level::Trace
+        } else if self.debug_mode {
+            Level::Debug
+        } else {
+            Level::Info
+        }
+    }
+
     pub fn from_env() -> Config {
-        let sid = get("HW_SESSION_ID", generate_random_id());
-        let uid = get("HW_USER_ID", generate_random_id());
-        let socket_path = get("HW_SOCKET_PATH", format!("/tmp/hw-{}.sock", sid));
-        Config {
-            enable_x11: booleanify("ENABLE_X11", true),
-            enable_gl: booleanify("ENABLE_GL", true),
-            enable_glx: booleanify("ENABLE_GLX", true),
-            enable_glfw: booleanify("ENABLE_GLFW", true),
-            enable_sdl2: booleanify("ENABLE_SDL2", true),
-            debug_mode: booleanify("DEBUG_HW", false),
-            tracing_mode: booleanify("TRACING_HW", false),
-            disable_control: booleanify("DISABLE_CONTROL", false),
-            window_width_override: try_get::<u32>("WINDOW_WIDTH"),
-            window_height_override: try_get::<u32>("WINDOW_HEIGHT"),
-            window_zero_origin: booleanify("WINDOW_ZERO_ORIGIN", false),
+        let mut source = ConfigSource::load();
+        let sid = source.get("session_id", "HW_SESSION_ID", generate_random_id());
+        let uid = source.get("user_id", "HW_USER_ID", generate_random_id());
+        let socket_path = source.get("socket_path", "HW_SOCKET_PATH", format!("/tmp/hw-{}.sock", sid));
+        // NETURAL_MODE is the old spelling, still honored
+        let netural_mode = source.booleanify("netural_mode", "NETURAL_MODE", false);
+        let netural_mode = source.booleanify("neutral_mode", "NEUTRAL_MODE", netural_mode);
+        let mut config = Config {
+            enable_x11: source.booleanify("enable_x11", "ENABLE_X11", true),
+            enable_gl: source.booleanify("enable_gl", "ENABLE_GL", true),
+            enable_glx: source.booleanify("enable_glx", "ENABLE_GLX", true),
+            enable_glfw: source.booleanify("enable_glfw", "ENABLE_GLFW", true),
+            enable_sdl2: source.booleanify("enable_sdl2", "ENABLE_SDL2", true),
+            enable_sdl3: source.booleanify("enable_sdl3", "ENABLE_SDL3", true),
+            enable_egl: source.booleanify("enable_egl", "ENABLE_EGL", true),
+            enable_vulkan: source.booleanify("enable_vulkan", "ENABLE_VULKAN", true),
+            debug_mode: source.booleanify("debug_mode", "DEBUG_HW", false),
+            tracing_mode: source.booleanify("tracing_mode", "TRACING_HW", false),
+            log: source.get("log", "HW_LOG", LogSpec::default()),
+            log_output: source.get("log_output", "HW_LOG_OUTPUT", LogOutput::Stdout),
+            list_hooks: source.booleanify("list_hooks", "LIST_HOOKS", false),
+            hook_timing: source.booleanify("hook_timing", "HOOK_TIMING", false),
+            log_first_hook_calls: source.booleanify("log_first_hook_calls", "LOG_FIRST_HOOK_CALLS", false),
+            disable_control: source.booleanify("disable_control", "DISABLE_CONTROL", false),
+            window_width_override: source.try_get::<u32>("window_width", "WINDOW_WIDTH"),
+            window_height_override: source.try_get::<u32>("window_height", "WINDOW_HEIGHT"),
+            window_zero_origin: source.booleanify("window_zero_origin", "WINDOW_ZERO_ORIGIN", false),
             connection_timeout: None,
-            connection_type: try_get::<String>("CONNECTION_TYPE").unwrap_or("null".to_owned()),
+            connection_type: source.get("connection_type", "CONNECTION_TYPE", "null".to_owned()),
             session_id: sid,
             user_id: uid,
             unix_socket_path: Some(socket_path),
-            capture_mode: booleanify("CAPTURE_MODE", false),
-            bind_addr: try_get::<SocketAddr>("SOCKET_ADDR"),
-            bind_type: try_get::<String>("SOCKET_TYPE"),
-            retitle_windows: booleanify("RETITLE_WINDOWS", false),
-            netural_mode: booleanify("NETURAL_MODE", false),
-            virtual_gamecontrollers: booleanify("VIRTUAL_GAMECONTROLLERS", false),
-        }
+            capture_mode: source.booleanify("capture_mode", "CAPTURE_MODE", false),
+            damage_detection: source.get("damage_detection", "DAMAGE_DETECTION", DamageDetection::Off),
+            frame_keepalive_ms: source.get("frame_keepalive_ms", "FRAME_KEEPALIVE_MS", 1000),
+            sdl_render_read_format: source.get("sdl_render_read_format", "SDL_RENDER_READ_FORMAT", FramePixelFormat::Rgba),
+            xlib_capture: source.get("xlib_capture", "XLIB_CAPTURE", XlibCaptureMode::Off),
+            xlib_capture_interval_ms: source.get("xlib_capture_interval_ms", "XLIB_CAPTURE_INTERVAL_MS", 16),
+            bind_addr: source.try_get::<SocketAddr>("socket_addr", "SOCKET_ADDR"),
+            bind_type: source.try_get::<String>("socket_type", "SOCKET_TYPE"),
+            net_input_rate_limit: source.get("net_input_rate_limit", "HW_NET_INPUT_RATE_LIMIT", 2000),
+            net_rate_limit: source.get("net_rate_limit", "HW_NET_RATE_LIMIT", 100),
+            net_peer_timeout_ms: source.get("net_peer_timeout_ms", "HW_NET_PEER_TIMEOUT_MS", 5000),
+            auth_token: source.try_get::<AuthToken>("auth_token", "HW_AUTH_TOKEN"),
+            net_require_encryption: source.booleanify("net_require_encryption", "HW_NET_REQUIRE_ENCRYPTION", false),
+            frame_stream_addr: source.try_get::<SocketAddr>("frame_stream_addr", "HW_FRAME_STREAM_ADDR"),
+            retitle_windows: source.booleanify("retitle_windows", "RETITLE_WINDOWS", false),
+            netural_mode,
+            virtual_gamecontrollers: source.booleanify("virtual_gamecontrollers", "VIRTUAL_GAMECONTROLLERS", false),
+            suppress_focus_loss: source.booleanify("suppress_focus_loss", "SUPPRESS_FOCUS_LOSS", true),
+            disabled_hooks: source.list("disabled_hooks", "HW_DISABLED_HOOKS"),
+            target_rules: source.get("target_rules", "HW_TARGET_RULES", TargetRules::default()),
+            target_first_only: source.booleanify("target_first_only", "HW_TARGET_FIRST_ONLY", false),
+            config_file: None,
+            profiles: Vec::new(),
+            config_errors: Vec::new(),
+        };
+        (config.config_file, config.profiles, config.config_errors) = source.finish();
+        config
     }
 }
 
@@ -121,8 +306,16 @@ impl Default for Config {
             enable_glx: true,
             enable_glfw: true,
             enable_sdl2: true,
+            enable_sdl3: true,
+            enable_egl: true,
+            enable_vulkan: true,
             debug_mode: false,
             tracing_mode: false,
+            log: LogSpec::default(),
+            log_output: LogOutput::Stdout,
+            list_hooks: false,
+            hook_timing: false,
+            log_first_hook_calls: false,
             window_width_override: None,
             window_height_override: None,
             window_zero_origin: false,
@@ -132,12 +325,110 @@ impl Default for Config {
             user_id: uid,
             unix_socket_path: Some(socket_path),
             capture_mode: false,
+            damage_detection: DamageDetection::Off,
+            frame_keepalive_ms: 1000,
+            sdl_render_read_format: FramePixelFormat::Rgba,
+            xlib_capture: XlibCaptureMode::Off,
+            xlib_capture_interval_ms: 16,
             bind_addr: None,
             bind_type: None,
+            net_input_rate_limit: 2000,
+            net_rate_limit: 100,
+            net_peer_timeout_ms: 5000,
+            auth_token: None,
+            net_require_encryption: false,
+            frame_stream_addr: None,
             disable_control: false,
             retitle_windows: false,
             netural_mode: false,
-            virtual_gamecontrollers: true
+            virtual_gamecontrollers: true,
+            suppress_focus_loss: true,
+            disabled_hooks: Vec::new(),
+            target_rules: TargetRules::default(),
+            target_first_only: false,
+            config_file: None,
+            profiles: Vec::new(),
+            config_errors: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(values: &[(&str, &str)]) -> ConfigSource {
+        ConfigSource {
+            file_path: Some("test.toml".to_string()),
+            file_values: values.iter().map(|(key, value)| (key.to_string(), (value.to_string(), format!("test.toml key {}", key)))).collect(),
+            used_keys: HashSet::new(),
+            profiles: Vec::new(),
+            errors: Vec::new(),
         }
     }
+
+    fn parsed(path: &str, text: &str) -> HashMap<String, String> {
+        parse_config_file(path, text).unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn parses_json_and_toml_the_same() {
+        let json = parsed("hw.json", r#"{"enable_glx": false, "frame_keepalive_ms": 500, "disabled_hooks": ["a", "b"], "auth_token": null}"#);
+        let toml = parsed("hw.toml", "enable_glx = false\nframe_keepalive_ms = 500\ndisabled_hooks = [\"a\", \"b\"]\n");
+        assert_eq!(json, toml);
+        assert_eq!(toml["enable_glx"], "false");
+        assert_eq!(toml["frame_keepalive_ms"], "500");
+        assert_eq!(toml["disabled_hooks"], "a\nb");
+    }
+
+    #[test]
+    fn picks_the_format_by_extension() {
+        assert!(parse_config_file("hw.toml", r#"{"enable_glx": false}"#).is_err());
+        assert!(parse_config_file("hw.json", "enable_glx = false").is_err());
+        assert!(parse_config_file("hw.conf", r#"{"enable_glx": false}"#).is_ok());
+        assert!(parse_config_file("hw.json", "[1, 2]").is_err());
+    }
+
+    #[test]
+    fn toml_tables_are_reported_as_unknown() {
+        let values = parsed("hw.toml", "enable_glx = true\n[nested]\nenable_gl = false\n");
+        assert!(!values.contains_key("nested"));
+        assert!(!values.contains_key("enable_gl"));
+    }
+
+    #[test]
+    fn env_wins_over_the_file() {
+        env::set_var("HW_TEST_CONFIG_ENV_WINS", "7");
+        let mut source = source(&[("env_wins", "3"), ("file_only", "4")]);
+        assert_eq!(source.get("env_wins", "HW_TEST_CONFIG_ENV_WINS", 0u32), 7);
+        assert_eq!(source.get("file_only", "HW_TEST_CONFIG_FILE_ONLY", 0u32), 4);
+        assert_eq!(source.get("missing", "HW_TEST_CONFIG_MISSING", 5u32), 5);
+        env::remove_var("HW_TEST_CONFIG_ENV_WINS");
+    }
+
+    #[test]
+    fn bad_values_fall_back_and_say_where_they_came_from() {
+        let mut source = source(&[("number", "lots"), ("flag", "maybe")]);
+        assert_eq!(source.get("number", "HW_TEST_CONFIG_NUMBER", 9u32), 9);
+        assert!(source.booleanify("flag", "HW_TEST_CONFIG_FLAG", true));
+        let (_, _, errors) = source.finish();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("test.toml key number"));
+        assert!(errors[1].starts_with("test.toml key flag"));
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let mut source = source(&[("enable_glx", "true"), ("enable_glxx", "true")]);
+        assert!(source.booleanify("enable_glx", "HW_TEST_CONFIG_ENABLE_GLX", false));
+        let (file_path, _, errors) = source.finish();
+        assert_eq!(file_path.as_deref(), Some("test.toml"));
+        assert_eq!(errors, vec!["test.toml key enable_glxx: unknown key".to_string()]);
+    }
+
+    #[test]
+    fn lists_split_on_commas_and_lines() {
+        let mut source = source(&[("disabled_hooks", "a, b\nc,,")]);
+        assert_eq!(source.list("disabled_hooks", "HW_TEST_CONFIG_DISABLED_HOOKS"), vec!["a", "b", "c"]);
+    }
 }
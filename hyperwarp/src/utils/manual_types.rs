@@ -8,12 +8,40 @@ pub mod glfw {
 }
 
 pub mod sdl2 {
-    use libc::c_void;
+    use libc::{c_int, c_ulong, c_void};
 
     pub type Uint32 = u32;
     pub enum SDL_Window {}
 
     pub type SDL_Renderer = *const c_void;
+
+    #[repr(C)]
+    pub struct SDL_version {
+        pub major: u8,
+        pub minor: u8,
+        pub patch: u8,
+    }
+
+    // only the x11 part of the info union, the padding keeps it as big as the real one (64 bytes)
+    #[repr(C)]
+    pub struct SDL_SysWMinfo_X11 {
+        pub version: SDL_version,
+        pub subsystem: c_int,
+        pub display: *mut c_void,
+        pub window: c_ulong,
+        pub _rest: [u8; 48],
+    }
+
+    // SDL 1.2's SDL_SysWMinfo on x11, it has no other subsystems
+    #[repr(C)]
+    pub struct SDL12_SysWMinfo {
+        pub version: SDL_version,
+        pub subsystem: c_int,
+        pub display: *mut c_void,
+        pub window: c_ulong,
+        // lock_func, unlock_func, fswindow, wmwindow, gfxdisplay
+        pub _rest: [u8; 40],
+    }
 }
 
 pub mod libc {
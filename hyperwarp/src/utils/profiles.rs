@@ -0,0 +1,263 @@
+// per executable config overrides, so a game that needs WINDOW_ZERO_ORIGIN doesn't need it set by hand every launch
+// builtin.json ships inside the library, then every *.json in HW_PROFILE_DIR (default ~/.config/hyperwarp/profiles) in name order
+// every matching profile applies and later ones win, HW_CONFIG and env vars still win over all of them
+
+use std::{env, fs, io::Read, path::PathBuf, sync::OnceLock};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+const BUILTIN_PROFILES: &str = include_str!("../../profiles/builtin.json");
+
+#[derive(Debug, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    // globs, * and ? stop at / and ** doesn't, {a,b} picks one
+    // every one that's set has to match and a profile without any never matches
+    #[serde(default)]
+    pub prog: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    // fnv-1a 64 of the executable in hex, the debug info shows it for the running one
+    #[serde(default)]
+    pub exe_hash: Option<String>,
+    // wrappers and helpers the app starts, the real app gets its own copy of us
+    #[serde(default)]
+    pub neutral: bool,
+    // same keys as HW_CONFIG, this is also where hook quirks like disabled_hooks go
+    #[serde(default)]
+    pub config: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ProfileFile {
+    profiles: Vec<Profile>,
+}
+
+pub struct MatchedProfile {
+    // the file it came from
+    pub origin: String,
+    pub profile: Profile,
+}
+
+pub struct Profiles {
+    pub prog: Option<String>,
+    pub exe_path: Option<String>,
+    pub matched: Vec<MatchedProfile>,
+    pub errors: Vec<String>,
+}
+
+lazy_static! {
+    pub static ref PROFILES: Profiles = Profiles::load();
+}
+
+// only hashed when a profile that otherwise matched asks for it, games can be big
+// not through PROFILES, matching runs while that's still being built
+static EXE_HASH: OnceLock<Option<String>> = OnceLock::new();
+
+const FNV1A_OFFSET: u64 = 0xcbf29ce484222325;
+const EXE_HASH_CHUNK: usize = 64 * 1024;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// streamed so the whole executable never sits in memory
+fn hash_file(path: &std::path::Path) -> std::io::Result<u64> {
+    let mut file = fs::File::open(path)?;
+    let mut chunk = vec![0u8; EXE_HASH_CHUNK];
+    let mut hash = FNV1A_OFFSET;
+    loop {
+        match file.read(&mut chunk) {
+            Ok(0) => return Ok(hash),
+            Ok(read) => hash = fnv1a(hash, &chunk[..read]),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+pub fn exe_hash() -> Option<&'static str> {
+    EXE_HASH.get_or_init(|| {
+        env::current_exe().ok()
+            .and_then(|path| hash_file(&path).ok())
+            .map(|hash| format!("{:016x}", hash))
+    }).as_deref()
+}
+
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut expr = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    let mut in_braces = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                expr += ".*";
+            },
+            '*' => expr += "[^/]*",
+            '?' => expr += "[^/]",
+            '{' if !in_braces => {
+                in_braces = true;
+                expr += "(?:";
+            },
+            '}' if in_braces => {
+                in_braces = false;
+                expr += ")";
+            },
+            ',' if in_braces => expr += "|",
+            other => expr += &regex::escape(&other.to_string()),
+        }
+    }
+    expr += "$";
+    Regex::new(&expr).map_or(false, |regex| regex.is_match(text))
+}
+
+fn user_profile_dir() -> Option<(PathBuf, bool)> {
+    if let Ok(dir) = env::var("HW_PROFILE_DIR") {
+        return Some((PathBuf::from(dir), true));
+    }
+    let config_dir = env::var("XDG_CONFIG_HOME").ok().filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var("HOME").ok().map(|home| PathBuf::from(home).join(".config")))?;
+    Some((config_dir.join("hyperwarp").join("profiles"), false))
+}
+
+impl Profiles {
+    pub fn load() -> Profiles {
+        let mut profiles = Profiles {
+            prog: stellar_protocol::util::prog(),
+            exe_path: env::current_exe().ok().map(|path| path.to_string_lossy().into_owned()),
+            matched: Vec::new(),
+            errors: Vec::new(),
+        };
+        profiles.load_file("builtin.json", BUILTIN_PROFILES);
+        if let Some((dir, explicit)) = user_profile_dir() {
+            match fs::read_dir(&dir) {
+                Ok(entries) => {
+                    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+                        .collect();
+                    paths.sort();
+                    for path in paths {
+                        let origin = path.to_string_lossy().into_owned();
+                        match fs::read_to_string(&path) {
+                            Ok(text) => profiles.load_file(&origin, &text),
+                            Err(err) => profiles.errors.push(format!("{}: {}", origin, err)),
+                        }
+                    }
+                },
+                // the default dir not existing is normal
+                Err(err) if explicit => profiles.errors.push(format!("HW_PROFILE_DIR {}: {}", dir.display(), err)),
+                Err(_) => {},
+            }
+        }
+        profiles
+    }
+
+    fn load_file(&mut self, origin: &str, text: &str) {
+        let file = match serde_json::from_str::<ProfileFile>(text) {
+            Ok(file) => file,
+            Err(err) => {
+                self.errors.push(format!("{}: {}", origin, err));
+                return;
+            }
+        };
+        for profile in file.profiles {
+            if profile.prog.is_none() && profile.path.is_none() && profile.exe_hash.is_none() {
+                self.errors.push(format!("{}: profile {} has nothing to match on (prog, path or exe_hash), skipping it", origin, profile.name));
+                continue;
+            }
+            if self.matches(&profile) {
+                self.matched.push(MatchedProfile {
+                    origin: origin.to_string(),
+                    profile,
+                });
+            }
+        }
+    }
+
+    fn matches(&self, profile: &Profile) -> bool {
+        let matches = |pattern: &Option<String>, value: Option<&str>| match (pattern, value) {
+            (None, _) => true,
+            (Some(pattern), Some(value)) => glob_matches(pattern, value),
+            (Some(_), None) => false,
+        };
+        matches(&profile.prog, self.prog.as_deref())
+            && matches(&profile.path, self.exe_path.as_deref())
+            // checked last so the exe only gets read when everything else already matched
+            && profile.exe_hash.as_ref().map_or(true, |hash| exe_hash().map_or(false, |exe_hash| exe_hash.eq_ignore_ascii_case(hash)))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.matched.iter().map(|matched| matched.profile.name.clone()).collect()
+    }
+}
+
+pub fn create_debug_output() -> String {
+    let exe_hash = match EXE_HASH.get() {
+        Some(hash) => format!("{:?}", hash),
+        None => "not hashed, no profile needed it".to_string(),
+    };
+    let mut output = format!("Profiles: prog {:?}, exe {:?}, exe hash {}\n", PROFILES.prog, PROFILES.exe_path, exe_hash);
+    for matched in PROFILES.matched.iter() {
+        output += &format!("  matched {} from {}\n", matched.profile.name, matched.origin);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_whole_names() {
+        assert!(glob_matches("Celeste*", "Celeste.bin.x86_64"));
+        assert!(glob_matches("game", "game"));
+        assert!(!glob_matches("game", "game2"));
+        assert!(!glob_matches("game", "my-game"));
+        assert!(glob_matches("game?", "game2"));
+        assert!(!glob_matches("game?", "game"));
+    }
+
+    #[test]
+    fn single_stars_stop_at_slashes() {
+        assert!(glob_matches("/games/*/run", "/games/celeste/run"));
+        assert!(!glob_matches("/games/*/run", "/games/celeste/bin/run"));
+        assert!(glob_matches("/games/**/run", "/games/celeste/bin/run"));
+        assert!(!glob_matches("/games/?", "/games//"));
+    }
+
+    #[test]
+    fn braces_pick_one_and_the_rest_is_literal() {
+        assert!(glob_matches("game.{x86_64,bin}", "game.bin"));
+        assert!(glob_matches("game.{x86_64,bin}", "game.x86_64"));
+        assert!(!glob_matches("game.{x86_64,bin}", "game.exe"));
+        // regex characters are escaped, commas outside braces are just commas
+        assert!(glob_matches("a+b(1),c", "a+b(1),c"));
+        assert!(!glob_matches("a.b", "axb"));
+    }
+
+    #[test]
+    fn hashes_files_in_chunks() {
+        // spans a few chunks and ends partway into one
+        let bytes: Vec<u8> = (0..EXE_HASH_CHUNK * 3 + 17).map(|i| (i * 31 % 251) as u8).collect();
+        let path = env::temp_dir().join(format!("hw-exe-hash-test-{}", std::process::id()));
+        fs::write(&path, &bytes).unwrap();
+        let hash = hash_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(hash, fnv1a(FNV1A_OFFSET, &bytes));
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(FNV1A_OFFSET, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(FNV1A_OFFSET, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(FNV1A_OFFSET, b"foobar"), 0x85944171f73967e8);
+    }
+}
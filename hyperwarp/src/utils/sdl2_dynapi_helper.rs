@@ -2,9 +2,10 @@ use std::collections::HashMap;
 
 use lazy_static::lazy_static;
 
-use crate::hooks::dlsym::{query_dlsym_cache, DLSYM_CACHE, LOG_DLSYM};
+use crate::{constants::{sdl2::SDL2_DYNAPI_VERSION, sdl3::SDL3_DYNAPI_VERSION, Library}, hooks::{self, dlsym::{query_dlsym_cache, DLSYM_CACHE, LOG_DLSYM}, registry::HOOKS}};
 
-use super::{pointer::Pointer, sdl2_dynapi::DYNAPI_FUNCS};
+use super::{pointer::Pointer, sdl2_dynapi::DYNAPI_FUNCS, sdl3_dynapi::SDL3_DYNAPI_FUNCS};
+use stellar_shared::{log_debug, log_info, log_warn};
 
 lazy_static! {
     pub static ref DYNAPI_FUNCS_INDEX: HashMap<String, usize> = {
@@ -16,31 +17,74 @@ lazy_static! {
     };
 }
 
-pub static mut INTERNAL_JUMP_TABLE: [usize; 838] = [0; 838];
+// one jump table layout we know the names for
+pub struct DynapiTable {
+    pub apiver: u32,
+    pub name: &'static str,
+    // slot order from SDL_dynapi_procs.h, SDL only ever appends so a newer build just has more slots past the end
+    pub funcs: &'static [&'static str],
+    // which registered hooks get patched in
+    pub library: Library,
+}
+
+pub static DYNAPI_TABLES: [DynapiTable; 2] = [
+    DynapiTable {
+        apiver: SDL2_DYNAPI_VERSION,
+        name: "SDL2",
+        funcs: &DYNAPI_FUNCS,
+        library: Library::SDL2,
+    },
+    DynapiTable {
+        apiver: SDL3_DYNAPI_VERSION,
+        name: "SDL3",
+        funcs: &SDL3_DYNAPI_FUNCS,
+        library: Library::SDL3,
+    },
+];
+
+pub fn find_dynapi_table(apiver: u32) -> Option<&'static DynapiTable> {
+    DYNAPI_TABLES.iter().find(|table| table.apiver == apiver)
+}
 
+// tablesize is sizeof(jump_table) on the caller's side, so bytes and not entries
 pub fn SDL_DYNAPI_entry_modified(apiver: u32, jump_table: *mut libc::c_void, tablesize: u32) -> i32 {
     if LOG_DLSYM {
-        println!("modded SDL_DYNAPI_entry called, api ver: {}, table size: {}", apiver, tablesize);
+        log_info!(Hooks, "modded SDL_DYNAPI_entry called, api ver: {}, table size: {}", apiver, tablesize);
     }
     let orig_func_ptr = query_dlsym_cache("SDL_DYNAPI_entry").expect("Grabbing original dlsym failed.").as_func();
     let orig_func: extern "C" fn(u32, *mut libc::c_void, u32) -> i32 = unsafe { std::mem::transmute(orig_func_ptr) };
     let result = orig_func(apiver, jump_table, tablesize);
-    let result_extension = orig_func(apiver, jump_table, tablesize);
-    
 
-    let bytes_per_pointer = std::mem::size_of::<*mut libc::c_void>();
-    if LOG_DLSYM {
-        println!("SDL_DYNAPI_entry modified, tablesize: {}, pointer size in bytes: {}", tablesize, bytes_per_pointer);
+    if result != 0 {
+        // the app falls back to its own statically linked SDL, nothing for us to patch
+        log_warn!(Hooks, "orig SDL_DYNAPI_entry returned {}, which is not ok", result);
+        return result;
     }
 
-    if result < 0 {
-        println!("orig SDL_DYNAPI_entry returned {}, which is not ok", result);
+    let table = match find_dynapi_table(apiver) {
+        Some(table) => table,
+        None => {
+            log_warn!(Hooks, "SDL_DYNAPI_entry: unknown dynapi version {}, leaving the jump table alone so this app won't be hooked through dynapi", apiver);
+            return result;
+        }
+    };
+
+    if table.apiver == SDL3_DYNAPI_VERSION {
+        hooks::sdl3::mark_sdl3();
+    }
+    if table.funcs.is_empty() {
+        log_warn!(Hooks, "no {} dynapi table was generated, {} calls through the jump table won't be hooked", table.name, table.name);
         return result;
     }
 
-    let modern_ok = result_extension >= 0;
-    if result_extension < 0 {
-        println!("requesting new funcs with orig SDL_DYNAPI_entry returned {}, may affect avalibility of new functions like joystick emu", result_extension);
+    let bytes_per_pointer = std::mem::size_of::<*mut libc::c_void>();
+    let slots = tablesize as usize / bytes_per_pointer;
+    if LOG_DLSYM {
+        log_info!(Hooks, "SDL_DYNAPI_entry modified, {} table with {} slots, we know {} of them", table.name, slots, table.funcs.len());
+    }
+    if slots > table.funcs.len() {
+        // newer SDL than our generated names, the extra slots don't get hooked
+        log_warn!(Hooks, "SDL_DYNAPI_entry: {} jump table has {} slots but only {} are known, regenerate the dynapi table to hook the rest", table.name, slots, table.funcs.len());
     }
 
     {
@@ -48,61 +92,36 @@ pub fn SDL_DYNAPI_entry_modified(apiver: u32, jump_table: *mut libc::c_void, tab
 
         let jump_table_usized: *mut usize = jump_table as *mut usize;
 
-        let internal_len = unsafe {
-            INTERNAL_JUMP_TABLE.len()
-        };
-
-        for (i, func) in DYNAPI_FUNCS.iter().enumerate() {
-            if i > (tablesize as usize) && i > internal_len {
-                if LOG_DLSYM {
-                    println!("skipping table index {} which contains {}", i, func);
-                }
-                continue;
-            }
-            let ptr_to_orig_ptr = unsafe {
-                if i > (tablesize as usize) {
-                    // use internal jump table
-                    if !modern_ok {
-                        continue;
-                    }
-                    INTERNAL_JUMP_TABLE.get_mut(i).unwrap() as *mut usize
-                } else {
-                    jump_table_usized.offset(i as isize)
-                }
-            };
+        // older SDL builds have fewer slots, anything past the end isn't in this table
+        for (i, func) in table.funcs.iter().enumerate().take(slots) {
+            let ptr_to_orig_ptr = unsafe { jump_table_usized.add(i) };
             let orig_ptr = unsafe {
                 *ptr_to_orig_ptr
             };
-            println!("SDL_dynapi helper: read the orig ptr as {} for {}", orig_ptr, func);
-            if !ptr_to_orig_ptr.is_null() {
+            if LOG_DLSYM {
+                log_info!(Hooks, "SDL_dynapi helper: read the orig ptr as {} for {}", orig_ptr, func);
+            }
+            if orig_ptr != 0 {
                 dlsym_cache_locked.insert(format!("{}_hw_sdl_dynapi", func), Pointer(orig_ptr as *const libc::c_void));
                 if !dlsym_cache_locked.contains_key(&format!("{}", func)) {
                     dlsym_cache_locked.insert(format!("{}", func), Pointer(orig_ptr as *const libc::c_void));
                 }
-                // if !dlsym_cache_locked.contains_key(&format!("{}_hw_direct", func)) {
                 dlsym_cache_locked.insert(format!("{}_hw_direct", func), Pointer(orig_ptr as *const libc::c_void));
-                // }
             } else {
-                println!("{}'s pointer is null {}", func, ptr_to_orig_ptr as usize);
+                log_debug!(Hooks, "{}'s pointer is null", func);
+                continue;
             }
-            if let Some(alt_ptr) = crate::hooks::sdl2::try_modify_symbol(func){
-                /*if func == &"SDL_DYNAPI_entry" || func.starts_with("SDL_Init") {
-                    continue; // No!
-                }*/
+            if let Some(alt_ptr) = HOOKS.lookup_library(func, table.library) {
                 // set offset to our new function pointer
                 unsafe {
-                    (jump_table.byte_offset((bytes_per_pointer * i) as isize) as *mut usize).write(alt_ptr as usize);
+                    ptr_to_orig_ptr.write(alt_ptr as usize);
                 }
                 if LOG_DLSYM {
-                    println!("SDL_DYNAPI_entry: modified {} to {}", func, alt_ptr as usize);
+                    log_info!(Hooks, "SDL_DYNAPI_entry: modified {} to {}", func, alt_ptr as usize);
                 }
             }
         }
     }
 
-    if result != 0 {
-        println!("orig SDL_DYNAPI_entry returned {}, which is not ok", result);
-        return result;
-    }
     0 // ok
-}
\ No newline at end of file
+}
@@ -0,0 +1,7 @@
+// SDL3 jump table order, regenerate with `./update_sdl_dynapi_func_defs.sh 3` which fetches SDL3's SDL_dynapi_procs.h
+// https://github.com/libsdl-org/SDL/blob/main/src/dynapi/SDL_dynapi_procs.h
+// still empty, the slot order has to come from the real header or we'd patch the wrong functions,
+// until it's generated SDL3 apps going through dynapi just don't get their jump table patched
+pub const SDL3_DYNAPI_FUNCS: [&str; 0] = [
+
+];
@@ -1,3 +1,7 @@
 # so uh you need to go to https://github.com/libsdl-org/SDL/blob/SDL2/src/dynapi/SDL_dynapi_procs.h
 # and evaluate the macros yourself and then delete all the comments (or not) and this script processes it
-python parse_sdl_dynapi_headers.py
\ No newline at end of file
+# SDL3's header has no platform ifdefs left so it gets fetched as is, the jump table only ever grows so main is fine
+if [ "${1:-2}" = "3" ]; then
+    curl -fsSL https://raw.githubusercontent.com/libsdl-org/SDL/main/src/dynapi/SDL_dynapi_procs.h -o sdl3_dynapi.h || exit 1
+fi
+python parse_sdl_dynapi_headers.py ${1:-2}
@@ -0,0 +1,278 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// challenge/response for control connections that aren't on the unix socket, plus the optional encryption after it
+// the host answers Hello with AuthChallenge(server nonce), the client sends back AuthResponse(client nonce, proof, encrypt)
+// and the host replies AuthResult, anything else from that peer before a good result is dropped
+// proof and session key are both hmac-sha256 keyed with the shared token over the two nonces, the token never goes over the wire
+
+pub const NONCE_LEN: usize = 32;
+pub const PROOF_LEN: usize = 32;
+// counter in front of every sealed message
+const COUNTER_LEN: usize = 8;
+const TAG_LEN: usize = 16;
+// how much longer sealing makes a message
+pub const SEAL_OVERHEAD: usize = COUNTER_LEN + TAG_LEN;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// pre-shared token, Debug doesn't print it since configs end up in debug info
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthToken(String);
+
+impl AuthToken {
+    pub fn generate() -> AuthToken {
+        AuthToken(random_bytes(32).iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for AuthToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let token = s.trim();
+        if token.is_empty() {
+            return Err("auth token is empty".to_string());
+        }
+        Ok(AuthToken(token.to_string()))
+    }
+}
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AuthToken(<{} chars>)", self.0.len())
+    }
+}
+
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).expect("no randomness available");
+    bytes
+}
+
+pub fn random_nonce() -> Vec<u8> {
+    random_bytes(NONCE_LEN)
+}
+
+fn keyed(token: &AuthToken, label: &[u8], server_nonce: &[u8], client_nonce: &[u8]) -> HmacSha256 {
+    // spelled out since KeyInit is in scope for the cipher and has a new_from_slice too
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(token.as_str().as_bytes()).expect("hmac takes keys of any length");
+    mac.update(label);
+    mac.update(server_nonce);
+    mac.update(client_nonce);
+    mac
+}
+
+pub fn prove(token: &AuthToken, server_nonce: &[u8], client_nonce: &[u8]) -> Vec<u8> {
+    keyed(token, b"stellar auth v1", server_nonce, client_nonce).finalize().into_bytes().to_vec()
+}
+
+// constant time
+pub fn verify(token: &AuthToken, server_nonce: &[u8], client_nonce: &[u8], proof: &[u8]) -> bool {
+    keyed(token, b"stellar auth v1", server_nonce, client_nonce).verify_slice(proof).is_ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Host,
+    Client,
+}
+
+impl Side {
+    fn direction(self) -> u8 {
+        match self {
+            Side::Host => 0,
+            Side::Client => 1,
+        }
+    }
+
+    fn other(self) -> Side {
+        match self {
+            Side::Host => Side::Client,
+            Side::Client => Side::Host,
+        }
+    }
+}
+
+// chacha20-poly1305 over whole messages, each one carries its counter so udp can lose some
+// counters only go up, anything at or below the last one we opened is a replay and gets rejected
+pub struct SessionCipher {
+    cipher: ChaCha20Poly1305,
+    side: Side,
+    sent: u64,
+    received: u64,
+}
+
+impl SessionCipher {
+    pub fn new(token: &AuthToken, server_nonce: &[u8], client_nonce: &[u8], side: Side) -> SessionCipher {
+        Self::with_label(token, b"stellar session key v1", server_nonce, client_nonce, side)
+    }
+
+    // the frame stream gets its own key from the ticket, which only ever went out over a sealed control connection
+    pub fn frame_stream(token: &AuthToken, ticket: &[u8], side: Side) -> SessionCipher {
+        Self::with_label(token, b"stellar frame stream key v1", ticket, &[], side)
+    }
+
+    fn with_label(token: &AuthToken, label: &[u8], server_nonce: &[u8], client_nonce: &[u8], side: Side) -> SessionCipher {
+        let key = keyed(token, label, server_nonce, client_nonce).finalize().into_bytes();
+        SessionCipher {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            side,
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    // both sides share a key so the direction goes in the nonce to keep them from ever reusing one
+    fn nonce(side: Side, counter: u64) -> Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[0] = side.direction();
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        Nonce::clone_from_slice(&nonce)
+    }
+
+    pub fn seal(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        self.sent += 1;
+        let sealed = self
+            .cipher
+            .encrypt(&Self::nonce(self.side, self.sent), plaintext)
+            .expect("encrypting into a vec can't fail");
+        let mut data = Vec::with_capacity(COUNTER_LEN + sealed.len());
+        data.extend_from_slice(&self.sent.to_be_bytes());
+        data.extend_from_slice(&sealed);
+        data
+    }
+
+    pub fn open(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if data.len() < COUNTER_LEN {
+            return Err("sealed message too short".to_string());
+        }
+        let (counter, sealed) = data.split_at(COUNTER_LEN);
+        let counter = u64::from_be_bytes(counter.try_into().unwrap());
+        if counter <= self.received {
+            return Err(format!("stale or replayed message {} (last was {})", counter, self.received));
+        }
+        let plaintext = self
+            .cipher
+            .decrypt(&Self::nonce(self.side.other(), counter), sealed)
+            .map_err(|_| "sealed message failed to decrypt".to_string())?;
+        self.received = counter;
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(text: &str) -> AuthToken {
+        text.parse().unwrap()
+    }
+
+    fn session() -> (SessionCipher, SessionCipher) {
+        let token = token("hunter2");
+        let (server_nonce, client_nonce) = (random_nonce(), random_nonce());
+        (
+            SessionCipher::new(&token, &server_nonce, &client_nonce, Side::Host),
+            SessionCipher::new(&token, &server_nonce, &client_nonce, Side::Client),
+        )
+    }
+
+    #[test]
+    fn tokens_are_trimmed_and_never_printed() {
+        assert_eq!(token("  abc\n").as_str(), "abc");
+        assert!(" \n".parse::<AuthToken>().is_err());
+        assert!(!format!("{:?}", token("secret")).contains("secret"));
+        let generated = AuthToken::generate();
+        assert_eq!(generated.as_str().len(), 64);
+        assert_ne!(generated, AuthToken::generate());
+    }
+
+    #[test]
+    fn proofs_verify_only_with_the_same_token_and_nonces() {
+        let (server_nonce, client_nonce) = (random_nonce(), random_nonce());
+        let proof = prove(&token("hunter2"), &server_nonce, &client_nonce);
+        assert_eq!(proof.len(), PROOF_LEN);
+        assert!(verify(&token("hunter2"), &server_nonce, &client_nonce, &proof));
+        assert!(!verify(&token("hunter3"), &server_nonce, &client_nonce, &proof));
+        assert!(!verify(&token("hunter2"), &random_nonce(), &client_nonce, &proof));
+        assert!(!verify(&token("hunter2"), &server_nonce, &random_nonce(), &proof));
+        // the nonces aren't interchangeable
+        assert!(!verify(&token("hunter2"), &client_nonce, &server_nonce, &proof));
+        let mut tampered = proof.clone();
+        tampered[0] ^= 1;
+        assert!(!verify(&token("hunter2"), &server_nonce, &client_nonce, &tampered));
+        assert!(!verify(&token("hunter2"), &server_nonce, &client_nonce, &proof[..PROOF_LEN - 1]));
+    }
+
+    #[test]
+    fn sealed_messages_round_trip_both_ways() {
+        let (mut host, mut client) = session();
+        for message in [&b"hello"[..], b"", &[0u8; 4096]] {
+            let sealed = host.seal(message);
+            assert_eq!(sealed.len(), message.len() + SEAL_OVERHEAD);
+            assert_ne!(&sealed[COUNTER_LEN..], message);
+            assert_eq!(client.open(&sealed).unwrap(), message);
+            assert_eq!(host.open(&client.seal(message)).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn tampered_messages_are_rejected() {
+        let (mut host, mut client) = session();
+        let sealed = host.seal(b"UserInputEvent");
+        for index in [0, COUNTER_LEN, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[index] ^= 1;
+            assert!(client.open(&tampered).is_err(), "flipping byte {} went unnoticed", index);
+        }
+        assert!(client.open(&sealed[..COUNTER_LEN - 1]).is_err());
+        assert!(client.open(&sealed[..sealed.len() - 1]).is_err());
+        // nothing above moved the counter on
+        assert_eq!(client.open(&sealed).unwrap(), b"UserInputEvent");
+    }
+
+    #[test]
+    fn replays_and_reflections_are_rejected() {
+        let (mut host, mut client) = session();
+        let first = host.seal(b"first");
+        let second = host.seal(b"second");
+        assert_eq!(client.open(&second).unwrap(), b"second");
+        // udp may reorder but anything older than what we've opened is gone
+        assert!(client.open(&first).is_err());
+        assert!(client.open(&second).is_err());
+        // a host message sent back at the host doesn't open, the direction is in the nonce
+        let mut other_host = session().0;
+        assert!(other_host.open(&host.seal(b"mirror")).is_err());
+        let mirrored = host.seal(b"mirror");
+        assert!(host.open(&mirrored).is_err());
+    }
+
+    #[test]
+    fn sessions_with_different_nonces_dont_share_keys() {
+        let (mut host, _) = session();
+        let (_, mut other_client) = session();
+        assert!(other_client.open(&host.seal(b"hello")).is_err());
+    }
+
+    #[test]
+    fn frame_stream_keys_are_per_ticket() {
+        let token = token("hunter2");
+        let ticket = random_bytes(32);
+        let mut host = SessionCipher::frame_stream(&token, &ticket, Side::Host);
+        let sealed = host.seal(b"frame");
+        assert_eq!(SessionCipher::frame_stream(&token, &ticket, Side::Client).open(&sealed).unwrap(), b"frame");
+        assert!(SessionCipher::frame_stream(&token, &random_bytes(32), Side::Client).open(&sealed).is_err());
+        // not the control session key for the same nonces either
+        assert!(SessionCipher::new(&token, &ticket, &[], Side::Client).open(&sealed).is_err());
+    }
+}
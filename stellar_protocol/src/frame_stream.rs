@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use crate::auth::SEAL_OVERHEAD;
+use crate::protocol::{FrameCompression, FrameLayout, FramePixelFormat};
+
+// frames over tcp for a streamerd that can't see hyperwarp's shm file
+// the client opens the connection and sends the ticket it got over the control connection, then only reads
+// every frame is a fixed header followed by payload_len bytes, lz4 block compressed or raw
+// resolution changes still go through sync events like they do for the shm slots
+// an empty 0x0 frame goes out when there was nothing to send for KEEPALIVE_INTERVAL so the client can time out a dead link
+// when the control connection that got the ticket is sealed the header and payload are each sealed with SessionCipher::frame_stream
+
+pub const TICKET_LEN: usize = 32;
+pub const HEADER_LEN: usize = 32;
+pub const SEALED_HEADER_LEN: usize = HEADER_LEN + SEAL_OVERHEAD;
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(2);
+const MAGIC: [u8; 4] = *b"HWFS";
+const FLAG_BOTTOM_UP: u8 = 1;
+// every pixel format we write is 4 bytes a pixel, wide multi monitor frames are fine as long as the whole thing stays under 512MiB
+const MAX_DIMENSION: u32 = 32768;
+const MAX_PIXELS: u64 = 1 << 27;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub sequence: u64,
+    pub width: u32,
+    pub height: u32,
+    pub compression: FrameCompression,
+    pub layout: FrameLayout,
+    pub raw_len: u32,
+    pub payload_len: u32,
+}
+
+impl FrameHeader {
+    pub fn keepalive(sequence: u64, compression: FrameCompression) -> FrameHeader {
+        FrameHeader {
+            sequence,
+            width: 0,
+            height: 0,
+            compression,
+            layout: FrameLayout::default(),
+            raw_len: 0,
+            payload_len: 0,
+        }
+    }
+
+    pub fn is_keepalive(&self) -> bool {
+        self.raw_len == 0
+    }
+
+    // magic, compression, pixel format, flags, 1 padding byte, sequence, width, height, raw len, payload len, all little endian
+    pub fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0u8; HEADER_LEN];
+        header[0..4].copy_from_slice(&MAGIC);
+        header[4] = match self.compression {
+            FrameCompression::None => 0,
+            FrameCompression::Lz4 => 1,
+        };
+        header[5] = match self.layout.format {
+            FramePixelFormat::Rgba => 0,
+            FramePixelFormat::Rgbx => 1,
+            FramePixelFormat::Bgra => 2,
+            FramePixelFormat::Bgrx => 3,
+        };
+        header[6] = if self.layout.bottom_up { FLAG_BOTTOM_UP } else { 0 };
+        header[8..16].copy_from_slice(&self.sequence.to_le_bytes());
+        header[16..20].copy_from_slice(&self.width.to_le_bytes());
+        header[20..24].copy_from_slice(&self.height.to_le_bytes());
+        header[24..28].copy_from_slice(&self.raw_len.to_le_bytes());
+        header[28..32].copy_from_slice(&self.payload_len.to_le_bytes());
+        header
+    }
+
+    pub fn decode(header: &[u8; HEADER_LEN]) -> Result<FrameHeader, String> {
+        if header[0..4] != MAGIC {
+            return Err("not a frame header".to_string());
+        }
+        let compression = match header[4] {
+            0 => FrameCompression::None,
+            1 => FrameCompression::Lz4,
+            other => return Err(format!("unknown frame compression {}", other)),
+        };
+        let format = match header[5] {
+            0 => FramePixelFormat::Rgba,
+            1 => FramePixelFormat::Rgbx,
+            2 => FramePixelFormat::Bgra,
+            3 => FramePixelFormat::Bgrx,
+            other => return Err(format!("unknown pixel format {}", other)),
+        };
+        if header[6] & !FLAG_BOTTOM_UP != 0 {
+            return Err(format!("unknown frame flags {:#x}", header[6]));
+        }
+        let read_u32 = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let decoded = FrameHeader {
+            sequence: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            width: read_u32(16),
+            height: read_u32(20),
+            compression,
+            layout: FrameLayout { format, bottom_up: header[6] & FLAG_BOTTOM_UP != 0 },
+            raw_len: read_u32(24),
+            payload_len: read_u32(28),
+        };
+        if decoded.width > MAX_DIMENSION || decoded.height > MAX_DIMENSION || decoded.width as u64 * decoded.height as u64 > MAX_PIXELS {
+            return Err(format!("frame of {}x{} is too big", decoded.width, decoded.height));
+        }
+        if decoded.raw_len as u64 != decoded.width as u64 * decoded.height as u64 * 4 {
+            return Err(format!("{} bytes doesn't fit a {}x{} frame", decoded.raw_len, decoded.width, decoded.height));
+        }
+        let max_payload = match compression {
+            FrameCompression::None => decoded.raw_len as usize,
+            FrameCompression::Lz4 => lz4_flex::block::get_maximum_output_size(decoded.raw_len as usize),
+        };
+        if decoded.payload_len as usize > max_payload {
+            return Err(format!("payload of {} bytes for a {} byte frame", decoded.payload_len, decoded.raw_len));
+        }
+        Ok(decoded)
+    }
+}
+
+// out is reused between frames so this only allocates when the resolution goes up
+pub fn compress(compression: FrameCompression, raw: &[u8], out: &mut Vec<u8>) {
+    match compression {
+        FrameCompression::None => {
+            out.clear();
+            out.extend_from_slice(raw);
+        }
+        FrameCompression::Lz4 => {
+            out.resize(lz4_flex::block::get_maximum_output_size(raw.len()), 0);
+            let len = lz4_flex::block::compress_into(raw, out).expect("output was sized for the worst case");
+            out.truncate(len);
+        }
+    }
+}
+
+pub fn decompress(header: &FrameHeader, payload: &[u8]) -> Result<Vec<u8>, String> {
+    match header.compression {
+        FrameCompression::None => Ok(payload.to_vec()),
+        FrameCompression::Lz4 => {
+            let mut raw = vec![0u8; header.raw_len as usize];
+            let len = lz4_flex::block::decompress_into(payload, &mut raw).map_err(|err| format!("lz4: {}", err))?;
+            if len != raw.len() {
+                return Err(format!("frame decompressed to {} bytes instead of {}", len, raw.len()));
+            }
+            Ok(raw)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // wraps for the oversized ones, which never get as far as the length check
+    fn header(width: u32, height: u32, compression: FrameCompression) -> FrameHeader {
+        let raw_len = width.wrapping_mul(height).wrapping_mul(4);
+        FrameHeader {
+            sequence: 7,
+            width,
+            height,
+            compression,
+            layout: FrameLayout { format: FramePixelFormat::Bgrx, bottom_up: true },
+            raw_len,
+            payload_len: raw_len,
+        }
+    }
+
+    #[test]
+    fn headers_round_trip() {
+        for compression in [FrameCompression::None, FrameCompression::Lz4] {
+            for format in [FramePixelFormat::Rgba, FramePixelFormat::Rgbx, FramePixelFormat::Bgra, FramePixelFormat::Bgrx] {
+                for bottom_up in [false, true] {
+                    let mut frame = header(1920, 1080, compression);
+                    frame.layout = FrameLayout { format, bottom_up };
+                    assert_eq!(FrameHeader::decode(&frame.encode()), Ok(frame));
+                }
+            }
+        }
+        // three 4k monitors side by side
+        let wide = header(11520, 2160, FrameCompression::None);
+        assert_eq!(FrameHeader::decode(&wide.encode()), Ok(wide));
+        let keepalive = FrameHeader::decode(&FrameHeader::keepalive(3, FrameCompression::Lz4).encode()).unwrap();
+        assert!(keepalive.is_keepalive());
+        assert_eq!(keepalive.sequence, 3);
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        let good = header(640, 480, FrameCompression::None).encode();
+        let broken = |at: usize, value: u8| {
+            let mut bytes = good;
+            bytes[at] = value;
+            FrameHeader::decode(&bytes)
+        };
+        assert!(broken(0, b'X').is_err());
+        assert!(broken(4, 2).is_err());
+        assert!(broken(5, 4).is_err());
+        assert!(broken(6, 2).is_err());
+
+        assert!(FrameHeader::decode(&header(MAX_DIMENSION + 1, 1, FrameCompression::None).encode()).is_err());
+        assert!(FrameHeader::decode(&header(1, MAX_DIMENSION + 1, FrameCompression::None).encode()).is_err());
+        assert!(FrameHeader::decode(&header(MAX_DIMENSION, MAX_DIMENSION, FrameCompression::None).encode()).is_err());
+
+        let mut short = header(640, 480, FrameCompression::None);
+        short.raw_len -= 4;
+        assert!(FrameHeader::decode(&short.encode()).is_err());
+        let mut payload = FrameHeader::keepalive(1, FrameCompression::None);
+        payload.payload_len = 1;
+        assert!(FrameHeader::decode(&payload.encode()).is_err());
+        let mut padded = header(640, 480, FrameCompression::None);
+        padded.payload_len += 1;
+        assert!(FrameHeader::decode(&padded.encode()).is_err());
+        // lz4 can come out a bit bigger than what went in
+        let mut grown = header(640, 480, FrameCompression::Lz4);
+        grown.payload_len += 1;
+        assert!(FrameHeader::decode(&grown.encode()).is_ok());
+    }
+
+    #[test]
+    fn compression_round_trips() {
+        let frame = header(64, 32, FrameCompression::Lz4);
+        let raw: Vec<u8> = (0..frame.raw_len).map(|index| (index / 16) as u8).collect();
+        let mut payload = Vec::new();
+        for compression in [FrameCompression::None, FrameCompression::Lz4] {
+            compress(compression, &raw, &mut payload);
+            let frame = FrameHeader { compression, payload_len: payload.len() as u32, ..frame };
+            assert_eq!(decompress(&frame, &payload).unwrap(), raw);
+        }
+        compress(FrameCompression::Lz4, &raw, &mut payload);
+        assert!(decompress(&FrameHeader { raw_len: frame.raw_len + 4, ..frame }, &payload).is_err());
+    }
+}
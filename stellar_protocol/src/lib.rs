@@ -4,6 +4,9 @@ use crate::protocol::StellarMessage;
 
 pub mod util;
 pub mod protocol;
+pub mod auth;
+pub mod frame_stream;
+pub mod session;
 
 // for eventual bincode 2 migration
 // lazy_static! {
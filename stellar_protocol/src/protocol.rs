@@ -12,7 +12,9 @@ pub enum StellarChannel {
     Frame,
     Synchornizations,
     WindowChanges, // synchronizations is more reliable for now
-    Signaling
+    Signaling,
+    // hyperwarp's log lines when its log output is the socket
+    Logs,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, EnumString, Display, EnumIter, VariantArray, Hash, Eq, Clone, Copy)]
@@ -24,6 +26,34 @@ pub enum GraphicsAPI {
     Metal, // idk if i'll ever be able to
 }
 
+// byte order of the pixels hyperwarp writes into the shimg frames
+#[derive(Serialize, Deserialize, PartialEq, Debug, EnumString, Display, EnumIter, VariantArray, Hash, Eq, Clone, Copy, Default)]
+#[strum(serialize_all = "lowercase")]
+pub enum FramePixelFormat {
+    #[default]
+    Rgba,
+    Rgbx,
+    Bgra,
+    Bgrx,
+}
+
+// sent along with every frame so streamerd handles each one the way it was captured
+// even when the capture path changes between frames
+#[derive(Serialize, Deserialize, PartialEq, Debug, Hash, Eq, Clone, Copy, Default)]
+pub struct FrameLayout {
+    pub format: FramePixelFormat,
+    // rows go bottom to top like glReadPixels hands them out, streamerd flips these
+    pub bottom_up: bool,
+}
+
+// how frames are packed on the network frame stream, see frame_stream.rs
+#[derive(Serialize, Deserialize, PartialEq, Debug, EnumString, Display, EnumIter, VariantArray, Hash, Eq, Clone, Copy)]
+#[strum(serialize_all = "lowercase")]
+pub enum FrameCompression {
+    None,
+    Lz4,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, EnumString, Display, EnumIter, VariantArray, Hash, Eq, Clone, Copy)]
 pub enum EncodingPreset {
     H264,
@@ -49,6 +79,18 @@ use strum::IntoEnumIterator;
 use strum::VariantArray;
 
 use crate::util;
+// frame slot ids carry the slot index in the low byte and a generation above it
+// hyperwarp bumps the generation every time a slot is reused so a release that shows up late can't free the new frame
+pub const FRAME_SLOT_INDEX_BITS: u32 = 8;
+
+pub fn frame_slot_id(index: usize, generation: u32) -> u32 {
+    (generation << FRAME_SLOT_INDEX_BITS) | index as u32
+}
+
+pub fn frame_slot_index(slot_id: u32) -> usize {
+    (slot_id & ((1 << FRAME_SLOT_INDEX_BITS) - 1)) as usize
+}
+
 pub fn get_all_channels() -> Vec<StellarChannel> {
     let vec: Vec<StellarChannel> = StellarChannel::iter().collect();
     vec
@@ -59,6 +101,11 @@ pub struct Handshake {
     pub resolution: (u32, u32),
     pub shimg_path: PathBuf,
     pub graphics_api: GraphicsAPI,
+    // how many frame sized slots the shimg file is split into
+    pub frame_slots: u32,
+    pub pixel_format: FramePixelFormat,
+    // set when hyperwarp can also send frames over tcp for a streamerd on another machine
+    pub frame_stream_port: Option<u16>,
 }
 
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, Copy, Clone)]
@@ -118,11 +165,89 @@ impl Default for HostInfo {
 pub struct Synchornization {
     pub resolution: Option<(u32, u32)>,
     pub graphics_api: Option<GraphicsAPI>,
+    pub pixel_format: Option<FramePixelFormat>,
+}
+
+// a window hyperwarp knows about
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct WindowInfo {
+    pub id: u64,
+    pub library: String,
+    pub title: Option<String>,
+    pub size: (u32, u32),
+    pub capturing: bool,
+    pub visible: bool,
+    pub fullscreen: bool,
+    pub focused: bool,
+    pub has_icon: bool,
+}
+
+// rgba8, rows tightly packed
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct WindowIcon {
+    pub width: u32,
+    pub height: u32,
+    #[serde(with = "util::compact_bytes")]
+    pub rgba: Vec<u8>,
+}
+
+// which window hyperwarp should capture from
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub enum CaptureTarget {
+    #[default]
+    Largest,
+    MostRecentlySwapped,
+    WindowId(u64),
+    TitleRegex(String),
+}
+
+// cli form: largest, recent, id:<window id>, title:<regex>
+impl std::str::FromStr for CaptureTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "largest" => Ok(CaptureTarget::Largest),
+            "recent" => Ok(CaptureTarget::MostRecentlySwapped),
+            _ => {
+                if let Some(id) = s.strip_prefix("id:") {
+                    id.parse::<u64>().map(CaptureTarget::WindowId).map_err(|err| format!("bad window id {}: {}", id, err))
+                } else if let Some(pattern) = s.strip_prefix("title:") {
+                    Ok(CaptureTarget::TitleRegex(pattern.to_string()))
+                } else {
+                    Err(format!("unknown capture target {}, expected largest, recent, id:<id> or title:<regex>", s))
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for CaptureTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureTarget::Largest => write!(f, "largest"),
+            CaptureTarget::MostRecentlySwapped => write!(f, "recent"),
+            CaptureTarget::WindowId(id) => write!(f, "id:{}", id),
+            CaptureTarget::TitleRegex(pattern) => write!(f, "title:{}", pattern),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct DebugInfo {
     pub message: String,
+    // every hook reached so far, in first call order
+    pub hook_calls: Vec<HookCallStats>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct HookCallStats {
+    pub name: String,
+    pub calls: u64,
+    // the rest stays 0 unless hook timing is on
+    pub timed_calls: u64,
+    pub total_ns: u64,
+    pub max_ns: u64,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -154,6 +279,29 @@ pub enum StellarMessage {
     // reciever, channel, message
     ReplyDataChannelMessage(String, String, StellarDirectControlMessage),
     BroadcastDataChannelMessage(String, StellarDirectControlMessage),
+    WindowListRequest,
+    WindowListResponse(Vec<WindowInfo>),
+    SetCaptureTarget(CaptureTarget),
+    // published on the WindowChanges channel
+    WindowChanged(WindowInfo),
+    WindowIconChanged(u64, WindowIcon),
+    WindowDestroyed(u64),
+    // published on the Logs channel, level, target, message
+    LogLine(String, String, String),
+    // see auth.rs, only used on transports other than the unix socket
+    AuthChallenge(Vec<u8>),
+    // client nonce, proof, encrypt everything after the result
+    AuthResponse(Vec<u8>, Vec<u8>, bool),
+    AuthResult(bool, String),
+    // asks for frames over the network frame stream, answered with a ticket to send when connecting to it
+    FrameStreamRequest(FrameCompression),
+    FrameStreamTicket(Option<Vec<u8>>),
+    // streamerd sends one every second and hyperwarp echoes it, a peer that goes quiet for too long is dropped on both ends
+    Heartbeat,
+    // new variants only ever go at the end, bincode numbers them by position so anything inserted breaks older peers
+    // slot id, slot size, how the frame in it is laid out
+    NewFrameSlot(u32, u64, FrameLayout),
+    ReleaseFrameSlot(u32),
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -206,6 +354,7 @@ pub enum StellarFrontendMessage {
     },
     HyperwarpDebugResponse {
         hyperwarp_debug: String,
+        hyperwarp_hook_calls: Vec<HookCallStats>,
         source: String
     },
     EndSessionRequest {
@@ -223,6 +372,14 @@ pub enum StellarDirectControlMessage {
     UpdateWindowTitle {
         title: String,
     },
+    #[serde(rename = "update_window_icon")]
+    // rgba is base64 since this goes to the frontend as json
+    UpdateWindowIcon {
+        width: u32,
+        height: u32,
+        #[serde(with = "util::compact_bytes")]
+        rgba: Vec<u8>,
+    },
     #[serde(alias = "keychange")]
     KeyChange {
         key: String,
@@ -456,6 +613,11 @@ pub enum InputEventPayload {
         change: u8,
         state: bool,
     },
+    // browser wheel deltas, positive y scrolls down
+    MouseScroll {
+        x: f32,
+        y: f32,
+    },
     JoystickBrowserUpdate {
         id: String,
         axis: Vec<f64>,
@@ -594,4 +756,48 @@ pub enum GameControllerBindType {
     None = 0,
     Button = 1,
     Axis = 2
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_targets_parse() {
+        assert_eq!("largest".parse::<CaptureTarget>(), Ok(CaptureTarget::Largest));
+        assert_eq!("recent".parse::<CaptureTarget>(), Ok(CaptureTarget::MostRecentlySwapped));
+        assert_eq!("id:42".parse::<CaptureTarget>(), Ok(CaptureTarget::WindowId(42)));
+        assert_eq!("title:^Celeste$".parse::<CaptureTarget>(), Ok(CaptureTarget::TitleRegex("^Celeste$".to_string())));
+        // everything after the prefix is the pattern, colons included
+        assert_eq!("title:a:b".parse::<CaptureTarget>(), Ok(CaptureTarget::TitleRegex("a:b".to_string())));
+    }
+
+    #[test]
+    fn capture_targets_reject_garbage() {
+        assert!("".parse::<CaptureTarget>().is_err());
+        assert!("Largest".parse::<CaptureTarget>().is_err());
+        assert!("id:".parse::<CaptureTarget>().is_err());
+        assert!("id:-1".parse::<CaptureTarget>().is_err());
+        assert!("id:0x10".parse::<CaptureTarget>().is_err());
+        assert!("window:1".parse::<CaptureTarget>().is_err());
+    }
+
+    #[test]
+    fn capture_targets_round_trip_through_display() {
+        for target in [CaptureTarget::Largest, CaptureTarget::MostRecentlySwapped, CaptureTarget::WindowId(7), CaptureTarget::TitleRegex("game .*".to_string())] {
+            assert_eq!(target.to_string().parse::<CaptureTarget>(), Ok(target));
+        }
+    }
+
+    // bincode writes the variant index first, a host and streamerd from different builds only agree if these never move
+    #[test]
+    fn message_variants_keep_their_wire_index() {
+        let index = |message: &StellarMessage| u32::from_le_bytes(crate::serialize(message)[..4].try_into().unwrap());
+        assert_eq!(index(&StellarMessage::NewFrame), 4);
+        assert_eq!(index(&StellarMessage::ToggleDebugOverlay), 5);
+        assert_eq!(index(&StellarMessage::HandshakeRequest), 12);
+        assert_eq!(index(&StellarMessage::DebugInfoRequestV2), 19);
+        assert_eq!(index(&StellarMessage::WindowListRequest), 25);
+        assert_eq!(index(&StellarMessage::Heartbeat), 37);
+        assert_eq!(index(&StellarMessage::ReleaseFrameSlot(0)), 39);
+    }
+}
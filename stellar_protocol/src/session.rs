@@ -0,0 +1,181 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::GraphicsAPI;
+use crate::util;
+
+// every hyperwarp with a control socket drops one of these into the session dir so streamerd can find it
+// without being told the socket path, it's removed on exit and listing skips ones whose process is gone
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionDescriptor {
+    pub pid: u32,
+    pub exe: String,
+    pub session_id: String,
+    pub socket_path: PathBuf,
+    pub shm_path: PathBuf,
+    pub graphics_api: GraphicsAPI,
+    // unix seconds
+    pub started_at: u64,
+}
+
+// read straight from the environment since both hyperwarp and streamerd have to agree on it
+// HW_SESSION_DIR wins, then sessions in util::runtime_dir
+// either way it has to be a private dir, a descriptor someone else planted could point --attach at their socket
+pub fn session_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("HW_SESSION_DIR").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    util::runtime_dir().join("sessions")
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+fn is_alive(pid: u32) -> bool {
+    Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+impl SessionDescriptor {
+    pub fn path(&self) -> PathBuf {
+        session_dir().join(format!("{}.json", self.session_id))
+    }
+
+    // written to a temp file first so a listing never sees half a descriptor
+    pub fn write(&self) -> io::Result<PathBuf> {
+        let path = self.path();
+        util::ensure_private_dir(&session_dir())?;
+        let temp_path = path.with_extension(format!("json.{}.tmp", self.pid));
+        let json = serde_json::to_vec_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(&temp_path, json)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(path)
+    }
+
+    // only if it's still ours, a restarted game with the same session id may have replaced it
+    pub fn remove(&self) {
+        let path = self.path();
+        let ours = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<SessionDescriptor>(&data).ok())
+            .is_some_and(|descriptor| descriptor.pid == self.pid);
+        if ours {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    // the exe name or full path, the session id or the pid
+    pub fn matches(&self, query: &str) -> bool {
+        if query.parse::<u32>() == Ok(self.pid) || query == self.session_id || query == self.exe {
+            return true;
+        }
+        Path::new(&self.exe).file_name().is_some_and(|name| name == query)
+    }
+}
+
+// live sessions, oldest first, descriptors left behind by processes that died without cleaning up are deleted
+pub fn list_sessions() -> io::Result<Vec<SessionDescriptor>> {
+    let dir = session_dir();
+    match util::check_private_dir(&dir) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    }
+    let entries = fs::read_dir(&dir)?;
+    let mut sessions = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let descriptor = match fs::read(&path).ok().and_then(|data| serde_json::from_slice::<SessionDescriptor>(&data).ok()) {
+            Some(descriptor) => descriptor,
+            None => continue,
+        };
+        if !is_alive(descriptor.pid) {
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+        sessions.push(descriptor);
+    }
+    sessions.sort_by_key(|descriptor| descriptor.started_at);
+    Ok(sessions)
+}
+
+// exactly one live session has to match, pids and session ids are checked before exe names
+pub fn find_session(query: &str) -> Result<SessionDescriptor, String> {
+    let sessions = list_sessions().map_err(|err| format!("could not read {}: {}", session_dir().display(), err))?;
+    let exact = sessions.iter().find(|descriptor| query.parse::<u32>() == Ok(descriptor.pid) || query == descriptor.session_id);
+    if let Some(descriptor) = exact {
+        return Ok(descriptor.clone());
+    }
+    let matching: Vec<&SessionDescriptor> = sessions.iter().filter(|descriptor| descriptor.matches(query)).collect();
+    match matching.as_slice() {
+        [descriptor] => Ok((*descriptor).clone()),
+        [] => Err(format!("no running session matches {} in {}", query, session_dir().display())),
+        _ => Err(format!(
+            "{} sessions match {}, pick one by session id or pid: {}",
+            matching.len(),
+            query,
+            matching.iter().map(|descriptor| format!("{} (pid {})", descriptor.session_id, descriptor.pid)).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    fn descriptor(session_id: &str) -> SessionDescriptor {
+        SessionDescriptor {
+            pid: std::process::id(),
+            exe: "/games/celeste/Celeste".to_string(),
+            session_id: session_id.to_string(),
+            socket_path: PathBuf::from("/tmp/hw.sock"),
+            shm_path: PathBuf::from("/dev/shm/hw"),
+            graphics_api: GraphicsAPI::OpenGL,
+            started_at: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn descriptors_match_by_pid_session_id_or_exe() {
+        let descriptor = descriptor("abc");
+        assert!(descriptor.matches(&std::process::id().to_string()));
+        assert!(descriptor.matches("abc"));
+        assert!(descriptor.matches("Celeste"));
+        assert!(descriptor.matches("/games/celeste/Celeste"));
+        assert!(!descriptor.matches("celeste"));
+        assert!(!descriptor.matches("0"));
+    }
+
+    // the only test touching HW_SESSION_DIR
+    #[test]
+    fn sessions_only_come_from_a_private_dir() {
+        let dir = env::temp_dir().join(format!("hw-session-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        env::set_var("HW_SESSION_DIR", &dir);
+        assert_eq!(list_sessions().unwrap(), Vec::new());
+
+        let path = descriptor("abc").write().unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().mode() & 0o777, 0o700);
+        assert_eq!(path, dir.join("abc.json"));
+        assert_eq!(find_session("abc").unwrap(), descriptor("abc"));
+
+        // anyone could have dropped that descriptor in
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(list_sessions().is_err());
+        assert!(find_session("abc").is_err());
+        assert!(descriptor("abc").write().is_err());
+
+        env::remove_var("HW_SESSION_DIR");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
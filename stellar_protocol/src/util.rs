@@ -1,7 +1,10 @@
 // https://stackoverflow.com/a/36848555
 
 use std::env;
-use std::path::Path;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
 use std::ffi::OsStr;
 
 pub fn prog() -> Option<String> {
@@ -11,4 +14,165 @@ pub fn prog() -> Option<String> {
         .and_then(Path::file_name)
         .and_then(OsStr::to_str)
         .map(String::from)
-}
\ No newline at end of file
+}
+
+// $XDG_RUNTIME_DIR/hyperwarp, or /tmp/hyperwarp-<uid> without one, for files other users shouldn't read or plant
+pub fn runtime_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("hyperwarp"),
+        None => PathBuf::from(format!("/tmp/hyperwarp-{}", unsafe { libc::getuid() })),
+    }
+}
+
+// whether a dir is one we can trust, ours, not a symlink and not writable by anyone else
+pub fn check_private_dir(dir: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(dir)?;
+    let problem = if !metadata.is_dir() {
+        Some("isn't a directory")
+    } else if metadata.uid() != unsafe { libc::getuid() } {
+        Some("belongs to another user")
+    } else if metadata.mode() & 0o022 != 0 {
+        Some("is writable by other users")
+    } else {
+        None
+    };
+    match problem {
+        Some(problem) => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} {}", dir.display(), problem))),
+        None => Ok(()),
+    }
+}
+
+// creates dir 0700 if it's missing, either way it has to pass check_private_dir
+pub fn ensure_private_dir(dir: &Path) -> io::Result<()> {
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    check_private_dir(dir)
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// standard alphabet with padding, what atob() in the frontend takes
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64_ALPHABET[(triple >> (18 - i * 6) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut output = Vec::with_capacity(text.len() / 4 * 3);
+    for (index, chunk) in text.chunks(4).enumerate() {
+        let last = index == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut triple: u32 = 0;
+        for c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|a| a == c)? as u32;
+            triple = triple << 6 | value;
+        }
+        triple <<= 6 * padding as u32;
+        let bytes = [(triple >> 16) as u8, (triple >> 8) as u8, triple as u8];
+        output.extend_from_slice(&bytes[..3 - padding]);
+    }
+    Some(output)
+}
+
+// bytes that end up in json for the frontend as base64 instead of an array of numbers, bincode keeps them raw
+pub mod compact_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&super::base64_encode(data))
+        } else {
+            serializer.serialize_bytes(data)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let text = String::deserialize(deserializer)?;
+            super::base64_decode(&text).ok_or_else(|| D::Error::custom("invalid base64"))
+        } else {
+            Vec::<u8>::deserialize(deserializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("hw-util-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn private_dirs_are_created_0700() {
+        let dir = scratch_dir("create");
+        ensure_private_dir(&dir.join("nested")).unwrap();
+        assert_eq!(fs::metadata(dir.join("nested")).unwrap().mode() & 0o777, 0o700);
+        // already there is fine too
+        ensure_private_dir(&dir.join("nested")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shared_dirs_and_symlinks_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = scratch_dir("refuse");
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        fs::set_permissions(dir.join("shared"), fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(ensure_private_dir(&dir.join("shared")).is_err());
+        fs::create_dir(dir.join("private")).unwrap();
+        fs::set_permissions(dir.join("private"), fs::Permissions::from_mode(0o700)).unwrap();
+        std::os::unix::fs::symlink(dir.join("private"), dir.join("link")).unwrap();
+        assert!(check_private_dir(&dir.join("private")).is_ok());
+        assert!(ensure_private_dir(&dir.join("link")).is_err());
+        fs::write(dir.join("file"), b"").unwrap();
+        assert!(check_private_dir(&dir.join("file")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn base64_matches_known_values() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xff, 0xfe, 0x00]), "//4A");
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        let data: Vec<u8> = (0..=255).collect();
+        for len in 0..data.len() {
+            assert_eq!(base64_decode(&base64_encode(&data[..len])).as_deref(), Some(&data[..len]));
+        }
+    }
+
+    #[test]
+    fn base64_rejects_garbage() {
+        assert_eq!(base64_decode("Zg="), None);
+        assert_eq!(base64_decode("Zg!="), None);
+        assert_eq!(base64_decode("Zg==Zm9v"), None);
+        assert_eq!(base64_decode("Z==="), None);
+    }
+}
@@ -2,6 +2,7 @@ use lazy_static::lazy_static;
 
 pub mod vendor;
 pub mod constants;
+pub mod logging;
 
 // for eventual bincode 2 migration
 // lazy_static! {
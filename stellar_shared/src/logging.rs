@@ -0,0 +1,322 @@
+// levelled logging shared by hyperwarp and streamerd, so neither has to print into the game's stdout unasked
+// levels are kept per target in atomics, a disabled log_debug! is one load and a compare
+// the spec looks like "info,hooks=debug,net=trace", a bare level sets every target not named after it
+
+use std::{cell::Cell, fmt, fs::{File, OpenOptions}, io::Write, path::PathBuf, str::FromStr, sync::{atomic::{AtomicU8, Ordering}, Mutex}};
+
+use lazy_static::lazy_static;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Off = 0,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Off => "off",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    fn from_u8(value: u8) -> Level {
+        match value {
+            0 => Level::Off,
+            1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" | "none" => Ok(Level::Off),
+            "error" => Ok(Level::Error),
+            "warn" | "warning" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!("unknown log level {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    General,
+    // symbol lookup and the hooks themselves
+    Hooks,
+    Input,
+    // reading frames back and the shm file
+    Capture,
+    // the socket between hyperwarp and streamerd, and stargate
+    Net,
+    // streamerd's gstreamer pipeline and webrtc
+    Pipeline,
+}
+
+pub const TARGETS: [Target; 6] = [Target::General, Target::Hooks, Target::Input, Target::Capture, Target::Net, Target::Pipeline];
+
+impl Target {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::General => "general",
+            Target::Hooks => "hooks",
+            Target::Input => "input",
+            Target::Capture => "capture",
+            Target::Net => "net",
+            Target::Pipeline => "pipeline",
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TARGETS.iter().find(|target| target.name() == s).copied().ok_or_else(|| format!("unknown log target {}", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LogSpec {
+    // None when the spec didn't say, so debug_mode and friends can pick
+    pub default: Option<Level>,
+    pub targets: Vec<(Target, Level)>,
+}
+
+impl LogSpec {
+    pub fn level_for(&self, target: Target, fallback: Level) -> Level {
+        self.targets.iter().rev()
+            .find(|(spec_target, _)| *spec_target == target)
+            .map(|(_, level)| *level)
+            .or(self.default)
+            .unwrap_or(fallback)
+    }
+}
+
+impl FromStr for LogSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut spec = LogSpec::default();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.split_once('=') {
+                Some((target, level)) => spec.targets.push((target.trim().parse()?, level.trim().parse()?)),
+                None => spec.default = Some(part.parse()?),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for LogSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self.default.iter().map(|level| level.name().to_string()).collect();
+        parts.extend(self.targets.iter().map(|(target, level)| format!("{}={}", target.name(), level.name())));
+        f.write_str(&parts.join(","))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogOutput {
+    Stdout,
+    Stderr,
+    File(PathBuf),
+    // handed to the forwarder given to init, hyperwarp sends these to streamerd
+    Socket,
+    Off,
+}
+
+impl FromStr for LogOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(LogOutput::Stdout),
+            "stderr" => Ok(LogOutput::Stderr),
+            "socket" => Ok(LogOutput::Socket),
+            "off" | "none" => Ok(LogOutput::Off),
+            _ => match s.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => Ok(LogOutput::File(PathBuf::from(path))),
+                _ => Err(format!("unknown log output {}, expected stdout, stderr, socket, off or file:<path>", s)),
+            },
+        }
+    }
+}
+
+pub type LogForwarder = fn(Target, Level, &str);
+
+struct Sink {
+    name: &'static str,
+    output: LogOutput,
+    file: Option<File>,
+    forwarder: Option<LogForwarder>,
+}
+
+// info everywhere until init runs, hyperwarp logs from dlsym long before its config exists
+#[allow(clippy::declare_interior_mutable_const)]
+const INFO: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static LEVELS: [AtomicU8; TARGETS.len()] = [INFO; TARGETS.len()];
+
+thread_local! {
+    static IN_WRITE: Cell<bool> = const { Cell::new(false) };
+}
+
+lazy_static! {
+    static ref SINK: Mutex<Sink> = Mutex::new(Sink {
+        name: "stellar",
+        output: LogOutput::Stdout,
+        file: None,
+        forwarder: None,
+    });
+}
+
+// fallback is the level for targets the spec doesn't mention, e.g. debug when debug_mode is on
+pub fn init(name: &'static str, spec: &LogSpec, fallback: Level, output: LogOutput, forwarder: Option<LogForwarder>) -> Result<(), String> {
+    let file = match &output {
+        LogOutput::File(path) => Some(OpenOptions::new().create(true).append(true).open(path).map_err(|err| format!("{}: {}", path.display(), err))?),
+        _ => None,
+    };
+    for target in TARGETS {
+        LEVELS[target as usize].store(spec.level_for(target, fallback) as u8, Ordering::Relaxed);
+    }
+    let mut sink = SINK.lock().unwrap();
+    sink.name = name;
+    sink.output = output;
+    sink.file = file;
+    sink.forwarder = forwarder;
+    Ok(())
+}
+
+#[inline]
+pub fn enabled(target: Target, level: Level) -> bool {
+    level as u8 <= LEVELS[target as usize].load(Ordering::Relaxed)
+}
+
+pub fn level(target: Target) -> Level {
+    Level::from_u8(LEVELS[target as usize].load(Ordering::Relaxed))
+}
+
+pub fn write(target: Target, level: Level, args: fmt::Arguments) {
+    // a log call from inside a sink (or a hook the file write goes through) would deadlock, drop it instead
+    if IN_WRITE.with(|in_write| in_write.replace(true)) {
+        return;
+    }
+    write_to_sink(&mut SINK.lock().unwrap(), target, level, args);
+    IN_WRITE.with(|in_write| in_write.set(false));
+}
+
+fn write_to_sink(sink: &mut Sink, target: Target, level: Level, args: fmt::Arguments) {
+    match sink.output {
+        LogOutput::Stdout => println!("[{} {} {}] {}", sink.name, level.name(), target.name(), args),
+        LogOutput::Stderr => eprintln!("[{} {} {}] {}", sink.name, level.name(), target.name(), args),
+        LogOutput::File(_) => {
+            let name = sink.name;
+            if let Some(file) = sink.file.as_mut() {
+                let _ = writeln!(file, "[{} {} {}] {}", name, level.name(), target.name(), args);
+            }
+        },
+        LogOutput::Socket => {
+            if let Some(forwarder) = sink.forwarder {
+                forwarder(target, level, &args.to_string());
+            }
+        },
+        LogOutput::Off => {},
+    }
+}
+
+#[macro_export]
+macro_rules! log_at {
+    ($target:ident, $level:ident, $($arg:tt)+) => {
+        if $crate::logging::enabled($crate::logging::Target::$target, $crate::logging::Level::$level) {
+            $crate::logging::write($crate::logging::Target::$target, $crate::logging::Level::$level, format_args!($($arg)+));
+        }
+    };
+}
+
+// log_info!(Capture, "fb is now {}x{}", width, height)
+#[macro_export]
+macro_rules! log_error {
+    ($target:ident, $($arg:tt)+) => { $crate::log_at!($target, Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($target:ident, $($arg:tt)+) => { $crate::log_at!($target, Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($target:ident, $($arg:tt)+) => { $crate::log_at!($target, Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($target:ident, $($arg:tt)+) => { $crate::log_at!($target, Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! log_trace {
+    ($target:ident, $($arg:tt)+) => { $crate::log_at!($target, Trace, $($arg)+) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_parse_defaults_and_targets() {
+        let spec: LogSpec = " info, hooks=debug ,net = trace,".parse().unwrap();
+        assert_eq!(spec.default, Some(Level::Info));
+        assert_eq!(spec.targets, vec![(Target::Hooks, Level::Debug), (Target::Net, Level::Trace)]);
+        assert_eq!(spec.to_string(), "info,hooks=debug,net=trace");
+        assert_eq!(spec.to_string().parse::<LogSpec>().unwrap(), spec);
+        assert_eq!("".parse::<LogSpec>().unwrap(), LogSpec::default());
+        assert_eq!("warning,input=none".parse::<LogSpec>().unwrap().to_string(), "warn,input=off");
+    }
+
+    #[test]
+    fn later_entries_win_and_unset_targets_fall_back() {
+        let spec: LogSpec = "capture=error,capture=debug".parse().unwrap();
+        assert_eq!(spec.level_for(Target::Capture, Level::Info), Level::Debug);
+        // no bare level, so the caller's fallback applies
+        assert_eq!(spec.level_for(Target::Net, Level::Warn), Level::Warn);
+        let spec: LogSpec = "trace,hooks=off".parse().unwrap();
+        assert_eq!(spec.level_for(Target::Hooks, Level::Info), Level::Off);
+        assert_eq!(spec.level_for(Target::Pipeline, Level::Info), Level::Trace);
+    }
+
+    #[test]
+    fn bad_specs_are_rejected() {
+        assert!("loud".parse::<LogSpec>().is_err());
+        assert!("gpu=debug".parse::<LogSpec>().is_err());
+        assert!("hooks=verbose".parse::<LogSpec>().is_err());
+        assert!("hooks=".parse::<LogSpec>().is_err());
+    }
+
+    #[test]
+    fn outputs_parse() {
+        assert_eq!("stderr".parse::<LogOutput>(), Ok(LogOutput::Stderr));
+        assert_eq!("none".parse::<LogOutput>(), Ok(LogOutput::Off));
+        assert_eq!("file:/tmp/hw.log".parse::<LogOutput>(), Ok(LogOutput::File(PathBuf::from("/tmp/hw.log"))));
+        assert!("file:".parse::<LogOutput>().is_err());
+        assert!("syslog".parse::<LogOutput>().is_err());
+    }
+}
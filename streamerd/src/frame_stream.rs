@@ -0,0 +1,145 @@
+use std::{borrow::Cow, io::{self, Read, Write}, net::{Shutdown, SocketAddr, TcpStream}, sync::{Arc, Mutex}, thread::{self, JoinHandle}, time::Duration};
+
+use crossbeam_channel::Sender;
+use stellar_protocol::auth::{SessionCipher, SEAL_OVERHEAD};
+use stellar_protocol::frame_stream::{self, FrameHeader, HEADER_LEN, KEEPALIVE_INTERVAL};
+use stellar_protocol::protocol::FrameLayout;
+use stellar_shared::{log_debug, log_info, log_warn};
+
+use crate::shm::FrameSlotGuard;
+use crate::streamerd::InternalMessage;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// hyperwarp sends a keepalive every KEEPALIVE_INTERVAL when there's no frame, missing a few means the link is dead
+const READ_TIMEOUT: Duration = Duration::from_secs(KEEPALIVE_INTERVAL.as_secs() * 5);
+
+// the latest frame waiting for the pipeline, either still in hyperwarp's shm or read off the frame stream
+pub enum PendingFrame {
+    Slot(FrameSlotGuard, FrameLayout),
+    Owned(Vec<u8>, FrameLayout),
+}
+
+impl PendingFrame {
+    pub fn len(&self) -> usize {
+        match self {
+            PendingFrame::Slot(guard, _) => guard.len(),
+            PendingFrame::Owned(data, _) => data.len(),
+        }
+    }
+
+    pub fn layout(&self) -> FrameLayout {
+        match self {
+            PendingFrame::Slot(_, layout) | PendingFrame::Owned(_, layout) => *layout,
+        }
+    }
+}
+
+impl AsRef<[u8]> for PendingFrame {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            PendingFrame::Slot(guard, _) => guard.as_ref(),
+            PendingFrame::Owned(data, _) => data,
+        }
+    }
+}
+
+#[derive(Default)]
+struct StreamSocket {
+    stream: Option<TcpStream>,
+    stopped: bool,
+}
+
+// the thread reading the frame stream and its socket so a reconnect can cut it off
+pub struct FrameStreamHandle {
+    thread: JoinHandle<()>,
+    socket: Arc<Mutex<StreamSocket>>,
+}
+
+impl FrameStreamHandle {
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    // shutting the socket down wakes the blocked read, once joined nothing stale lands in frame anymore
+    pub fn stop(self) {
+        {
+            let mut socket = self.socket.lock().unwrap();
+            socket.stopped = true;
+            if let Some(stream) = &socket.stream {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+        let _ = self.thread.join();
+    }
+}
+
+// connects to hyperwarp's frame stream with the ticket from FrameStreamTicket and keeps the newest frame in frame
+// a frame the pipeline hasn't pushed yet is just replaced, hyperwarp drops on its side when we fall further behind
+// cipher has to be there exactly when our control connection is sealed, hyperwarp seals the frames then
+pub fn spawn(addr: SocketAddr, ticket: Vec<u8>, cipher: Option<SessionCipher>, frame: Arc<Mutex<Option<PendingFrame>>>, streaming_cmd_queue: Sender<InternalMessage>) -> io::Result<FrameStreamHandle> {
+    let socket = Arc::new(Mutex::new(StreamSocket::default()));
+    let thread_socket = socket.clone();
+    let thread = thread::Builder::new().name("frame-stream".to_string()).spawn(move || {
+        log_info!(Net, "Connecting to Hyperwarp frame stream at {}", addr);
+        match receive_frames(addr, &ticket, cipher, &thread_socket, &frame, &streaming_cmd_queue) {
+            Ok(()) => log_info!(Net, "Hyperwarp frame stream closed"),
+            Err(err) => log_warn!(Net, "Hyperwarp frame stream at {} ended: {}", addr, err),
+        }
+    })?;
+    Ok(FrameStreamHandle { thread, socket })
+}
+
+fn receive_frames(addr: SocketAddr, ticket: &[u8], mut cipher: Option<SessionCipher>, socket: &Mutex<StreamSocket>, frame: &Mutex<Option<PendingFrame>>, streaming_cmd_queue: &Sender<InternalMessage>) -> io::Result<()> {
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    {
+        let mut socket = socket.lock().unwrap();
+        // stopped while we were still connecting
+        if socket.stopped {
+            return Ok(());
+        }
+        socket.stream = Some(stream.try_clone()?);
+    }
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.write_all(ticket)?;
+    let overhead = if cipher.is_some() { SEAL_OVERHEAD } else { 0 };
+    let mut header = vec![0u8; HEADER_LEN + overhead];
+    let mut payload: Vec<u8> = Vec::new();
+    let mut last_sequence = 0;
+    loop {
+        match stream.read_exact(&mut header) {
+            Ok(()) => {}
+            // hyperwarp closing on us between frames is a normal end
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        }
+        let header = open(&mut cipher, &header)?;
+        // sealing keeps the length so this is always HEADER_LEN
+        let header = FrameHeader::decode(header.as_ref().try_into().unwrap()).map_err(invalid_data)?;
+        payload.resize(header.payload_len as usize + overhead, 0);
+        stream.read_exact(&mut payload)?;
+        if header.is_keepalive() {
+            // still has to go through the cipher so the counters stay in step
+            open(&mut cipher, &payload)?;
+            continue;
+        }
+        let raw = frame_stream::decompress(&header, &open(&mut cipher, &payload)?).map_err(invalid_data)?;
+        if header.sequence != last_sequence + 1 {
+            log_debug!(Net, "frame stream skipped from {} to {}", last_sequence, header.sequence);
+        }
+        last_sequence = header.sequence;
+        frame.lock().unwrap().replace(PendingFrame::Owned(raw, header.layout));
+        let _ = streaming_cmd_queue.send(InternalMessage::NewFrameAvailable);
+    }
+}
+
+fn open<'a>(cipher: &mut Option<SessionCipher>, data: &'a [u8]) -> io::Result<Cow<'a, [u8]>> {
+    match cipher {
+        Some(cipher) => cipher.open(data).map(Cow::Owned).map_err(invalid_data),
+        None => Ok(Cow::Borrowed(data)),
+    }
+}
+
+fn invalid_data(err: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
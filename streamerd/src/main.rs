@@ -1,4 +1,5 @@
 use clap::Parser;
+use stellar_shared::{log_debug, log_error, log_info, logging};
 
 // allocator debug
 // #[cfg(not(target_env = "msvc"))]
@@ -9,16 +10,35 @@ use clap::Parser;
 // static GLOBAL: Jemalloc = Jemalloc;
 
 use crate::streamerd::{Streamer, StreamerConfig};
+pub mod frame_stream;
 pub mod producer;
+pub mod shm;
 pub mod webrtc;
 pub mod streamerd;
 pub mod test;
+pub mod window_meta;
 
 fn main() {
     // test::test_networking();
-    println!("Starting streamer daemon v{}",env!("CARGO_PKG_VERSION"));
-    let config = StreamerConfig::parse();
-    println!("Loaded config: {:?}", config);
+    let mut config = StreamerConfig::parse();
+    if config.list_sessions {
+        if let Err(err) = streamerd::print_sessions() {
+            eprintln!("could not list sessions: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Err(err) = logging::init("streamerd", &config.log.clone().unwrap_or_default(), config.log_fallback_level(), config.log_output.clone(), None) {
+        eprintln!("could not set up logging: {}", err);
+    }
+    log_info!(General, "Starting streamer daemon v{}",env!("CARGO_PKG_VERSION"));
+    if config.mode.is_external_capture() {
+        if let Err(err) = config.resolve_session() {
+            log_error!(General, "{}", err);
+            std::process::exit(1);
+        }
+    }
+    log_debug!(General, "Loaded config: {:?}", config);
     let mut streamerd = Streamer::new(config);
     streamerd.run();
 }
\ No newline at end of file
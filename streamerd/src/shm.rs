@@ -0,0 +1,93 @@
+use std::{fs::File, os::fd::AsRawFd, path::Path, sync::{Arc, Mutex}};
+
+use message_io::node::NodeHandler;
+use stellar_protocol::protocol::frame_slot_index;
+
+use crate::streamerd::StreamerSignal;
+
+// read only view of the shimg file hyperwarp writes frames into
+pub struct ShmFrameMapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// the mapping is never written to from our side, hyperwarp won't touch a slot until we release it
+unsafe impl Send for ShmFrameMapping {}
+unsafe impl Sync for ShmFrameMapping {}
+
+impl ShmFrameMapping {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            anyhow::bail!("shm file {} is empty", path.display());
+        }
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_SHARED, file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            anyhow::bail!("mmap of {} failed: {}", path.display(), std::io::Error::last_os_error());
+        }
+        Ok(ShmFrameMapping { ptr, len })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn slot(&self, slot: usize, slot_size: usize) -> Option<&[u8]> {
+        let start = slot.checked_mul(slot_size)?;
+        if start + slot_size > self.len {
+            return None;
+        }
+        Some(unsafe { std::slice::from_raw_parts((self.ptr as *const u8).add(start), slot_size) })
+    }
+}
+
+impl Drop for ShmFrameMapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+// a frame sitting in one of hyperwarp's slots, the slot is handed back when this is dropped
+// slot is the id hyperwarp sent including its generation, only the index part says where it is
+// this is what gets wrapped into gstreamer buffers so no copies happen
+pub struct FrameSlotGuard {
+    mapping: Arc<ShmFrameMapping>,
+    slot: u32,
+    slot_size: usize,
+    handler: Option<Arc<Mutex<NodeHandler<StreamerSignal>>>>,
+}
+
+impl FrameSlotGuard {
+    pub fn new(mapping: Arc<ShmFrameMapping>, slot: u32, slot_size: usize, handler: Option<Arc<Mutex<NodeHandler<StreamerSignal>>>>) -> Option<Self> {
+        // make sure as_ref can never go out of bounds
+        mapping.slot(frame_slot_index(slot), slot_size)?;
+        Some(FrameSlotGuard { mapping, slot, slot_size, handler })
+    }
+
+    pub fn slot(&self) -> u32 {
+        self.slot
+    }
+
+    pub fn len(&self) -> usize {
+        self.slot_size
+    }
+}
+
+impl AsRef<[u8]> for FrameSlotGuard {
+    fn as_ref(&self) -> &[u8] {
+        self.mapping.slot(frame_slot_index(self.slot), self.slot_size).unwrap()
+    }
+}
+
+impl Drop for FrameSlotGuard {
+    fn drop(&mut self) {
+        if let Some(handler) = &self.handler {
+            handler.lock().unwrap().signals().send(StreamerSignal::ReleaseFrameSlot(self.slot));
+        }
+    }
+}
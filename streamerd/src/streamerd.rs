@@ -13,15 +13,23 @@ use gstreamer::{prelude::*, Buffer, BufferFlags, DebugGraphDetails, Element, Err
 use gstreamer_app::AppSrc;
 use gstreamer_video::{prelude::*, VideoColorimetry, VideoFlags, VideoInfo, VideoInterlaceMode};
 use gstreamer_webrtc::{WebRTCDataChannel, WebRTCPeerConnectionState, WebRTCSessionDescription};
-use message_io::{adapters::unix_socket::{create_null_socketaddr, UnixSocketConnectConfig}, network::{adapter::NetworkAddr, Endpoint}, node::{self, NodeEvent, NodeHandler}, util::thread};
+use message_io::{adapters::unix_socket::{create_null_socketaddr, UnixSocketConnectConfig}, network::{adapter::NetworkAddr, Endpoint, Transport}, node::{self, NodeEvent, NodeHandler}, util::thread};
 
 use rust_socketio::{client::Client, ClientBuilder};
 use serde_json::json;
-use stellar_protocol::protocol::{create_default_acl, may_mutate_pipeline, streamer_state_to_u8, EncodingPreset, GraphicsAPI, InputEvent, InputEventPayload, PipelineOptimization, PrivligeDefinition, StellarChannel, StellarDirectControlMessage, StellarFrontendMessage, StellarMessage, StreamerState};
+use stellar_protocol::auth::{self, AuthToken, SessionCipher, Side};
+use stellar_protocol::session::{self, SessionDescriptor};
+use stellar_protocol::protocol::{create_default_acl, frame_slot_index, CaptureTarget, FrameCompression, FramePixelFormat, may_mutate_pipeline, streamer_state_to_u8, EncodingPreset, GraphicsAPI, InputEvent, InputEventPayload, PipelineOptimization, PrivligeDefinition, StellarChannel, StellarDirectControlMessage, StellarFrontendMessage, StellarMessage, StreamerState};
 use stellar_shared::constants::{linux::{WEB_BTN_TO_LINUX_BUTTON, decode_keyevent_code_to_evdev}, sdl2::{decode_keyevent_code_int, decode_keyevent_key_int}};
+use stellar_shared::logging::{self, Level, LogOutput, LogSpec, Target};
+use stellar_shared::{log_debug, log_error, log_info, log_trace, log_warn};
 
-use std::time::Instant;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
 
+use crate::frame_stream::{self, FrameStreamHandle, PendingFrame};
+use crate::window_meta::WindowMetadata;
+use crate::shm::{FrameSlotGuard, ShmFrameMapping};
 use crate::webrtc::{self, WebRTCPeer, WebRTCPreprocessor};
 
 // https://docs.rs/clap/latest/clap/_derive/_cookbook/git_derive/index.html
@@ -65,6 +73,7 @@ pub enum InternalMessage {
     ProcessDirectMessage(String, stellar_protocol::protocol::StellarDirectControlMessage),
     SendDirectMessage(String, String, stellar_protocol::protocol::StellarDirectControlMessage),
     BroadcastDirectMessage(String, stellar_protocol::protocol::StellarDirectControlMessage),
+    NewFrameAvailable,
 }
 
 pub struct SystemHints {
@@ -73,6 +82,16 @@ pub struct SystemHints {
 
 pub const INTERNAL_DEBUG: bool = false;
 
+// keeps us from being expired by hyperwarp (HW_NET_PEER_TIMEOUT_MS), it echoes each one back
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+// nothing at all from hyperwarp for this long and we assume the connection is dead and start over
+const HYPERWARP_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_millis(250);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+// with nothing new from hyperwarp the last frame is pushed again this often so the encoder keeps producing output
+// kept low so an idle game doesn't cost a full encode per vsync
+const IDLE_REPUSH_INTERVAL: Duration = Duration::from_millis(250);
+
 #[derive(Parser, Debug)]
 #[command(version, about = "rust streaming daemon using gstreamer", long_about = None)]
 pub struct StreamerConfig {
@@ -80,17 +99,31 @@ pub struct StreamerConfig {
     pub mode: OperationMode,
     #[arg(short, long, help = "Socket to connect to for Hyperwarp")]
     socket: Option<PathBuf>,
+    #[arg(long = "attach", conflicts_with_all = ["socket", "connect"], help = "find the Hyperwarp session to connect to by exe name or path, session id or pid, see --list-sessions")]
+    pub attach: Option<String>,
+    #[arg(long = "list-sessions", help = "list the Hyperwarp sessions running on this machine and exit")]
+    pub list_sessions: bool,
+    #[arg(long = "connect", env = "STREAMERD_CONNECT", help = "reach Hyperwarp over the network instead of its unix socket: tcp:<host:port> or udp:<host:port>")]
+    pub connect: Option<HyperwarpAddr>,
+    #[arg(long = "token", env = "HW_AUTH_TOKEN", help = "token Hyperwarp asks for on network connections, it writes a generated one to $XDG_RUNTIME_DIR/hyperwarp/<session>.token when it wasn't given one")]
+    pub token: Option<AuthToken>,
+    #[arg(long = "token-file", help = "read the token for --connect from this file")]
+    pub token_file: Option<PathBuf>,
+    #[arg(long = "encrypt", help = "encrypt the --connect connection once authenticated, always on for udp")]
+    pub encrypt: bool,
+    #[arg(long = "frame-compression", default_value_t = FrameCompression::Lz4, help = "how Hyperwarp packs frames it streams to a --connect streamerd: lz4 or none")]
+    pub frame_compression: FrameCompression,
     #[arg(short = 't', long = "test", help = "Test mode", default_value_t = false)]
     test_mode: bool,
     #[arg(long = "realtime", help = "experimental realtime tricks", default_value_t = true)]
     experimental_realtime: bool,
-    #[arg(short, long, default_value_t = GraphicsAPI::Unknown, help = "Graphics api to assume. Will autodetect if not specified.")]
+    #[arg(short, long, default_value_t = GraphicsAPI::Unknown, help = "Unused, every frame from Hyperwarp says whether it needs flipping. Kept so old launch scripts still work.")]
     pub graphics_api: GraphicsAPI,
     #[arg(short = 'u', long = "url", default_value_t = { "http://127.0.0.1:8001".to_string() }, help = "Stargate address to connect to. Needed for signaling and other small things.")]
     stargate_addr: String,
-    #[arg(long = "secret", env = "STARGATE_SECRET", help = "Session secret to authenticate and elevate when connecting to Stargate server.")]
-    secret: String,
-    #[arg(short = 'p', long = "pid", env = "TARGET_PROCESS_PID", help = "determine socket based off pid instead")]
+    #[arg(long = "secret", env = "STARGATE_SECRET", required_unless_present = "list_sessions", help = "Session secret to authenticate and elevate when connecting to Stargate server.")]
+    secret: Option<String>,
+    #[arg(short = 'p', long = "pid", env = "TARGET_PROCESS_PID", help = "determine socket based off pid instead, same as --attach <pid>")]
     pid: Option<u32>,
     #[arg(short = 'd', long = "debug", help = "Ask process for debug info as well.")]
     debug: bool,
@@ -110,6 +143,176 @@ pub struct StreamerConfig {
     height: u32,
     #[arg(long, help = "render node to use on the Wayland compositor")]
     render_node: Option<String>,
+    #[arg(long = "capture-target", help = "which window to capture when the app has several: largest, recent, id:<window id> or title:<regex>")]
+    pub capture_target: Option<CaptureTarget>,
+    #[arg(long = "log", env = "STREAMERD_LOG", help = "log levels, a default level and/or target=level pairs like info,pipeline=debug (targets: general, hooks, input, capture, net, pipeline)")]
+    pub log: Option<LogSpec>,
+    #[arg(long = "log-output", default_value = "stdout", help = "where logs go: stdout, stderr, off or file:<path>")]
+    pub log_output: LogOutput,
+}
+
+impl StreamerConfig {
+    // --debug also turns our own logs up, same as DEBUG_HW does for hyperwarp
+    pub fn log_fallback_level(&self) -> Level {
+        if self.debug {
+            Level::Debug
+        } else {
+            Level::Info
+        }
+    }
+
+    // --socket and --connect are used as is, --attach or --pid go through the session descriptors hyperwarp publishes
+    // with none of them we take the only running session if there's exactly one
+    pub fn resolve_session(&mut self) -> Result<()> {
+        if self.socket.is_some() || self.connect.is_some() {
+            return Ok(());
+        }
+        let query = self.attach.clone().or_else(|| self.pid.map(|pid| pid.to_string()));
+        let descriptor = match &query {
+            Some(query) => session::find_session(query).map_err(anyhow::Error::msg)?,
+            None => {
+                let mut sessions = session::list_sessions()?;
+                if sessions.len() != 1 {
+                    bail!("{} Hyperwarp sessions running, pick one with --attach or pass --socket (see --list-sessions)", sessions.len());
+                }
+                sessions.remove(0)
+            }
+        };
+        log_info!(Net, "Attaching to {} (pid {}, session {}) on {}", descriptor.exe, descriptor.pid, descriptor.session_id, descriptor.socket_path.display());
+        self.socket = Some(descriptor.socket_path);
+        Ok(())
+    }
+
+    // udp peers are only known by their address so hyperwarp won't take them unencrypted
+    pub fn encrypt_link(&self) -> bool {
+        self.encrypt || matches!(self.connect, Some(HyperwarpAddr::Udp(_)))
+    }
+
+    // --token wins over --token-file
+    pub fn auth_token(&self) -> Result<Option<AuthToken>> {
+        if let Some(token) = &self.token {
+            return Ok(Some(token.clone()));
+        }
+        match &self.token_file {
+            Some(path) => match std::fs::read_to_string(path)?.parse::<AuthToken>() {
+                Ok(token) => Ok(Some(token)),
+                Err(err) => bail!("{}: {}", path.display(), err),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+// --list-sessions, plain stdout since it runs before logging is set up
+pub fn print_sessions() -> Result<()> {
+    let sessions = session::list_sessions()?;
+    if sessions.is_empty() {
+        println!("no Hyperwarp sessions in {}", session::session_dir().display());
+        return Ok(());
+    }
+    let now = session::now();
+    println!("{:<8} {:<24} {:<10} {:<8} {:<32} {}", "PID", "SESSION", "API", "UPTIME", "EXE", "SOCKET");
+    for SessionDescriptor { pid, exe, session_id, socket_path, graphics_api, started_at, .. } in sessions {
+        let uptime = now.saturating_sub(started_at);
+        println!("{:<8} {:<24} {:<10} {:<8} {:<32} {}", pid, session_id, graphics_api.to_string(), format!("{}s", uptime), exe, socket_path.display());
+    }
+    Ok(())
+}
+
+// where Hyperwarp's control server is when it isn't on this machine's unix socket
+#[derive(Debug, Clone)]
+pub enum HyperwarpAddr {
+    Tcp(SocketAddr),
+    Udp(SocketAddr),
+}
+
+impl HyperwarpAddr {
+    pub fn addr(&self) -> SocketAddr {
+        match self {
+            HyperwarpAddr::Tcp(addr) | HyperwarpAddr::Udp(addr) => *addr,
+        }
+    }
+}
+
+impl FromStr for HyperwarpAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, addr) = s.split_once(':').ok_or_else(|| format!("expected tcp:<host:port> or udp:<host:port>, got {}", s))?;
+        // hostnames are resolved once up front, containers usually hand us a service name
+        let addr = addr
+            .to_socket_addrs()
+            .map_err(|err| format!("could not resolve {}: {}", addr, err))?
+            .next()
+            .ok_or_else(|| format!("{} resolved to nothing", addr))?;
+        match kind {
+            "tcp" => Ok(HyperwarpAddr::Tcp(addr)),
+            "udp" => Ok(HyperwarpAddr::Udp(addr)),
+            _ => Err(format!("unknown transport {}, expected tcp or udp", kind)),
+        }
+    }
+}
+
+// the endpoint comes back right away, for tcp Connected says later whether it actually worked
+fn connect_to_hyperwarp(handler: &NodeHandler<StreamerSignal>, config: &StreamerConfig) -> Result<Endpoint> {
+    let endpoint = match &config.connect {
+        Some(addr) => {
+            log_info!(Net, "Connecting to Hyperwarp at {:?}", addr);
+            match addr {
+                HyperwarpAddr::Tcp(addr) => handler.network().connect(Transport::FramedTcp, *addr)?.0,
+                HyperwarpAddr::Udp(addr) => handler.network().connect(Transport::Udp, *addr)?.0,
+            }
+        }
+        None => {
+            let socket_path = config.socket.clone().expect("socket path not set or not valid");
+            log_info!(Net, "Connecting to socket: {}", socket_path.display());
+            let temp_socket_path: PathBuf = format!("/tmp/hyperwarp/client-{}.sock", std::process::id()).into();
+            // left behind by the connection we're replacing, binding fails if it's still there
+            let _ = std::fs::remove_file(&temp_socket_path);
+            handler.network().connect_with(message_io::network::TransportConnect::UnixSocketDatagram(UnixSocketConnectConfig::new(temp_socket_path)), NetworkAddr::Path(socket_path))?.0
+        }
+    };
+    Ok(endpoint)
+}
+
+// doubles with every failed attempt, reset once hyperwarp answers again
+fn schedule_reconnect(handler: &Mutex<NodeHandler<StreamerSignal>>, attempts: &mut u32) {
+    let delay = RECONNECT_BACKOFF_MIN.saturating_mul(1 << (*attempts).min(6)).min(RECONNECT_BACKOFF_MAX);
+    log_info!(Net, "Reconnecting to Hyperwarp in {:?}", delay);
+    handler.lock().unwrap().signals().send_with_timer(StreamerSignal::Reconnect, delay);
+    *attempts += 1;
+}
+
+// everything going to Hyperwarp goes through here so it gets sealed once the link is encrypted
+fn send_to_hyperwarp(handler: &Mutex<NodeHandler<StreamerSignal>>, endpoint: Option<Endpoint>, cipher: &mut Option<SessionCipher>, message: &StellarMessage) {
+    if let Some(endpoint) = endpoint {
+        let data = stellar_protocol::serialize(message);
+        let data = match cipher {
+            Some(cipher) => cipher.seal(&data),
+            None => data,
+        };
+        handler.lock().unwrap().network().send(endpoint, &data);
+    }
+}
+
+// what we ask for once Hyperwarp lets us talk, right away on the unix socket and after the auth result otherwise
+fn send_hyperwarp_setup(handler: &Mutex<NodeHandler<StreamerSignal>>, endpoint: Endpoint, cipher: &mut Option<SessionCipher>, config: &StreamerConfig) {
+    let endpoint = Some(endpoint);
+    log_debug!(Net, "sending initial handshake request");
+    send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::HandshakeRequest);
+    send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::HelloName("Testing protocol".to_string()));
+    // over the network frames come through the frame stream once the handshake says where it is
+    if config.connect.is_none() {
+        send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::SubscribeChannel(StellarChannel::Frame));
+    }
+    send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::SubscribeChannel(StellarChannel::Synchornizations));
+    send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::SubscribeChannel(StellarChannel::WindowChanges));
+    send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::SubscribeChannel(StellarChannel::Logs));
+    if let Some(capture_target) = &config.capture_target {
+        log_debug!(Net, "requesting capture target {}", capture_target);
+        send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::SetCaptureTarget(capture_target.clone()));
+    }
+    send_to_hyperwarp(handler, endpoint, cipher, &StellarMessage::WindowListRequest);
 }
 
 impl std::fmt::Display for OperationMode {
@@ -127,6 +330,10 @@ pub enum StreamerSignal {
     DebugInfoRequest,
     SocketCreated(Arc<Mutex<Client>>),
     ForwardedDataChannelMessage(String, stellar_protocol::protocol::StellarDirectControlMessage),
+    ReleaseFrameSlot(u32),
+    Heartbeat,
+    // drop whatever is left of the connection and dial hyperwarp again, the handshake after it rebuilds our state
+    Reconnect,
 }
 
 pub struct DataChannelTracker {
@@ -179,7 +386,7 @@ pub struct Streamer {
     pub streaming_command_recv: Receiver<InternalMessage>,
     pub client_comms_command_queue: Sender<InternalMessage>,
     pub client_comms_command_recv: Receiver<InternalMessage>,
-    pub frame: Arc<RwLock<Vec<u8>>>,
+    pub frame: Arc<Mutex<Option<PendingFrame>>>, // latest frame from hyperwarp that has not been pushed yet
     pub socketio_client: Option<Arc<Mutex<Client>>>,
     pub data_channel_tracker: Arc<Mutex<DataChannelTracker>>,
     pub acls: DashMap<String, PrivligeDefinition>,
@@ -209,6 +416,15 @@ pub fn build_capsfilter(caps: gstreamer::Caps) -> anyhow::Result<gstreamer::Elem
 
 pub const DEFAULT_ACL: PrivligeDefinition = create_default_acl();
 
+pub fn video_format_for_pixel_format(pixel_format: FramePixelFormat) -> gstreamer_video::VideoFormat {
+    match pixel_format {
+        FramePixelFormat::Rgba => gstreamer_video::VideoFormat::Rgba,
+        FramePixelFormat::Rgbx => gstreamer_video::VideoFormat::Rgbx,
+        FramePixelFormat::Bgra => gstreamer_video::VideoFormat::Bgra,
+        FramePixelFormat::Bgrx => gstreamer_video::VideoFormat::Bgrx,
+    }
+}
+
 pub fn should_forward_data_channel_message(message: &StellarDirectControlMessage) -> bool {
     if matches!(message, StellarDirectControlMessage::AddGamepad { .. }) {
         return true;
@@ -242,7 +458,7 @@ impl Streamer {
             messaging_handler: None,
             streaming_command_queue: sender,
             streaming_command_recv: receiver,
-            frame: Arc::new(RwLock::new(vec![])),
+            frame: Arc::new(Mutex::new(None)),
             socketio_client: None,
             data_channel_tracker: Arc::new(Mutex::new(DataChannelTracker::new())),
             acls: DashMap::new(),
@@ -264,9 +480,9 @@ impl Streamer {
     }*/
 
     pub fn run(&mut self) {
-        println!("Starting streamer processing thread");
+        log_debug!(General, "Starting streamer processing thread");
         if self.config.mode == OperationMode::Hyperwarp {
-            println!("Starting Hyperwarp client thread");
+            log_debug!(Net, "Starting Hyperwarp client thread");
             let hyperwarp_thread_handle = self.start_hyperwarp_client_thread();
             self.handles.push(hyperwarp_thread_handle);
         }
@@ -285,7 +501,7 @@ impl Streamer {
         let socket =  self.get_socket();
         let error_msg = StellarFrontendMessage::Error { error: message.to_string() };
         if let Err(err) = socket.emit("send_to", json!([socket_id, error_msg])) {
-            println!("Error complaining to socket: {:?}", err);
+            log_error!(General, "Error complaining to socket: {:?}", err);
         }
     }
 
@@ -301,7 +517,7 @@ impl Streamer {
         let main_thread_queue_sender = self.streaming_command_queue.clone();
         let handler_lock_option = self.messaging_handler.clone();
         std::thread::spawn(move || {
-            println!("Starting data channel message processing thread");
+            log_debug!(General, "Starting data channel message processing thread");
             while let Ok(msg) = my_comms_queue.recv() {
                 match msg {
                     InternalMessage::ProcessDirectMessage(source_socket_id, message ) => {
@@ -343,17 +559,24 @@ impl Streamer {
                                     handler.signals().send(StreamerSignal::ProcessInput(input_event));
                                 }
                             },
+                            StellarDirectControlMessage::MouseScroll { delta_x, delta_y, timestamp } => {
+                                // raw browser deltas, each platform scales these itself
+                                let input_event = InputEvent::new(InputEventPayload::MouseScroll { x: delta_x, y: delta_y });
+                                if let Some(handler) = handler_option {
+                                    handler.signals().send(StreamerSignal::ProcessInput(input_event));
+                                }
+                            },
                             _ => {
                                 // check if is forwardable
                                 if should_forward_data_channel_message(&message) {
                                     if let Some(handler) = handler_option {
                                         handler.signals().send(StreamerSignal::ForwardedDataChannelMessage(source_socket_id.clone(), message));
                                     } else {
-                                        println!("Unhandled direct message {:?} from socket id {:?} (nowhere to forward)", message, source_socket_id);
+                                        log_warn!(General, "Unhandled direct message {:?} from socket id {:?} (nowhere to forward)", message, source_socket_id);
                                         // this just means an internal streamerd impl has not handled this in addition for other modes
                                     }
                                 } else {
-                                    println!("Unhandled direct message {:?} from socket id {:?}", message, source_socket_id);
+                                    log_warn!(General, "Unhandled direct message {:?} from socket id {:?}", message, source_socket_id);
                                 }
                             }
                         }
@@ -363,7 +586,7 @@ impl Streamer {
                     }
                 }
             }
-            println!("data channel work queue died");
+            log_debug!(General, "data channel work queue died");
         })
     }
 
@@ -375,7 +598,7 @@ impl Streamer {
         // println!("grabbing a main loop");
         // let main_loop = glib::MainLoop::new(None, false);
 
-        println!("initalizing streaming");
+        log_debug!(Pipeline, "initalizing streaming");
         gstreamer::init().expect("library load failed");
 
         if self.config.experimental_realtime {
@@ -387,7 +610,7 @@ impl Streamer {
         let pipeline = gstreamer::Pipeline::default();
         
 
-        println!("pipeline initalizing");
+        log_debug!(Pipeline, "pipeline initalizing");
 
         // requires --gst-enable-gst-debug at build time for gstreamer
         pipeline.debug_to_dot_data(DebugGraphDetails::all());
@@ -511,12 +734,12 @@ impl Streamer {
         pipeline.add_many([&videoconvert, &videoflip, &debug_tee]).expect("adding els failed");
         gstreamer::Element::link_many(&initial_link).expect("linking failed");
 
-        /*println!("create queue before preprocessor");
+        /*log_debug!(Pipeline, "create queue before preprocessor");
         let queue = gstreamer::ElementFactory::make("queue").build().expect("could not create queue element");
         pipeline.add(&queue).expect("adding elements to pipeline failed");
         gstreamer::Element::link_many([&debug_tee, &queue]).expect("linking failed");*/
 
-        println!("initing preprocessor");
+        log_debug!(Pipeline, "initing preprocessor");
 
         let mut preprocessor = WebRTCPreprocessor::new_preset(self.config.encoder, self.config.optimizations, self.config.mode);
         preprocessor.set_config(config.clone());
@@ -526,7 +749,7 @@ impl Streamer {
         }
         preprocessor.set_default_settings();
         if let Some(mtu) = self.config.mtu {
-            println!("setting mtu to {}", mtu);
+            log_debug!(Pipeline, "setting mtu to {}", mtu);
             preprocessor.payloader.set_property("mtu", mtu);
         }
         preprocessor.attach_to_pipeline(&pipeline, {
@@ -538,123 +761,73 @@ impl Streamer {
             }
         });
 
-        println!("setting up second tee element");
+        log_debug!(Pipeline, "setting up second tee element");
 
         let video_tee = gstreamer::ElementFactory::make("tee").property("allow-not-linked", true).name("video_tee").build().expect("could not create video tee");
         // connect video tee to preprocessor\
         pipeline.add(&video_tee).expect("adding video tee to pipeline failed");
         gstreamer::Element::link_many([preprocessor.get_last_element(), &video_tee]).expect("linking video tee to preprocessor failed");
 
-        println!("pipeline shared section linked");
+        log_debug!(Pipeline, "pipeline shared section linked");
 
-        println!("getting bus and clock");
+        log_debug!(Pipeline, "getting bus and clock");
 
         let bus = pipeline.bus().expect("Bus not found?");
         let sys_clock = gstreamer::SystemClock::obtain();
 
-        println!("begin event ingest");
+        log_debug!(Pipeline, "begin event ingest");
 
         let mut should_update = false;
         let mut socket_connected = false;
         let mut socket_authed = false;
         let mut wayland_display: Option<String> = None;
-        let mut graphics_api = config.graphics_api;
+        let mut pixel_format = FramePixelFormat::default();
         let mut streamer_state = StreamerState::Handshaking;
 
-        let update_frame_func = |appsrc: &AppSrc, video_info: &VideoInfo| {
+        // last buffer we pushed, re-pushed at IDLE_REPUSH_INTERVAL while hyperwarp skips unchanged frames
+        let mut last_buffer: Option<Buffer> = None;
+        let mut last_push = Instant::now();
+        // whether videoflip is flipping right now, every frame says whether it needs it
+        let mut flipping = false;
+
+        let mut update_frame_func = |appsrc: &AppSrc, video_info: &VideoInfo| {
             
             // benchmark thing
             // let starting = Instant::now();
-            
-            let mut buffer = gstreamer::Buffer::with_size(video_info.size()).unwrap();
-            
-            // set pts to current time
-            {
-                let buffer = buffer.get_mut().unwrap();
-                // buffer.set_pts(sys_clock.time());
-                // buffer.set_flags(BufferFlags::LIVE);
-                let mut vframe = gstreamer_video::VideoFrameRef::from_buffer_ref_writable(buffer, &video_info).unwrap();
-                // Remember some values from the frame for later usage
-                let width = vframe.width() as usize;
-                let height = vframe.height() as usize;
-
-                // Each line of the first plane has this many bytes
-                let stride = vframe.plane_stride()[0] as usize;
-                // let buf_mut = vframe.planes_data_mut();
-                let frame_reader = self_frame.read().unwrap();
-
-                // println!("producing frame of {}x{}", width, height);
-                // Iterate over each of the height many lines of length stride
-                for line in vframe
-                    .plane_data_mut(0)
-                    .unwrap()
-                    .chunks_exact_mut(stride * height)
-                    .take(1)
-                {
-                    // Iterate over each pixel of 4 bytes in that line
-                    /*let chunk = line[..(4 * width)].chunks_exact_mut(4);
-                    let mut x = 0;
-                    for pixel in chunk {
-                        if let Some(offset) = calc_offset(width, height, x, y) {
-                            if offset + 3 < frame_reader.len() {
-                                // RGBA color format
-                                pixel[0] = frame_reader[offset];
-                                pixel[1] = frame_reader[offset + 1];
-                                pixel[2] = frame_reader[offset + 2];
-                                pixel[3] = frame_reader[offset + 3];
-                            } else {
-                                // mismatch
-                                pixel[0] = 255;
-                                pixel[1] = 0;
-                                pixel[2] = 0;
-                                pixel[3] = 0;
-                            }
-                        } else {
-                            // does not exist color
-                            pixel[0] = 128;
-                            pixel[1] = 128;
-                            pixel[2] = 128;
-                            pixel[3] = 0;
-                        }
-                        x += 1;
+
+            let pending = self_frame.lock().unwrap().take();
+
+            let buffer = match pending {
+                Some(pending) => {
+                    let layout = pending.layout();
+                    if pending.len() != video_info.size() || video_format_for_pixel_format(layout.format) != video_info.format() {
+                        // caps haven't caught up with a resize or format change yet, dropping a slot guard hands the slot back
+                        log_warn!(Pipeline, "frame is {} bytes of {} but caps expect {} bytes of {:?}, skipping", pending.len(), layout.format, video_info.size(), video_info.format());
+                        return;
                     }
-                    y += 1;*/
-
-                    // new version: (still slow)
-                    /*for i in 0..(width * height) {
-                        let offset = i * 4;
-                        if offset + 3 < frame_reader.len() {
-                            // RGBA color format
-                            line[offset] = frame_reader[offset];
-                            line[offset + 1] = frame_reader[offset + 1];
-                            line[offset + 2] = frame_reader[offset + 2];
-                            line[offset + 3] = frame_reader[offset + 3];
-                        } else {
-                            // mismatch
-                            line[offset] = 255;
-                            line[offset + 1] = 0;
-                            line[offset + 2] = 0;
-                            line[offset + 3] = 0;
-                        }
-                    }*/
-
-                    // faster version:
-                    let bound = cmp::min(stride * height, frame_reader.len());
-                    // ok I hope this works
-                    // filling makes it 30x slower, in fact this is like I think an extra 20ms to30ms
-                    // line.fill(0);
-                    if bound > 0 {
-                        line[0..bound].copy_from_slice(&frame_reader[0..bound]);
+                    if layout.bottom_up != flipping {
+                        flipping = layout.bottom_up;
+                        log_debug!(Pipeline, "frames are {}, vertical flip {}", if flipping { "bottom up" } else { "top down" }, if flipping { "on" } else { "off" });
+                        // wow gstreamer needs to make like constants for these
+                        videoflip.set_property_from_str("method", if flipping { "vertical-flip" } else { "none" });
                     }
+                    // wraps the mapped slot or the received frame directly, a slot is released when gstreamer frees the buffer
+                    let buffer = Buffer::from_slice(pending);
+                    last_buffer = Some(buffer.clone());
+                    buffer
+                },
+                None => match &last_buffer {
+                    Some(buffer) if buffer.size() == video_info.size() && last_push.elapsed() >= IDLE_REPUSH_INTERVAL => buffer.clone(),
+                    _ => return,
                 }
-                // println!("cped {}x{}", width, height)
-            }
+            };
+
             match appsrc.push_buffer(buffer) {
                 Ok(_) => {
-                    
+                    last_push = Instant::now();
                 },
                 Err(err) => {
-                    println!("Error pushing buffer: {:?}", err);
+                    log_error!(Pipeline, "Error pushing buffer: {:?}", err);
                 },
             }
 
@@ -686,14 +859,14 @@ impl Streamer {
         video_tee.link(&video_hacky_sink).expect("linking tee to fakesink failed");
 
         // glib::idle_add(move );
-        println!("attempting to set play pipeline");
+        log_debug!(Pipeline, "attempting to set play pipeline");
         pipeline.set_state(gstreamer::State::Playing).expect("Could not set pipeline to playing");
 
         /*while running {
             for msg in bus.iter_timed(gstreamer::ClockTime::from_mseconds(1)) {
                 use gstreamer::MessageView;
 
-                println!("{:?}", msg);
+                log_trace!(Pipeline, "{:?}", msg);
         
                 match msg.view() {
                     MessageView::Eos(..) => break,
@@ -707,7 +880,7 @@ impl Streamer {
             }
             
         }*/
-        println!("entering run loop");
+        log_debug!(Pipeline, "entering run loop");
 
         bus.add_watch(|bus, msg| {
             glib::ControlFlow::Continue
@@ -721,14 +894,14 @@ impl Streamer {
                 use gstreamer::MessageView;
                 // qos is spammy
                 // if !matches!(msg.view(), MessageView::Qos(..)) {
-                    println!("gst: {:?}", msg);
+                    log_trace!(Pipeline, "gst: {:?}", msg);
                     if msg.type_() == gstreamer::MessageType::Application {
                         let structure_opt = msg.structure();
                         if let Some(structure) = structure_opt {
                             if self.config.mode == OperationMode::WaylandDesktop && structure.name() == "wayland.src" {
                                 // found wayland src
                                 let display_value: String = structure.get("WAYLAND_DISPLAY").unwrap();
-                                println!("wayland display value {}", display_value);
+                                log_debug!(Pipeline, "wayland display value {}", display_value);
                                 if socket_authed {
                                     self.get_socket().emit("ext_wayland_init", json!(display_value)).expect("Could not send wayland init to socket");
                                 }
@@ -744,11 +917,11 @@ impl Streamer {
 
                 match msg.view() {
                     MessageView::Eos(..) => {
-                        println!("Exiting at end of stream.");
+                        log_info!(Pipeline, "Exiting at end of stream.");
                         return Ok(());
                     },
                     MessageView::Error(err) => {
-                        println!("Error: {} {:?}", err.error(), err.debug());
+                        log_error!(Pipeline, "Error: {} {:?}", err.error(), err.debug());
                         pipeline.debug_to_dot_file_with_ts(DebugGraphDetails::all(), PathBuf::from("errordump.dot"));
                         if let Some(src) = err.src() {
                             // enum webrtc peers
@@ -756,7 +929,7 @@ impl Streamer {
                             for (peer_id, webrtc_peer) in downstream_peers.iter() {
                                 if src.has_ancestor(&webrtc_peer.bin) {
                                     // TODO: handle disconnect by detaching
-                                    println!("traced error a webrtc component, stopping webrtc peer");
+                                    log_debug!(Pipeline, "traced error a webrtc component, stopping webrtc peer");
                                     to_remove.push(peer_id.clone());
                                 }
                             }
@@ -765,12 +938,12 @@ impl Streamer {
                                 let downstream_peer = downstream_peers.remove(&peer_id);
                                 if let Some(downstream_peer) = downstream_peer {
                                     if let Err(err) = downstream_peer.destroy(&pipeline, &video_tee) {
-                                        println!("Error destroying peer on error/disconnect: {:?}", err);
+                                        log_error!(Pipeline, "Error destroying peer on error/disconnect: {:?}", err);
                                     } else {
-                                        println!("Destroyed peer on error/disconnect {}", peer_id);
+                                        log_debug!(Pipeline, "Destroyed peer on error/disconnect {}", peer_id);
                                     }
                                 } else {
-                                    println!("unexpected missing peer on remove? {}", peer_id);
+                                    log_warn!(Pipeline, "unexpected missing peer on remove? {}", peer_id);
                                 }
                             }
                         };
@@ -793,30 +966,24 @@ impl Streamer {
                 match imsg {
                     // TODO: deduplicate code between handshake and sync, but closure does not currently work because it needs to mutate video_info
                     InternalMessage::HandshakeReceived(handshake) => {
-                        println!("handshake details {:#?}", handshake);
+                        log_debug!(Pipeline, "handshake details {:#?}", handshake);
                         if self.is_externally_capturing() {
                             let res=  handshake.resolution;
-                            println!("updating to {:?}", res);
+                            pixel_format = handshake.pixel_format;
+                            log_debug!(Pipeline, "updating to {:?} ({})", res, pixel_format);
                             video_info =
-                                gstreamer_video::VideoInfo::builder(gstreamer_video::VideoFormat::Rgba, res.0, res.1)
+                                gstreamer_video::VideoInfo::builder(video_format_for_pixel_format(pixel_format), res.0, res.1)
                                 //         .fps(gst::Fraction::new(2, 1))
                                 .fps(gstreamer::Fraction::new(self.config.fps as i32, 1))
                                     .build()
                                     .expect("Failed to create video info on demand for source");
-                            println!("video info {:#?}",video_info);
+                            log_debug!(Pipeline, "video info {:#?}",video_info);
                             if !INTERNAL_DEBUG && self.is_externally_capturing() {
                                 appsrc.set_caps(Some(&video_info.to_caps().expect("Cap generation failed")));
                             }
-                            println!("Adjusted caps for resolution {:?}", res);
-                            graphics_api = handshake.graphics_api;
-                            println!("setting graphics api to {:?}", graphics_api);
-                            let flip = stellar_protocol::protocol::should_flip_buffers_for_graphics_api(graphics_api);
-                            if flip {
-                                // wow gstreamer needs to make like constants for these
-                                videoflip.set_property_from_str("method", "vertical-flip");
-                            } else {
-                                videoflip.set_property_from_str("method", "none");
-                            }
+                            log_debug!(Pipeline, "Adjusted caps for resolution {:?}", res);
+                            // flipping is up to each frame, see update_frame_func
+                            log_debug!(Pipeline, "hyperwarp uses {:?}", handshake.graphics_api);
                         }
                         streamer_state = StreamerState::Running;
                         {
@@ -824,43 +991,41 @@ impl Streamer {
                             let socket = self.get_socket();
                             // this tells the ui to switch out of the loading screen
                             let state_num = streamer_state_to_u8(streamer_state);
-                            println!("syncing state to ui: {:?}", state_num);
+                            log_debug!(Pipeline, "syncing state to ui: {:?}", state_num);
                             if let Err(err) = socket.emit("set_session_state", json!(state_num)) {
-                                println!("Error setting session state on remote Stargate server: {:?}", err);
+                                log_error!(Net, "Error setting session state on remote Stargate server: {:?}", err);
                             }else{
-                                println!("Requested to set session state on remote Stargate server.");
+                                log_debug!(Net, "Requested to set session state on remote Stargate server.");
                             }
                         }
                     },
                     InternalMessage::SetShouldUpdate(new_should_update) => {
                         should_update = new_should_update;
                     },
+                    InternalMessage::NewFrameAvailable => {
+                        // nothing to do, waking up the loop is enough
+                    },
                     InternalMessage::SynchornizationReceived(sync_details) => {
-                        println!("syncing {:?}", sync_details);
-                        if let Some(res) = sync_details.resolution {
+                        log_debug!(Pipeline, "syncing {:?}", sync_details);
+                        if let Some(new_pixel_format) = sync_details.pixel_format {
+                            pixel_format = new_pixel_format;
+                        }
+                        if sync_details.resolution.is_some() || sync_details.pixel_format.is_some() {
+                            let res = sync_details.resolution.unwrap_or((video_info.width(), video_info.height()));
                             video_info =
-                            gstreamer_video::VideoInfo::builder(gstreamer_video::VideoFormat::Rgba, res.0, res.1)
+                            gstreamer_video::VideoInfo::builder(video_format_for_pixel_format(pixel_format), res.0, res.1)
                             //         .fps(gst::Fraction::new(2, 1))
                                 .build()
                                 .expect("Failed to create video info on demand for source");
-                            println!("video info {:#?}",video_info);
+                            log_debug!(Pipeline, "video info {:#?}",video_info);
                             if !INTERNAL_DEBUG && self.is_externally_capturing() {
                                 appsrc.set_caps(Some(&video_info.to_caps().expect("Cap generation failed")));
                             }
-                            println!("Adjusted caps for resolution {:?}", res);
+                            log_debug!(Pipeline, "Adjusted caps for resolution {:?}", res);
                         }
 
                         if let Some(new_graphics_api) = sync_details.graphics_api {
-                            println!("setting graphics api to {:?}", new_graphics_api);
-                            graphics_api = new_graphics_api;
-                            let flip = stellar_protocol::protocol::should_flip_buffers_for_graphics_api(graphics_api);
-                            if flip {
-                                // wow gstreamer needs to make like constants for these
-                                videoflip.set_property_from_str("method", "vertical-flip");
-                            } else {
-                                videoflip.set_property("method", "none");
-                            }
-                        
+                            log_debug!(Pipeline, "hyperwarp now uses {:?}", new_graphics_api);
                         }
 
                         if !INTERNAL_DEBUG && self.is_externally_capturing() {
@@ -869,12 +1034,12 @@ impl Streamer {
                     },
                     InternalMessage::SocketConnected => {
                         if !socket_connected {
-                            println!("Stargate socket connected for the first time.");
+                            log_info!(Net, "Stargate socket connected for the first time.");
                             socket_connected = true;
                             // first connect logic
                             let socket =  self.get_socket();
                         } else {
-                            println!("Stargate socket reconnected.");
+                            log_info!(Net, "Stargate socket reconnected.");
                         }
                     },
                     InternalMessage::SocketAuthenticated => {
@@ -892,21 +1057,21 @@ impl Streamer {
                             }
                         }
                         if let Err(err) = socket.emit("set_session_state", json!(streamer_state_to_u8(streamer_state))) {
-                            println!("Error setting session state on remote Stargate server: {:?}", err);
+                            log_error!(Net, "Error setting session state on remote Stargate server: {:?}", err);
                         }else{
-                            println!("Request to set session state on remote Stargate server. New state {}", streamer_state);
+                            log_debug!(Net, "Request to set session state on remote Stargate server. New state {}", streamer_state);
                         }
                     },
                     InternalMessage::SocketPeerFrontendMessageWithPipeline(origin_socketid, frontend_message) => {
                         match frontend_message {
                             StellarFrontendMessage::ProvisionWebRTC { rtc_provision_start } => 'handle_provisioning_webrtc: {
-                                println!("Provisioning webrtc for socket id {:?} client claim start: {:?}", origin_socketid, rtc_provision_start);
+                                log_debug!(Pipeline, "Provisioning webrtc for socket id {:?} client claim start: {:?}", origin_socketid, rtc_provision_start);
                                 if let Err(err) = preprocessor.play() {
-                                    println!("Error forceplaying preprocessor: {:?}", err);
+                                    log_error!(Pipeline, "Error forceplaying preprocessor: {:?}", err);
                                 }
 
                                 if downstream_peers.contains_key(&origin_socketid) {
-                                    println!("Already have a peer for socket id {:?}. Duplicate connection?", origin_socketid);
+                                    log_warn!(Pipeline, "Already have a peer for socket id {:?}. Duplicate connection?", origin_socketid);
                                     break 'handle_provisioning_webrtc; // intresting new label syntax
                                 }
                                 // if downstream_peers.is_empty() {
@@ -932,7 +1097,7 @@ impl Streamer {
                                 let origin_socketid_for_negotiation = origin_socketid.clone();
 
                                 downstream_peer_el_group.webrtcbin.connect_closure("on-negotiation-needed", false, glib::closure!(move |_webrtcbin: &gstreamer::Element| {
-                                    println!("element prompted negotiation");
+                                    log_debug!(Pipeline, "element prompted negotiation");
                                     // this was causing some headaches by being spammed
                                     // hopefully it stops doing this
                                     // normally in js you can call setLocalDescription and do something
@@ -945,7 +1110,7 @@ impl Streamer {
                                 
                                 // https://github.com/servo/media/blob/45756bef67037ade0f4f0125d579fdc3f3d457c8/backends/gstreamer/webrtc.rs#L584
                                 downstream_peer_el_group.webrtcbin.connect("on-data-channel", false, move |channel| {
-                                    println!("on-data-channel called");
+                                    log_debug!(Pipeline, "on-data-channel called");
                                     let channel = channel[1]
                                         .get::<WebRTCDataChannel>()
                                         .map_err(|e| e.to_string())
@@ -958,21 +1123,21 @@ impl Streamer {
                                 });
 
                                 downstream_peer_el_group.webrtcbin.connect_closure("on-new-transceiver", false, glib::closure!(move |_webrtcbin: &gstreamer::Element| {
-                                    println!("on-new-transceiver called");
+                                    log_debug!(Pipeline, "on-new-transceiver called");
                                 }));
 
                                 let socket_arc = self.socketio_client.clone().unwrap();
                                 let origin_socketid_for_ice_sending = origin_socketid.clone();
 
                                 downstream_peer_el_group.webrtcbin.connect_closure("on-ice-candidate", false, glib::closure!(move |_webrtcbin: &gstreamer::Element, mlineindex: u32, candidate: &str| {
-                                    println!("element got (produced) an ice candidate {} {}", mlineindex, candidate);
+                                    log_debug!(Pipeline, "element got (produced) an ice candidate {} {}", mlineindex, candidate);
                                     let candidate_str = candidate.to_string();
                                     if candidate_str.len() == 0 {
-                                        println!("{}'s webrtcbin is done sending ice candidates", origin_socketid_for_ice_sending);
+                                        log_debug!(Pipeline, "{}'s webrtcbin is done sending ice candidates", origin_socketid_for_ice_sending);
                                     }
                                     let socket = socket_arc.lock().unwrap();
                                     if let Err(err) = socket.emit("send_to", json!([origin_socketid_for_ice_sending, StellarFrontendMessage::Ice { candidate: candidate_str, sdp_mline_index: mlineindex }])) {
-                                        println!("Error sending ice candidate to socket id {:?}: {:?}", origin_socketid_for_ice_sending, err);
+                                        log_error!(Pipeline, "Error sending ice candidate to socket id {:?}: {:?}", origin_socketid_for_ice_sending, err);
                                     }
                                 }));
 
@@ -982,7 +1147,7 @@ impl Streamer {
                                 // https://github.com/centricular/webrtcsink/blob/main/plugins/src/webrtcsink/imp.rs
                                 downstream_peer_el_group.webrtcbin.connect_notify(Some("connection-state"),  move |webrtcbin, _pspec| {
                                     let state = webrtcbin.property::<WebRTCPeerConnectionState>("connection-state");
-                                    println!("{}'s connection state is {:?}", origin_socketid_for_state_change, state);
+                                    log_debug!(Pipeline, "{}'s connection state is {:?}", origin_socketid_for_state_change, state);
                                     streaming_cmd_queue_for_state_change.send(InternalMessage::PeerStateChange(origin_socketid_for_state_change.clone(), state));
                                 });
 
@@ -1010,9 +1175,9 @@ impl Streamer {
 
                                 downstream_peer_el_group.bin.call_async(move |bin| {
                                     if let Err(err) = bin.sync_state_with_parent() {
-                                        println!("Error syncing bin state with parent: {:?}", err);
+                                        log_error!(Pipeline, "Error syncing bin state with parent: {:?}", err);
                                     } else {
-                                        println!("Bin state synced with parent, new status {:?}", bin.current_state());
+                                        log_debug!(Pipeline, "Bin state synced with parent, new status {:?}", bin.current_state());
                                     }
 
                                     // video_src_pad.remove_probe(video_block);
@@ -1048,7 +1213,7 @@ impl Streamer {
                                             let message = match serde_json::from_str::<stellar_protocol::protocol::StellarDirectControlMessage>(data) {
                                                 Ok(message) => message,
                                                 Err(err) => {
-                                                    println!("Error parsing direct control message from data channel: {:?} string contents {}", err, data);
+                                                    log_error!(Pipeline, "Error parsing direct control message from data channel: {:?} string contents {}", err, data);
                                                     return;
                                                 }
                                             };
@@ -1063,15 +1228,15 @@ impl Streamer {
                                 }
 
                                 downstream_peers.insert(origin_socketid.clone(), downstream_peer_el_group);
-                                println!("Added downstream peer to pipeline");
+                                log_info!(Pipeline, "Added downstream peer to pipeline");
 
                                 
                                 /*if let Err(err) = socket.emit("send_to", json!([origin_socketid, StellarFrontendMessage::ProvisionWebRTCReply { provision_ok: true }])) {
-                                    println!("Error sending provision reply to socket id {:?}: {:?}", origin_socketid, err);
+                                    log_error!(Net, "Error sending provision reply to socket id {:?}: {:?}", origin_socketid, err);
                                 }*/
 
                                 /* } else {
-                                    println!("Failed to play downstream peer");
+                                    log_error!(Pipeline, "Failed to play downstream peer");
                                     downstream_peer_el_group.remove_from_pipeline(&pipeline, &video_tee);
                                 } */
                                 
@@ -1079,46 +1244,46 @@ impl Streamer {
                             },
                             StellarFrontendMessage::Ice { candidate, sdp_mline_index } => {
                                 if candidate.len() == 0 {
-                                    println!("{} is done sending ice candidates", origin_socketid);
+                                    log_debug!(Pipeline, "{} is done sending ice candidates", origin_socketid);
                                 }
                                 if let Some(webrtc_peer) = downstream_peers.get(&origin_socketid) {
                                     webrtc_peer.webrtcbin.emit_by_name::<()>("add-ice-candidate", &[&sdp_mline_index, &candidate]);
                                 } else {
-                                    println!("warn: ice candidate accidentally dropped, race?");
+                                    log_warn!(Pipeline, "ice candidate accidentally dropped, race?");
                                 }
                             },
                             StellarFrontendMessage::Sdp { type_, sdp } => {
                                 if let Some(webrtc_peer) = downstream_peers.get_mut(&origin_socketid) {
                                     if type_ == "answer" {
                                         webrtc_peer.may_offer = true;
-                                        println!("processing client sdp answer {}", origin_socketid);
-                                        println!("{}", sdp);
+                                        log_debug!(Pipeline, "processing client sdp answer {}", origin_socketid);
+                                        log_trace!(Pipeline, "{}", sdp);
                                         if let Err(err) = webrtc_peer.process_sdp_answer(&sdp) {
                                             self.complain_to_socket(&origin_socketid, &format!("Error processing sdp answer from socket id {:?}", err));
-                                            println!("Error processing sdp answer from socket id {:?}: {:?}", origin_socketid, err);
+                                            log_error!(Pipeline, "Error processing sdp answer from socket id {:?}: {:?}", origin_socketid, err);
                                         }
                                     }else if type_ == "offer" {
                                         // client makes offer, unused no more.
                                         if webrtc_peer.may_offer {
                                             let streaming_cmd_queue_for_reply = self.streaming_command_queue.clone();
                                             let source_id = origin_socketid.clone();
-                                            println!("processing sdp offer");
+                                            log_debug!(Pipeline, "processing sdp offer");
                                             if let Err(err) = webrtc_peer.process_sdp_offer(&sdp, Box::new(move |reply| {
-                                                println!("answering sdp offer");
+                                                log_debug!(Pipeline, "answering sdp offer");
                                                 streaming_cmd_queue_for_reply.send(InternalMessage::SocketSdpAnswer(source_id.clone(), reply));
                                             })) {
                                                 self.complain_to_socket(&origin_socketid, &format!("Error processing sdp offer from socket id {:?}", err));
-                                                println!("Error processing sdp offer from socket id {:?}: {:?}", origin_socketid, err);
+                                                log_error!(Pipeline, "Error processing sdp offer from socket id {:?}: {:?}", origin_socketid, err);
                                             }    
                                         } else {
-                                            println!("ignoring sdp offer are already doing something");
+                                            log_warn!(Pipeline, "ignoring sdp offer are already doing something");
                                         }
                                         
                                     }else{
-                                        println!("Unhandled sdp type {:?} from socket id {:?}", type_, origin_socketid);
+                                        log_warn!(Pipeline, "Unhandled sdp type {:?} from socket id {:?}", type_, origin_socketid);
                                     }
                                 }else {
-                                    println!("warn: sdp accidentally dropped, race?");
+                                    log_warn!(Pipeline, "sdp accidentally dropped, race?");
                                 }
                             },
                             StellarFrontendMessage::DebugInfoRequest { debug_info_request } => {
@@ -1137,18 +1302,18 @@ impl Streamer {
                                     response.push_str(&format!("Peer's webrtcbin {}: {:?}\n", peer_id, peer.webrtcbin.current_state()));
                                     response.push_str(&format!("Peer's may_offer {}: {:?}\n", peer_id, peer.may_offer));
                                     if let Err(err) = peer.play() {
-                                        println!("Error forceplaying webrtcbin: {:?}", err);
+                                        log_error!(Pipeline, "Error forceplaying webrtcbin: {:?}", err);
                                     }
                                 }
                                 if let Err(err) = self.get_socket().emit("send_to", json!([origin_socketid, StellarFrontendMessage::DebugResponse { debug: response }])) {
-                                    println!("Error sending debug info request to socket id {:?}: {:?}", origin_socketid, err);
+                                    log_error!(Pipeline, "Error sending debug info request to socket id {:?}: {:?}", origin_socketid, err);
                                 }
 
                                 // pipeline.debug_to_dot_file_with_ts(DebugGraphDetails::all(), PathBuf::from("/tmp/debug.dot"));
                                 pipeline.debug_to_dot_file_with_ts(DebugGraphDetails::all(), PathBuf::from("pipeline_dump"));
-                                println!("sent pipeline dump");
+                                log_debug!(Pipeline, "sent pipeline dump");
                                 if let Err(err) = preprocessor.play() {
-                                    println!("Error forceplaying preprocessor: {:?}", err);
+                                    log_error!(Pipeline, "Error forceplaying preprocessor: {:?}", err);
                                 }
                             },
                             StellarFrontendMessage::OfferRequest { offer_request_source } => {
@@ -1157,21 +1322,21 @@ impl Streamer {
                                 }
                             }
                             _ => {
-                                println!("Unhandled frontend message {:?}", frontend_message);
+                                log_warn!(Net, "Unhandled frontend message {:?}", frontend_message);
                             }
                         }
                     },
                     InternalMessage::SocketSdpAnswer(origin_socketid, desc) => {
                         if let Some(webrtc_peer) = downstream_peers.get_mut(&origin_socketid) {
                             webrtc_peer.set_remote_description(&desc);
-                            println!("sending our answer sdp to socket id {:?}", origin_socketid);
+                            log_debug!(Pipeline, "sending our answer sdp to socket id {:?}", origin_socketid);
                             let reply = StellarFrontendMessage::Sdp {
                                 type_: "answer".to_string(),
                                 sdp: desc.sdp().as_text().expect("Could not turn the session description into a string").to_string(),
                             };
                             let socket = self.get_socket();
                             if let Err(err) = socket.emit("send_to", json!([origin_socketid, reply])) {
-                                println!("Error sending sdp answer to socket id {:?}: {:?}", origin_socketid, err);
+                                log_error!(Pipeline, "Error sending sdp answer to socket id {:?}: {:?}", origin_socketid, err);
                             }
                         }
                     },
@@ -1185,9 +1350,9 @@ impl Streamer {
                             };
                             let socket = self.get_socket();
                             if let Err(err) = socket.emit("send_to", json!([origin_socketid, reply])) {
-                                println!("Error sending sdp answer to socket id {:?}: {:?}", origin_socketid, err);
+                                log_error!(Pipeline, "Error sending sdp answer to socket id {:?}: {:?}", origin_socketid, err);
                             } else {
-                                println!("Sent sdp offer to socket id {:?}", origin_socketid);
+                                log_debug!(Pipeline, "Sent sdp offer to socket id {:?}", origin_socketid);
                             }
                         }
                     },
@@ -1195,7 +1360,7 @@ impl Streamer {
                         if let Some(webrtc_peer) = downstream_peers.get_mut(&origin_socketid) {
                             if webrtc_peer.may_offer {
                                 webrtc_peer.may_offer = false;
-                                println!("generating offer for origin {:?}", origin);
+                                log_debug!(Pipeline, "generating offer for origin {:?}", origin);
 
                                 let streaming_cmd_queue_for_reply = self.streaming_command_queue.clone();
 
@@ -1207,21 +1372,21 @@ impl Streamer {
                                             .expect("Send value")
                                             .get::<gstreamer_webrtc::WebRTCSessionDescription>()
                                             .expect("Invalid argument");
-                                        println!("sending offer requested by {:?}", origin);
+                                        log_debug!(Pipeline, "sending offer requested by {:?}", origin);
                                         let _ = streaming_cmd_queue_for_reply.send(InternalMessage::SocketSdpOffer(origin_socketid.clone(), offer));
                                     }else if let Err(err) = reply {
-                                        println!("Error generating offer: {:?}", err);
+                                        log_error!(Pipeline, "Error generating offer: {:?}", err);
                                     }else{
-                                        println!("offer generation failed");
+                                        log_error!(Pipeline, "offer generation failed");
                                     }
                                 });
 
                                 webrtc_peer.webrtcbin.emit_by_name::<()>("create-offer", &[&None::<gstreamer::Structure>, &promise]);
                             } else {
-                                println!("ignoring offer generation because we are already generating offer {}", origin_socketid);
+                                log_warn!(Pipeline, "ignoring offer generation because we are already generating offer {}", origin_socketid);
                             }
                         } else {
-                            println!("can't generate sdp offer for {:?} because it has not requested rtc", origin_socketid);
+                            log_warn!(Pipeline, "can't generate sdp offer for {:?} because it has not requested rtc", origin_socketid);
                         }
                     },
                     InternalMessage::PeerStateChange(origin_socketid, state) => {
@@ -1233,7 +1398,7 @@ impl Streamer {
                                 WebRTCPeerConnectionState::Connected => {
                                     webrtc_peer.may_offer = true;
                                     if let Err(err) = webrtc_peer.play() {
-                                        println!("Error forceplaying webrtcbin: {:?}", err);
+                                        log_error!(Pipeline, "Error forceplaying webrtcbin: {:?}", err);
                                     }
                                 },
                                 WebRTCPeerConnectionState::Disconnected => {
@@ -1246,23 +1411,23 @@ impl Streamer {
                                     should_disconnect = true;
                                 },
                                 _ => {
-                                    println!("unhandled webrtc peer connection state {:?}", state);
+                                    log_warn!(Pipeline, "unhandled webrtc peer connection state {:?}", state);
                                 },
                             }
 
                             if should_disconnect {
-                                println!("disconnecting webrtc peer {}", origin_socketid);
+                                log_debug!(Pipeline, "disconnecting webrtc peer {}", origin_socketid);
                                 webrtc_peer.stop().expect("Error stopping webrtc peer");
                                 webrtc_peer.remove_from_pipeline(&pipeline).expect("Error removing webrtc peer from pipeline");
                                 if webrtc_peer.data_channels.len() > 0 {
-                                    println!("{} data channels removed from peer", webrtc_peer.data_channels.len());
+                                    log_debug!(Pipeline, "{} data channels removed from peer", webrtc_peer.data_channels.len());
                                 }
                                 webrtc_peer.data_channels.iter().for_each(|channel| {
                                     self.data_channel_tracker.lock().unwrap().remove_data_channel(channel.id());
                                 });
                                 downstream_peers.remove(&origin_socketid);// drop bye bye
                                 /*if let Err(err) = pipeline.set_state(gstreamer::State::Playing) {
-                                    println!("Error setting pipeline state to playing: {:?}", err);
+                                    log_error!(Pipeline, "Error setting pipeline state to playing: {:?}", err);
                                 }*/
                             }
                         }
@@ -1271,12 +1436,12 @@ impl Streamer {
                         if let Some(webrtc_peer) = downstream_peers.get_mut(&origin_socketid) {
                             webrtc_peer.may_offer = true;
                             if let Err(err) = webrtc_peer.play() {
-                                println!("Error forceplaying webrtcbin: {:?}", err);
+                                log_error!(Pipeline, "Error forceplaying webrtcbin: {:?}", err);
                             }
 
                             let socket = self.get_socket();
                             if let Err(err) = socket.emit("send_to", json!([origin_socketid, StellarFrontendMessage::ProvisionWebRTCReply { provision_ok: true }])) {
-                                println!("Error sending provision reply to socket id {:?}: {:?}", origin_socketid, err);
+                                log_error!(Pipeline, "Error sending provision reply to socket id {:?}: {:?}", origin_socketid, err);
                             }
                         }
                     },
@@ -1295,7 +1460,7 @@ impl Streamer {
                                     let message = match serde_json::from_str::<stellar_protocol::protocol::StellarDirectControlMessage>(data) {
                                         Ok(message) => message,
                                         Err(err) => {
-                                            println!("Error parsing direct control message from data channel: {:?}", err);
+                                            log_error!(Pipeline, "Error parsing direct control message from data channel: {:?}", err);
                                             return;
                                         }
                                     };
@@ -1324,12 +1489,12 @@ impl Streamer {
                                 let message = serde_json::to_string(&message).expect("Could not serialize message");
                                 channel.send_string(Some(&message));
                             } else {
-                                println!("No data channel found for label {} on socket id {}, did it disconnect?", channel_label, socket_id);
+                                log_warn!(Pipeline, "No data channel found for label {} on socket id {}, did it disconnect?", channel_label, socket_id);
                             }
                         }
                     },
                     InternalMessage::BroadcastDirectMessage(channel_label, message) => {
-                        println!("broadcasting direct message to channel {} {:#?}", channel_label, message);
+                        log_debug!(Pipeline, "broadcasting direct message to channel {} {:#?}", channel_label, message);
                         for (socket_id, rtc_peer) in downstream_peers.iter() {
                             rtc_peer.get_data_channels().iter().for_each(|channel| {
                                 let label = channel.label();
@@ -1388,7 +1553,7 @@ impl Streamer {
 
                                         capture_el.send_event(gstreamer::event::CustomUpstream::new(event_data_structure));
                                     } else {
-                                        println!("Could not map web button index {} to linux button index (illegal mouse button?)", button_index_web);
+                                        log_warn!(Input, "Could not map web button index {} to linux button index (illegal mouse button?)", button_index_web);
                                     }
                                 },
                                 StellarDirectControlMessage::MouseScroll { delta_x, delta_y, timestamp } => {
@@ -1415,13 +1580,13 @@ impl Streamer {
                                 StellarDirectControlMessage::RemoveGamepadReply { remote_id, success, message } => todo!(),*/
                             }
                             if !ignored {
-                                println!("Handled direct message {:?} from socket id {:?}", message, source_socket_id);
+                                log_debug!(Input, "Handled direct message {:?} from socket id {:?}", message, source_socket_id);
                             }
                         }
                     },
                     _ => { // if thi warns about unreachable code, it's very good because we implemented everything
                         // TODO: print more descriptive
-                        println!("BAD: Unimplemented message {:#?}", imsg.type_id());
+                        log_error!(Input, "Unimplemented message {:#?}", imsg.type_id());
                     }
                 };
             }
@@ -1431,7 +1596,7 @@ impl Streamer {
             // TOOD: make this loop not thrash cpu by waiting for events, does it still/
         }
 
-        println!("streamer thread exited cleanly.");
+        log_info!(Net, "streamer thread exited cleanly.");
         Ok(())
     }
 
@@ -1451,13 +1616,13 @@ impl Streamer {
             main_thread_cmd_queue_1.send(InternalMessage::SocketConnected);
             // now we need to elevate privs
             if let Err(err) = client.emit("upgrade_privs", json!(config.secret.clone())) {
-                println!("Initial privlige elevation failed: {:?}, may retry on reconnect", err);
+                log_warn!(Net, "Initial privlige elevation failed: {:?}, may retry on reconnect", err);
             }
         }).on("upgraded", move |payload, client| {
-            println!("Privlige upgrade accepted.");
+            log_info!(Net, "Privlige upgrade accepted.");
             main_thread_cmd_queue_2.send(InternalMessage::SocketAuthenticated);
         }).on("peer_message", move |payload, client| {
-            println!("peer_message {:#?}", payload);
+            log_debug!(Net, "peer_message {:#?}", payload);
             match payload {
                 rust_socketio::Payload::Binary(bin) => {
                     // serde in js would never
@@ -1490,15 +1655,15 @@ impl Streamer {
                                             if may_mutate_pipeline(&other) {
                                                 main_thread_cmd_queue_3.send(InternalMessage::SocketPeerFrontendMessageWithPipeline(src_socketid.clone(), other));
                                             } else {
-                                                println!("Unhandled frontend message {:?}", other);
+                                                log_warn!(Net, "Unhandled frontend message {:?}", other);
                                             }
                                         }
                                     }
                                 },
-                                Err(err) => println!("malformed frontend message {:?}", err),
+                                Err(err) => log_warn!(Net, "malformed frontend message {:?}", err),
                             }
                         } else {
-                            println!("very malformed frontend message, missing source socket id string");
+                            log_warn!(Net, "very malformed frontend message, missing source socket id string");
                         }
                     }
                     
@@ -1509,11 +1674,11 @@ impl Streamer {
             }
         });
 
-        println!("Connecting to Stargate server");
+        log_info!(Net, "Connecting to Stargate server");
 
         let socket = socket_builder.connect()?;
 
-        println!("Connected to Stargate server");
+        log_info!(Net, "Connected to Stargate server");
 
         let arc = Arc::new(Mutex::new(socket));
         
@@ -1533,16 +1698,28 @@ impl Streamer {
     pub fn start_hyperwarp_client_thread(&mut self) -> JoinHandle<()> {
         let config = self.config.clone();
         let stopper = self.stop.clone();
-        let socket_path = self.config.socket.clone().expect("socket path not set or not valid");
         let (handler, listener) = node::split::<StreamerSignal>();
-        println!("Connecting to socket: {}", socket_path.display());
-        // _ is so rustc doesn't complain about unused variable
-        let temp_socket_path: PathBuf = format!("/tmp/hyperwarp/client-{}.sock", std::process::id()).into();
-        let _ = handler.network().connect_with(message_io::network::TransportConnect::UnixSocketDatagram(UnixSocketConnectConfig::new(temp_socket_path)), NetworkAddr::Path(socket_path));
+        let token = match config.auth_token() {
+            Ok(token) => token,
+            Err(err) => {
+                log_error!(Net, "could not read auth token: {:?}", err);
+                None
+            }
+        };
+        if config.connect.is_some() && token.is_none() {
+            log_error!(Net, "no --token or --token-file, Hyperwarp will turn us away");
+        }
+        let initial_endpoint = match connect_to_hyperwarp(&handler, &config) {
+            Ok(endpoint) => Some(endpoint),
+            Err(err) => {
+                log_error!(Net, "Could not connect to Hyperwarp: {:?}", err);
+                None
+            }
+        };
         let handler_wrapper = Arc::new(Mutex::new(handler));
         let handler_wrapper_2 = handler_wrapper.clone();
         self.messaging_handler = Some(handler_wrapper_2); // this part runs before the thread is started so it always exists
-        println!("Starting Hyperwarp client event thread");
+        log_debug!(Net, "Starting Hyperwarp client event thread");
 
         let streaming_cmd_queue = self.streaming_command_queue.clone();
         let frame = self.frame.clone();
@@ -1551,103 +1728,243 @@ impl Streamer {
         std::thread::spawn(move || {
 
             let inner_run = || -> Result<()> {
-                println!("Enter Hyperwarp client event processing");
-                let mut shm_file: Option<std::fs::File> = None;
-                let mut current_endpoint: Option<Endpoint> = None;
+                log_debug!(Net, "Enter Hyperwarp client event processing");
+                let mut shm_mapping: Option<Arc<ShmFrameMapping>> = None;
+                let mut shm_path: Option<PathBuf> = None;
+                let mut current_endpoint: Option<Endpoint> = initial_endpoint;
+                // anything from hyperwarp counts, it echoes our heartbeats so this stays fresh while it's alive
+                let mut last_received = Instant::now();
+                let mut reconnecting = false;
+                let mut reconnect_attempts: u32 = 0;
+                if current_endpoint.is_none() {
+                    reconnecting = true;
+                    schedule_reconnect(&handler_wrapper, &mut reconnect_attempts);
+                }
+                handler_wrapper.lock().unwrap().signals().send_with_timer(StreamerSignal::Heartbeat, HEARTBEAT_INTERVAL);
+                // set once authenticated with --encrypt
+                let mut cipher: Option<SessionCipher> = None;
+                // server and client nonce while we wait for the auth result
+                let mut auth_nonces: Option<(Vec<u8>, Vec<u8>)> = None;
+                // --connect only, where the frame stream listens and the thread reading it
+                let mut frame_stream_port: Option<u16> = None;
+                let mut frame_stream_thread: Option<FrameStreamHandle> = None;
+                let mut window_metadata = WindowMetadata::new();
+                let broadcast_window_updates = |updates: Vec<StellarDirectControlMessage>| {
+                    for update in updates {
+                        streaming_cmd_queue.send(InternalMessage::BroadcastDirectMessage("reliable".to_string(), update));
+                    }
+                };
                 let mut socket: Option<Arc<Mutex<Client>>> = None;
                 listener.for_each(move |event| {
                     match event {
                         NodeEvent::Network(netevent) => {
                             match netevent {
                                 message_io::network::NetEvent::Connected(endpoint, ready) => {
-                                    println!("Connected to Hyperwarp socket");
+                                    log_info!(Net, "Connected to Hyperwarp socket");
+                                    current_endpoint = Some(endpoint);
+                                    cipher = None;
                                     if ready {
-                                        // say hello
-                                        println!("sending hello");
-                                        let handler = handler_wrapper.lock().unwrap();
-                                        let network = handler.network();
-                                        network.send(endpoint.clone(), &stellar_protocol::serialize(&StellarMessage::Hello));
-                                        println!("sending initial handshake request");
-                                        network.send(endpoint.clone(), &stellar_protocol::serialize(&StellarMessage::HandshakeRequest));
-                                        network.send(endpoint.clone(), &stellar_protocol::serialize(&StellarMessage::HelloName("Testing protocol".to_string())));
-                                        network.send(endpoint.clone(), &stellar_protocol::serialize(&StellarMessage::SubscribeChannel(StellarChannel::Frame)));
-                                        network.send(endpoint.clone(), &stellar_protocol::serialize(&StellarMessage::SubscribeChannel(StellarChannel::Synchornizations)));
+                                        // say hello, over the network this gets us a challenge and the rest waits for the result
+                                        log_debug!(Net, "sending hello");
+                                        send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &StellarMessage::Hello);
+                                        if config.connect.is_none() {
+                                            send_hyperwarp_setup(&handler_wrapper, endpoint, &mut cipher, &config);
+                                        }
                                     } else {
-                                        println!("One client did not successfully ready. {}", endpoint.addr());
+                                        log_warn!(Net, "One client did not successfully ready. {}", endpoint.addr());
+                                        if !reconnecting {
+                                            reconnecting = true;
+                                            schedule_reconnect(&handler_wrapper, &mut reconnect_attempts);
+                                        }
                                     }
-                                    current_endpoint = Some(endpoint);
                                 },
                                 message_io::network::NetEvent::Accepted(_, _) => {
-                                    println!("Connect accepted from Hyperwarp socket");
+                                    log_debug!(Net, "Connect accepted from Hyperwarp socket");
                                 },
                                 message_io::network::NetEvent::Message(_endpoint, data) => {
+                                    last_received = Instant::now();
+                                    reconnect_attempts = 0;
+                                    let data = match &mut cipher {
+                                        Some(cipher) => match cipher.open(data) {
+                                            Ok(data) => data,
+                                            Err(err) => {
+                                                log_warn!(Net, "Dropping message from Hyperwarp: {}", err);
+                                                return;
+                                            }
+                                        },
+                                        None => data.to_vec(),
+                                    };
                                     match stellar_protocol::deserialize_safe(&data) {
                                         Some(message) => {
                                             if !matches!(message, StellarMessage::NewFrame) {
                                                 // println!("{:?} message", message);
                                             }
                                             match message {
-                                                StellarMessage::HandshakeResponse(handshake) => {
-                                                    // setup buffer
-                                                    {
-                                                        let mut writable_frame = frame.write().unwrap();
-                                                        writable_frame.clear();
-                                                        let resolution = handshake.resolution;
-                                                        // this doesn't actually need to happen because it's cleared and appended anyways
-                                                        writable_frame.resize((4 * resolution.0 * resolution.1) as usize, 0);
-                                                        println!("init streamer frame buffer {} bytes", (4 * resolution.0 * resolution.1));
+                                                StellarMessage::AuthChallenge(server_nonce) => {
+                                                    match &token {
+                                                        Some(token) => {
+                                                            log_debug!(Net, "answering auth challenge");
+                                                            let client_nonce = auth::random_nonce();
+                                                            let proof = auth::prove(token, &server_nonce, &client_nonce);
+                                                            send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &StellarMessage::AuthResponse(client_nonce.clone(), proof, config.encrypt_link()));
+                                                            auth_nonces = Some((server_nonce, client_nonce));
+                                                        },
+                                                        None => log_error!(Net, "Hyperwarp wants a token and we weren't given one"),
                                                     }
-                                                    {
-                                                        shm_file = Some(std::fs::File::open(&handshake.shimg_path).expect("Failed to open shm file"));
-                                                        println!("opened shm file for frame buffer");
+                                                },
+                                                StellarMessage::AuthResult(true, _) => {
+                                                    log_info!(Net, "Authenticated with Hyperwarp{}", if config.encrypt_link() { ", encrypting from here on" } else { "" });
+                                                    if config.encrypt_link() {
+                                                        if let (Some(token), Some((server_nonce, client_nonce))) = (&token, &auth_nonces) {
+                                                            cipher = Some(SessionCipher::new(token, server_nonce, client_nonce, Side::Client));
+                                                        }
                                                     }
-                                                    streaming_cmd_queue.send(InternalMessage::HandshakeReceived(handshake));
+                                                    auth_nonces = None;
+                                                    if let Some(endpoint) = current_endpoint {
+                                                        send_hyperwarp_setup(&handler_wrapper, endpoint, &mut cipher, &config);
+                                                    }
+                                                },
+                                                StellarMessage::AuthResult(false, reason) => {
+                                                    log_error!(Net, "Hyperwarp turned us away: {}", reason);
+                                                    auth_nonces = None;
                                                 },
-                                                StellarMessage::NewFrame => {
-                                                    if let Some(shm_file) = &mut shm_file {
-                                                        let mut writable_frame = frame.write().unwrap();
-                                                        writable_frame.clear(); // because read_to_end "appends"
-                                                        shm_file.read_to_end(&mut writable_frame).expect("Reading from shm file failed");
-                                                        shm_file.seek(std::io::SeekFrom::Start(0)).expect("Seeking to start of image failed");
+                                                StellarMessage::HandshakeResponse(handshake) => {
+                                                    // drop any frame from before the handshake
+                                                    frame.lock().unwrap().take();
+                                                    if config.connect.is_some() {
+                                                        // the shm file is on another machine, ask for the frame stream instead
+                                                        frame_stream_port = handshake.frame_stream_port;
+                                                        let streaming = frame_stream_thread.as_ref().map_or(false, |thread| !thread.is_finished());
+                                                        match frame_stream_port {
+                                                            Some(_) if streaming => {},
+                                                            Some(_) => send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &StellarMessage::FrameStreamRequest(config.frame_compression)),
+                                                            None => log_error!(Net, "Hyperwarp has no frame stream, start it with HW_FRAME_STREAM_ADDR to get frames over the network"),
+                                                        }
                                                     } else {
-                                                        println!("shm file not setup yet, can't acquire frame");
+                                                        // the file may still be empty if nothing was captured yet, we map it once frames arrive
+                                                        shm_mapping = ShmFrameMapping::open(&handshake.shimg_path).ok().map(Arc::new);
+                                                        shm_path = Some(handshake.shimg_path.clone());
+                                                        log_debug!(Net, "shm file for frame buffer is {} with {} slots", handshake.shimg_path.display(), handshake.frame_slots);
+                                                    }
+                                                    streaming_cmd_queue.send(InternalMessage::HandshakeReceived(handshake));
+                                                },
+                                                StellarMessage::FrameStreamTicket(Some(ticket)) => {
+                                                    match (&config.connect, frame_stream_port) {
+                                                        (Some(addr), Some(port)) => {
+                                                            let addr = SocketAddr::new(addr.addr().ip(), port);
+                                                            // hyperwarp seals the frames when this connection is sealed
+                                                            let frame_cipher = match (&cipher, &token) {
+                                                                (Some(_), Some(token)) => Some(SessionCipher::frame_stream(token, &ticket, Side::Client)),
+                                                                _ => None,
+                                                            };
+                                                            if let Some(old) = frame_stream_thread.take() {
+                                                                old.stop();
+                                                            }
+                                                            match frame_stream::spawn(addr, ticket, frame_cipher, frame.clone(), streaming_cmd_queue.clone()) {
+                                                                Ok(thread) => frame_stream_thread = Some(thread),
+                                                                Err(err) => log_error!(Net, "Could not start frame stream thread: {:?}", err),
+                                                            }
+                                                        },
+                                                        _ => log_warn!(Net, "Got a frame stream ticket we didn't ask for"),
+                                                    }
+                                                },
+                                                StellarMessage::FrameStreamTicket(None) => {
+                                                    log_error!(Net, "Hyperwarp turned down the frame stream request");
+                                                },
+                                                StellarMessage::NewFrameSlot(slot, slot_size, layout) => {
+                                                    let slot_size = slot_size as usize;
+                                                    let slot_end = (frame_slot_index(slot) + 1) * slot_size;
+                                                    // hyperwarp grows the file when the resolution goes up, remap to see the new slots
+                                                    if shm_mapping.as_ref().map_or(true, |mapping| mapping.len() < slot_end) {
+                                                        if let Some(path) = &shm_path {
+                                                            match ShmFrameMapping::open(path) {
+                                                                Ok(mapping) => shm_mapping = Some(Arc::new(mapping)),
+                                                                Err(err) => log_warn!(Net, "Could not map shm file: {:?}", err),
+                                                            }
+                                                        }
+                                                    }
+                                                    let guard = shm_mapping.as_ref().and_then(|mapping| {
+                                                        FrameSlotGuard::new(mapping.clone(), slot, slot_size, Some(handler_wrapper.clone()))
+                                                    });
+                                                    match guard {
+                                                        Some(guard) => {
+                                                            // replacing an unpushed frame drops it which releases its slot
+                                                            let previous = frame.lock().unwrap().replace(PendingFrame::Slot(guard, layout));
+                                                            drop(previous);
+                                                            streaming_cmd_queue.send(InternalMessage::NewFrameAvailable);
+                                                        },
+                                                        None => {
+                                                            log_warn!(Net, "shm file not setup yet, can't acquire frame in slot {}", slot);
+                                                            send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &StellarMessage::ReleaseFrameSlot(slot));
+                                                        }
                                                     }
                                                 },
                                                 StellarMessage::SynchronizationEvent(sync_details) => {
                                                     // this doesn't happen enough I think to be spammy?
-                                                    println!("recieving sync event on hyperwarp conn thread");
+                                                    log_debug!(Net, "recieving sync event on hyperwarp conn thread");
                                                     streaming_cmd_queue.send(InternalMessage::SynchornizationReceived(sync_details));
                                                 },
+                                                StellarMessage::WindowListResponse(windows) => {
+                                                    log_debug!(Net, "hyperwarp has {} windows", windows.len());
+                                                    for window in windows.iter() {
+                                                        log_debug!(Net, "  {} {} {:?} {}x{}{}", window.id, window.library, window.title, window.size.0, window.size.1, if window.capturing { " (capturing)" } else { "" });
+                                                    }
+                                                    broadcast_window_updates(window_metadata.replace_all(windows));
+                                                },
+                                                StellarMessage::WindowChanged(window) => {
+                                                    broadcast_window_updates(window_metadata.update_window(window));
+                                                },
+                                                StellarMessage::WindowIconChanged(id, icon) => {
+                                                    log_debug!(Net, "window {} icon changed ({}x{})", id, icon.width, icon.height);
+                                                    broadcast_window_updates(window_metadata.update_icon(id, icon));
+                                                },
+                                                StellarMessage::WindowDestroyed(id) => {
+                                                    broadcast_window_updates(window_metadata.remove_window(id));
+                                                },
                                                 StellarMessage::DebugInfoResponseV2(debug_info, source) => {
-                                                    println!("Debug info response from hyperwarp ({}): {:?}", source, debug_info);
+                                                    log_debug!(Net, "Debug info response from hyperwarp ({}): {:?}", source, debug_info);
                                                     if let Some(socket) = &socket {
                                                         let _ = socket.lock().unwrap().emit("send_to", json!([source, StellarFrontendMessage::HyperwarpDebugResponse { 
                                                             hyperwarp_debug: debug_info.message,
+                                                            hyperwarp_hook_calls: debug_info.hook_calls,
                                                             source: source.clone()
                                                          }]));
                                                     }
                                                 },
                                                 StellarMessage::ReplyDataChannelMessage(source, channel, direct_message) => {
-                                                    println!("Reply data channel message from hyperwarp ({}): {:?}", source, direct_message);
+                                                    log_debug!(Net, "Reply data channel message from hyperwarp ({}): {:?}", source, direct_message);
                                                     streaming_cmd_queue.send(InternalMessage::SendDirectMessage(source, channel, direct_message));
                                                 },
                                                 StellarMessage::BroadcastDataChannelMessage(channel, direct_message) => {
-                                                    println!("Broadcast data channel message from hyperwarp: {:?}", direct_message);
+                                                    log_debug!(Net, "Broadcast data channel message from hyperwarp: {:?}", direct_message);
                                                     streaming_cmd_queue.send(InternalMessage::BroadcastDirectMessage(channel, direct_message));
                                                 },
+                                                StellarMessage::LogLine(level, target, line) => {
+                                                    // hyperwarp already filtered these by its own HW_LOG, ours applies on top
+                                                    let level = level.parse().unwrap_or(Level::Info);
+                                                    let target = target.parse().unwrap_or(Target::General);
+                                                    if logging::enabled(target, level) {
+                                                        logging::write(target, level, format_args!("hyperwarp: {}", line));
+                                                    }
+                                                },
                                                 _ => {
 
                                                 }
                                             }
                                         },
                                         None => {
-                                            println!("Received invalid message from Hyperwarp socket...");
+                                            log_warn!(Net, "Received invalid message from Hyperwarp socket...");
                                         }
                                     }
                                 },
                                 message_io::network::NetEvent::Disconnected(_) => {
-                                    println!("Disconnected from Hyperwarp socket...");
+                                    log_info!(Net, "Disconnected from Hyperwarp socket...");
                                     current_endpoint = None;
+                                    cipher = None;
+                                    if !reconnecting {
+                                        reconnecting = true;
+                                        schedule_reconnect(&handler_wrapper, &mut reconnect_attempts);
+                                    }
                                 },
                             }
                         },
@@ -1659,34 +1976,65 @@ impl Streamer {
                                 },
                                 StreamerSignal::ProcessInput(input_event) => {
                                     if is_externally_capturing {
-                                        let handler = handler_wrapper.lock().unwrap();
-                                        let network = handler.network();
                                         let message = stellar_protocol::protocol::StellarMessage::UserInputEvent(input_event);
                                         // println!("sent input");
-                                        if let Some(endpoint) = &current_endpoint {
-                                            network.send(endpoint.clone(), &stellar_protocol::serialize(&message));
-                                        }
+                                        send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &message);
                                     }
                                 },
                                 StreamerSignal::DebugInfoRequest => {
-                                    let handler = handler_wrapper.lock().unwrap();
-                                    let network = handler.network();
                                     let message = stellar_protocol::protocol::StellarMessage::DebugInfoRequestV2;
-                                    println!("sent debug info request to hyperwarp");
-                                    if let Some(endpoint) = &current_endpoint {
-                                        network.send(endpoint.clone(), &stellar_protocol::serialize(&message));
-                                    }
+                                    log_debug!(Net, "sent debug info request to hyperwarp");
+                                    send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &message);
                                 },
                                 StreamerSignal::SocketCreated(sent_socket) => {
                                     socket = Some(sent_socket);
                                 },
                                 StreamerSignal::ForwardedDataChannelMessage(source_socket_id, message) => {
-                                    let handler = handler_wrapper.lock().unwrap();
-                                    let network = handler.network();
                                     let message = stellar_protocol::protocol::StellarMessage::ForwardedDataChannelMessage(source_socket_id, message);
                                     // println!("sent forwarded data channel message to hyperwarp");
-                                    if let Some(endpoint) = &current_endpoint {
-                                        network.send(endpoint.clone(), &stellar_protocol::serialize(&message));
+                                    send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &message);
+                                },
+                                StreamerSignal::ReleaseFrameSlot(slot) => {
+                                    send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &StellarMessage::ReleaseFrameSlot(slot));
+                                },
+                                StreamerSignal::Heartbeat => {
+                                    if !reconnecting && current_endpoint.is_some() {
+                                        if last_received.elapsed() > HYPERWARP_TIMEOUT {
+                                            log_warn!(Net, "Nothing from Hyperwarp for {:?}, assuming the connection is gone", last_received.elapsed());
+                                            reconnecting = true;
+                                            schedule_reconnect(&handler_wrapper, &mut reconnect_attempts);
+                                        } else {
+                                            // from the moment the link is up, hyperwarp ignores them until we're through auth
+                                            send_to_hyperwarp(&handler_wrapper, current_endpoint, &mut cipher, &StellarMessage::Heartbeat);
+                                        }
+                                    }
+                                    handler_wrapper.lock().unwrap().signals().send_with_timer(StreamerSignal::Heartbeat, HEARTBEAT_INTERVAL);
+                                },
+                                StreamerSignal::Reconnect => {
+                                    // nothing from the old connection carries over, the handshake replay maps the shm file and resets the caps again
+                                    if let Some(endpoint) = current_endpoint.take() {
+                                        handler_wrapper.lock().unwrap().network().remove(endpoint.resource_id());
+                                    }
+                                    cipher = None;
+                                    auth_nonces = None;
+                                    shm_mapping = None;
+                                    shm_path = None;
+                                    // the old frame stream could sit on a half open socket forever and would keep filling frame
+                                    if let Some(thread) = frame_stream_thread.take() {
+                                        thread.stop();
+                                    }
+                                    frame.lock().unwrap().take();
+                                    let result = connect_to_hyperwarp(&handler_wrapper.lock().unwrap(), &config);
+                                    match result {
+                                        Ok(endpoint) => {
+                                            current_endpoint = Some(endpoint);
+                                            last_received = Instant::now();
+                                            reconnecting = false;
+                                        },
+                                        Err(err) => {
+                                            log_warn!(Net, "Could not reconnect to Hyperwarp: {:?}", err);
+                                            schedule_reconnect(&handler_wrapper, &mut reconnect_attempts);
+                                        }
                                     }
                                 }
                             }
@@ -1698,7 +2046,7 @@ impl Streamer {
             };
 
             inner_run().expect("Hyperwarp client thread panicked");
-            println!("Hyperwarp client thread exited cleanly.");
+            log_info!(Net, "Hyperwarp client thread exited cleanly.");
         })
     }
 
@@ -3,6 +3,7 @@ use std::{path::PathBuf, thread};
 
 use message_io::network::adapter::NetworkAddr;
 use stellar_protocol::protocol::{StellarMessage};
+use stellar_shared::{log_debug, log_info, log_warn};
 use message_io::node;
 use message_io::{adapters::unix_socket::{create_null_socketaddr, UnixSocketConnectConfig, UnixSocketListenConfig}, node::{NodeEvent, NodeHandler}};
 
@@ -33,16 +34,16 @@ pub fn test_networking() {
                                 Some(message) => {
                                     match message {
                                         StellarMessage::HelloName(name) => {
-                                            println!("Received hello from client: {}", name);
+                                            log_info!(Net, "Received hello from client: {}", name);
                                             handler.network().send(endpoint, &stellar_protocol::serialize(&StellarMessage::HelloName("Kitten".to_string())));
                                         },
                                         _ => {
-                                            println!("Received invalid message from Hyperwarp socket...");
+                                            log_warn!(Net, "Received invalid message from Hyperwarp socket...");
                                         }
                                     }
                                 },
                                 None => {
-                                    println!("Received invalid message from Hyperwarp socket...");
+                                    log_warn!(Net, "Received invalid message from Hyperwarp socket...");
                                 }
                             }
                         },
@@ -52,7 +53,7 @@ pub fn test_networking() {
                     };
                 },
                 _ => {
-                    println!("meh other event");
+                    log_debug!(Net, "meh other event");
                 }
             }
         });
@@ -77,7 +78,7 @@ pub fn test_networking() {
             thread::sleep(std::time::Duration::from_millis(133));
         }
 
-        println!("handling other events");
+        log_debug!(Net, "handling other events");
 
         listener.for_each(move |event| {
             if let NodeEvent::Network(netevent) = event {
@@ -86,15 +87,15 @@ pub fn test_networking() {
                         Some(message) => {
                             match message {
                                 StellarMessage::HelloName(name) => {
-                                    println!("Received hello from server: {}", name);
+                                    log_info!(Net, "Received hello from server: {}", name);
                                 },
                                 _ => {
-                                    println!("Received other type {:#?} from server...", message);
+                                    log_info!(Net, "Received other type {:#?} from server...", message);
                                 }
                             }
                         },
                         _ => {
-                            println!("Received invalid message from socket...");
+                            log_warn!(Net, "Received invalid message from socket...");
                         }
                     }
                 }
@@ -15,6 +15,7 @@ use gio::subclass::prelude::ObjectSubclass;
 use lazy_static::lazy_static;
 
 use crate::streamerd::{OperationMode, StreamerConfig, build_capsfilter};
+use stellar_shared::{log_debug, log_error};
 
 // https://gitlab.freedesktop.org/gstreamer/gstreamer/-/blob/main/subprojects/gst-examples/webrtc/sendrecv/gst-rust/src/main.rs#L30
 const TWCC_URI: &str = "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
@@ -280,7 +281,7 @@ impl WebRTCPeer {
                     on_promise();
                 },
                 Err(err) => {
-                    println!("failed at getting answer struct: {:?}", err);
+                    log_error!(Pipeline, "failed at getting answer struct: {:?}", err);
                 }
             }
         });
@@ -314,17 +315,17 @@ impl WebRTCPeer {
                                         on_answer(answer);
                                     },
                                     Err(err) => {
-                                        println!("failed at getting answer struct: {:?}", err);
+                                        log_error!(Pipeline, "failed at getting answer struct: {:?}", err);
                                     }
                                 }
                             },
                             None => {
-                                println!("no answer created");
+                                log_error!(Pipeline, "no answer created");
                             }
                         }
                     },
                     Err(err) => {
-                        println!("early error creating answer: {:?}", err);
+                        log_error!(Pipeline, "early error creating answer: {:?}", err);
                     }
                 }
             });
@@ -415,7 +416,7 @@ impl WebRTCPreprocessor {
         /*if let Some(parser_element) = pipeline.by_name("parser") {
             return;
             // relink with caps restriction
-            println!("relinking parser element");
+            log_debug!(Pipeline, "relinking parser element");
             match self.preset {
                 EncodingPreset::H264 => {
                     self.encoder.unlink(&parser_element);
@@ -473,8 +474,8 @@ impl WebRTCPreprocessor {
         let encoder_el_type = WebRTCPreprocessor::get_encoder_element_type(preset, optimizations);
         let payloader_el_type = WebRTCPreprocessor::get_payloader_element_type(preset, optimizations);
 
-        println!("encoder type: {:?}", encoder_el_type);
-        println!("payloader type: {:?}", payloader_el_type);
+        log_debug!(Pipeline, "encoder type: {:?}", encoder_el_type);
+        log_debug!(Pipeline, "payloader type: {:?}", payloader_el_type);
 
         match optimizations {
             PipelineOptimization::NVIDIA | PipelineOptimization::AMD => {
@@ -531,13 +532,13 @@ impl WebRTCPreprocessor {
             PipelineOptimization::AMD | PipelineOptimization::DMABuf => {
                 match preset {
                     EncodingPreset::H264 => {
-                        println!("pushing capsfilter for h264");
+                        log_debug!(Pipeline, "pushing capsfilter for h264");
                         // middle.push(gstreamer::ElementFactory::make("queue").build().expect("could not create workaround queue element"));
                         // middle.push(build_capsfilter(gstreamer::Caps::builder("video/x-h264").field("stream-format", "byte-stream").field("profile", "main").build()).expect("could not create special capsfilter"));
                         middle.push(gstreamer::ElementFactory::make("h264parse").name("parser").build().expect("could not create h264parse element"));
                     },
                     EncodingPreset::H265 => {
-                        println!("pushing capsfilter for h265");
+                        log_debug!(Pipeline, "pushing capsfilter for h265");
                         // middle.push(gstreamer::ElementFactory::make("queue").build().expect("could not create workaround queue element"));
                         // middle.push(build_capsfilter(gstreamer::Caps::builder("video/x-h265").field("stream-format", "byte-stream").field("profile", "main").build()).expect("could not create special capsfilter"));
                         middle.push(gstreamer::ElementFactory::make("h265parse").name("parser").build().expect("could not create h265parse element"));
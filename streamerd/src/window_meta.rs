@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use stellar_protocol::protocol::{StellarDirectControlMessage, WindowIcon, WindowInfo};
+
+// mirrors hyperwarp's window registry so we know what to tell the browser about the captured window
+#[derive(Default)]
+pub struct WindowMetadata {
+    windows: HashMap<u64, WindowInfo>,
+    icons: HashMap<u64, WindowIcon>,
+    forwarded_title: Option<String>,
+    forwarded_icon: Option<u64>,
+}
+
+impl WindowMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn replace_all(&mut self, windows: Vec<WindowInfo>) -> Vec<StellarDirectControlMessage> {
+        self.windows = windows.into_iter().map(|window| (window.id, window)).collect();
+        self.icons.retain(|id, _| self.windows.contains_key(id));
+        self.pending_updates()
+    }
+
+    pub fn update_window(&mut self, window: WindowInfo) -> Vec<StellarDirectControlMessage> {
+        if window.capturing {
+            // only one window can be captured at a time
+            for other in self.windows.values_mut() {
+                other.capturing = false;
+            }
+        }
+        self.windows.insert(window.id, window);
+        self.pending_updates()
+    }
+
+    pub fn update_icon(&mut self, id: u64, icon: WindowIcon) -> Vec<StellarDirectControlMessage> {
+        self.icons.insert(id, icon);
+        // force a resend even if the captured window didn't change
+        if self.forwarded_icon == Some(id) {
+            self.forwarded_icon = None;
+        }
+        self.pending_updates()
+    }
+
+    pub fn remove_window(&mut self, id: u64) -> Vec<StellarDirectControlMessage> {
+        self.windows.remove(&id);
+        self.icons.remove(&id);
+        self.pending_updates()
+    }
+
+    pub fn capture_window(&self) -> Option<&WindowInfo> {
+        self.windows.values().find(|window| window.capturing)
+    }
+
+    // messages the browser needs to catch up with the captured window, empty if nothing changed since last time
+    fn pending_updates(&mut self) -> Vec<StellarDirectControlMessage> {
+        let mut updates = Vec::new();
+        let (title, capture_id) = match self.capture_window() {
+            Some(window) => (window.title.clone(), Some(window.id)),
+            None => (None, None),
+        };
+        if let Some(title) = title {
+            if self.forwarded_title.as_ref() != Some(&title) {
+                self.forwarded_title = Some(title.clone());
+                updates.push(StellarDirectControlMessage::UpdateWindowTitle { title });
+            }
+        }
+        if let Some(capture_id) = capture_id {
+            if self.forwarded_icon != Some(capture_id) {
+                if let Some(icon) = self.icons.get(&capture_id) {
+                    self.forwarded_icon = Some(capture_id);
+                    updates.push(StellarDirectControlMessage::UpdateWindowIcon { width: icon.width, height: icon.height, rgba: icon.rgba.clone() });
+                }
+            }
+        }
+        updates
+    }
+}